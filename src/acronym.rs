@@ -0,0 +1,58 @@
+// Acronym mode: a string of several words is reduced to its initials, so an
+// acronym or initialism ("NASA", "N.A.S.A.") can be scored against what it
+// stands for instead of against the full words.
+
+/// Words an acronym usually skips, as in "Department of Defense" (DOD).
+const SKIPPED: [&str; 11] = [
+    "a", "an", "and", "for", "in", "of", "on", "or", "the", "to", "&",
+];
+
+/// When one of `a` and `b` is a single word and the other several, the
+/// single word with punctuation dropped and the other's initials, both
+/// lowercase and in the order given. Initials are taken with and without
+/// the words acronyms tend to skip, and hyphenated parts count as words;
+/// the variant `score` likes best wins. `None` if neither string looks like
+/// an acronym of the other.
+pub fn initials(
+    a: &str,
+    b: &str,
+    score: impl Fn(&[char], &[char]) -> f64,
+) -> Option<(String, String)> {
+    let words = |s: &str| s.split_whitespace().count();
+    let (short, long, swapped) = match (words(a), words(b)) {
+        (1, n) if n > 1 => (a, b, false),
+        (n, 1) if n > 1 => (b, a, true),
+        _ => return None,
+    };
+    let acronym: Vec<char> = short
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let parts: Vec<String> = long
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '/')
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    let first = |keep: &dyn Fn(&str) -> bool| -> Vec<char> {
+        parts
+            .iter()
+            .filter(|w| keep(w))
+            .filter_map(|w| w.chars().find(|c| c.is_alphanumeric()))
+            .collect()
+    };
+    let every = first(&|_| true);
+    let skipping = first(&|w| !SKIPPED.contains(&w));
+    let best = if score(&acronym, &skipping) > score(&acronym, &every) {
+        skipping
+    } else {
+        every
+    };
+    let (acronym, best): (String, String) =
+        (acronym.into_iter().collect(), best.into_iter().collect());
+    Some(if swapped {
+        (best, acronym)
+    } else {
+        (acronym, best)
+    })
+}
@@ -0,0 +1,117 @@
+// `--all --agreement`: for a batch of pairs, how often each two algorithms
+// agree on which pairs match, so a small set of algorithms can be picked
+// that doesn't just repeat one of its members. Each algorithm calls a pair
+// a match when its normalized similarity reaches its threshold.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{Config, LabeledError, Record, Spanned, Value};
+use rayon::prelude::*;
+
+use crate::preprocess::Pipeline;
+use crate::{aliases, canonical, check_algorithm, similarity, text, AlgoParams, ALGORITHMS};
+
+/// One row per algorithm of a square table: the share of the input's pairs
+/// on which it agrees with each algorithm, along with its threshold and the
+/// share of pairs it calls a match.
+pub fn report(
+    call: &EvaluatedCall,
+    input: &Value,
+    coerce: Option<&Config>,
+) -> Result<Value, LabeledError> {
+    let span = call.head;
+    let thresholds = thresholds(call)?;
+    let Value::List { vals, .. } = input else {
+        return Err(LabeledError::new(format!(
+            "--agreement needs a list, got {}",
+            input.get_type()
+        ))
+        .with_label(
+            "Expected the strings of the pairs from pipeline",
+            input.span(),
+        )
+        .with_help("pipe in a list of strings, each compared with the string argument"));
+    };
+    let other: Spanned<String> = call.req(0)?;
+    let pipeline = Pipeline::from_call(call)?;
+    let params = AlgoParams::from_call(call)?;
+    let other: Vec<char> = pipeline.apply(&other.item).chars().collect();
+
+    // each pair's verdict by each algorithm
+    let verdicts = vals
+        .par_iter()
+        .map(|v| {
+            let s = text(v, coerce).ok_or_else(|| {
+                LabeledError::new(format!("can't compare {}", v.get_type()))
+                    .with_label("not a string", v.span())
+                    .with_help("add --coerce to compare values as they are displayed")
+            })?;
+            let chars: Vec<char> = pipeline.apply(&s).chars().collect();
+            Ok(thresholds
+                .iter()
+                .map(|(a, t)| similarity(a, &chars, &other, &params) >= *t)
+                .collect::<Vec<bool>>())
+        })
+        .collect::<Result<Vec<_>, LabeledError>>()?;
+    if verdicts.is_empty() {
+        return Err(LabeledError::new("no pairs")
+            .with_label("nothing to agree on", input.span())
+            .with_help("pipe in at least one string"));
+    }
+
+    let share = |count: usize| Value::float(count as f64 / verdicts.len() as f64, span);
+    let rows = thresholds
+        .iter()
+        .enumerate()
+        .map(|(i, (a, t))| {
+            let mut row = Record::new();
+            row.push("algorithm", Value::string(*a, span));
+            row.push("threshold", Value::float(*t, span));
+            row.push(
+                "match_rate",
+                share(verdicts.iter().filter(|v| v[i]).count()),
+            );
+            for (j, (b, _)) in thresholds.iter().enumerate() {
+                row.push(*b, share(verdicts.iter().filter(|v| v[i] == v[j]).count()));
+            }
+            Value::record(row, span)
+        })
+        .collect();
+    Ok(Value::list(rows, span))
+}
+
+/// Reads `--agreement`: a threshold for every algorithm, or a record of the
+/// algorithms to compare and each one's threshold.
+fn thresholds(call: &EvaluatedCall) -> Result<Vec<(&'static str, f64)>, LabeledError> {
+    let Some(value) = call.get_flag::<Value>("agreement")? else {
+        return Ok(vec![]);
+    };
+    let check = |v: &Value| {
+        let t = v.coerce_float()?;
+        match (0.0..=1.0).contains(&t) {
+            true => Ok(t),
+            false => Err(LabeledError::new("invalid threshold")
+                .with_label("must be between 0 and 1", v.span())
+                .with_help("thresholds are normalized similarities, whatever the algorithm")),
+        }
+    };
+    match &value {
+        Value::Record { val, .. } => val
+            .iter()
+            .map(|(name, t)| {
+                let name = aliases::resolve(
+                    call,
+                    Spanned {
+                        item: name.clone(),
+                        span: t.span(),
+                    },
+                )?;
+                check_algorithm(&name.item, name.span)?;
+                Ok((canonical(&name.item), check(t)?))
+            })
+            .collect(),
+        v => {
+            let t = check(v)?;
+            Ok(ALGORITHMS.iter().map(|a| (a.name, t)).collect())
+        }
+    }
+}
@@ -0,0 +1,191 @@
+// agrep-style approximate pattern matching (Sellers' algorithm).
+//
+// The pattern may match anywhere in the text with at most `k` insertions,
+// deletions or substitutions. `?` matches any single character and `*` any
+// run of characters at no cost; `\?`, `\*` and `\\` match literally.
+
+#[derive(Clone, Copy, PartialEq)]
+enum Token {
+    Lit(char),
+    Any,
+    Star,
+}
+
+impl Token {
+    fn hits(self, c: char) -> bool {
+        match self {
+            Token::Lit(p) => p == c,
+            _ => true,
+        }
+    }
+}
+
+fn parse(pattern: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '\\' => Token::Lit(chars.next().unwrap_or('\\')),
+            '?' => Token::Any,
+            '*' => Token::Star,
+            c => Token::Lit(c),
+        });
+    }
+    tokens
+}
+
+pub struct Match {
+    /// Char offset where the match starts.
+    pub start: usize,
+    /// Char offset just past the end of the match.
+    pub end: usize,
+    pub errors: usize,
+}
+
+/// The match with the fewest errors (leftmost on ties), if any is within `k`.
+pub fn find(pattern: &str, text: &str, k: usize) -> Option<Match> {
+    let pat = parse(pattern);
+    let m = pat.len();
+
+    // Cost and start offset of the best alignment of pat[..i] ending at the
+    // current text position, one column at a time.
+    let mut cost = vec![0; m + 1];
+    let mut start = vec![0; m + 1];
+    for i in 1..=m {
+        cost[i] = cost[i - 1] + usize::from(pat[i - 1] != Token::Star);
+    }
+
+    let mut best: Option<Match> = None;
+    let mut consider = |cost: usize, start: usize, end: usize| {
+        if cost <= k && !matches!(&best, Some(b) if b.errors <= cost) {
+            best = Some(Match {
+                start,
+                end,
+                errors: cost,
+            });
+        }
+    };
+    consider(cost[m], start[m], 0);
+
+    for (j, t) in text.chars().enumerate() {
+        let mut next_cost = vec![0; m + 1];
+        let mut next_start = vec![j + 1; m + 1];
+        for i in 1..=m {
+            let (c, s) = match pat[i - 1] {
+                Token::Star => {
+                    if cost[i] <= next_cost[i - 1] {
+                        (cost[i], start[i])
+                    } else {
+                        (next_cost[i - 1], next_start[i - 1])
+                    }
+                }
+                tok => {
+                    let diag = (cost[i - 1] + usize::from(!tok.hits(t)), start[i - 1]);
+                    let skip_pattern = (next_cost[i - 1] + 1, next_start[i - 1]);
+                    let skip_text = (cost[i] + 1, start[i]);
+                    [diag, skip_pattern, skip_text]
+                        .into_iter()
+                        .min_by_key(|(c, _)| *c)
+                        .unwrap_or(diag)
+                }
+            };
+            next_cost[i] = c;
+            next_start[i] = s;
+        }
+        cost = next_cost;
+        start = next_start;
+        consider(cost[m], start[m], j + 1);
+    }
+
+    best
+}
+
+/// Edit distance between the whole pattern and the whole text, using the same
+/// wildcard rules as [`find`].
+pub fn distance(pattern: &str, text: &str) -> usize {
+    let pat = parse(pattern);
+    let m = pat.len();
+
+    let mut cost = vec![0; m + 1];
+    for i in 1..=m {
+        cost[i] = cost[i - 1] + usize::from(pat[i - 1] != Token::Star);
+    }
+    for (j, t) in text.chars().enumerate() {
+        let mut next = vec![j + 1; m + 1];
+        for i in 1..=m {
+            next[i] = match pat[i - 1] {
+                Token::Star => cost[i].min(next[i - 1]),
+                tok => (cost[i - 1] + usize::from(!tok.hits(t)))
+                    .min(next[i - 1] + 1)
+                    .min(cost[i] + 1),
+            };
+        }
+        cost = next;
+    }
+    cost[m]
+}
+
+/// Number of characters the pattern must consume, i.e. everything but `*`.
+pub fn min_len(pattern: &str) -> usize {
+    parse(pattern)
+        .into_iter()
+        .filter(|t| *t != Token::Star)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAIRS: [(&str, &str); 6] = [
+        ("kitten", "sitting"),
+        ("shell", "nushell scripts"),
+        ("", "abc"),
+        ("abc", ""),
+        ("colour", "the color red"),
+        ("straße", "strasse"),
+    ];
+
+    #[test]
+    fn literal_distance_is_levenshtein() {
+        for (p, t) in PAIRS {
+            assert_eq!(
+                distance(p, t),
+                textdistance::str::levenshtein(p, t),
+                "{p} {t}"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_are_the_closest_substring() {
+        for (p, t) in PAIRS {
+            let chars: Vec<char> = t.chars().collect();
+            let substrings = (0..=chars.len())
+                .flat_map(|i| (i..=chars.len()).map(move |j| (i, j)))
+                .map(|(i, j)| chars[i..j].iter().collect::<String>());
+            let fewest = substrings
+                .map(|s| textdistance::str::levenshtein(p, &s))
+                .min()
+                .unwrap_or(0);
+            let m = find(p, t, usize::MAX).unwrap();
+            assert_eq!(m.errors, fewest, "{p} {t}");
+            let matched: String = chars[m.start..m.end].iter().collect();
+            assert_eq!(
+                textdistance::str::levenshtein(p, &matched),
+                fewest,
+                "{p} {t}"
+            );
+            assert!(fewest == 0 || find(p, t, fewest - 1).is_none());
+        }
+    }
+
+    #[test]
+    fn wildcards_cost_nothing() {
+        assert_eq!(distance("n?sh*", "nushell"), 0);
+        assert_eq!(distance(r"a\*", "a*"), 0);
+        assert_eq!(distance(r"a\*", "ab"), 1);
+        let m = find("c*r", "the color red", 0).unwrap();
+        assert_eq!((m.start, m.errors), (4, 0));
+    }
+}
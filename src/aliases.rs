@@ -0,0 +1,245 @@
+// Deprecated algorithm names: textdistance's own, and ones that stood for
+// an algorithm before it was split or renamed. Each still resolves to the
+// algorithm it stands for, with a warning in the output rather than on
+// stderr, where it would interleave with the rows; `--strict-names` makes it
+// an error, for CI scripts to catch before the name is dropped.
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{record, Example, LabeledError, PipelineData, Signature, Span, Spanned, Value};
+
+use crate::{profile, StrSimilarityPlugin};
+
+/// A deprecated name and the algorithm it resolves to.
+pub struct Alias {
+    pub name: &'static str,
+    pub algorithm: &'static str,
+    pub note: &'static str,
+}
+
+pub const DEPRECATED: [Alias; 5] = [
+    Alias {
+        name: "lcsseq",
+        algorithm: "longest_common_subsequence",
+        note: "textdistance's name; the short name is lcsubseq",
+    },
+    Alias {
+        name: "lcsstr",
+        algorithm: "longest_common_substring",
+        note: "textdistance's name; the short name is lcsubstr",
+    },
+    Alias {
+        name: "damerau",
+        algorithm: "damerau_levenshtein",
+        note: "ambiguous with the restricted variant; the short name is dlev",
+    },
+    Alias {
+        name: "sift4",
+        algorithm: "sift4_simple",
+        note: "split into sift4_simple and sift4_common",
+    },
+    Alias {
+        name: "dice",
+        algorithm: "sorensen_dice",
+        note: "the short name is soredice",
+    },
+];
+
+/// The alias `name` is, if it's deprecated.
+pub fn deprecated(name: &str) -> Option<&'static Alias> {
+    DEPRECATED
+        .iter()
+        .find(|a| a.name.eq_ignore_ascii_case(name))
+}
+
+/// `name` as the algorithm it stands for, or an error for a deprecated name
+/// under `--strict-names`.
+pub fn resolve(
+    call: &EvaluatedCall,
+    name: Spanned<String>,
+) -> Result<Spanned<String>, LabeledError> {
+    let Some(alias) = deprecated(&name.item) else {
+        return Ok(name);
+    };
+    if call.has_flag("strict-names")? {
+        return Err(LabeledError::new("deprecated algorithm name")
+            .with_label(
+                format!("'{}' is now '{}'", alias.name, alias.algorithm),
+                name.span,
+            )
+            .with_help(format!(
+                "use -a {}, or drop --strict-names to keep the old name working",
+                alias.algorithm
+            )));
+    }
+    Ok(Spanned {
+        item: alias.algorithm.into(),
+        span: name.span,
+    })
+}
+
+/// A warning record for each deprecated name `--algorithm` or
+/// `--algorithms` gives; `None` without any.
+pub fn warnings(call: &EvaluatedCall, span: Span) -> Result<Option<Value>, LabeledError> {
+    let mut names = Vec::new();
+    for flag in ["algorithm", "algorithms"] {
+        match call.get_flag::<Value>(flag)? {
+            Some(Value::String { val, .. }) => names.push(val),
+            Some(Value::List { vals, .. }) => names.extend(
+                vals.iter()
+                    .filter_map(|v| v.as_str().ok().map(String::from)),
+            ),
+            _ => {}
+        }
+    }
+    let rows: Vec<Value> = names
+        .iter()
+        .filter_map(|name| deprecated(name))
+        .map(|alias| {
+            Value::record(
+                record! {
+                    "kind" => Value::string("deprecated_algorithm", span),
+                    "name" => Value::string(alias.name, span),
+                    "replacement" => Value::string(alias.algorithm, span),
+                    "note" => Value::string(alias.note, span),
+                },
+                span,
+            )
+        })
+        .collect();
+    Ok((!rows.is_empty()).then(|| Value::list(rows, span)))
+}
+
+/// A command taking `--strict-names`, with the [`warnings`] of the
+/// deprecated names it was given, itself or by its profile, added to its output as a `warnings`
+/// column: on a record, and on each record of a table or stream. A bare
+/// score has nowhere to put one; `--versioned` wraps it in a record that
+/// does.
+pub struct Warned(pub Box<dyn PluginCommand<Plugin = StrSimilarityPlugin>>);
+
+impl PluginCommand for Warned {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        self.0.name()
+    }
+
+    fn description(&self) -> &str {
+        self.0.description()
+    }
+
+    fn extra_description(&self) -> &str {
+        self.0.extra_description()
+    }
+
+    fn signature(&self) -> Signature {
+        self.0.signature()
+    }
+
+    fn search_terms(&self) -> Vec<&str> {
+        self.0.search_terms()
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        self.0.examples()
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        // a profile, preset or default can name the algorithm too
+        let applied = profile::apply(plugin, engine, call, &self.signature())?;
+        let warnings = warnings(&applied, call.head)?;
+        let output = self.0.run(plugin, engine, call, input)?;
+        Ok(match warnings {
+            Some(warnings) => attach(output, warnings),
+            None => output,
+        })
+    }
+}
+
+/// `output` with `warnings` in a `warnings` column of each of its records.
+fn attach(output: PipelineData, warnings: Value) -> PipelineData {
+    let add = move |v: Value| match v {
+        Value::Record { .. } => {
+            let span = v.span();
+            let mut record = v.into_record().unwrap_or_default();
+            record.insert("warnings", warnings.clone());
+            Value::record(record, span)
+        }
+        v => v,
+    };
+    match output {
+        PipelineData::Value(
+            Value::List {
+                vals,
+                internal_span,
+            },
+            meta,
+        ) => PipelineData::Value(
+            Value::list(vals.into_iter().map(add).collect(), internal_span),
+            meta,
+        ),
+        PipelineData::Value(v, meta) => PipelineData::Value(add(v), meta),
+        PipelineData::ListStream(stream, meta) => {
+            PipelineData::ListStream(stream.modify(|rows| rows.map(add)), meta)
+        }
+        output => output,
+    }
+}
+
+/// The deprecated names, for `capabilities`.
+pub fn list(span: Span) -> Value {
+    let rows = DEPRECATED
+        .iter()
+        .map(|a| {
+            Value::record(
+                record! {
+                    "name" => Value::string(a.name, span),
+                    "replacement" => Value::string(a.algorithm, span),
+                    "note" => Value::string(a.note, span),
+                },
+                span,
+            )
+        })
+        .collect();
+    Value::list(rows, span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ALGORITHMS;
+
+    #[test]
+    fn aliases_resolve_to_algorithms_and_shadow_none() {
+        for alias in &DEPRECATED {
+            assert!(ALGORITHMS.iter().any(|a| a.name == alias.algorithm));
+            assert!(!ALGORITHMS
+                .iter()
+                .any(|a| a.name == alias.name || a.short == alias.name));
+        }
+        assert_eq!(
+            deprecated("LCSSeq").map(|a| a.algorithm),
+            Some("longest_common_subsequence")
+        );
+    }
+
+    #[test]
+    fn warnings_go_on_every_row() {
+        let row = Value::test_record(record! { "score" => Value::test_float(0.5) });
+        let table = Value::test_list(vec![row.clone(), row, Value::test_float(0.5)]);
+        let output = attach(PipelineData::Value(table, None), Value::test_string("w"));
+        let PipelineData::Value(Value::List { vals, .. }, _) = output else {
+            panic!("not a list");
+        };
+        let warned: Vec<bool> = vals
+            .iter()
+            .map(|v| v.as_record().is_ok_and(|r| r.get("warnings").is_some()))
+            .collect();
+        assert_eq!(warned, [true, true, false]);
+    }
+}
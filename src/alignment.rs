@@ -0,0 +1,58 @@
+// Which words of one string correspond to which of the other, for seeing why
+// two multi-word strings score the way they do.
+
+use nu_protocol::{record, Span, Value};
+
+/// Pairs the tokens of `a` and `b` one to one, taking the best-scoring pair
+/// of unpaired tokens first, and lists every token of `a` in order with its
+/// partner in `b` and their score, then the tokens of `b` left over. Tokens
+/// with nothing in common with any free token stay unmatched.
+pub fn report(a: &[&str], b: &[&str], score: impl Fn(&str, &str) -> f64, span: Span) -> Value {
+    let mut pairs: Vec<(f64, usize, usize)> = a
+        .iter()
+        .enumerate()
+        .flat_map(|(i, x)| b.iter().enumerate().map(move |(j, y)| (i, j, x, y)))
+        .map(|(i, j, x, y)| (score(x, y), i, j))
+        .filter(|(s, _, _)| *s > 0.0)
+        .collect();
+    pairs.sort_by(|x, y| y.0.total_cmp(&x.0).then((x.1, x.2).cmp(&(y.1, y.2))));
+
+    let mut partner: Vec<Option<(usize, f64)>> = vec![None; a.len()];
+    let mut taken = vec![false; b.len()];
+    for (s, i, j) in pairs {
+        if partner[i].is_none() && !taken[j] {
+            partner[i] = Some((j, s));
+            taken[j] = true;
+        }
+    }
+
+    let token = |tokens: &[&str], i: Option<usize>| match i {
+        Some(i) => (Value::string(tokens[i], span), Value::int(i as i64, span)),
+        None => (Value::nothing(span), Value::nothing(span)),
+    };
+    let row = |l: Option<usize>, r: Option<usize>, s: Option<f64>| {
+        let ((left, left_index), (right, right_index)) = (token(a, l), token(b, r));
+        Value::record(
+            record! {
+                "left" => left,
+                "left_index" => left_index,
+                "right" => right,
+                "right_index" => right_index,
+                "score" => s.map_or(Value::nothing(span), |s| Value::float(s, span)),
+                "status" => Value::string(if s.is_some() { "matched" } else { "unmatched" }, span),
+            },
+            span,
+        )
+    };
+    let mut rows: Vec<Value> = partner
+        .iter()
+        .enumerate()
+        .map(|(i, p)| row(Some(i), p.map(|(j, _)| j), p.map(|(_, s)| s)))
+        .collect();
+    rows.extend(
+        (0..b.len())
+            .filter(|j| !taken[*j])
+            .map(|j| row(None, Some(j), None)),
+    );
+    Value::list(rows, span)
+}
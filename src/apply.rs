@@ -0,0 +1,122 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{Category, Example, LabeledError, Signature, Span, SyntaxShape, Value};
+
+use crate::StrSimilarityPlugin;
+
+pub struct StrSimilarityApply;
+
+impl SimplePluginCommand for StrSimilarityApply {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity apply"
+    }
+
+    fn description(&self) -> &str {
+        "Rebuild a string by replaying a patch from `str similarity diff --patch` on the input"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .required(
+                "patch",
+                SyntaxShape::List(Box::new(SyntaxShape::Record(vec![]))),
+                "Patch produced by `str similarity diff --patch`",
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![Example {
+            description: "Replay a stored patch",
+            example: "'kitten' | str similarity apply [[op count text]; [delete 1 null] [insert 1 s] [equal 3 null] [delete 1 null] [insert 1 i] [equal 1 null] [insert 1 g]]",
+            result: Some(Value::test_string("sitting")),
+        }]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let patch: Vec<Value> = call.req(0)?;
+        let source: Vec<char> = match input {
+            Value::String { val, .. } => val.chars().collect(),
+            v => {
+                return Err(LabeledError::new(format!(
+                    "requires string input, got {}",
+                    v.get_type()
+                ))
+                .with_label("Expected a string from pipeline", v.span())
+                .with_help("pipe in the string the patch was made from"))
+            }
+        };
+
+        let mut out = String::new();
+        let mut pos: usize = 0;
+        for step in &patch {
+            let op = field(step, "op")?;
+            let op = op
+                .as_str()
+                .map_err(|_| bad_step(step, "op must be a string"))?;
+            match op {
+                "insert" => {
+                    let text = field(step, "text")?;
+                    out.push_str(
+                        text.as_str()
+                            .map_err(|_| bad_step(step, "text must be a string"))?,
+                    );
+                }
+                "equal" | "delete" => {
+                    let count = field(step, "count")?
+                        .as_int()
+                        .ok()
+                        .and_then(|c| usize::try_from(c).ok())
+                        .ok_or_else(|| bad_step(step, "count must be a non-negative int"))?;
+                    let Some(chars) = source.get(pos..pos.saturating_add(count)) else {
+                        return Err(mismatch(step.span()));
+                    };
+                    if op == "equal" {
+                        out.extend(chars);
+                    }
+                    pos += count;
+                }
+                _ => return Err(bad_step(step, "op must be equal, delete or insert")),
+            }
+        }
+        if pos != source.len() {
+            return Err(mismatch(call.head));
+        }
+
+        Ok(Value::string(out, input.span()))
+    }
+}
+
+fn field<'a>(step: &'a Value, name: &str) -> Result<&'a Value, LabeledError> {
+    step.as_record()
+        .ok()
+        .and_then(|r| r.get(name))
+        .ok_or_else(|| bad_step(step, &format!("missing '{name}'")))
+}
+
+fn bad_step(step: &Value, msg: &str) -> LabeledError {
+    LabeledError::new("invalid patch step").with_label(msg, step.span())
+}
+
+fn mismatch(span: Span) -> LabeledError {
+    LabeledError::new("patch does not fit the input")
+        .with_label("patch and input lengths disagree", span)
+        .with_help("apply the patch to the same string it was made from")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityApply)
+    }
+}
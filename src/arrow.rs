@@ -0,0 +1,117 @@
+// `--arrow-friendly`: batch output reshaped so every row has the same
+// columns with the same type, which is what `polars into-df` and other
+// dataframe plugins need to infer a schema. Rows get every column any row
+// has, empty where they had none; a column mixing integers and floats is
+// all floats; and a column still mixing types is all strings. Records in a
+// column are reshaped the same way, so they become uniform structs.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{Config, LabeledError, Record, Value};
+
+/// `rows` reshaped with `--arrow-friendly`, or as they are without it.
+pub fn shape(call: &EvaluatedCall, mut rows: Vec<Value>) -> Result<Vec<Value>, LabeledError> {
+    if call.has_flag("arrow-friendly")? {
+        unify(rows.iter_mut().collect());
+    }
+    Ok(rows)
+}
+
+/// Makes the values of one column, or the rows of a table, the same type.
+fn unify(mut column: Vec<&mut Value>) {
+    let present = || {
+        column
+            .iter()
+            .filter(|v| !matches!(v, Value::Nothing { .. }))
+    };
+    if present().count() == 0 {
+        return;
+    }
+    if present().all(|v| matches!(v, Value::Record { .. })) {
+        let mut names: Vec<String> = vec![];
+        for v in present() {
+            if let Value::Record { val, .. } = v {
+                for name in val.columns() {
+                    if !names.contains(name) {
+                        names.push(name.clone());
+                    }
+                }
+            }
+        }
+        // each column in the order first seen, filling in the missing ones
+        let mut records: Vec<Record> = vec![];
+        let mut spans = vec![];
+        for v in column.iter_mut() {
+            if let Value::Record { val, .. } = v {
+                let old = std::mem::take(val.to_mut());
+                let span = v.span();
+                records.push(
+                    names
+                        .iter()
+                        .map(|n| {
+                            let cell = old.get(n).cloned().unwrap_or(Value::nothing(span));
+                            (n.clone(), cell)
+                        })
+                        .collect(),
+                );
+                spans.push(Some(span));
+            } else {
+                spans.push(None);
+            }
+        }
+        for name in &names {
+            unify(records.iter_mut().filter_map(|r| r.get_mut(name)).collect());
+        }
+        let mut records = records.into_iter();
+        for (v, span) in column.iter_mut().zip(spans) {
+            if let Some(span) = span {
+                **v = Value::record(records.next().expect("a record per row"), span);
+            }
+        }
+        return;
+    }
+    let numbers = present().all(|v| matches!(v, Value::Int { .. } | Value::Float { .. }));
+    if numbers {
+        if present().any(|v| matches!(v, Value::Float { .. })) {
+            for v in column.iter_mut() {
+                if let Value::Int { val, .. } = v {
+                    **v = Value::float(*val as f64, v.span());
+                }
+            }
+        }
+        return;
+    }
+    let first = present().next().map(|v| v.get_type());
+    if present().any(|v| Some(v.get_type()) != first) {
+        let config = Config::default();
+        for v in column.iter_mut() {
+            if !matches!(v, Value::Nothing { .. }) {
+                **v = Value::string(v.to_expanded_string(", ", &config), v.span());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    #[test]
+    fn fills_columns_and_widens_numbers() {
+        let mut rows = [
+            Value::test_record(record! { "score" => Value::test_int(2) }),
+            Value::test_record(record! {
+                "score" => Value::test_float(0.5),
+                "input" => Value::test_string("nushell"),
+            }),
+        ];
+        unify(rows.iter_mut().collect());
+        assert_eq!(
+            rows[0],
+            Value::test_record(record! {
+                "score" => Value::test_float(2.0),
+                "input" => Value::test_nothing(),
+            })
+        );
+    }
+}
@@ -0,0 +1,66 @@
+// `-a auto`: an algorithm picked from what the strings look like, for
+// anyone who'd rather not study the catalog. Short single tokens are codes
+// or names whose typos are edits, sentences are the same words in any
+// order, and documents are compared by the shingles they share.
+
+/// Up to this many chars without whitespace is an identifier.
+const IDENTIFIER_CHARS: usize = 32;
+
+/// More chars than this, or several lines, is a document.
+const DOCUMENT_CHARS: usize = 200;
+
+/// The size of a document's shingles, the overlapping runs of chars it's
+/// compared by.
+pub const SHINGLE: usize = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Choice {
+    /// damerau_levenshtein on chars, for swapped letters.
+    Identifier,
+    /// levenshtein on the words of each string sorted, so word order
+    /// doesn't matter.
+    Sentence,
+    /// cosine on shingles.
+    Document,
+}
+
+/// The choice for comparing `a` and `b`, by the longer of them.
+pub fn choose(a: &str, b: &str) -> Choice {
+    let chars = a.chars().count().max(b.chars().count());
+    let words = |s: &str| s.split_whitespace().nth(1).is_some();
+    if chars > DOCUMENT_CHARS || a.contains('\n') || b.contains('\n') {
+        Choice::Document
+    } else if chars <= IDENTIFIER_CHARS && !words(a) && !words(b) {
+        Choice::Identifier
+    } else {
+        Choice::Sentence
+    }
+}
+
+impl Choice {
+    /// The algorithm that scores the strings.
+    pub fn algorithm(self) -> &'static str {
+        match self {
+            Choice::Identifier => "damerau_levenshtein",
+            Choice::Sentence => "levenshtein",
+            Choice::Document => "cosine",
+        }
+    }
+
+    /// What was chosen, as reported: the algorithm and how the strings were
+    /// prepared for it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Choice::Identifier => "damerau_levenshtein",
+            Choice::Sentence => "token_sort",
+            Choice::Document => "cosine_shingles",
+        }
+    }
+}
+
+/// The words of `s` in sorted order, separated by single spaces.
+pub fn sort_words(s: &str) -> String {
+    let mut words: Vec<&str> = s.split_whitespace().collect();
+    words.sort_unstable();
+    words.join(" ")
+}
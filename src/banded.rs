@@ -0,0 +1,50 @@
+// `--band` on its own: levenshtein through the cells within `k` of the
+// table's diagonal only, in time proportional to the strings' length times
+// `k` rather than to the product of their lengths. A path of at most `k`
+// edits never leaves the band, so distances up to `k` come out exact and
+// anything further is only known to be more than `k`.
+
+/// The levenshtein distance of `a` and `b` if it's at most `k`.
+pub fn levenshtein<E: Eq>(a: &[E], b: &[E], k: usize) -> Option<usize> {
+    let (n, m) = (a.len(), b.len());
+    if n.abs_diff(m) > k {
+        return None;
+    }
+    // out of the band, yet safe to add to
+    const FAR: usize = usize::MAX / 2;
+    let mut prev: Vec<usize> = (0..=m).map(|j| if j <= k { j } else { FAR }).collect();
+    let mut cur = vec![FAR; m + 1];
+    for i in 1..=n {
+        let (lo, hi) = (i.saturating_sub(k), (i + k).min(m));
+        // left over from two rows up, whose band reached further left
+        if lo > 0 {
+            cur[lo - 1] = FAR;
+        }
+        for j in lo..=hi {
+            cur[j] = match j {
+                0 => i,
+                _ => (prev[j - 1] + usize::from(a[i - 1] != b[j - 1]))
+                    .min(prev[j] + 1)
+                    .min(cur[j - 1] + 1),
+            };
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    Some(prev[m]).filter(|&d| d <= k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_within_the_band() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        let (a, b) = (chars("kitten"), chars("sitting"));
+        assert_eq!(levenshtein(&a, &b, 3), Some(3));
+        assert_eq!(levenshtein(&a, &b, 10), Some(3));
+        assert_eq!(levenshtein(&a, &b, 2), None);
+        assert_eq!(levenshtein(&chars("abcdef"), &chars("bcdefa"), 1), None);
+        assert_eq!(levenshtein(&chars(""), &chars("ab"), 2), Some(2));
+    }
+}
@@ -0,0 +1,166 @@
+// `str similarity bench`: how long each algorithm takes on the user's own
+// strings, to pick one fast enough before running a large join. Pairs are
+// scored one after another on a single thread so the timings don't compete.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Value,
+};
+
+use crate::{
+    aliases, canonical, check_algorithm, number, similarity, AlgoParams, StrSimilarityPlugin,
+    ALGORITHMS,
+};
+
+/// Runs over the pairs per algorithm without `--iterations`.
+const ITERATIONS: usize = 10;
+
+pub struct StrSimilarityBench;
+
+impl SimplePluginCommand for StrSimilarityBench {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity bench"
+    }
+
+    fn description(&self) -> &str {
+        "Time algorithms on the input strings"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .optional(
+                "string",
+                SyntaxShape::String,
+                "String to compare each input string with; without it, each is compared with the next",
+            )
+            .named(
+                "algorithms",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Algorithms to time (default: all of them)",
+                None,
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "iterations",
+                SyntaxShape::Int,
+                "Times to score every pair with each algorithm (default: 10)",
+                Some('i'),
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Time every algorithm on a list of names against a query",
+                example: "open names.txt | lines | str similarity bench 'jon smith' | sort-by mean_ns",
+                result: None,
+            },
+            Example {
+                description: "Compare the cost of two algorithms over neighbouring lines",
+                example: "open names.txt | lines | str similarity bench --algorithms [smith_waterman prefix] --iterations 100",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let span = call.head;
+        let algorithms: Vec<&str> = match call.get_flag::<Vec<Spanned<String>>>("algorithms")? {
+            Some(names) => names
+                .into_iter()
+                .map(|n| {
+                    let n = aliases::resolve(call, n)?;
+                    check_algorithm(&n.item, n.span).map(|_| canonical(&n.item))
+                })
+                .collect::<Result<_, _>>()?,
+            None => ALGORITHMS.iter().map(|a| a.name).collect(),
+        };
+        let iterations = match call.get_flag::<Spanned<i64>>("iterations")? {
+            Some(n) if n.item < 1 => {
+                return Err(LabeledError::new("invalid iteration count")
+                    .with_label("need at least one iteration", n.span)
+                    .with_help(format!("the default is {ITERATIONS} iterations")));
+            }
+            Some(n) => n.item as usize,
+            None => ITERATIONS,
+        };
+        let params = AlgoParams::from_call(call)?;
+
+        let strings: Vec<Vec<char>> = match input {
+            Value::List { vals, .. } => vals
+                .iter()
+                .map(|v| v.as_str().map(|s| s.chars().collect()))
+                .collect::<Result<_, _>>()?,
+            Value::String { val, .. } => vec![val.chars().collect()],
+            v => {
+                return Err(LabeledError::new(format!(
+                    "requires a list of strings, got {}",
+                    v.get_type()
+                ))
+                .with_label("Expected strings from pipeline", v.span())
+                .with_help("pipe in the strings to time the algorithms on"))
+            }
+        };
+        let target: Option<Vec<char>> = call.opt::<String>(0)?.map(|s| s.chars().collect());
+        let pairs: Vec<(&[char], &[char])> = match &target {
+            Some(t) => strings.iter().map(|s| (&s[..], &t[..])).collect(),
+            None => strings.windows(2).map(|w| (&w[0][..], &w[1][..])).collect(),
+        };
+        if pairs.is_empty() {
+            return Err(LabeledError::new("nothing to time")
+                .with_label("no pairs of strings", input.span())
+                .with_help(
+                    "pipe in at least two strings, or one and a string to compare it with",
+                ));
+        }
+
+        let rows = algorithms
+            .into_iter()
+            .map(|algo| {
+                let mut total = 0.0;
+                let start = Instant::now();
+                for _ in 0..iterations {
+                    total = pairs
+                        .iter()
+                        .map(|(a, b)| black_box(similarity(algo, a, b, &params)))
+                        .sum::<f64>();
+                }
+                // a clock too coarse for the run still divides
+                let elapsed = start.elapsed().as_secs_f64().max(1e-9);
+                let runs = (iterations * pairs.len()) as f64;
+                Value::record(
+                    record! {
+                        "algorithm" => Value::string(algo, span),
+                        "mean_ns" => number((elapsed * 1e9 / runs).round(), span),
+                        "throughput" => number((runs / elapsed).round(), span),
+                        "score" => Value::float(total / pairs.len() as f64, span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
@@ -0,0 +1,520 @@
+use std::fs::File;
+use std::path::PathBuf;
+use std::str::Utf8Error;
+use std::sync::mpsc;
+use std::thread;
+
+use memmap2::Mmap;
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signals, Signature, Span, Spanned, SyntaxShape, Type,
+    Value,
+};
+use rayon::prelude::*;
+
+use crate::format::Format;
+use crate::topk::{Ties, TopK};
+use crate::why;
+use crate::{algorithm, canonical, compute_tokens, is_distance, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityBest;
+
+impl SimplePluginCommand for StrSimilarityBest {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity best"
+    }
+
+    fn description(&self) -> &str {
+        "Find the candidates of the input list closest to a query"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_types(vec![
+                (Type::List(Box::new(Type::String)), Type::table()),
+                (Type::Nothing, Type::table()),
+            ])
+            .required("query", SyntaxShape::String, "String to look up")
+            .named(
+                "against-file",
+                SyntaxShape::Filepath,
+                "Read the candidates from this file, one per line, instead of from the input",
+                None,
+            )
+            .named(
+                "dictionary",
+                SyntaxShape::Filepath,
+                "Read the candidates from this word list, split on whitespace, instead of from the input",
+                None,
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "top",
+                SyntaxShape::Int,
+                "Number of candidates to return (default: 1)",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum normalized similarity (0 to 1) for a candidate to be returned, whether the algorithm is a similarity or a distance; the maximum with --farthest",
+                Some('t'),
+            )
+            .switch(
+                "farthest",
+                "Return the least similar candidates instead, to pick diverse samples or find outliers",
+                None,
+            )
+            .switch(
+                "why",
+                "Add a why column: a line of text saying why each candidate was picked, e.g. the score, a shared prefix or equal phonetic codes",
+                None,
+            )
+            .named(
+                "ties",
+                SyntaxShape::String,
+                "Which of equally scored candidates come first: first (in input order, the default), shortest, alphabetical, or all of them even beyond --top",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Find the file meant by a sloppy name",
+                example: "ls | get name | str similarity best 'cargotoml' --top 3 --algorithm jaro_winkler",
+                result: None,
+            },
+            Example {
+                description: "Find the names least like a reference spelling, to spot outliers",
+                example: "open names.txt | lines | str similarity best 'John Smith' --farthest --top 5",
+                result: None,
+            },
+            Example {
+                description: "Look up a word in a dictionary too large to load",
+                example: "str similarity best 'recieve' --against-file /usr/share/dict/words --top 5",
+                result: None,
+            },
+            Example {
+                description: "Look up a word in a word list with several words to a line",
+                example: "str similarity best 'recieve' --dictionary wordlist.txt --top 5",
+                result: None,
+            },
+            Example {
+                description: "Pick the closest word",
+                example: "[nushell fishshell powershell] | str similarity best nutshell",
+                result: Some(Value::test_list(vec![Value::test_record(record! {
+                    "value" => Value::test_string("nushell"),
+                    "score" => Value::test_float(0.125),
+                })])),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let format = Format::from_call(call)?;
+        let span = call.head;
+        let query: Spanned<String> = call.req(0)?;
+        let algo = algorithm(call)?;
+        let params = AlgoParams::from_call(call)?;
+        let farthest = call.has_flag("farthest")?;
+        let why = call.has_flag("why")?;
+        let top = match call.get_flag::<Spanned<i64>>("top")? {
+            Some(k) if k.item < 1 => {
+                return Err(LabeledError::new("invalid top count")
+                    .with_label("need at least one candidate", k.span)
+                    .with_help("the default is the single best candidate"));
+            }
+            Some(k) => k.item as usize,
+            None => 1,
+        };
+        let threshold = match call.get_flag::<Spanned<f64>>("threshold")? {
+            Some(t) if !(0.0..=1.0).contains(&t.item) => {
+                return Err(LabeledError::new("invalid threshold")
+                    .with_label("must be between 0 and 1", t.span)
+                    .with_help("thresholds are normalized similarities, whatever the algorithm"));
+            }
+            Some(t) => t.item,
+            None if farthest => 1.0,
+            None => 0.0,
+        };
+        let ties = Ties::from_call(call)?;
+        let lines = call.get_flag::<Spanned<String>>("against-file")?;
+        let words = call.get_flag::<Spanned<String>>("dictionary")?;
+        let file = match (lines, words) {
+            (Some(_), Some(words)) => {
+                return Err(LabeledError::new("two files of candidates")
+                    .with_label("--against-file gives the candidates too", words.span)
+                    .with_help("drop one of --against-file and --dictionary"));
+            }
+            (Some(path), None) => Some((path, Split::Lines)),
+            (None, Some(path)) => Some((path, Split::Words)),
+            (None, None) => None,
+        };
+        let candidates = match file {
+            Some((path, split)) => {
+                if !matches!(input, Value::Nothing { .. }) {
+                    return Err(LabeledError::new("two lists of candidates")
+                        .with_label("candidates come from this file", path.span)
+                        .with_help(format!(
+                            "drop the input, or --{} to match the input",
+                            split.flag()
+                        )));
+                }
+                let cwd = engine.get_current_dir()?;
+                Candidates::File {
+                    path: nu_path::expand_path_with(&path.item, cwd, true),
+                    split,
+                    span: path.span,
+                }
+            }
+            None => {
+                let Value::List { vals, .. } = input else {
+                    return Err(LabeledError::new(format!(
+                        "requires list input, got {}",
+                        input.get_type()
+                    ))
+                    .with_label("Expected a list of candidates", input.span())
+                    .with_help("e.g. [nushell fishshell] | str similarity best nutshell"));
+                };
+                Candidates::List(vals)
+            }
+        };
+
+        // Ranked by similarity so that distances come out ascending and
+        // similarities descending, but reported as the algorithm's own
+        // normalized score.
+        let distance = is_distance(&algo);
+        let query: Vec<char> = query.item.chars().collect();
+        let score = |c: &str| {
+            let chars: Vec<char> = c.chars().collect();
+            let n = compute_tokens(&algo, &query, &chars, &params).normalized;
+            (if distance { 1.0 - n } else { n }, n)
+        };
+        // the farthest are the closest by the opposite of similarity
+        let mut best = TopK::new(top);
+        candidates.each(engine.signals(), score, |seq, c, (sim, n)| {
+            let sim = match farthest {
+                true if sim <= threshold => -sim,
+                false if sim >= threshold => sim,
+                _ => return,
+            };
+            let (len, text, order) = ties.rank(seq, c);
+            best.push(sim, (len, text.to_string(), order), (seq, c.to_string(), n));
+        })?;
+        let mut best: Vec<(usize, String, f64)> =
+            best.into_sorted().into_iter().map(|(c, _)| c).collect();
+        if ties == Ties::All && best.len() == top {
+            // another pass for the candidates tied with the last one kept,
+            // rather than holding on to every candidate for it
+            let last = best[top - 1].2;
+            let kept: Vec<usize> = best.iter().map(|(seq, _, _)| *seq).collect();
+            candidates.each(engine.signals(), score, |seq, c, (_, n)| {
+                if n == last && !kept.contains(&seq) {
+                    best.push((seq, c.to_string(), n));
+                }
+            })?;
+        }
+        let rows = best
+            .into_iter()
+            .map(|(_, c, n)| {
+                let mut row = record! {
+                    "value" => Value::string(&c, span),
+                    "score" => format.score(n, span),
+                };
+                if why {
+                    let sim = if distance { 1.0 - n } else { n };
+                    let chars: Vec<char> = c.chars().collect();
+                    let text = why::justify(canonical(&algo), sim, &query, &chars);
+                    row.push("why", Value::string(text, span));
+                }
+                Value::record(row, span)
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// Candidates of a file handed from the thread reading it to the scoring
+/// at a time.
+const BATCH_LINES: usize = 1024;
+
+/// Batches read ahead of the scoring: enough to keep it busy while the disk
+/// catches up, few enough to keep memory flat when the disk is faster.
+const READ_AHEAD: usize = 4;
+
+/// How a file of candidates is split into them.
+#[derive(Clone, Copy)]
+enum Split {
+    /// One per line, `--against-file`.
+    Lines,
+    /// Separated by whitespace, `--dictionary`.
+    Words,
+}
+
+impl Split {
+    fn flag(self) -> &'static str {
+        match self {
+            Split::Lines => "against-file",
+            Split::Words => "dictionary",
+        }
+    }
+
+    /// The first candidate of `text` and what follows it, or `None` when
+    /// none is left.
+    fn next(self, text: &[u8]) -> Option<(&[u8], &[u8])> {
+        match self {
+            Split::Lines if text.is_empty() => None,
+            Split::Lines => {
+                let (line, rest) = match text.iter().position(|&b| b == b'\n') {
+                    Some(end) => (&text[..end], &text[end + 1..]),
+                    None => (text, &text[text.len()..]),
+                };
+                Some((line.strip_suffix(b"\r").unwrap_or(line), rest))
+            }
+            Split::Words => {
+                let start = text.iter().position(|b| !b.is_ascii_whitespace())?;
+                let text = &text[start..];
+                let end = text
+                    .iter()
+                    .position(u8::is_ascii_whitespace)
+                    .unwrap_or(text.len());
+                Some(text.split_at(end))
+            }
+        }
+    }
+}
+
+/// Where the candidates come from: the input list, or a file mapped into
+/// memory and split a batch at a time, so a dictionary of any size is paged
+/// in and out by the OS rather than loaded, and takes no more memory than
+/// the best candidates so far.
+enum Candidates<'a> {
+    List(&'a [Value]),
+    File {
+        path: PathBuf,
+        split: Split,
+        span: Span,
+    },
+}
+
+impl Candidates<'_> {
+    /// Calls `f` with each candidate, its position and its `score`, in
+    /// order. Candidates are scored a batch at a time in parallel; a file's
+    /// next batches are split on another thread meanwhile, which is where
+    /// its pages are read, so reading and scoring overlap.
+    fn each<T: Send>(
+        &self,
+        signals: &Signals,
+        score: impl Fn(&str) -> T + Sync,
+        mut f: impl FnMut(usize, &str, T),
+    ) -> Result<(), LabeledError> {
+        let mut batch = |start: usize, batch: &[&str]| {
+            let scores: Vec<T> = batch.par_iter().map(|c| score(c)).collect();
+            for (i, (c, s)) in batch.iter().zip(scores).enumerate() {
+                f(start + i, c, s);
+            }
+        };
+        let (path, split, span) = match self {
+            Candidates::List(vals) => {
+                let strings = vals
+                    .iter()
+                    .map(|v| v.as_str())
+                    .collect::<Result<Vec<_>, _>>()?;
+                batch(0, &strings);
+                return Ok(());
+            }
+            Candidates::File { path, split, span } => (path, *split, *span),
+        };
+        let unreadable = |e: String| {
+            LabeledError::new("can't read the candidates")
+                .with_label(format!("{}: {e}", path.display()), span)
+                .with_help(match split {
+                    Split::Lines => {
+                        "--against-file takes a UTF-8 text file of one candidate per line"
+                    }
+                    Split::Words => {
+                        "--dictionary takes a UTF-8 text file of words separated by whitespace"
+                    }
+                })
+        };
+        let file = File::open(path).map_err(|e| unreadable(e.to_string()))?;
+        // SAFETY: the map is only read. A file truncated while it's mapped
+        // faults on the pages it lost, as with any mapped file; that's the
+        // price of not loading it.
+        let map = unsafe { Mmap::map(&file) }.map_err(|e| unreadable(e.to_string()))?;
+        #[cfg(unix)]
+        let _ = map.advise(memmap2::Advice::Sequential);
+        let (sender, receiver) = mpsc::sync_channel(READ_AHEAD);
+        thread::scope(|scope| {
+            // stops once the receiver is gone, e.g. after ctrl-c
+            let map = &map;
+            scope.spawn(move || {
+                let mut rest: &[u8] = map;
+                loop {
+                    let (candidates, tail) = split_batch(rest, split, BATCH_LINES);
+                    rest = tail;
+                    let last = !matches!(&candidates, Ok(c) if c.len() == BATCH_LINES);
+                    if sender.send(candidates).is_err() || last {
+                        return;
+                    }
+                }
+            });
+            let mut start = 0;
+            for candidates in receiver {
+                signals.check(span)?;
+                let candidates = candidates.map_err(|e| unreadable(e.to_string()))?;
+                batch(start, &candidates);
+                start += candidates.len();
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Up to `n` candidates of `text` split as `split` says, and the text after
+/// them.
+fn split_batch(mut text: &[u8], split: Split, n: usize) -> (Result<Vec<&str>, Utf8Error>, &[u8]) {
+    let mut candidates = Vec::with_capacity(n);
+    while candidates.len() < n {
+        let Some((candidate, rest)) = split.next(text) else {
+            break;
+        };
+        match std::str::from_utf8(candidate) {
+            Ok(c) => candidates.push(c),
+            Err(e) => return (Err(e), rest),
+        }
+        text = rest;
+    }
+    (Ok(candidates), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityBest)
+    }
+
+    /// The candidates of a file holding `text`, split as `split` says. Each
+    /// test names its own file, so tests running at once don't share one.
+    fn file_candidates(test: &str, text: &str, split: Split) -> Vec<String> {
+        let name = format!("str_similarity_{}_{test}.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, text).unwrap();
+        let mut candidates = vec![];
+        let file = Candidates::File {
+            path: path.clone(),
+            split,
+            span: Span::test_data(),
+        };
+        file.each(&Signals::empty(), str::len, |seq, c, len| {
+            assert_eq!((seq, len), (candidates.len(), c.len()));
+            candidates.push(c.to_string());
+        })
+        .unwrap();
+        std::fs::remove_file(path).unwrap();
+        candidates
+    }
+
+    #[test]
+    fn file_candidates_are_lines() {
+        let text = "nushell\r\n\nfishshell\npowershell\n";
+        let lines = file_candidates("lines", text, Split::Lines);
+        assert_eq!(lines, ["nushell", "", "fishshell", "powershell"]);
+    }
+
+    #[test]
+    fn dictionary_candidates_are_words() {
+        let text = "  nushell fishshell\r\n\n\tpowershell \n";
+        let words = file_candidates("words", text, Split::Words);
+        assert_eq!(words, ["nushell", "fishshell", "powershell"]);
+        assert!(file_candidates("empty", "", Split::Words).is_empty());
+    }
+
+    #[test]
+    fn batches_keep_file_order() {
+        let name = format!("str_similarity_{}_batches.txt", std::process::id());
+        let path = std::env::temp_dir().join(name);
+        let text: Vec<String> = (0..2 * BATCH_LINES + 5).map(|i| i.to_string()).collect();
+        std::fs::write(&path, text.join("\n")).unwrap();
+        let mut count = 0;
+        let file = Candidates::File {
+            path: path.clone(),
+            split: Split::Lines,
+            span: Span::test_data(),
+        };
+        file.each(
+            &Signals::empty(),
+            |c| c.parse::<usize>().unwrap(),
+            |seq, _, n| {
+                assert_eq!(seq, n);
+                count += 1;
+            },
+        )
+        .unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(count, text.len());
+    }
+}
@@ -0,0 +1,129 @@
+// Time and interruption limits for long batch runs.
+//
+// Batch commands check the budget between units of work and, once it runs
+// out, return what they computed so far instead of discarding it.
+
+use std::sync::{mpsc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{record, LabeledError, Signals, Span, Spanned, Value};
+
+pub struct Budget {
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    /// Reads `--timeout`; without it the run is only limited by ctrl-c.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let deadline = duration(call, "timeout")?.map(|d| Instant::now() + d);
+        Ok(Budget { deadline })
+    }
+
+    /// Reads a limit in seconds from the flag `name`.
+    pub fn from_seconds(call: &EvaluatedCall, name: &str) -> Result<Self, LabeledError> {
+        let deadline = match call.get_flag::<Spanned<f64>>(name)? {
+            Some(s) if s.item <= 0.0 => {
+                return Err(LabeledError::new("invalid time limit")
+                    .with_label("must be greater than zero", s.span)
+                    .with_help(format!("e.g. --{name} 2.5")))
+            }
+            Some(s) => Some(Instant::now() + Duration::from_secs_f64(s.item)),
+            None => None,
+        };
+        Ok(Budget { deadline })
+    }
+
+    /// Whether the run has a deadline, beyond ctrl-c.
+    pub fn limited(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    /// Why the run has to stop now, if it does.
+    pub fn exhausted(&self, signals: &Signals) -> Option<&'static str> {
+        if signals.interrupted() {
+            Some("interrupted")
+        } else if matches!(self.deadline, Some(d) if Instant::now() >= d) {
+            Some("timeout")
+        } else {
+            None
+        }
+    }
+}
+
+/// A positive duration flag.
+pub fn duration(call: &EvaluatedCall, name: &str) -> Result<Option<Duration>, LabeledError> {
+    match call.get_flag::<Spanned<i64>>(name)? {
+        Some(t) if t.item <= 0 => Err(LabeledError::new("invalid timeout")
+            .with_label("timeout must be greater than zero", t.span)
+            .with_help("give a duration such as 30sec or 2min")),
+        Some(t) => Ok(Some(Duration::from_nanos(t.item as u64))),
+        None => Ok(None),
+    }
+}
+
+/// Comparisons running on threads of their own, those that ran over their
+/// limit and are still finishing included.
+static RUNNING: Mutex<usize> = Mutex::new(0);
+
+/// Signaled whenever one of the [`RUNNING`] comparisons finishes.
+static FINISHED: Condvar = Condvar::new();
+
+/// A place among the [`RUNNING`] comparisons, given up when dropped, even
+/// by a comparison that panicked.
+struct Slot;
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        *RUNNING.lock().unwrap_or_else(|e| e.into_inner()) -= 1;
+        FINISHED.notify_one();
+    }
+}
+
+/// Runs `f`, giving up on it after `limit`. The algorithms can't be stopped
+/// midway, so a comparison that runs over keeps going on its own thread until
+/// it finishes and its result is dropped; the caller just doesn't wait. At
+/// most one comparison per core runs at once, so a stream of pathological
+/// pairs can't pile up threads: a comparison waits its limit for a place and
+/// gives up without running if none frees.
+pub fn with_limit<T: Send + 'static>(
+    limit: Option<Duration>,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> Option<T> {
+    let Some(limit) = limit else {
+        return Some(f());
+    };
+    let deadline = Instant::now() + limit;
+    let most = thread::available_parallelism().map_or(1, |n| n.get());
+    let running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+    let (mut running, _) = FINISHED
+        .wait_timeout_while(running, limit, |r| *r >= most)
+        .unwrap_or_else(|e| e.into_inner());
+    if *running >= most {
+        return None;
+    }
+    *running += 1;
+    drop(running);
+    let slot = Slot;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _slot = slot;
+        // the receiver is gone once the limit passed
+        let _ = tx.send(f());
+    });
+    rx.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+        .ok()
+}
+
+/// The rows finished before a run was cut short, with the reason it stopped.
+pub fn partial(rows: Vec<Value>, reason: &str, span: Span) -> Value {
+    Value::record(
+        record! {
+            "rows" => Value::list(rows, span),
+            "truncated" => Value::bool(true, span),
+            "reason" => Value::string(reason, span),
+        },
+        span,
+    )
+}
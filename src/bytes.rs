@@ -0,0 +1,53 @@
+// Bytes mode: binary values, and strings with `--bytes`, are compared byte
+// by byte rather than char by char, for hashes, binary blobs and file names
+// that aren't valid UTF-8. Only the algorithms that mean something on raw
+// bytes run; the rest read words or sounds.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Value};
+
+use crate::registry::Property;
+use crate::{canonical, flag_span, lookup, ALGORITHMS};
+
+/// The algorithms that run on bytes.
+pub fn algorithms() -> Vec<&'static str> {
+    ALGORITHMS
+        .iter()
+        .filter(|a| a.has(Property::Bytes))
+        .map(|a| a.name)
+        .collect()
+}
+
+/// Whether the input is compared as bytes: it or the argument is binary, or
+/// `--bytes` asks for it.
+pub fn wanted(call: &EvaluatedCall, input: &Value) -> Result<bool, LabeledError> {
+    let binary = |v: &Value| matches!(v, Value::Binary { .. });
+    Ok(binary(input)
+        || call.opt::<Value>(0)?.is_some_and(|v| binary(&v))
+        || call.has_flag("bytes")?)
+}
+
+/// The bytes of a binary value, or of a string's UTF-8.
+pub fn of(v: &Value) -> Option<&[u8]> {
+    match v {
+        Value::Binary { val, .. } => Some(val),
+        Value::String { val, .. } => Some(val.as_bytes()),
+        _ => None,
+    }
+}
+
+/// Errors unless `algo` is one of the [`algorithms`] that run on bytes.
+pub fn check_algorithm(call: &EvaluatedCall, algo: &str) -> Result<(), LabeledError> {
+    if lookup(algo).is_some_and(|a| a.has(Property::Bytes)) {
+        return Ok(());
+    }
+    Err(LabeledError::new("algorithm doesn't run on bytes")
+        .with_label(
+            format!("{} compares text", canonical(algo)),
+            flag_span(call, "algorithm"),
+        )
+        .with_help(format!(
+            "binary input and --bytes take {}",
+            algorithms().join(", ")
+        )))
+}
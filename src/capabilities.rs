@@ -0,0 +1,104 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{record, Category, Example, LabeledError, Record, Signature, Type, Value};
+
+use crate::{
+    aliases, bytes, code, encode, identifier, list::list_algorithms, mask, preprocess, profile,
+    StrSimilarityPlugin, MODES, PARAMETERS, TEXTDISTANCE_VERSION,
+};
+
+pub struct StrSimilarityCapabilities;
+
+impl SimplePluginCommand for StrSimilarityCapabilities {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity capabilities"
+    }
+
+    fn description(&self) -> &str {
+        "Describe what this build of the plugin supports, for scripts to check before using it"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_type(Type::Nothing, Type::record())
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Check that an algorithm is available before using it",
+                example:
+                    "str similarity capabilities | get algorithms.algorithm | any { $in == nysiis }",
+                result: Some(Value::test_bool(true)),
+            },
+            Example {
+                description: "List the modes",
+                example: "str similarity capabilities | get modes",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        use nu_plugin::Plugin;
+        let span = call.head;
+        let strings = |items: &[&str]| {
+            Value::list(
+                items.iter().map(|s| Value::string(*s, span)).collect(),
+                span,
+            )
+        };
+        let commands: Vec<Value> = plugin
+            .commands()
+            .iter()
+            .map(|c| Value::string(c.name(), span))
+            .collect();
+        Ok(Value::record(
+            record! {
+                "version" => Value::string(plugin.version(), span),
+                "textdistance_version" => Value::string(TEXTDISTANCE_VERSION, span),
+                "commands" => Value::list(commands, span),
+                "algorithms" => list_algorithms().with_span(span),
+                "deprecated_algorithms" => aliases::list(span),
+                "byte_algorithms" => strings(&bytes::algorithms()),
+                "modes" => strings(&MODES),
+                "parameters" => strings(&PARAMETERS),
+                "preprocess" => strings(&preprocess::STEPS),
+                "masks" => strings(&mask::KINDS),
+                "presets" => strings(&profile::PRESETS),
+                "encodings" => strings(&encode::METHODS),
+                "schemes" => strings(&identifier::SCHEMES),
+                "languages" => strings(&code::LANGUAGES),
+                "backends" => Value::record(
+                    record! {
+                        "parallel" => Value::string("rayon", span),
+                        "threads" => Value::int(rayon::current_num_threads() as i64, span),
+                    },
+                    span,
+                ),
+                // the build has no optional cargo features yet; each one
+                // gets a key here saying whether it was compiled in
+                "features" => Value::record(Record::new(), span),
+            },
+            span,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityCapabilities)
+    }
+}
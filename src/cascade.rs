@@ -0,0 +1,198 @@
+// `join --cascade`: algorithms tried in turn, each only for the rows the
+// ones before it found no match for, so cheap strict ones (`exact` first of
+// all) settle most rows and permissive ones only see the leftovers. Each
+// row says which stage matched it, for an audit of how loose a match was.
+// A stage is an algorithm's name, held to `--threshold`, or a record with
+// its own: `{algo: jaro_winkler, min: 0.95}`.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Spanned, Value};
+
+use crate::validate::given;
+use crate::{aliases, canonical, check_algorithm, flag_span, similarity, AlgoParams};
+
+/// The stage matching strings equal after preprocessing.
+const EXACT: &str = "exact";
+
+/// Flags picking the algorithm or the matches themselves, which the stages
+/// do instead, and those of the budgeted, resumable run of single-algorithm
+/// joins, which a cascade doesn't go through.
+pub const EXCLUSIVE: [&str; 12] = [
+    "algorithm",
+    "field-algorithms",
+    "max",
+    "top",
+    "ties",
+    "explain-match",
+    "plan",
+    "sample",
+    "rejects",
+    "timeout",
+    "stats",
+    "unordered",
+];
+
+/// An algorithm, or `exact`, and the score it needs if not `--threshold`.
+struct Stage {
+    name: &'static str,
+    min: Option<f64>,
+}
+
+pub struct Cascade(Vec<Stage>);
+
+impl Cascade {
+    /// Reads `--cascade`; `None` without it.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        let Some(stages) = call.get_flag::<Vec<Value>>("cascade")? else {
+            return Ok(None);
+        };
+        if let Some(flag) = EXCLUSIVE.into_iter().find(|f| given(call, f)) {
+            return Err(
+                LabeledError::new(format!("--cascade and --{flag} are exclusive"))
+                    .with_label("the stages pick the matches", flag_span(call, flag))
+                    .with_help(format!("drop --{flag}")),
+            );
+        }
+        if stages.is_empty() {
+            return Err(LabeledError::new("empty cascade")
+                .with_label("no stage to match with", flag_span(call, "cascade"))
+                .with_help("list the stages, strictest first, e.g. --cascade [exact jaro_winkler levenshtein]"));
+        }
+        let threshold = call.get_flag::<f64>("threshold")?.is_some();
+        let stages = stages
+            .iter()
+            .map(|v| Stage::from_value(call, v, threshold))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Cascade(stages)))
+    }
+
+    /// The first stage with a candidate scoring at least `threshold`
+    /// against `s`: the index of its best candidate, the score and the
+    /// stage's name. Of equally good candidates the first wins.
+    pub fn best(
+        &self,
+        s: &[char],
+        candidates: &[&[char]],
+        threshold: f64,
+        params: &AlgoParams,
+    ) -> Option<(usize, f64, &'static str)> {
+        self.0.iter().find_map(|stage| {
+            let threshold = stage.min.unwrap_or(threshold);
+            let mut best: Option<(usize, f64)> = None;
+            for (i, c) in candidates.iter().enumerate() {
+                let score = match stage.name {
+                    EXACT if s == *c => 1.0,
+                    EXACT => continue,
+                    algo => similarity(algo, s, c, params),
+                };
+                if score >= threshold && !best.is_some_and(|(_, b)| b >= score) {
+                    best = Some((i, score));
+                }
+            }
+            best.map(|(i, score)| (i, score, stage.name))
+        })
+    }
+}
+
+impl Stage {
+    /// A stage from a name or an `{algo, min}` record, checked before any
+    /// matching starts. Without `--threshold`, an algorithm needs its own.
+    fn from_value(call: &EvaluatedCall, v: &Value, threshold: bool) -> Result<Self, LabeledError> {
+        let (name, min) = match v {
+            Value::String { val, .. } => (val.clone(), None),
+            Value::Record { val, .. } => {
+                if let Some(field) = val
+                    .columns()
+                    .find(|c| !["algo", "min"].contains(&c.as_str()))
+                {
+                    return Err(LabeledError::new("unknown stage field")
+                        .with_label(format!("'{field}' is not a stage field"), v.span())
+                        .with_help("a stage is {algo: <name>, min: <threshold>}, min optional"));
+                }
+                let Some(algo) = val.get("algo") else {
+                    return Err(LabeledError::new("stage without an algorithm")
+                        .with_label("no algo field", v.span())
+                        .with_help("e.g. {algo: jaro_winkler, min: 0.95}"));
+                };
+                let min = match val.get("min") {
+                    Some(m) => {
+                        let t = m.coerce_float()?;
+                        if !(0.0..=1.0).contains(&t) {
+                            return Err(LabeledError::new("invalid threshold")
+                                .with_label("must be between 0 and 1", m.span())
+                                .with_help("thresholds are normalized similarities, whatever the algorithm"));
+                        }
+                        Some(t)
+                    }
+                    None => None,
+                };
+                (algo.coerce_string()?, min)
+            }
+            v => {
+                return Err(LabeledError::new(format!(
+                    "a stage is a name or a record, got {}",
+                    v.get_type()
+                ))
+                .with_label("not a stage", v.span())
+                .with_help("e.g. --cascade [exact {algo: jaro_winkler, min: 0.95} levenshtein]"))
+            }
+        };
+        if name.eq_ignore_ascii_case(EXACT) {
+            return Ok(Stage { name: EXACT, min });
+        }
+        let name = aliases::resolve(
+            call,
+            Spanned {
+                item: name,
+                span: v.span(),
+            },
+        )?;
+        check_algorithm(&name.item, name.span)?;
+        if min.is_none() && !threshold {
+            return Err(LabeledError::new("stage without a threshold")
+                .with_label("would match every row with its best candidate", v.span())
+                .with_help(format!(
+                    "give one with {{algo: {}, min: 0.9}}, or for every stage with --threshold",
+                    name.item
+                )));
+        }
+        Ok(Stage {
+            name: canonical(&name.item),
+            min,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_stages_only_see_rows_earlier_ones_missed() {
+        let stage = |name, min| Stage { name, min };
+        let cascade = Cascade(vec![stage(EXACT, None), stage("levenshtein", None)]);
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        let (nushell, nutshell) = (chars("nushell"), chars("nutshell"));
+        let candidates = [nutshell.as_slice(), nushell.as_slice()];
+        let params = AlgoParams::default();
+        assert_eq!(
+            cascade.best(&nushell, &candidates, 0.8, &params),
+            Some((1, 1.0, EXACT))
+        );
+        let found = cascade.best(&chars("nutshel"), &candidates, 0.8, &params);
+        assert_eq!(
+            found.map(|(i, _, stage)| (i, stage)),
+            Some((0, "levenshtein"))
+        );
+        assert_eq!(
+            cascade.best(&chars("bash"), &candidates, 0.8, &params),
+            None
+        );
+        // a stage's own threshold overrides the default
+        let strict = Cascade(vec![stage("levenshtein", Some(0.9))]);
+        assert_eq!(
+            strict.best(&chars("nutshel"), &candidates, 0.5, &params),
+            None
+        );
+    }
+}
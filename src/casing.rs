@@ -0,0 +1,49 @@
+// Carrying the letter case of one string over to another.
+
+/// `replacement` in the casing pattern of `original`: all caps, all lower
+/// case, or Title Case. Mixed casings like "McDonald" match none of these
+/// and leave `replacement` as it is.
+pub fn restyle(original: &str, replacement: &str) -> String {
+    let letters: Vec<char> = original.chars().filter(|c| c.is_alphabetic()).collect();
+    let upper = letters.iter().filter(|c| c.is_uppercase()).count();
+    let lower = letters.iter().filter(|c| c.is_lowercase()).count();
+    if letters.is_empty() {
+        replacement.to_string()
+    } else if lower == 0 && letters.len() > 1 {
+        replacement.to_uppercase()
+    } else if upper == 0 {
+        replacement.to_lowercase()
+    } else if is_title(original) {
+        title(replacement)
+    } else {
+        replacement.to_string()
+    }
+}
+
+/// Whether every word starts with a capital and has no other.
+fn is_title(s: &str) -> bool {
+    s.split_whitespace().all(|w| {
+        let mut letters = w.chars().filter(|c| c.is_alphabetic());
+        match letters.next() {
+            Some(first) => first.is_uppercase() && !letters.any(char::is_uppercase),
+            None => true,
+        }
+    })
+}
+
+fn title(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut start = true;
+    for c in s.chars() {
+        if c.is_whitespace() {
+            start = true;
+            out.push(c);
+        } else if start && c.is_alphabetic() {
+            start = false;
+            out.extend(c.to_uppercase());
+        } else {
+            out.extend(c.to_lowercase());
+        }
+    }
+    out
+}
@@ -0,0 +1,261 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Record, Signature, Span, Spanned, SyntaxShape, Value,
+};
+
+use crate::sample::Rng;
+use crate::{algorithm, compute_tokens, similarity, AlgoParams, StrSimilarityPlugin};
+
+/// Rounding slack allowed before a property counts as violated.
+const EPSILON: f64 = 1e-9;
+
+pub struct StrSimilarityCheck;
+
+impl SimplePluginCommand for StrSimilarityCheck {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity check"
+    }
+
+    fn description(&self) -> &str {
+        "Check whether an algorithm behaves like a metric on sample strings"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to check",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "samples",
+                SyntaxShape::Int,
+                "Number of random strings to generate when none are piped in (default: 60)",
+                Some('s'),
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "Seed for the random strings, which are otherwise the same every run; each property then holds the seed used",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Check levenshtein on random strings",
+                example: "str similarity check -a levenshtein",
+                result: None,
+            },
+            Example {
+                description: "Check jaro_winkler on your own data",
+                example: "open names.txt | lines | str similarity check -a jaro_winkler",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let algo = algorithm(call)?;
+        let params = AlgoParams::from_call(call)?;
+        let count = match call.get_flag::<Spanned<i64>>("samples")? {
+            Some(n) if n.item < 1 => {
+                return Err(LabeledError::new("invalid sample count")
+                    .with_label("need at least one sample", n.span)
+                    .with_help("the default is 60 samples"));
+            }
+            Some(n) => n.item as usize,
+            None => 60,
+        };
+
+        let mut seed = None;
+        let samples: Vec<String> = match input {
+            Value::Nothing { .. } => {
+                let mut rng = match call.has_flag("seed")? {
+                    true => Rng::from_call(call)?,
+                    false => Rng::new(DEFAULT_STATE),
+                };
+                seed = call.has_flag("seed")?.then(|| rng.seed());
+                random_strings(count, &mut rng)
+            }
+            Value::List { vals, .. } => vals
+                .iter()
+                .map(|v| v.as_str().map(str::to_string))
+                .collect::<Result<_, _>>()?,
+            Value::String { val, .. } => vec![val.clone()],
+            v => {
+                return Err(LabeledError::new(format!(
+                    "requires a list of strings, got {}",
+                    v.get_type()
+                ))
+                .with_label("Expected sample strings from pipeline", v.span())
+                .with_help("pipe in a list of strings, or nothing to check random ones"))
+            }
+        };
+        let chars: Vec<Vec<char>> = samples.iter().map(|s| s.chars().collect()).collect();
+        let dist = |i: usize, j: usize| 1.0 - similarity(&algo, &chars[i], &chars[j], &params);
+        let n = chars.len();
+
+        let span = call.head;
+        let mut range = Property::new("normalized range");
+        let mut identity = Property::new("identity");
+        let mut symmetry = Property::new("symmetry");
+        let mut triangle = Property::new("triangle inequality");
+
+        for i in 0..n {
+            let d = dist(i, i);
+            identity.check(d.abs() <= EPSILON, || {
+                record! {
+                    "a" => Value::string(&samples[i], span),
+                    "distance" => Value::float(d, span),
+                }
+            });
+
+            for j in 0..n {
+                let v = compute_tokens(&algo, &chars[i], &chars[j], &params).normalized;
+                range.check((-EPSILON..=1.0 + EPSILON).contains(&v), || {
+                    record! {
+                        "a" => Value::string(&samples[i], span),
+                        "b" => Value::string(&samples[j], span),
+                        "value" => Value::float(v, span),
+                    }
+                });
+
+                if j > i {
+                    let (ab, ba) = (dist(i, j), dist(j, i));
+                    symmetry.check((ab - ba).abs() <= EPSILON, || {
+                        record! {
+                            "a" => Value::string(&samples[i], span),
+                            "b" => Value::string(&samples[j], span),
+                            "ab" => Value::float(ab, span),
+                            "ba" => Value::float(ba, span),
+                        }
+                    });
+                }
+
+                // every triple would be n^3; one middle point per pair is
+                // plenty to surface violations on random data
+                let k = (i + j + 1) % n;
+                let (ac, ab, bc) = (dist(i, k), dist(i, j), dist(j, k));
+                triangle.check(ac <= ab + bc + EPSILON, || {
+                    record! {
+                        "a" => Value::string(&samples[i], span),
+                        "b" => Value::string(&samples[j], span),
+                        "c" => Value::string(&samples[k], span),
+                        "ac" => Value::float(ac, span),
+                        "ab + bc" => Value::float(ab + bc, span),
+                    }
+                });
+            }
+        }
+
+        let rows = [range, identity, symmetry, triangle]
+            .into_iter()
+            .map(|p| p.into_value(seed, span))
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// Tally of one property: how often it was checked, how often it failed and
+/// the first counterexample.
+struct Property {
+    name: &'static str,
+    checked: usize,
+    violations: usize,
+    example: Option<Record>,
+}
+
+impl Property {
+    fn new(name: &'static str) -> Self {
+        Property {
+            name,
+            checked: 0,
+            violations: 0,
+            example: None,
+        }
+    }
+
+    fn check(&mut self, holds: bool, example: impl FnOnce() -> Record) {
+        self.checked += 1;
+        if !holds {
+            self.violations += 1;
+            self.example.get_or_insert_with(example);
+        }
+    }
+
+    fn into_value(self, seed: Option<i64>, span: Span) -> Value {
+        let mut row = record! {
+            "property" => Value::string(self.name, span),
+            "checked" => Value::int(self.checked as i64, span),
+            "violations" => Value::int(self.violations as i64, span),
+            "example" => match self.example {
+                Some(r) => Value::record(r, span),
+                None => Value::nothing(span),
+            },
+        };
+        if let Some(seed) = seed {
+            row.push("seed", Value::int(seed, span));
+        }
+        Value::record(row, span)
+    }
+}
+
+/// Generator state for the random strings without `--seed`, fixed so runs
+/// repeat.
+const DEFAULT_STATE: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// Short strings over a small alphabet, so random pairs share enough
+/// characters to exercise the interesting cases.
+fn random_strings(count: usize, rng: &mut Rng) -> Vec<String> {
+    const ALPHABET: &[u8] = b"abcde";
+    let mut next = move || rng.next();
+    (0..count)
+        .map(|_| {
+            let len = (next() % 9) as usize;
+            (0..len)
+                .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize] as char)
+                .collect()
+        })
+        .collect()
+}
@@ -0,0 +1,303 @@
+// `--checkpoint <path>`: a long join saves the chunks of input rows it has
+// finished to a nuon file every ten seconds, and `--resume` picks up from
+// that file after a crash or ctrl-c, scoring only the chunks still missing.
+// The file holds a fingerprint of the inputs and flags, so a checkpoint is
+// never resumed into a different job, and the chunk size, so chunks line up
+// whatever the thread count of the run resuming it.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{record, Config, LabeledError, Span, Value};
+
+use crate::fingerprint::fnv1a;
+use crate::flag_span;
+use crate::validate::given;
+
+/// Input rows per chunk for commands without a chunk size of their own.
+const CHUNK_ROWS: usize = 1024;
+
+/// How long finished chunks may wait before they're written out.
+const INTERVAL: Duration = Duration::from_secs(10);
+
+/// Flags whose runs aren't split into chunks of rows to save.
+pub const EXCLUSIVE: [&str; 3] = ["plan", "sample", "cascade"];
+
+/// Flags that don't change which rows a run produces, so a run may resume
+/// with different ones.
+const IGNORED: [&str; 5] = ["checkpoint", "resume", "timeout", "threads", "unordered"];
+
+/// The rows of a finished chunk, with the scores the limit cuts them by,
+/// and its near misses.
+#[derive(Clone)]
+pub struct Chunk {
+    pub rows: Vec<(Option<f64>, Value)>,
+    pub near: Vec<Value>,
+}
+
+pub struct Checkpoint {
+    path: PathBuf,
+    fingerprint: String,
+    state: Mutex<State>,
+}
+
+struct State {
+    chunk_rows: Option<usize>,
+    chunks: BTreeMap<usize, Chunk>,
+    saved: Instant,
+    /// The first failure to write the file from a worker thread.
+    error: Option<LabeledError>,
+}
+
+impl Checkpoint {
+    /// Reads `--checkpoint` and `--resume`, loading the file to resume from;
+    /// `None` without `--checkpoint`. `values` are the inputs the rows come
+    /// from.
+    pub fn from_call<'a>(
+        call: &EvaluatedCall,
+        engine: &EngineInterface,
+        values: impl IntoIterator<Item = &'a Value>,
+    ) -> Result<Option<Self>, LabeledError> {
+        let Some(path) = call.get_flag::<String>("checkpoint")? else {
+            if call.has_flag("resume")? {
+                return Err(LabeledError::new("--resume needs --checkpoint")
+                    .with_label("no file to resume from", flag_span(call, "resume"))
+                    .with_help("give the checkpoint file the interrupted run saved to"));
+            }
+            return Ok(None);
+        };
+        if let Some(flag) = EXCLUSIVE.into_iter().find(|f| given(call, f)) {
+            return Err(
+                LabeledError::new(format!("--checkpoint and --{flag} are exclusive"))
+                    .with_label("no chunks of rows to save", flag_span(call, flag))
+                    .with_help(format!("drop --{flag}")),
+            );
+        }
+        let path = nu_path::expand_path_with(path, engine.get_current_dir()?, true);
+        let checkpoint = Checkpoint {
+            path,
+            fingerprint: fingerprint(call, values),
+            state: Mutex::new(State {
+                chunk_rows: None,
+                chunks: BTreeMap::new(),
+                saved: Instant::now(),
+                error: None,
+            }),
+        };
+        if call.has_flag("resume")? {
+            checkpoint.load(call.head)?;
+        }
+        Ok(Some(checkpoint))
+    }
+
+    /// The chunk size of the run resumed, which this one has to keep.
+    pub fn chunk_rows(&self) -> Option<usize> {
+        self.lock().chunk_rows
+    }
+
+    /// Starts saving chunks of `chunk_rows` rows, writing the file once so
+    /// an unwritable path fails before any work, and hands over the chunks
+    /// the run resumed has already finished, by their first row.
+    pub fn begin(
+        &self,
+        chunk_rows: usize,
+        span: Span,
+    ) -> Result<BTreeMap<usize, Chunk>, LabeledError> {
+        let mut state = self.lock();
+        state.chunk_rows = Some(chunk_rows);
+        self.write(&state, span)?;
+        // kept too, to be written again with the new ones
+        Ok(state.chunks.clone())
+    }
+
+    /// Adds a finished chunk, starting at row `start`, writing the file if
+    /// it hasn't been for a while.
+    pub fn record(&self, start: usize, rows: &[(Option<f64>, Value)], near: &[Value], span: Span) {
+        let mut state = self.lock();
+        let chunk = Chunk {
+            rows: rows.to_vec(),
+            near: near.to_vec(),
+        };
+        state.chunks.insert(start, chunk);
+        if state.saved.elapsed() >= INTERVAL && state.error.is_none() {
+            match self.write(&state, span) {
+                Ok(()) => state.saved = Instant::now(),
+                Err(e) => state.error = Some(e),
+            }
+        }
+    }
+
+    /// Writes every finished chunk, or reports the first failure to.
+    pub fn finish(&self, span: Span) -> Result<(), LabeledError> {
+        let mut state = self.lock();
+        if let Some(e) = state.error.take() {
+            return Err(e);
+        }
+        self.write(&state, span)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn load(&self, span: Span) -> Result<(), LabeledError> {
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            // nothing saved yet: the run starts from scratch
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => {
+                return Err(LabeledError::new("can't read the checkpoint")
+                    .with_label(format!("{}: {e}", self.path.display()), span)
+                    .with_help("check the file's permissions"))
+            }
+        };
+        let invalid = |e: LabeledError| {
+            LabeledError::new("invalid checkpoint")
+                .with_label(format!("{} is not a checkpoint", self.path.display()), span)
+                .with_help("resume from the file --checkpoint saved to, or drop --resume")
+                .with_inner(e)
+        };
+        let saved = nuon::from_nuon(&text, Some(span)).map_err(|e| invalid(e.into()))?;
+        let field = |v: &Value, name: &str| {
+            v.get_data_by_key(name)
+                .ok_or_else(|| invalid(LabeledError::new(format!("no {name} field"))))
+        };
+        if field(&saved, "fingerprint")?.coerce_string()? != self.fingerprint {
+            return Err(LabeledError::new("checkpoint of another run")
+                .with_label(
+                    format!("{} was saved with other inputs or flags", self.path.display()),
+                    span,
+                )
+                .with_help("resume with the inputs and flags of the interrupted run, or drop --resume to start over"));
+        }
+        let chunk_rows = field(&saved, "chunk_rows")?.as_int()?;
+        if chunk_rows < 1 {
+            return Err(invalid(LabeledError::new(format!(
+                "chunk_rows is {chunk_rows}, not at least 1"
+            ))));
+        }
+        let mut state = self.lock();
+        state.chunk_rows = Some(chunk_rows as usize);
+        for chunk in field(&saved, "chunks")?.as_list()? {
+            let start = field(chunk, "start")?.as_int()? as usize;
+            let rows = field(chunk, "rows")?
+                .as_list()?
+                .iter()
+                .map(|r| {
+                    let score = match field(r, "score")? {
+                        Value::Nothing { .. } => None,
+                        s => Some(s.coerce_float()?),
+                    };
+                    Ok((score, field(r, "row")?))
+                })
+                .collect::<Result<_, LabeledError>>()?;
+            let near = field(chunk, "near")?.as_list()?.to_vec();
+            state.chunks.insert(start, Chunk { rows, near });
+        }
+        Ok(())
+    }
+
+    /// Writes the file whole next to where it goes, then moves it there, so
+    /// a crash mid-write leaves the last checkpoint intact.
+    fn write(&self, state: &State, span: Span) -> Result<(), LabeledError> {
+        let chunks = state
+            .chunks
+            .iter()
+            .map(|(start, c)| {
+                let rows = c
+                    .rows
+                    .iter()
+                    .map(|(score, row)| {
+                        Value::record(
+                            record! {
+                                "score" => score.map_or(Value::nothing(span), |s| Value::float(s, span)),
+                                "row" => row.clone(),
+                            },
+                            span,
+                        )
+                    })
+                    .collect();
+                Value::record(
+                    record! {
+                        "start" => Value::int(*start as i64, span),
+                        "rows" => Value::list(rows, span),
+                        "near" => Value::list(c.near.clone(), span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+        let saved = record! {
+            "fingerprint" => Value::string(&self.fingerprint, span),
+            "chunk_rows" => Value::int(state.chunk_rows.unwrap_or(1) as i64, span),
+            "chunks" => Value::list(chunks, span),
+        };
+        let text = nuon::to_nuon(&Value::record(saved, span), nuon::ToStyle::Raw, Some(span))?;
+        let partial = self.path.with_extension("partial");
+        std::fs::write(&partial, text)
+            .and_then(|_| std::fs::rename(&partial, &self.path))
+            .map_err(|e| {
+                LabeledError::new("can't save the checkpoint")
+                    .with_label(format!("{}: {e}", self.path.display()), span)
+                    .with_help("pick a writable path for --checkpoint")
+            })
+    }
+}
+
+/// The rows for `len` input rows, scored by `score` a chunk at a time with
+/// a checkpoint, skipping the chunks the run it resumes finished and saving
+/// the others, or all at once without one.
+pub fn chunked(
+    checkpoint: Option<&Checkpoint>,
+    len: usize,
+    span: Span,
+    score: impl Fn(Range<usize>) -> Vec<(Option<f64>, Value)>,
+) -> Result<Vec<(Option<f64>, Value)>, LabeledError> {
+    let Some(checkpoint) = checkpoint else {
+        return Ok(score(0..len));
+    };
+    let chunk_rows = checkpoint.chunk_rows().unwrap_or(CHUNK_ROWS);
+    let done = checkpoint.begin(chunk_rows, span)?;
+    let mut rows = vec![];
+    for start in (0..len).step_by(chunk_rows) {
+        match done.get(&start) {
+            Some(chunk) => rows.extend(chunk.rows.iter().cloned()),
+            None => {
+                let chunk = score(start..(start + chunk_rows).min(len));
+                checkpoint.record(start, &chunk, &[], span);
+                rows.extend(chunk);
+            }
+        }
+    }
+    checkpoint.finish(span)?;
+    Ok(rows)
+}
+
+/// A hash of `values` and the flags that shape the rows, as hex.
+fn fingerprint<'a>(call: &EvaluatedCall, values: impl IntoIterator<Item = &'a Value>) -> String {
+    let config = Config::default();
+    let mut text = String::new();
+    for v in values {
+        text.push_str(&v.to_expanded_string(", ", &config));
+        text.push('\n');
+    }
+    for v in &call.positional {
+        text.push_str(&v.to_expanded_string(", ", &config));
+        text.push('\n');
+    }
+    let mut flags: Vec<_> = call
+        .named
+        .iter()
+        .filter(|(n, _)| !IGNORED.contains(&n.item.as_str()))
+        .collect();
+    flags.sort_by(|a, b| a.0.item.cmp(&b.0.item));
+    for (name, v) in flags {
+        let value = v.as_ref().map(|v| v.to_expanded_string(", ", &config));
+        text.push_str(&format!("--{} {}\n", name.item, value.unwrap_or_default()));
+    }
+    format!("{:016x}", fnv1a(&text.chars().collect::<Vec<_>>()))
+}
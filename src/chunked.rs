@@ -0,0 +1,114 @@
+// Approximate scoring of long documents, chunk by chunk.
+//
+// Most algorithms are quadratic in the string length, so two long documents
+// are split into chunks instead, the i-th chunk of one scored against the
+// i-th of the other in parallel, and the chunk scores averaged. Text moved
+// between chunks is missed, which is the price of staying tractable.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Spanned};
+
+use crate::{flag_span, parallel, similarity, AlgoParams};
+
+pub struct Chunking {
+    /// Chars per chunk; `None` splits at blank lines.
+    size: Option<usize>,
+    /// Weigh each chunk pair by its length instead of equally.
+    weighted: bool,
+}
+
+impl Chunking {
+    /// Reads `--chunked`, `--chunk-size` and `--aggregate`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        let size = call.get_flag::<Spanned<i64>>("chunk-size")?;
+        let aggregate = call.get_flag::<Spanned<String>>("aggregate")?;
+        if !call.has_flag("chunked")? {
+            let flag = match (&size, &aggregate) {
+                (Some(_), _) => "chunk-size",
+                (None, Some(_)) => "aggregate",
+                (None, None) => return Ok(None),
+            };
+            return Err(LabeledError::new(format!("--{flag} needs --chunked"))
+                .with_label(
+                    "only used when scoring chunk by chunk",
+                    flag_span(call, flag),
+                )
+                .with_help("add --chunked"));
+        }
+        let size = match size {
+            Some(s) if s.item < 1 => {
+                return Err(LabeledError::new("invalid chunk size")
+                    .with_label("a chunk needs at least one character", s.span)
+                    .with_help("leave out --chunk-size to split at blank lines"));
+            }
+            Some(s) => Some(s.item as usize),
+            None => None,
+        };
+        let weighted = match aggregate {
+            Some(a) if a.item == "mean" => false,
+            Some(a) if a.item == "weighted" => true,
+            Some(a) => {
+                return Err(LabeledError::new("unknown aggregate")
+                    .with_label(format!("'{}' is not an aggregate", a.item), a.span)
+                    .with_help("valid aggregates: mean, weighted"));
+            }
+            None => true,
+        };
+        Ok(Some(Chunking { size, weighted }))
+    }
+
+    fn split(&self, text: &str) -> Vec<Vec<char>> {
+        match self.size {
+            Some(size) => text
+                .chars()
+                .collect::<Vec<_>>()
+                .chunks(size)
+                .map(<[char]>::to_vec)
+                .collect(),
+            None => paragraphs(text)
+                .iter()
+                .map(|p| p.chars().collect())
+                .collect(),
+        }
+    }
+
+    /// Normalized similarity of `a` and `b` from their aligned chunks. A
+    /// chunk with no counterpart scores against an empty one, so extra text
+    /// on either side lowers the score.
+    pub fn similarity(&self, algo: &str, a: &str, b: &str, params: &AlgoParams) -> f64 {
+        let (mut ca, mut cb) = (self.split(a), self.split(b));
+        let n = ca.len().max(cb.len());
+        if n == 0 {
+            return 1.0;
+        }
+        ca.resize(n, vec![]);
+        cb.resize(n, vec![]);
+        let pairs: Vec<(Vec<char>, Vec<char>)> = ca.into_iter().zip(cb).collect();
+        let scored: Vec<(f64, f64)> = parallel::map_chunks(&pairs, 1, true, |p| {
+            let (x, y) = &p[0];
+            let weight = if self.weighted {
+                x.len().max(y.len()) as f64
+            } else {
+                1.0
+            };
+            (similarity(algo, x, y, params), weight)
+        });
+        let total: f64 = scored.iter().map(|(_, w)| w).sum();
+        scored.iter().map(|(s, w)| s * w).sum::<f64>() / total
+    }
+}
+
+/// The runs of non-blank lines in `text`.
+pub fn paragraphs(text: &str) -> Vec<String> {
+    let mut paragraphs = vec![];
+    let mut current: Vec<&str> = vec![];
+    for line in text.lines().chain([""]) {
+        if !line.trim().is_empty() {
+            current.push(line);
+        } else if !current.is_empty() {
+            paragraphs.push(current.join("\n"));
+            current.clear();
+        }
+    }
+    paragraphs
+}
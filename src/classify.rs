@@ -0,0 +1,317 @@
+// `str similarity classify`: text categorization without rules, by the
+// label of the closest of a few hand-picked examples. Each input string
+// goes to the label whose exemplars score best together, or to none when
+// even that label is below the threshold.
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value,
+};
+use rayon::prelude::*;
+
+use crate::format::Format;
+use crate::{algorithm, flag_span, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityClassify;
+
+impl SimplePluginCommand for StrSimilarityClassify {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity classify"
+    }
+
+    fn description(&self) -> &str {
+        "Label each input string with the label of its closest exemplar"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_types(vec![
+                (Type::List(Box::new(Type::String)), Type::table()),
+                (Type::String, Type::record()),
+            ])
+            .required(
+                "exemplars",
+                SyntaxShape::Table(vec![]),
+                "Rows of a label and an example string of it; a label may have several",
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Normalized similarity (0 to 1) the closest exemplar needs for its label to be given (default: 0, always labeling)",
+                Some('t'),
+            )
+            .named(
+                "aggregate",
+                SyntaxShape::String,
+                "How a label's exemplars score together: max (the default), mean, or top-mean, the mean of its --top-k closest",
+                None,
+            )
+            .named(
+                "top-k",
+                SyntaxShape::Int,
+                "Exemplars of each label averaged by --aggregate top-mean (default: 3)",
+                None,
+            )
+            .named(
+                "label",
+                SyntaxShape::String,
+                "Column of the exemplars holding the label (default: label)",
+                None,
+            )
+            .named(
+                "exemplar",
+                SyntaxShape::String,
+                "Column of the exemplars holding the example string (default: exemplar)",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Sort log lines into kinds by a few examples of each",
+                example: "open --raw app.log | lines | str similarity classify (open kinds.csv) -a cosine -t 0.4 | group-by label",
+                result: None,
+            },
+            Example {
+                description: "Score each label by its two closest exemplars, for labels phrased many ways",
+                example: "open --raw app.log | lines | str similarity classify (open kinds.csv) --aggregate top-mean --top-k 2",
+                result: None,
+            },
+            Example {
+                description: "Label a message",
+                example: "'disk is full' | str similarity classify [[label exemplar]; [storage 'disk full'] [network 'connection reset']]",
+                result: Some(Value::test_record(record! {
+                    "value" => Value::test_string("disk is full"),
+                    "label" => Value::test_string("storage"),
+                    "exemplar" => Value::test_string("disk full"),
+                    "score" => Value::test_float(0.75),
+                })),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let format = Format::from_call(call)?;
+        let span = call.head;
+        let exemplars: Spanned<Vec<Value>> = call.req(0)?;
+        let algo = algorithm(call)?;
+        let params = AlgoParams::from_call(call)?;
+        let threshold = match call.get_flag::<Spanned<f64>>("threshold")? {
+            Some(t) if !(0.0..=1.0).contains(&t.item) => {
+                return Err(LabeledError::new("invalid threshold")
+                    .with_label("must be between 0 and 1", t.span)
+                    .with_help("thresholds are normalized similarities, whatever the algorithm"));
+            }
+            Some(t) => t.item,
+            None => 0.0,
+        };
+        let label: String = call.get_flag("label")?.unwrap_or_else(|| "label".into());
+        let exemplar: String = call
+            .get_flag("exemplar")?
+            .unwrap_or_else(|| "exemplar".into());
+        let aggregate = Aggregate::from_call(call)?;
+
+        let list_span = exemplars.span;
+        let rows = exemplars
+            .item
+            .iter()
+            .map(|row| {
+                let field = |col: &str| {
+                    row.as_record()
+                        .ok()
+                        .and_then(|r| r.get(col))
+                        .ok_or_else(|| {
+                            LabeledError::new(format!("missing column '{col}'"))
+                                .with_label("exemplar without it", row.span())
+                                .with_help("pick the columns with --label and --exemplar")
+                        })
+                };
+                let text = field(&exemplar)?.as_str()?;
+                Ok((field(&label)?, text))
+            })
+            .collect::<Result<Vec<_>, LabeledError>>()?;
+        // each label with its exemplars, in the order they first appear
+        let mut labels: Vec<(&Value, Vec<Exemplar>)> = vec![];
+        for (label, text) in rows {
+            let exemplar = (text, text.chars().collect());
+            match labels.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, exemplars)) => exemplars.push(exemplar),
+                None => labels.push((label, vec![exemplar])),
+            }
+        }
+        if labels.is_empty() {
+            return Err(LabeledError::new("no exemplars")
+                .with_label("nothing to label by", list_span)
+                .with_help("give at least one row of a label and an example string"));
+        }
+
+        // the first of equally close labels, and of a label's equally close
+        // exemplars, wins
+        let classify = |v: &Value| -> Result<Value, LabeledError> {
+            let chars: Vec<char> = v.as_str()?.chars().collect();
+            let mut best = (f64::NEG_INFINITY, labels[0].0, "");
+            for (label, exemplars) in &labels {
+                let mut scores: Vec<(f64, &str)> = exemplars
+                    .iter()
+                    .map(|(text, e)| (similarity(&algo, &chars, e, &params), *text))
+                    .collect();
+                // stable, so ties keep their order
+                scores.sort_by(|a, b| b.0.total_cmp(&a.0));
+                let score = aggregate.of(scores.iter().map(|s| s.0));
+                if score > best.0 {
+                    best = (score, label, scores[0].1);
+                }
+            }
+            let (score, label, text) = best;
+            let accepted = score >= threshold;
+            Ok(Value::record(
+                record! {
+                    "value" => v.clone(),
+                    "label" => match accepted {
+                        true => label.clone(),
+                        false => Value::nothing(span),
+                    },
+                    "exemplar" => match accepted {
+                        true => Value::string(text, span),
+                        false => Value::nothing(span),
+                    },
+                    "score" => format.score(score, span),
+                },
+                span,
+            ))
+        };
+        match input {
+            Value::List { vals, .. } => {
+                let rows = vals.par_iter().map(classify).collect::<Result<_, _>>()?;
+                Ok(Value::list(rows, span))
+            }
+            v @ Value::String { .. } => classify(v),
+            v => Err(
+                LabeledError::new(format!("requires strings, got {}", v.get_type()))
+                    .with_label("Expected strings to label from pipeline", v.span())
+                    .with_help("pipe in a string or a list of strings"),
+            ),
+        }
+    }
+}
+
+/// An exemplar's text, and its chars as compared.
+type Exemplar<'a> = (&'a str, Vec<char>);
+
+/// How a label's exemplar scores combine into its score.
+enum Aggregate {
+    Max,
+    Mean,
+    /// The mean of the best this many.
+    TopMean(usize),
+}
+
+impl Aggregate {
+    /// Reads `--aggregate` and `--top-k`.
+    fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let aggregate = call.get_flag::<Spanned<String>>("aggregate")?;
+        let top_k = call.get_flag::<Spanned<i64>>("top-k")?;
+        let name = aggregate.as_ref().map_or("max", |a| a.item.as_str());
+        match (name, top_k) {
+            ("top-mean", Some(k)) if k.item < 1 => Err(LabeledError::new("invalid top-k")
+                .with_label("need at least one exemplar", k.span)
+                .with_help("the default averages the 3 closest exemplars")),
+            ("top-mean", k) => Ok(Aggregate::TopMean(k.map_or(3, |k| k.item as usize))),
+            (_, Some(_)) => Err(LabeledError::new("--top-k needs --aggregate top-mean")
+                .with_label(
+                    "only top-mean averages some exemplars",
+                    flag_span(call, "top-k"),
+                )
+                .with_help("add --aggregate top-mean")),
+            ("max", None) => Ok(Aggregate::Max),
+            ("mean", None) => Ok(Aggregate::Mean),
+            (name, None) => Err(LabeledError::new("unknown aggregate")
+                .with_label(
+                    format!("'{name}' is not an aggregate"),
+                    aggregate.as_ref().map_or(call.head, |a| a.span),
+                )
+                .with_help("valid aggregates: max, mean, top-mean")),
+        }
+    }
+
+    /// The label score of exemplar `scores`, best first.
+    fn of(&self, scores: impl Iterator<Item = f64>) -> f64 {
+        let scores: Vec<f64> = match self {
+            Aggregate::Max => scores.take(1).collect(),
+            Aggregate::Mean => scores.collect(),
+            Aggregate::TopMean(k) => scores.take(*k).collect(),
+        };
+        scores.iter().sum::<f64>() / scores.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityClassify)
+    }
+}
@@ -0,0 +1,164 @@
+// Near-duplicate region detection across files.
+//
+// Every file is cut into overlapping windows of non-blank lines and each
+// window is fingerprinted by winnowing. Windows in different files sharing
+// enough fingerprints are matched through an inverted index, and runs of
+// matches along the same diagonal are merged into one region.
+
+use std::collections::HashMap;
+
+use crate::fingerprint;
+
+/// Fingerprints shared by more windows than this are boilerplate (closing
+/// braces, blank `else` blocks, ...) and are left out of candidate search.
+const MAX_POSTINGS: usize = 256;
+
+struct Window {
+    file: usize,
+    /// Position among the file's windows.
+    index: usize,
+    /// 1-based, inclusive.
+    lines: (usize, usize),
+    hashes: Vec<u64>,
+}
+
+pub struct Region {
+    pub left_file: usize,
+    pub left_lines: (usize, usize),
+    pub right_file: usize,
+    pub right_lines: (usize, usize),
+    /// Mean Jaccard similarity of the matched windows.
+    pub score: f64,
+}
+
+fn windows(file: usize, text: &str, size: usize) -> Vec<Window> {
+    let lines: Vec<(usize, String)> = text
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.split_whitespace().collect::<Vec<_>>().join(" ")))
+        .filter(|(_, l)| !l.is_empty())
+        .collect();
+
+    let count = lines.len().saturating_sub(size.max(1)) + 1;
+    (0..count.min(lines.len()))
+        .map(|i| {
+            let chunk = &lines[i..(i + size).min(lines.len())];
+            let joined: Vec<&str> = chunk.iter().map(|(_, l)| l.as_str()).collect();
+            let mut hashes = fingerprint::winnow(&joined.join("\n"));
+            hashes.sort_unstable();
+            hashes.dedup();
+            Window {
+                file,
+                index: i,
+                lines: (chunk[0].0, chunk[chunk.len() - 1].0),
+                hashes,
+            }
+        })
+        .filter(|w| !w.hashes.is_empty())
+        .collect()
+}
+
+/// Regions of different files whose windows of `size` lines have a
+/// fingerprint Jaccard similarity of at least `threshold`.
+pub fn find(files: &[&str], size: usize, threshold: f64) -> Vec<Region> {
+    let windows: Vec<Window> = files
+        .iter()
+        .enumerate()
+        .flat_map(|(f, text)| windows(f, text, size))
+        .collect();
+
+    let mut postings: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (id, w) in windows.iter().enumerate() {
+        for &h in &w.hashes {
+            postings.entry(h).or_default().push(id);
+        }
+    }
+
+    // (left window, right window, score), left always in the earlier file
+    let mut matches = vec![];
+    for (a, wa) in windows.iter().enumerate() {
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for h in &wa.hashes {
+            let ids = &postings[h];
+            if ids.len() > MAX_POSTINGS {
+                continue;
+            }
+            for &b in ids {
+                if windows[b].file > wa.file {
+                    *shared.entry(b).or_default() += 1;
+                }
+            }
+        }
+        for (b, n) in shared {
+            let union = wa.hashes.len() + windows[b].hashes.len() - n;
+            let score = n as f64 / union as f64;
+            if score >= threshold {
+                matches.push((a, b, score));
+            }
+        }
+    }
+
+    let diagonal = |a: usize, b: usize| {
+        let (wa, wb) = (&windows[a], &windows[b]);
+        (
+            wa.file,
+            wb.file,
+            wa.index as isize - wb.index as isize,
+            wa.index,
+        )
+    };
+    matches.sort_by_key(|&(a, b, _)| diagonal(a, b));
+
+    // consecutive windows on one diagonal are a single longer region
+    let mut regions: Vec<Region> = vec![];
+    let mut run: Option<((usize, usize, isize, usize), usize)> = None;
+    for (a, b, score) in matches {
+        let key = diagonal(a, b);
+        let (wa, wb) = (&windows[a], &windows[b]);
+        match (&mut run, regions.last_mut()) {
+            (Some((prev, n)), Some(r))
+                if (prev.0, prev.1, prev.2) == (key.0, key.1, key.2) && prev.3 + 1 == key.3 =>
+            {
+                r.left_lines.1 = wa.lines.1;
+                r.right_lines.1 = wb.lines.1;
+                r.score = (r.score * *n as f64 + score) / (*n + 1) as f64;
+                *prev = key;
+                *n += 1;
+            }
+            _ => {
+                regions.push(Region {
+                    left_file: wa.file,
+                    left_lines: wa.lines,
+                    right_file: wb.file,
+                    right_lines: wb.lines,
+                    score,
+                });
+                run = Some((key, 1));
+            }
+        }
+    }
+    drop_shadowed(regions)
+}
+
+/// Windows one line off the true alignment often still pass the threshold;
+/// keep only the largest region among those overlapping on both sides.
+fn drop_shadowed(mut regions: Vec<Region>) -> Vec<Region> {
+    let len = |r: &Region| r.left_lines.1 - r.left_lines.0;
+    regions.sort_by(|a, b| len(b).cmp(&len(a)).then(b.score.total_cmp(&a.score)));
+
+    let overlaps = |a: (usize, usize), b: (usize, usize)| a.0 <= b.1 && b.0 <= a.1;
+    let mut kept: Vec<Region> = vec![];
+    for r in regions {
+        let shadowed = kept.iter().any(|k| {
+            k.left_file == r.left_file
+                && k.right_file == r.right_file
+                && overlaps(k.left_lines, r.left_lines)
+                && overlaps(k.right_lines, r.right_lines)
+        });
+        if !shadowed {
+            kept.push(r);
+        }
+    }
+    kept.sort_by_key(|r| (r.left_file, r.left_lines, r.right_file, r.right_lines));
+    kept
+}
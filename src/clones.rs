@@ -0,0 +1,156 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Value,
+};
+
+use crate::format::Format;
+use crate::{clone_detect, StrSimilarityPlugin};
+
+pub struct StrSimilarityClones;
+
+impl SimplePluginCommand for StrSimilarityClones {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity clones"
+    }
+
+    fn description(&self) -> &str {
+        "Find near-duplicate regions across a set of files"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .named(
+                "window",
+                SyntaxShape::Int,
+                "Non-blank lines per compared window (default: 6)",
+                Some('w'),
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum fingerprint similarity (0 to 1) for two windows to count as clones (default: 0.8)",
+                Some('t'),
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Find copy-pasted blocks across scripts",
+                example: "ls **/*.nu | str similarity clones",
+                result: None,
+            },
+            Example {
+                description: "Look for shorter, looser duplicates in two files",
+                example: "['a.rs' 'b.rs'] | str similarity clones --window 4 --threshold 0.6",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let window = match call.get_flag::<Spanned<i64>>("window")? {
+            Some(w) if w.item < 1 => {
+                return Err(LabeledError::new("invalid window")
+                    .with_label("window must be at least one line", w.span)
+                    .with_help("the default window is 6 lines"));
+            }
+            Some(w) => w.item as usize,
+            None => 6,
+        };
+        let threshold: f64 = call.get_flag("threshold")?.unwrap_or(0.8);
+
+        let cwd = engine.get_current_dir()?;
+        let mut names = vec![];
+        let mut texts = vec![];
+        for v in paths(input)? {
+            let name = v.as_str()?;
+            let path = nu_path::expand_path_with(name, &cwd, true);
+            let text = std::fs::read_to_string(&path).map_err(|e| {
+                LabeledError::new(format!("cannot read {}", path.display()))
+                    .with_label(e.to_string(), v.span())
+            })?;
+            names.push(name);
+            texts.push(text);
+        }
+
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let span = call.head;
+        let format = Format::from_call(call)?;
+        let rows = clone_detect::find(&texts, window, threshold)
+            .into_iter()
+            .map(|r| {
+                Value::record(
+                    record! {
+                        "left_file" => Value::string(names[r.left_file], span),
+                        "left_start" => Value::int(r.left_lines.0 as i64, span),
+                        "left_end" => Value::int(r.left_lines.1 as i64, span),
+                        "right_file" => Value::string(names[r.right_file], span),
+                        "right_start" => Value::int(r.right_lines.0 as i64, span),
+                        "right_end" => Value::int(r.right_lines.1 as i64, span),
+                        "score" => format.score(r.score, span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// File names from a list of paths, or the `name` column of `ls` output.
+fn paths(input: &Value) -> Result<Vec<&Value>, LabeledError> {
+    let vals = match input {
+        Value::List { vals, .. } => vals.as_slice(),
+        Value::String { .. } => std::slice::from_ref(input),
+        v => {
+            return Err(LabeledError::new(format!(
+                "requires a list of paths, got {}",
+                v.get_type()
+            ))
+            .with_label("Expected file paths or `ls` output from pipeline", v.span())
+            .with_help("e.g. ls **/*.rs | str similarity clones"))
+        }
+    };
+    vals.iter()
+        .map(|v| match v {
+            Value::Record { val, .. } => val.get("name").ok_or_else(|| {
+                LabeledError::new("missing file name")
+                    .with_label("record has no 'name' column", v.span())
+                    .with_help("pipe in `ls` output or a list of paths")
+            }),
+            Value::String { .. } => Ok(v),
+            v => Err(
+                LabeledError::new(format!("expected a path, got {}", v.get_type()))
+                    .with_label("not a path", v.span())
+                    .with_help("pipe in `ls` output or a list of paths"),
+            ),
+        })
+        .collect()
+}
@@ -0,0 +1,341 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value,
+};
+use rayon::prelude::*;
+
+use crate::format::Format;
+use crate::group::distinct;
+use crate::{algorithm, flag_span, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityCluster;
+
+/// How members join a cluster.
+#[derive(Clone, Copy, PartialEq)]
+enum Linkage {
+    /// Each string joins the first cluster whose representative it's close
+    /// enough to, or starts its own.
+    Greedy,
+    /// Any two strings close enough to each other share a cluster, so a
+    /// chain of small differences can link distant strings.
+    Single,
+}
+
+impl SimplePluginCommand for StrSimilarityCluster {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity cluster"
+    }
+
+    fn description(&self) -> &str {
+        "Cluster near-duplicate strings, one row per distinct member with its cluster's representative"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_type(Type::List(Box::new(Type::String)), Type::table())
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum normalized similarity (0 to 1) for a string to join a cluster (default: 0.8), or with --outliers its average similarity to the rest of the list (default: 0.3)",
+                Some('t'),
+            )
+            .switch(
+                "outliers",
+                "Instead of clustering, score each string by its average similarity to the rest of the list and flag those below the threshold",
+                None,
+            )
+            .named(
+                "linkage",
+                SyntaxShape::String,
+                "greedy (the default): join the first cluster whose representative is close enough; single: join any cluster with a member close enough",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Cluster misspellings",
+                example:
+                    "[apple aple banana bananna apple] | str similarity cluster -a jaro_winkler",
+                result: None,
+            },
+            Example {
+                description: "Link chains of small differences into one cluster",
+                example:
+                    "open names.csv | get name | str similarity cluster --linkage single -t 0.9",
+                result: None,
+            },
+            Example {
+                description: "Find the probable garbage in a column",
+                example:
+                    "open people.csv | get city | str similarity cluster --outliers | where outlier",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let format = Format::from_call(call)?;
+        let span = call.head;
+        let algo = algorithm(call)?;
+        let params = AlgoParams::from_call(call)?;
+        let outliers = call.has_flag("outliers")?;
+        if outliers && call.named.iter().any(|(n, _)| n.item == "linkage") {
+            return Err(LabeledError::new("--outliers and --linkage are exclusive")
+                .with_label("outliers aren't clustered", flag_span(call, "linkage"))
+                .with_help("drop --linkage"));
+        }
+        let threshold = match call.get_flag::<Spanned<f64>>("threshold")? {
+            Some(t) if !(0.0..=1.0).contains(&t.item) => {
+                return Err(LabeledError::new("invalid threshold")
+                    .with_label("must be between 0 and 1", t.span)
+                    .with_help("thresholds are normalized similarities, whatever the algorithm"));
+            }
+            Some(t) => t.item,
+            None if outliers => 0.3,
+            None => 0.8,
+        };
+        let linkage = match call.get_flag::<Spanned<String>>("linkage")? {
+            None => Linkage::Greedy,
+            Some(l) => match l.item.as_str() {
+                "greedy" => Linkage::Greedy,
+                "single" => Linkage::Single,
+                _ => {
+                    return Err(LabeledError::new("unknown linkage")
+                        .with_label(format!("'{}' is not a linkage", l.item), l.span)
+                        .with_help("valid linkages: greedy, single"))
+                }
+            },
+        };
+        let Value::List { vals, .. } = input else {
+            return Err(LabeledError::new(format!(
+                "requires list input, got {}",
+                input.get_type()
+            ))
+            .with_label("Expected a list of strings", input.span())
+            .with_help("e.g. [apple aple banana] | str similarity cluster"));
+        };
+        let strings = vals
+            .iter()
+            .map(|v| v.as_str())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // most frequent first, so a cluster's usual spelling represents it
+        let distinct = distinct(strings);
+        let chars: Vec<Vec<char>> = distinct.iter().map(|(s, _)| s.chars().collect()).collect();
+        let score = |i: usize, j: usize| similarity(&algo, &chars[i], &chars[j], &params);
+        if outliers {
+            let counts: Vec<usize> = distinct.iter().map(|(_, n)| *n).collect();
+            let mut rows: Vec<(usize, Option<f64>)> =
+                means(&counts, score).into_iter().enumerate().collect();
+            // the least typical first; a lone string has nothing to compare with
+            rows.sort_by(|(_, a), (_, b)| {
+                a.unwrap_or(f64::INFINITY)
+                    .total_cmp(&b.unwrap_or(f64::INFINITY))
+            });
+            let rows = rows
+                .into_iter()
+                .map(|(i, mean)| {
+                    Value::record(
+                        record! {
+                            "value" => Value::string(distinct[i].0, span),
+                            "count" => Value::int(counts[i] as i64, span),
+                            "score" => mean.map_or(Value::nothing(span), |m| format.score(m, span)),
+                            "outlier" => Value::bool(mean.is_some_and(|m| m < threshold), span),
+                        },
+                        span,
+                    )
+                })
+                .collect();
+            return Ok(Value::list(rows, span));
+        }
+        let clusters = match linkage {
+            Linkage::Greedy => greedy(chars.len(), threshold, score),
+            Linkage::Single => single(chars.len(), threshold, score),
+        };
+
+        let rows = clusters
+            .into_iter()
+            .enumerate()
+            .flat_map(|(id, members)| {
+                let representative = members[0];
+                members.into_iter().map(move |m| (id, representative, m))
+            })
+            .map(|(id, r, m)| {
+                Value::record(
+                    record! {
+                        "cluster_id" => Value::int(id as i64, span),
+                        "representative" => Value::string(distinct[r].0, span),
+                        "member" => Value::string(distinct[m].0, span),
+                        "score" => format.score(score(r, m), span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// Clusters of the indices `0..n`, each led by its representative, the
+/// first index to start it.
+pub fn greedy(n: usize, threshold: f64, score: impl Fn(usize, usize) -> f64) -> Vec<Vec<usize>> {
+    let mut clusters: Vec<Vec<usize>> = vec![];
+    for i in 0..n {
+        match clusters.iter_mut().find(|c| score(c[0], i) >= threshold) {
+            Some(c) => c.push(i),
+            None => clusters.push(vec![i]),
+        }
+    }
+    clusters
+}
+
+/// The connected components of the indices `0..n` linked by pairs scoring
+/// at least `threshold`, each led by its lowest index. Every pair is
+/// scored, in parallel.
+fn single(n: usize, threshold: f64, score: impl Fn(usize, usize) -> f64 + Sync) -> Vec<Vec<usize>> {
+    let links: Vec<(usize, usize)> = (0..n)
+        .into_par_iter()
+        .flat_map_iter(|i| {
+            let score = &score;
+            (i + 1..n)
+                .filter(move |&j| score(i, j) >= threshold)
+                .map(move |j| (i, j))
+        })
+        .collect();
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for (i, j) in links {
+        let (a, b) = (root(&mut parent, i), root(&mut parent, j));
+        parent[a.max(b)] = a.min(b);
+    }
+    let mut clusters: Vec<Vec<usize>> = vec![];
+    let mut cluster_of = vec![usize::MAX; n];
+    for i in 0..n {
+        let r = root(&mut parent, i);
+        if cluster_of[r] == usize::MAX {
+            cluster_of[r] = clusters.len();
+            clusters.push(vec![]);
+        }
+        clusters[cluster_of[r]].push(i);
+    }
+    clusters
+}
+
+/// For each of the distinct strings `0..counts.len()`, occurring
+/// `counts[i]` times, its average similarity to every other element of the
+/// list, its own repeats included, computed in parallel. `None` for a list
+/// of one element.
+fn means(counts: &[usize], score: impl Fn(usize, usize) -> f64 + Sync) -> Vec<Option<f64>> {
+    let total: usize = counts.iter().sum();
+    if total < 2 {
+        return vec![None; counts.len()];
+    }
+    (0..counts.len())
+        .into_par_iter()
+        .map(|i| {
+            let others: f64 = (0..counts.len())
+                .map(|j| {
+                    if i == j {
+                        (counts[i] - 1) as f64
+                    } else {
+                        counts[j] as f64 * score(i, j)
+                    }
+                })
+                .sum();
+            Some(others / (total - 1) as f64)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn single_linkage_follows_chains() {
+        // 0 and 2 are only linked through 1
+        let close = |i: usize, j: usize| if i.abs_diff(j) == 1 { 1.0 } else { 0.0 };
+        assert_eq!(greedy(3, 0.5, close), vec![vec![0, 1], vec![2]]);
+        assert_eq!(single(3, 0.5, close), vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn outliers_score_low_against_the_rest() {
+        // 0 appears twice and is close to 1; 2 is like nothing else
+        let score = |i: usize, j: usize| if i + j == 1 { 0.8 } else { 0.0 };
+        let means: Vec<f64> = means(&[2, 1, 1], score)
+            .into_iter()
+            .map(|m| (m.unwrap() * 100.0).round() / 100.0)
+            .collect();
+        assert_eq!(means, [0.6, 0.53, 0.0]);
+        assert_eq!(super::means(&[1], score), [None]);
+    }
+}
@@ -0,0 +1,156 @@
+// Normalization of source code before scoring.
+//
+// Identifiers are split into lowercase words on camelCase and snake_case
+// boundaries, punctuation becomes its own token and whitespace collapses to
+// single spaces, so `parseHttpUrl(x)` and `parse_http_url( x )` compare equal.
+// Comments can be dropped as well when the language is known.
+
+/// Comment markers of one language family.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Comments {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+}
+
+pub const LANGUAGES: [&str; 20] = [
+    "c",
+    "cpp",
+    "csharp",
+    "go",
+    "java",
+    "javascript",
+    "js",
+    "kotlin",
+    "rust",
+    "swift",
+    "ts",
+    "typescript",
+    "nu",
+    "python",
+    "ruby",
+    "sh",
+    "toml",
+    "yaml",
+    "lua",
+    "sql",
+];
+
+pub fn comments(language: &str) -> Option<Comments> {
+    let c_like = Comments {
+        line: &["//"],
+        block: Some(("/*", "*/")),
+    };
+    let hash = Comments {
+        line: &["#"],
+        block: None,
+    };
+    match language.to_lowercase().as_str() {
+        "c" | "cpp" | "csharp" | "go" | "java" | "javascript" | "js" | "kotlin" | "rust"
+        | "swift" | "ts" | "typescript" => Some(c_like),
+        "nu" | "python" | "ruby" | "sh" | "toml" | "yaml" => Some(hash),
+        "lua" => Some(Comments {
+            line: &["--"],
+            block: Some(("--[[", "]]")),
+        }),
+        "sql" => Some(Comments {
+            line: &["--"],
+            block: Some(("/*", "*/")),
+        }),
+        _ => None,
+    }
+}
+
+pub fn normalize(s: &str, comments: Option<&Comments>) -> String {
+    let stripped;
+    let s = match comments {
+        Some(c) => {
+            stripped = strip_comments(s, c);
+            stripped.as_str()
+        }
+        None => s,
+    };
+
+    let mut tokens: Vec<String> = vec![];
+    let mut ident = String::new();
+    for c in s.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            ident.push(c);
+            continue;
+        }
+        tokens.extend(split_identifier(&ident));
+        ident.clear();
+        if !c.is_whitespace() {
+            tokens.push(c.to_string());
+        }
+    }
+    tokens.extend(split_identifier(&ident));
+    tokens.join(" ")
+}
+
+/// `parseHTTPResponse_v2` -> `parse http response v2`.
+fn split_identifier(ident: &str) -> Vec<String> {
+    let chars: Vec<char> = ident.chars().collect();
+    let mut words = vec![];
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase()
+            && match prev {
+                Some(p) if p.is_lowercase() || p.is_ascii_digit() => true,
+                // the last capital of an acronym starts the next word
+                Some(p) if p.is_uppercase() => next.is_some_and(|n| n.is_lowercase()),
+                _ => false,
+            };
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Drops comments, keeping line breaks. Markers inside double-quoted string
+/// literals are left alone.
+fn strip_comments(s: &str, comments: &Comments) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    let mut in_string = false;
+    while let Some(c) = rest.chars().next() {
+        if in_string {
+            if c == '\\' {
+                let escaped: String = rest.chars().take(2).collect();
+                out.push_str(&escaped);
+                rest = &rest[escaped.len()..];
+                continue;
+            }
+            in_string = c != '"';
+        } else if c == '"' {
+            in_string = true;
+        } else if let Some((open, close)) =
+            comments.block.filter(|(open, _)| rest.starts_with(open))
+        {
+            rest = match rest[open.len()..].find(close) {
+                Some(end) => &rest[open.len() + end + close.len()..],
+                None => "",
+            };
+            out.push(' ');
+            continue;
+        } else if comments.line.iter().any(|m| rest.starts_with(m)) {
+            rest = rest.find('\n').map_or("", |end| &rest[end..]);
+            continue;
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
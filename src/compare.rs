@@ -0,0 +1,1709 @@
+use std::sync::Arc;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    ast::CellPath, record, Category, Config, Example, IntoInterruptiblePipelineData, LabeledError,
+    PipelineData, Signature, Span, Spanned, SyntaxShape, Value,
+};
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::list::list_algorithms;
+use crate::output::{
+    against_columns, all_field, batch, batch_row, by_columns, compute_all, in_columns, Verbose,
+};
+use crate::{
+    acronym, agreement, agrep, algorithm, algorithm_version, alignment, arrow, auto, banded, bytes,
+    canonical, chunked, coerce, compute, compute_tokens, custom, dates, edit_ops, edits,
+    expansions, glob, hirschberg, identifier, is_auto, mode, ngrams, normalize, numbers, pad,
+    parallel, paths, preprocess, profile, similarity, suffix_array, suffix_automaton, text,
+    tokenize, validate, visual, weights, AlgoParams, CodeCache, Format, Inputs, Mode, Orientation,
+    Pad, Score, StrSimilarityPlugin, Token,
+};
+
+pub struct StrSimilarity;
+
+impl PluginCommand for StrSimilarity {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity"
+    }
+
+    fn description(&self) -> &str {
+        "Compare strings to find similarity by algorithm"
+    }
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .optional(
+                "string",
+                SyntaxShape::OneOf(vec![SyntaxShape::String, SyntaxShape::Binary]),
+                "String to compare with, or binary to compare byte by byte",
+            )
+            .rest(
+                "rest",
+                SyntaxShape::CellPath,
+                "For a data structure input, score the strings at these cell paths, replacing them with their scores",
+            )
+            .switch(
+                "normalize",
+                "Normalize the results between 0 and 1",
+                Some('n'),
+            )
+            .switch("list", "List all available algorithms", Some('l'))
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute, or auto to pick one by what the strings look like",
+                Some('a'),
+            )
+            .named(
+                "custom",
+                SyntaxShape::Closure(Some(vec![SyntaxShape::String, SyntaxShape::String])),
+                "Score with a closure instead of an algorithm: it gets the two preprocessed strings and returns a similarity from 0 to 1",
+                None,
+            )
+            .named(
+                "custom-weight",
+                SyntaxShape::Number,
+                "Blend the --custom closure's score with the algorithm's instead of replacing it, the closure's weighing this much (0 to 1)",
+                None,
+            )
+            .switch(
+                "similarity",
+                "Report every algorithm as a similarity, higher meaning closer, converting distances",
+                None,
+            )
+            .switch(
+                "distance",
+                "Report every algorithm as a distance, 0 meaning identical, converting similarities",
+                None,
+            )
+            .switch("all", "Run all algorithms", Some('A'))
+            .switch(
+                "sort",
+                "With --all, order the algorithms by their normalized score, closest first",
+                None,
+            )
+            .named(
+                "agreement",
+                SyntaxShape::OneOf(vec![SyntaxShape::Number, SyntaxShape::Record(vec![])]),
+                "With --all and list input, report how often each two algorithms agree on which pairs match, at this normalized similarity threshold or a record of one per algorithm",
+                None,
+            )
+            .switch(
+                "verbose",
+                "With list input, add each row's compute time and the algorithm actually used, after --lenient and mode fallbacks",
+                None,
+            )
+            .named(
+                "threads",
+                SyntaxShape::Int,
+                "Number of threads scoring list elements and --all algorithms in parallel (default: one per core)",
+                None,
+            )
+            .switch(
+                "versioned",
+                "Return the score in a record with the algorithm's name and version, so stored scores can be invalidated when an implementation changes",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "affix-basis",
+                SyntaxShape::String,
+                "Length prefix and suffix scores are normalized by: shorter, longer (the default) or first, the input's",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "errors",
+                SyntaxShape::Int,
+                "Treat the string as a pattern (? and * wildcards) and find it in the input with at most this many errors",
+                None,
+            )
+            .named(
+                "pattern",
+                SyntaxShape::String,
+                "Regex whose first capture group (or whole match) is extracted from the input before comparing",
+                Some('p'),
+            )
+            .switch(
+                "glob",
+                "Treat the string as a glob pattern and score how closely the input path matches it",
+                Some('g'),
+            )
+            .named(
+                "mode",
+                SyntaxShape::String,
+                "Preprocess both strings before scoring: code (split identifiers, collapse whitespace), lines (compare line by line), words (compare word by word), acronym (compare a single word with the initials of several), visual (fold fullwidth and halfwidth forms so strings compare as they render), identifier (compare codes like SKUs position by position, forgiving adjacent swaps), date (read both as dates in any common format and score how close they are, falling back to the algorithm when either isn't a date) or path (score file paths by directory, stem and extension, with / and \\ alike)",
+                None,
+            )
+            .switch(
+                "check-digit",
+                "With --mode identifier, treat the last char of each code as a check digit, counted only when the rest of the codes match",
+                None,
+            )
+            .switch(
+                "ignore-extension",
+                "With --mode path, leave file extensions out of the score",
+                None,
+            )
+            .named(
+                "scheme",
+                SyntaxShape::String,
+                "With --mode identifier, the codes' format: isbn, ean or iban, whose checksums are validated; the result is then a record of the score and whether each code is valid",
+                None,
+            )
+            .named(
+                "tokenize",
+                SyntaxShape::String,
+                "What the algorithms compare: chars (the default), words, or ngram (overlapping runs of --ngram-size chars), mostly for the token-counting algorithms like jaccard and cosine",
+                None,
+            )
+            .named(
+                "ngram-size",
+                SyntaxShape::Int,
+                "With --tokenize ngram, chars per n-gram (default: 2)",
+                None,
+            )
+            .named(
+                "language",
+                SyntaxShape::String,
+                "With --mode code, strip comments of this language (rust, python, sql, ...)",
+                None,
+            )
+            .named(
+                "empty-score",
+                SyntaxShape::Number,
+                "Score to return when either string is empty, instead of treating it as having nothing in common",
+                None,
+            )
+            .switch(
+                "bytes",
+                "Compare strings byte by byte with hamming, levenshtein or jaccard, as binary input always is",
+                None,
+            )
+            .switch(
+                "coerce",
+                "Compare numbers, dates and other non-string input by how they are displayed instead of erroring",
+                None,
+            )
+            .switch(
+                "lenient",
+                "Fall back to levenshtein when --algorithm names no algorithm instead of erroring",
+                None,
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .switch(
+                "chunked",
+                "Score long texts approximately: split both into aligned chunks, score the chunk pairs in parallel and return their mean normalized similarity",
+                None,
+            )
+            .named(
+                "chunk-size",
+                SyntaxShape::Int,
+                "With --chunked, characters per chunk instead of splitting at blank lines",
+                None,
+            )
+            .named(
+                "aggregate",
+                SyntaxShape::String,
+                "With --chunked, how to combine chunk scores: weighted by chunk length (default) or mean",
+                None,
+            )
+            .named(
+                "pad",
+                SyntaxShape::String,
+                "With -a hamming, pad the shorter string on the left or right to the longer one's length, reporting how much was added",
+                None,
+            )
+            .named(
+                "pad-char",
+                SyntaxShape::String,
+                "Character to pad with for --pad (default: space)",
+                None,
+            )
+            .switch(
+                "show-alignment",
+                "Return a record with the score and the strings aligned as levenshtein, damerau_levenshtein, hamming or smith_waterman aligns them, gaps as - and a marker line between: | kept, x substituted, ~ transposed",
+                None,
+            )
+            .named(
+                "band",
+                SyntaxShape::Int,
+                "Compute levenshtein within this many cells of the diagonal, exact up to that many edits and a lower bound past them; with --detailed or --show-alignment, the cells kept when strings are too long to trace through the full table (default: 100)",
+                None,
+            )
+            .switch(
+                "detailed",
+                "Return a record with the score, its normalized value, the largest possible raw value, the edits for levenshtein, damerau_levenshtein and hamming, and the shared string for lcs, prefix and suffix algorithms",
+                None,
+            )
+            .switch(
+                "show",
+                "With -a prefix or -a suffix, return the shared prefix or suffix itself and its length",
+                None,
+            )
+            .named(
+                "profile",
+                SyntaxShape::String,
+                "Take any flag not given here from the settings saved under this name with `str similarity profile save`",
+                None,
+            )
+            .named(
+                "config",
+                SyntaxShape::OneOf(vec![SyntaxShape::Record(vec![]), SyntaxShape::Filepath]),
+                "Take any flag not given here from this record of long flag names and values, or from the nuon or json file holding one",
+                None,
+            )
+            .named(
+                "preset",
+                SyntaxShape::String,
+                "Take any flag not given here or by --profile from a built-in preset: loose, standard or strict",
+                None,
+            )
+            .named(
+                "mask",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Replace volatile tokens in both strings with placeholders before anything else: uuids, timestamps, ips, hex, numbers",
+                None,
+            )
+            .named(
+                "mask-pattern",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Also replace matches of these regular expressions with a placeholder, like --mask",
+                None,
+            )
+            .named(
+                "preprocess",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Steps run in order on both strings before anything else: lowercase, strip-accents, strip-punctuation, collapse-spaces, trim, fold-compat, deleet",
+                None,
+            )
+            .switch(
+                "ignore-case",
+                "Lowercase both strings before scoring, like --preprocess [lowercase]",
+                None,
+            )
+            .switch(
+                "trim",
+                "Trim whitespace from both ends of both strings before scoring",
+                None,
+            )
+            .switch(
+                "collapse-whitespace",
+                "Squeeze runs of whitespace in both strings into one space before scoring",
+                None,
+            )
+            .switch(
+                "strip-accents",
+                "Drop diacritics from both strings before scoring, reading letters like ø and ł as o and l",
+                None,
+            )
+            .switch(
+                "strip-invisible",
+                "Drop soft hyphens, zero width spaces and joiners and byte order marks from both strings before scoring",
+                None,
+            )
+            .switch(
+                "fold-compat",
+                "Compare what a human reads: apply NFKC to both strings, turning ligatures, superscripts and roman numerals into plain letters and digits, and fold typographic quotes and dashes",
+                None,
+            )
+            .named(
+                "expansions",
+                SyntaxShape::Any,
+                "Abbreviations to expand in both strings before scoring, as a record like {St: Street, Dr: [Doctor Drive]} or a table with abbreviation and expansion columns; of several expansions the best-scoring one is used",
+                None,
+            )
+            .named(
+                "token-weights",
+                SyntaxShape::Record(vec![]),
+                "With --mode words and a token-counting algorithm, how much each listed word counts, e.g. {inc: 0.2, the: 0.1}; other words count 1",
+                None,
+            )
+            .switch(
+                "token-alignment",
+                "With --mode words, list which words of each string matched which, with their scores, and which were left unmatched",
+                None,
+            )
+            .switch(
+                "numbers-as-words",
+                "Spell out every number in both strings before scoring, so 2nd and Second match",
+                None,
+            )
+            .switch(
+                "words-as-numbers",
+                "Write every spelled-out number in both strings in digits before scoring",
+                None,
+            )
+            .named(
+                "as-column",
+                SyntaxShape::String,
+                "With a cell path or --columns, keep the column and add the score in a new column of this name instead",
+                None,
+            )
+            .switch(
+                "include-inputs",
+                "Add the strings each row compared as left_input and right_input, for results read apart from their input",
+                None,
+            )
+            .switch(
+                "include-normalized",
+                "Like --include-inputs, with the strings as preprocessing left them",
+                None,
+            )
+            .named(
+                "preview",
+                SyntaxShape::Int,
+                "With --include-inputs or --include-normalized, cut inputs longer than this many characters short with an ellipsis",
+                None,
+            )
+            .switch(
+                "arrow-friendly",
+                "Give every row the same columns, each of one type (numbers all floats where any is), for polars into-df; collects a streamed input first",
+                None,
+            )
+            .named(
+                "columns",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Score each row of a table by two of its columns, the first against the second, adding a score column",
+                None,
+            )
+            .named(
+                "against-columns",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Score every input string against each of these targets instead of the string argument, giving a table with a column of scores per target",
+                None,
+            )
+            .named(
+                "common-substrings",
+                SyntaxShape::Int,
+                "List every maximal substring of at least this many chars that both strings contain, instead of a score",
+                None,
+            )
+            .switch(
+                "trivially-equal",
+                "Return which of case, accents, punctuation and whitespace have to be normalized for the strings to be identical, or none if that is not enough",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Compare two strings for similarity".into(),
+                example: "'nutshell' | str similarity 'nushell'".into(),
+                result: None,
+            },
+            Example {
+                description:
+                    "Compare two strings for similarity and normalize the output value".into(),
+                example: "'nutshell' | str similarity -n 'nushell'".into(),
+                result: None,
+            },
+            Example {
+                description: "Compare two strings for similarity using a specific algorithm"
+                    .into(),
+                example: "'nutshell' | str similarity 'nushell' -a levenshtein".into(),
+                result: None,
+            },
+            Example {
+                description: "List all the included similarity algorithms".into(),
+                example: "str similarity 'nu' --list".into(),
+                result: None,
+            },
+            Example {
+                description: "Compare two strings for similarity with all algorithms".into(),
+                example: "'nutshell' | str similarity 'nushell' -A".into(),
+                result: None,
+            },
+            Example {
+                description: "Compare two strings for similarity with all algorithms and normalize the output value".into(),
+                example: "'nutshell' | str similarity 'nushell' -A -n".into(),
+                result: None,
+            },
+            Example {
+                description: "Rank all algorithms on one scale, closest first",
+                example: "'nutshell' | str similarity 'nushell' -A -n --similarity | sort-by distance --reverse".into(),
+                result: None,
+            },
+            Example {
+                description: "Get levenshtein's similarity, the length minus the distance",
+                example: "'nutshell' | str similarity 'nushell' -a levenshtein --similarity",
+                result: Some(Value::test_int(7)),
+            },
+            Example {
+                description: "Keep the algorithm version next to a stored score",
+                example: "'nutshell' | str similarity 'nushell' -a jarw --versioned",
+                result: None,
+            },
+            Example {
+                description: "Fail on deprecated algorithm names, as a CI script would",
+                example: "'nutshell' | str similarity 'nushell' -a lcsseq --strict-names",
+                result: None,
+            },
+            Example {
+                description: "Only count characters within two positions of each other as jaro matches",
+                example: "'nutshell' | str similarity 'nushell' -a jaro --jaro-window 2",
+                result: None,
+            },
+            Example {
+                description: "Find where a wildcard pattern occurs in the input, allowing one typo",
+                example: "'error in modul foo.rs' | str similarity 'module ?*.rs' --errors 1",
+                result: None,
+            },
+            Example {
+                description: "Score only the order id at the end of the line, tolerating typos",
+                example: "'shipped order #AB-1234X' | str similarity 'AB-1243X' --pattern '#(\\S+)$'",
+                result: None,
+            },
+            Example {
+                description: "Score real files against a mistyped path pattern",
+                example: "ls **/*.rs | get name | each { str similarity 'src/mian.rs' --glob }",
+                result: None,
+            },
+            Example {
+                description: "Compare code snippets regardless of naming style, whitespace and comments",
+                example: "'let userId = getId(); // fetch' | str similarity 'let user_id = get_id();' --mode code --language js",
+                result: Some(Value::test_int(0)),
+            },
+            Example {
+                description: "Count the lines that differ between two files",
+                example: "open old.txt | str similarity (open new.txt) --mode lines",
+                result: None,
+            },
+            Example {
+                description: "Score with the settings saved in a profile",
+                example: "'Zoë Smith' | str similarity 'zoe  smith' --profile names",
+                result: None,
+            },
+            Example {
+                description: "Compare what a human reads rather than code points",
+                example: "'ﬁle Ⅻ x²' | str similarity 'file XII x2' --fold-compat",
+                result: Some(Value::test_int(0)),
+            },
+            Example {
+                description: "Compare log messages regardless of the ids and counts in them",
+                example: "'job 3fa9c2d1e failed after 3 retries' | str similarity 'job 77b0e4a1f failed after 5 retries' --mask [hex numbers]",
+                result: Some(Value::test_int(0)),
+            },
+            Example {
+                description: "Ignore case, accents and extra spaces",
+                example: "'Bjørn  Olsen' | str similarity 'bjorn olsen' --ignore-case --strip-accents --collapse-whitespace",
+                result: Some(Value::test_int(0)),
+            },
+            Example {
+                description: "Score the lines of a huge file as they are read, stopping at the first close one",
+                example: "open big.txt | lines | str similarity 'query' -a jaro_winkler | where score > 0.9 | first",
+                result: None,
+            },
+            Example {
+                description: "Compare the character bigrams two strings share",
+                example: "'night' | str similarity 'nacht' -a jaccard --tokenize ngram --ngram-size 2",
+                result: Some(Value::test_float(1.0 / 7.0)),
+            },
+            Example {
+                description: "Compare fullwidth and halfwidth text as it renders",
+                example: "'ＡＢＣ　ｶﾞｷﾞ' | str similarity 'ABC ガギ' --mode visual",
+                result: Some(Value::test_int(0)),
+            },
+            Example {
+                description: "Read leetspeak and ignore case before comparing",
+                example: "'H4X0R' | str similarity 'haxor' --preprocess [lowercase deleet]",
+                result: Some(Value::test_int(0)),
+            },
+            Example {
+                description: "Expand street abbreviations before comparing addresses",
+                example: "'12 Main St.' | str similarity '12 Main Street' --expansions {St: Street, Dr: [Doctor Drive]}",
+                result: Some(Value::test_int(0)),
+            },
+            Example {
+                description: "See which words of two names correspond",
+                example: "'Smith John Q' | str similarity 'Jon Smyth' --mode words -a jaro_winkler --token-alignment",
+                result: None,
+            },
+            Example {
+                description: "Let legal suffixes count for less when comparing company names",
+                example: "'Acme Widgets Inc' | str similarity 'Acme Widgets LLC' --mode words -a jaccard --token-weights {inc: 0.1, llc: 0.1}",
+                result: None,
+            },
+            Example {
+                description: "Treat numbers in digits and in words alike",
+                example: "'2nd Ave' | str similarity 'Second Ave' --words-as-numbers",
+                result: Some(Value::test_int(0)),
+            },
+            Example {
+                description: "Match an acronym against what it stands for",
+                example: "'NASA' | str similarity 'National Aeronautics and Space Administration' --mode acronym -a jaro_winkler",
+                result: Some(Value::test_int(1)),
+            },
+            Example {
+                description: "Treat a missing value as unknown rather than as a total mismatch",
+                example: "'' | str similarity nushell -a jaro --empty-score 0.5",
+                result: Some(Value::test_float(0.5)),
+            },
+            Example {
+                description: "Favor strings that start alike, whatever the algorithm",
+                example: "'nutshell' | str similarity 'nushell' -a sorensen_dice -n --prefix-boost [0.1 4]",
+                result: None,
+            },
+            Example {
+                description: "Ignore how often each letter repeats",
+                example: "'aaab' | str similarity 'ab' -a jaccard --multiset false",
+                result: Some(Value::test_int(1)),
+            },
+            Example {
+                description: "Ask how much of the input the argument covers, ignoring the argument's extra tokens",
+                example: "'ab' | str similarity 'aaab' -a tversky --params {alpha: 1 beta: 0}",
+                result: Some(Value::test_int(1)),
+            },
+            Example {
+                description: "Compare a number with a string",
+                example: "12345 | str similarity '12354' --coerce",
+                result: Some(Value::test_int(2)),
+            },
+            Example {
+                description: "Compare two long reports paragraph by paragraph",
+                example: "open draft.md | str similarity (open final.md) -a ratcliff_obershelp --chunked",
+                result: None,
+            },
+            Example {
+                description: "Compare numbers of different lengths digit by digit, from the right",
+                example: "'1234' | str similarity '91234' -a hamming --pad left --pad-char 0",
+                result: Some(Value::test_record(record! {
+                    "distance" => Value::test_int(1),
+                    "padding" => Value::test_int(1),
+                })),
+            },
+            Example {
+                description: "Score how much of a typed abbreviation starts a longer name",
+                example: "'config' | str similarity 'configuration' -a prefix -n --affix-basis shorter",
+                result: Some(Value::test_int(1)),
+            },
+            Example {
+                description: "Find the common root of two file names",
+                example: "'report_2024_q1.csv' | str similarity 'report_2024_q2.csv' -a prefix --show",
+                result: Some(Value::test_record(record! {
+                    "prefix" => Value::test_string("report_2024_q"),
+                    "length" => Value::test_int(13),
+                })),
+            },
+            Example {
+                description: "Explain why two strings are basically the same",
+                example: "'Café  au lait' | str similarity 'cafe au lait' --trivially-equal",
+                result: Some(Value::test_list(vec![
+                    Value::test_string("case"),
+                    Value::test_string("accents"),
+                    Value::test_string("whitespace"),
+                ])),
+            },
+            Example {
+                description: "Compare several strings at once",
+                example: "['nushell' 'fishshell' 'powershell'] | str similarity 'nutshell'",
+                result: None,
+            },
+            Example {
+                description: "Score a column of a table in place",
+                example: "open users.csv | str similarity 'john smith' name --algorithm jaro",
+                result: None,
+            },
+            Example {
+                description: "Keep the column and add its scores next to it",
+                example: "open users.csv | str similarity 'john smith' name --algorithm jaro --as-column name_score",
+                result: None,
+            },
+            Example {
+                description: "Rank candidates against several targets side by side",
+                example: "[kitten sitting mitten] | str similarity --against-columns [sitting smitten] -n",
+                result: None,
+            },
+            Example {
+                description: "List the boilerplate two documents share",
+                example: "open a.md | str similarity (open b.md) --common-substrings 40",
+                result: None,
+            },
+            Example {
+                description: "Compare container numbers, forgiving a swapped pair of digits",
+                example: "'MSKU 123456-7' | str similarity 'MSKU1234657' --mode identifier --check-digit",
+                result: None,
+            },
+            Example {
+                description: "Compare dates written in different formats",
+                example: "'2024-03-05' | str similarity 'March 5, 2024' --mode date",
+                result: Some(Value::test_int(1)),
+            },
+            Example {
+                description: "Compare file paths part by part, whatever the separators",
+                example: "'docs/Report-final.PDF' | str similarity 'archive\\docs\\report_final.pdf' --mode path -a jaro_winkler",
+                result: None,
+            },
+            Example {
+                description: "Match an ISBN-10 with its ISBN-13 and check both are valid",
+                example: "'0-306-40615-2' | str similarity '978-0-306-40615-7' --mode identifier --scheme isbn",
+                result: Some(Value::test_record(record! {
+                    "score" => Value::test_int(1),
+                    "input_valid" => Value::test_bool(true),
+                    "argument_valid" => Value::test_bool(true),
+                })),
+            },
+            Example {
+                description: "Check whether two names sound alike",
+                example: "'Smith' | str similarity 'Smyth' -a soundex",
+                result: Some(Value::test_int(1)),
+            },
+            Example {
+                description: "Count the edits behind a levenshtein distance",
+                example: "'kitten' | str similarity 'sitting' --detailed | get edit_ops",
+                result: None,
+            },
+            Example {
+                description: "Let the plugin pick the algorithm, and see which it picked",
+                example: "'Smith, John' | str similarity 'John Smith' -a auto --detailed | get algorithm",
+                result: Some(Value::test_string("token_sort")),
+            },
+            Example {
+                description: "Version an auto-picked detailed score: the algorithm that ran goes outside, the choice inside",
+                example: "'Smith, John' | str similarity 'John Smith' -a auto --detailed --versioned | select algorithm score.algorithm | values",
+                result: Some(Value::test_list(vec![
+                    Value::test_string("levenshtein"),
+                    Value::test_string("token_sort"),
+                ])),
+            },
+            Example {
+                description: "Ask whether two names are the same, with curated settings",
+                example: "'Café Zürich' | str similarity 'cafe zurich' --preset standard",
+                result: Some(Value::test_int(1)),
+            },
+            Example {
+                description: "Find the rows that take longest to score",
+                example: "open docs.txt | lines | str similarity 'query' -a smith_waterman --verbose | sort-by elapsed --reverse | first 5",
+                result: None,
+            },
+            Example {
+                description: "Run every algorithm on a long list with four threads",
+                example: "open names.txt | lines | str similarity 'jon smith' --all --threads 4",
+                result: None,
+            },
+            Example {
+                description: "Keep the strings each row compared, as preprocessing left them",
+                example: "[Nushell] | str similarity 'nutshell' --ignore-case --include-normalized",
+                result: Some(Value::test_list(vec![Value::test_record(record! {
+                    "value" => Value::test_string("Nushell"),
+                    "left_input" => Value::test_string("nushell"),
+                    "right_input" => Value::test_string("nutshell"),
+                    "score" => Value::test_int(1),
+                })])),
+            },
+            Example {
+                description: "Score the actual values of a table against the expected ones",
+                example: "[[expected actual]; [kitten sitting]] | str similarity --columns [expected actual]",
+                result: Some(Value::test_list(vec![Value::test_record(record! {
+                    "expected" => Value::test_string("kitten"),
+                    "actual" => Value::test_string("sitting"),
+                    "score" => Value::test_int(3),
+                })])),
+            },
+            Example {
+                description: "See how levenshtein lines the strings up",
+                example: "'nutshell' | str similarity 'nushell' --show-alignment",
+                result: Some(Value::test_record(record! {
+                    "score" => Value::test_int(1),
+                    "top" => Value::test_string("nutshell"),
+                    "markers" => Value::test_string("|| |||||"),
+                    "bottom" => Value::test_string("nu-shell"),
+                    "approximate" => Value::test_bool(false),
+                })),
+            },
+            Example {
+                description: "Compute levenshtein up to two edits, past which the distance is a lower bound",
+                example: "'kitten' | str similarity 'sitting' --band 2",
+                result: Some(Value::test_record(record! {
+                    "score" => Value::test_int(3),
+                    "exact" => Value::test_bool(false),
+                })),
+            },
+            Example {
+                description: "Trace the edits between two long files within 20 cells of the diagonal",
+                example: "open old.txt | str similarity (open new.txt) --detailed --band 20 | get edit_ops approximate",
+                result: None,
+            },
+            Example {
+                description: "Count the bytes that differ between two binary values",
+                example: "0x[de ad be ef] | str similarity 0x[de ad be ee] -a hamming",
+                result: Some(Value::test_int(1)),
+            },
+            Example {
+                description: "See which algorithms rate two strings closest, with raw and normalized scores side by side",
+                example: "'nutshell' | str similarity 'nushell' --all --sort | first 5",
+                result: None,
+            },
+            Example {
+                description: "See which algorithms call the same names a match, to keep only ones that disagree",
+                example: "open names.txt | lines | str similarity 'jon smith' --all --agreement 0.8",
+                result: None,
+            },
+            Example {
+                description: "Score with your own logic: 1 for part numbers equal but for leading zeros, else 0",
+                example: "[ACME-001 ACME-002] | str similarity 'ACME-1' --custom {|a, b| if ($a | str replace -r '-0+' '-') == ($b | str replace -r '-0+' '-') { 1 } else { 0 } }",
+                result: None,
+            },
+            Example {
+                description: "Nudge jaro_winkler's scores with a bonus for sharing the same year",
+                example: "'Summer Sale 2023' | str similarity 'summer sale 2024' -a jaro_winkler -n --custom-weight 0.3 --custom {|a, b| if ($a | parse -r '(\\d{4})') == ($b | parse -r '(\\d{4})') { 1 } else { 0 } }",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let mut applied = profile::apply(plugin, engine, call, &PluginCommand::signature(self))?;
+        validate::check(&mut applied, call)?;
+        let call = applied;
+        let settings = Settings::from_call(plugin, engine, &call)?;
+        let pool = parallel::pool(&call)?;
+        let whole = call.has_flag("arrow-friendly")?
+            || call.has_flag("against-columns")?
+            || call.has_flag("columns")?
+            || call.get_flag::<Value>("agreement")?.is_some()
+            || !call.rest::<CellPath>(1)?.is_empty();
+        match input {
+            // Elements are scored a thread's worth at a time as they are
+            // pulled downstream, so memory stays flat and `first` or `take`
+            // stop the work early.
+            PipelineData::ListStream(stream, _) if !whole => {
+                let span = stream.span();
+                let all = all_field(&call)?;
+                let coerce = settings.coerce.as_deref();
+                let verbose = Verbose::from_call(&call, coerce)?;
+                let inputs = match Inputs::from_call(&call)? {
+                    Some(i) => Some(i.against(&call, coerce)?),
+                    None => None,
+                };
+                let chunk = parallel::threads(pool.as_ref());
+                let mut elements = stream.into_iter();
+                let chunks = std::iter::from_fn(move || {
+                    let chunk: Vec<Value> = elements.by_ref().take(chunk).collect();
+                    (!chunk.is_empty()).then_some(chunk)
+                });
+                Ok(chunks
+                    .flat_map(move |chunk| {
+                        let compare = |v: &Value| StrSimilarity.compare(&settings, &call, v);
+                        parallel::install(pool.as_ref(), || {
+                            chunk
+                                .par_iter()
+                                .map(|v| {
+                                    batch_row(v, all, verbose.as_ref(), inputs.as_ref(), compare)
+                                        .unwrap_or_else(|e| Value::error(e.into(), span))
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                    })
+                    .into_pipeline_data(span, engine.signals().clone()))
+            }
+            input => {
+                let input = input.into_value(call.head)?;
+                let scores =
+                    parallel::install(pool.as_ref(), || self.compare(&settings, &call, &input))?;
+                let scores = match scores {
+                    Value::List {
+                        vals,
+                        internal_span,
+                    } => Value::list(arrow::shape(&call, vals)?, internal_span),
+                    scores => scores,
+                };
+                Ok(PipelineData::Value(scores, None))
+            }
+        }
+    }
+}
+
+/// The flags [`StrSimilarity::score`] reads, parsed once per call rather
+/// than for every element of a stream: regexes compiled, preprocessing
+/// built and the algorithm checked up front.
+struct Settings {
+    cache: CodeCache,
+    coerce: Option<Arc<Config>>,
+    custom: Option<Arc<custom::Custom>>,
+    list: bool,
+    /// The algorithm, or `None` when `-a auto` picks one per pair.
+    algorithm: Option<String>,
+    normalize: bool,
+    all: bool,
+    orientation: Option<Orientation>,
+    versioned: bool,
+    glob: bool,
+    params: AlgoParams,
+    errors: Option<Spanned<i64>>,
+    pattern: Option<Regex>,
+    mode: Option<Mode>,
+    ignore_extension: bool,
+    check_digit: bool,
+    scheme: Option<identifier::Scheme>,
+    empty_score: Option<f64>,
+    trivially_equal: bool,
+    pad: Option<Pad>,
+    chunking: Option<chunked::Chunking>,
+    common: Option<usize>,
+    pipeline: preprocess::Pipeline,
+    expansions: Option<expansions::Expansions>,
+    token_weights: Option<weights::TokenWeights>,
+    token_alignment: bool,
+    number_style: Option<numbers::Style>,
+    show: bool,
+    sort: bool,
+    detailed: bool,
+    show_alignment: bool,
+    band: Option<usize>,
+    format: Format,
+}
+
+impl Settings {
+    fn from_call(
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<Self, LabeledError> {
+        let custom = custom::Custom::from_call(engine, call)?;
+        let list = call.has_flag("list")?;
+        let algorithm = match list || is_auto(call)? {
+            true => None,
+            false => Some(algorithm(call)?),
+        };
+        let pattern = match call.get_flag::<Spanned<String>>("pattern")? {
+            Some(p) => Some(Regex::new(&p.item).map_err(|e| {
+                LabeledError::new("invalid pattern")
+                    .with_label(e.to_string(), p.span)
+                    .with_help("--pattern takes a regular expression; use --glob for wildcards")
+            })?),
+            None => None,
+        };
+        let common = match call.get_flag::<Spanned<i64>>("common-substrings")? {
+            Some(m) if m.item < 1 => {
+                return Err(LabeledError::new("invalid minimum length")
+                    .with_label("must be at least 1", m.span)
+                    .with_help("e.g. --common-substrings 20 to find shared boilerplate"));
+            }
+            Some(m) => Some(m.item as usize),
+            None => None,
+        };
+        Ok(Settings {
+            cache: plugin.preprocessed.clone(),
+            coerce: coerce(call, engine)?,
+            params: AlgoParams {
+                custom: custom.clone(),
+                ..AlgoParams::from_call(call)?
+            },
+            custom,
+            list,
+            algorithm,
+            normalize: call.has_flag("normalize")?,
+            all: call.has_flag("all")?,
+            orientation: Orientation::from_call(call)?,
+            versioned: call.has_flag("versioned")?,
+            glob: call.has_flag("glob")?,
+            errors: call.get_flag("errors")?,
+            pattern,
+            mode: tokenize(call, mode(call)?)?,
+            ignore_extension: call.has_flag("ignore-extension")?,
+            check_digit: call.has_flag("check-digit")?,
+            scheme: identifier::scheme(call)?,
+            empty_score: call.get_flag("empty-score")?,
+            trivially_equal: call.has_flag("trivially-equal")?,
+            pad: pad(call)?,
+            chunking: chunked::Chunking::from_call(call)?,
+            common,
+            pipeline: preprocess::Pipeline::from_call(call)?,
+            expansions: expansions::Expansions::from_call(call)?,
+            token_weights: weights::TokenWeights::from_call(call)?,
+            token_alignment: call.has_flag("token-alignment")?,
+            number_style: numbers::Style::from_call(call)?,
+            show: call.has_flag("show")?,
+            sort: call.has_flag("sort")?,
+            detailed: call.has_flag("detailed")?,
+            show_alignment: call.has_flag("show-alignment")?,
+            band: band(call)?,
+            format: Format::from_call(call)?,
+        })
+    }
+}
+
+impl StrSimilarity {
+    /// The scores for a value of the input: a table with `--against-columns`,
+    /// the rows with the score of their two `--columns` added, the value
+    /// with scores at the cell paths given, a row per element of a list, or
+    /// else the score of one string.
+    fn compare(
+        &self,
+        settings: &Settings,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let coerce = settings.coerce.as_deref();
+        let custom = settings.custom.as_ref();
+        if let Some(columns) = call.get_flag::<Spanned<Vec<String>>>("columns")? {
+            return by_columns(call, input, columns, |row_call, a| {
+                let score = self.score(settings, row_call, a)?;
+                custom::check(custom)?;
+                Ok(score)
+            });
+        }
+        if let Some(targets) = call.get_flag::<Spanned<Vec<String>>>("against-columns")? {
+            return against_columns(call, input, targets, custom);
+        }
+        let paths: Vec<CellPath> = call.rest(1)?;
+        if !paths.is_empty() {
+            // each cell is compared on its own, without the cell paths
+            let mut cell_call = call.clone();
+            cell_call.positional.truncate(1);
+            return in_columns(call, input, &paths, |v| {
+                self.compare(settings, &cell_call, v)
+            });
+        }
+        if call.get_flag::<Value>("agreement")?.is_some() {
+            return agreement::report(call, input, coerce);
+        }
+        if let Value::List { vals, .. } = input {
+            let all = all_field(call)?;
+            let verbose = Verbose::from_call(call, coerce)?;
+            let inputs = match Inputs::from_call(call)? {
+                Some(i) => Some(i.against(call, coerce)?),
+                None => None,
+            };
+            return batch(
+                vals,
+                all,
+                verbose.as_ref(),
+                inputs.as_ref(),
+                input.span(),
+                |v| self.compare(settings, call, v),
+            );
+        }
+        let score = self.score(settings, call, input)?;
+        custom::check(custom)?;
+        Ok(score)
+    }
+
+    fn score(
+        &self,
+        settings: &Settings,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let Settings {
+            cache,
+            coerce,
+            custom,
+            pattern,
+            scheme,
+            pad,
+            chunking,
+            pipeline,
+            expansions,
+            token_weights,
+            ..
+        } = settings;
+        let coerce = coerce.as_deref();
+        if bytes::wanted(call, input)? {
+            if custom.is_some() {
+                return Err(LabeledError::new("--custom compares strings")
+                    .with_label("binary input", input.span())
+                    .with_help("convert the input with `decode`, or drop --custom"));
+            }
+            return compare_bytes(call, input);
+        }
+        let compare_to_str_optn: Option<Spanned<String>> = call.opt(0)?;
+        let compare_to_str = match compare_to_str_optn {
+            Some(p) => p,
+            None => {
+                return Err(LabeledError::new("found nothing")
+                    .with_label("Expected a string as a parameter", call.head)
+                    .with_help("e.g. 'nutshell' | str similarity nushell"));
+            }
+        };
+        if settings.list {
+            return Ok(list_algorithms());
+        }
+        // `-a auto` picks per pair and reports normalized similarities, so
+        // rows scored by different algorithms still compare
+        let auto = match &settings.algorithm {
+            None => text(input, coerce).map(|t| auto::choose(&t, &compare_to_str.item)),
+            Some(_) => None,
+        };
+        let sim = match (auto, &settings.algorithm) {
+            (Some(choice), _) => choice.algorithm().to_string(),
+            (None, Some(algo)) => algo.clone(),
+            (None, None) => algorithm(call)?,
+        };
+        let normalize = auto.is_some() || settings.normalize;
+        let all = settings.all;
+        let orientation = match settings.orientation {
+            None if auto.is_some() => Some(Orientation::Similarity),
+            orientation => orientation,
+        };
+        let versioned = settings.versioned;
+        // a deprecated name's warnings go in this record, or the detailed
+        // one, and nowhere a bare score would change shape
+        let version = |score: Value| match versioned {
+            true => Value::record(
+                record! {
+                    "algorithm" => Value::string(canonical(&sim), input.span()),
+                    "algorithm_version" => Value::string(algorithm_version(&sim), input.span()),
+                    "score" => score,
+                },
+                input.span(),
+            ),
+            false => score,
+        };
+        // `-a auto` names its choice in the detailed record, inside the
+        // versioned one's score, which names the algorithm it ran
+        let chosen = |details: Value| match (auto, details) {
+            (Some(choice), Value::Record { val, internal_span }) => {
+                let mut record = val.into_owned();
+                record.insert("algorithm", Value::string(choice.name(), internal_span));
+                Value::record(record, internal_span)
+            }
+            (_, details) => details,
+        };
+        let glob = settings.glob;
+        let params = &settings.params;
+        let errors = &settings.errors;
+        let mode = match (settings.mode.clone(), auto) {
+            (None, Some(auto::Choice::Document)) => Some(Mode::Ngrams(auto::SHINGLE)),
+            (mode, _) => mode,
+        };
+        let identifier = matches!(mode, Some(Mode::Identifier));
+        let date = matches!(mode, Some(Mode::Date));
+        let path = matches!(mode, Some(Mode::Path));
+        let ignore_extension = settings.ignore_extension;
+        let check_digit = settings.check_digit;
+        let empty_score = settings.empty_score;
+        let trivially_equal = settings.trivially_equal;
+        let common = settings.common;
+        let token_alignment = settings.token_alignment;
+        let number_style = settings.number_style;
+        let show = settings.show;
+        let sort = settings.sort;
+        let detailed = settings.detailed;
+        let show_alignment = settings.show_alignment;
+        let band = settings.band;
+        let format = settings.format;
+        let details = Details {
+            normalize,
+            orientation,
+            format,
+            band,
+        };
+        let banded = band.filter(|_| !detailed && !show_alignment);
+        let input_span = input.span();
+
+        let ret_val = match text(input, coerce) {
+            Some(input_val) => {
+                let input_val = match &pattern {
+                    Some(re) => match extract(re, &input_val) {
+                        Some(captured) => captured,
+                        None => return Ok(Value::nothing(input_span)),
+                    },
+                    None => &*input_val,
+                };
+                let preprocessed = pipeline.apply(input_val);
+                let input_val = &*preprocessed;
+                let compare_to_str = Spanned {
+                    item: pipeline.apply(&compare_to_str.item).into_owned(),
+                    span: compare_to_str.span,
+                };
+                let sorted;
+                let (input_val, compare_to_str) = match auto {
+                    Some(auto::Choice::Sentence) => {
+                        sorted = auto::sort_words(input_val);
+                        let other = Spanned {
+                            item: auto::sort_words(&compare_to_str.item),
+                            span: compare_to_str.span,
+                        };
+                        (&*sorted, other)
+                    }
+                    _ => (input_val, compare_to_str),
+                };
+                let expanded;
+                let (input_val, compare_to_str) = match &expansions {
+                    Some(e) => {
+                        let score = |a: &str, b: &str| {
+                            let (a, b): (Vec<char>, Vec<char>) =
+                                (a.chars().collect(), b.chars().collect());
+                            similarity(&sim, &a, &b, params)
+                        };
+                        let (left, right) = e.apply(input_val, &compare_to_str.item, score);
+                        expanded = left;
+                        let other = Spanned {
+                            item: right,
+                            span: compare_to_str.span,
+                        };
+                        (&*expanded, other)
+                    }
+                    None => (input_val, compare_to_str),
+                };
+                let renumbered;
+                let (input_val, compare_to_str) = match number_style {
+                    Some(style) => {
+                        renumbered = numbers::normalize(input_val, style);
+                        let other = Spanned {
+                            item: numbers::normalize(&compare_to_str.item, style),
+                            span: compare_to_str.span,
+                        };
+                        (&*renumbered, other)
+                    }
+                    None => (input_val, compare_to_str),
+                };
+                let normalized;
+                let acronym;
+                let (input_val, compare_to_str) = match &mode {
+                    Some(Mode::Code(comments)) => {
+                        normalized = cache.normalize(input_val, comments.as_ref());
+                        let other = Spanned {
+                            item: cache
+                                .normalize(&compare_to_str.item, comments.as_ref())
+                                .to_string(),
+                            span: compare_to_str.span,
+                        };
+                        (&*normalized, other)
+                    }
+                    Some(Mode::Acronym) => {
+                        let score = |a: &[char], b: &[char]| similarity(&sim, a, b, params);
+                        match acronym::initials(input_val, &compare_to_str.item, score) {
+                            Some((left, right)) => {
+                                acronym = left;
+                                let other = Spanned {
+                                    item: right,
+                                    span: compare_to_str.span,
+                                };
+                                (&*acronym, other)
+                            }
+                            None => (input_val, compare_to_str),
+                        }
+                    }
+                    Some(Mode::Visual) => {
+                        normalized = visual::fold(input_val).into();
+                        let other = Spanned {
+                            item: visual::fold(&compare_to_str.item),
+                            span: compare_to_str.span,
+                        };
+                        (&*normalized, other)
+                    }
+                    _ => (input_val, compare_to_str),
+                };
+                let tokens = match mode {
+                    Some(Mode::Lines) => Some((
+                        edits::line_keys(input_val),
+                        edits::line_keys(&compare_to_str.item),
+                    )),
+                    Some(Mode::Words) => Some((
+                        input_val.split_whitespace().collect(),
+                        compare_to_str.item.split_whitespace().collect(),
+                    )),
+                    Some(Mode::Ngrams(n)) => Some((
+                        ngrams::ngrams(input_val, n),
+                        ngrams::ngrams(&compare_to_str.item, n),
+                    )),
+                    _ => None,
+                };
+                if trivially_equal {
+                    match normalize::trivially_equal(input_val, &compare_to_str.item) {
+                        Some(steps) => Value::list(
+                            steps
+                                .into_iter()
+                                .map(|s| Value::string(s, input_span))
+                                .collect(),
+                            input_span,
+                        ),
+                        None => Value::string("none", input_span),
+                    }
+                } else if let Some(min) = common {
+                    common_substrings(input_val, &compare_to_str.item, min, input_span)
+                } else if show {
+                    shared_affix(&sim, input_val, &compare_to_str.item, input_span)
+                } else if let Some(chunking) = &chunking {
+                    version(format.score(
+                        chunking.similarity(&sim, input_val, &compare_to_str.item, params),
+                        input_span,
+                    ))
+                } else if let Some(pad) = &pad {
+                    let mut c1: Vec<char> = compare_to_str.item.chars().collect();
+                    let mut c2: Vec<char> = input_val.chars().collect();
+                    let padding = pad.apply(&mut c1, &mut c2);
+                    Value::record(
+                        record! {
+                            "distance" => format.score(
+                                compute_tokens(&sim, &c1, &c2, params).get(normalize),
+                                input_span,
+                            ),
+                            "padding" => Value::int(padding as i64, input_span),
+                        },
+                        input_span,
+                    )
+                } else if let Some(k) = errors {
+                    if k.item < 0 {
+                        return Err(LabeledError::new("invalid error count")
+                            .with_label("errors must be zero or greater", k.span)
+                            .with_help("--errors 0 finds exact occurrences only"));
+                    }
+                    approximate_match(&compare_to_str.item, input_val, k.item as usize, input_span)
+                } else if glob {
+                    format.score(glob::score(&compare_to_str.item, input_val), input_span)
+                } else if identifier {
+                    let oriented = |score: f64| match orientation {
+                        Some(Orientation::Distance) => 1.0 - score,
+                        _ => score,
+                    };
+                    match scheme {
+                        Some(scheme) => {
+                            let (a, b) = (
+                                scheme.normalize(input_val),
+                                scheme.normalize(&compare_to_str.item),
+                            );
+                            Value::record(
+                                record! {
+                                    "score" => version(format.score(oriented(scheme.similarity(&a, &b)), input_span)),
+                                    "input_valid" => Value::bool(scheme.is_valid(&a), input_span),
+                                    "argument_valid" => Value::bool(scheme.is_valid(&b), input_span),
+                                },
+                                input_span,
+                            )
+                        }
+                        None => {
+                            let (a, b) = (
+                                identifier::normalize(input_val),
+                                identifier::normalize(&compare_to_str.item),
+                            );
+                            let score = identifier::similarity(&a, &b, check_digit);
+                            version(format.score(oriented(score), input_span))
+                        }
+                    }
+                } else if let Some(score) = date
+                    .then(|| dates::similarity(input_val, &compare_to_str.item))
+                    .flatten()
+                {
+                    version(format.score(
+                        match orientation {
+                            Some(Orientation::Distance) => 1.0 - score,
+                            _ => score,
+                        },
+                        input_span,
+                    ))
+                } else if path {
+                    let score = |a: &str, b: &str| {
+                        let (a, b): (Vec<char>, Vec<char>) =
+                            (a.chars().collect(), b.chars().collect());
+                        similarity(&sim, &a, &b, params)
+                    };
+                    let oriented = |score: f64| match orientation {
+                        Some(Orientation::Distance) => format.score(1.0 - score, input_span),
+                        _ => format.score(score, input_span),
+                    };
+                    paths::compare(
+                        input_val,
+                        &compare_to_str.item,
+                        ignore_extension,
+                        score,
+                        oriented,
+                        input_span,
+                    )
+                } else if let Some(score) = empty_score
+                    .filter(|_| !all && (input_val.is_empty() || compare_to_str.item.is_empty()))
+                {
+                    version(format.score(score, input_span))
+                } else if show_alignment {
+                    let (score, [top, markers, bottom], approximate) = match &tokens {
+                        Some((l1, l2)) => details.aligned(&sim, l1, l2, params),
+                        None => {
+                            let c1: Vec<char> = input_val.chars().collect();
+                            let c2: Vec<char> = compare_to_str.item.chars().collect();
+                            details.aligned(&sim, &c1, &c2, params)
+                        }
+                    };
+                    Value::record(
+                        record! {
+                            "score" => version(format.score(score, input_span)),
+                            "top" => Value::string(top, input_span),
+                            "markers" => Value::string(markers, input_span),
+                            "bottom" => Value::string(bottom, input_span),
+                            "approximate" => Value::bool(approximate, input_span),
+                        },
+                        input_span,
+                    )
+                } else if let Some(k) = banded {
+                    let (score, exact) = match &tokens {
+                        Some((l1, l2)) => banded_score(l1, l2, k),
+                        None => {
+                            let c1: Vec<char> = input_val.chars().collect();
+                            let c2: Vec<char> = compare_to_str.item.chars().collect();
+                            banded_score(&c1, &c2, k)
+                        }
+                    };
+                    let score = score.oriented(&sim, normalize, orientation);
+                    Value::record(
+                        record! {
+                            "score" => version(format.score(score, input_span)),
+                            "exact" => Value::bool(exact, input_span),
+                        },
+                        input_span,
+                    )
+                } else if let Some((l1, l2)) = tokens {
+                    if token_alignment {
+                        let score = |a: &str, b: &str| {
+                            let (a, b): (Vec<char>, Vec<char>) =
+                                (a.chars().collect(), b.chars().collect());
+                            similarity(&sim, &a, &b, params)
+                        };
+                        alignment::report(&l1, &l2, score, input_span)
+                    } else if let Some(w) = token_weights {
+                        version(format.score(w.score(&sim, &l1, &l2, params.multiset), input_span))
+                    } else if all {
+                        compute_all(&l1, &l2, orientation, versioned, sort, params, &format)?
+                    } else if detailed {
+                        version(chosen(details.of(&sim, &l1, &l2, params, input_span)))
+                    } else {
+                        version(format.score(
+                            compute_tokens(&sim, &l1, &l2, params).oriented(
+                                &sim,
+                                normalize,
+                                orientation,
+                            ),
+                            input_span,
+                        ))
+                    }
+                } else if all {
+                    let c1: Vec<char> = input_val.chars().collect();
+                    let c2: Vec<char> = compare_to_str.item.chars().collect();
+                    compute_all(&c1, &c2, orientation, versioned, sort, params, &format)?
+                } else if detailed {
+                    let c1: Vec<char> = input_val.chars().collect();
+                    let c2: Vec<char> = compare_to_str.item.chars().collect();
+                    version(chosen(details.of(&sim, &c1, &c2, params, input_span)))
+                } else {
+                    let score = compare_strings(
+                        &sim,
+                        compare_to_str,
+                        normalize,
+                        orientation,
+                        params,
+                        input_val,
+                    );
+                    version(format.score(score, input_span))
+                }
+            }
+            None => {
+                return Err(LabeledError::new(format!(
+                    "requires some input, got {}",
+                    input.get_type()
+                ))
+                .with_label("Expected something from pipeline", input.span())
+                .with_help(
+                    "pipe in the string to compare, or pass --coerce for numbers and dates",
+                ));
+            }
+        };
+
+        Ok(ret_val)
+    }
+}
+
+/// The maximal substrings `a` and `b` share, longest first, with where they
+/// first occur in each and how often.
+fn common_substrings(a: &str, b: &str, min: usize, span: Span) -> Value {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let rows = suffix_array::common_substrings(&a, &b, min)
+        .into_iter()
+        .map(|c| {
+            let text: String = a[c.left_start..c.left_start + c.len].iter().collect();
+            Value::record(
+                record! {
+                    "substring" => Value::string(text, span),
+                    "length" => Value::int(c.len as i64, span),
+                    "left_start" => Value::int(c.left_start as i64, span),
+                    "right_start" => Value::int(c.right_start as i64, span),
+                    "left_occurrences" => Value::int(c.left_count as i64, span),
+                    "right_occurrences" => Value::int(c.right_count as i64, span),
+                },
+                span,
+            )
+        })
+        .collect();
+    Value::list(rows, span)
+}
+
+/// The prefix (or, for the suffix algorithm, suffix) both strings share,
+/// with its length in chars.
+fn shared_affix(algo: &str, a: &str, b: &str, span: Span) -> Value {
+    let suffix = matches!(algo.to_lowercase().as_str(), "suf" | "suffix");
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let (shared, col): (String, _) = if suffix {
+        let n = a
+            .iter()
+            .rev()
+            .zip(b.iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count();
+        (a[a.len() - n..].iter().collect(), "suffix")
+    } else {
+        let n = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+        (a[..n].iter().collect(), "prefix")
+    };
+    let length = shared.chars().count() as i64;
+    Value::record(
+        record! {
+            col => Value::string(shared, span),
+            "length" => Value::int(length, span),
+        },
+        span,
+    )
+}
+
+fn compare_strings(
+    sim_algo: &str,
+    compare_to_str: Spanned<String>,
+    normalize: bool,
+    orientation: Option<Orientation>,
+    params: &AlgoParams,
+    input_val: &str,
+) -> f64 {
+    let compare_from = input_val;
+    let compare_to = compare_to_str.item;
+
+    compute(
+        sim_algo,
+        compare_from,
+        &compare_to,
+        normalize,
+        orientation,
+        params,
+    )
+}
+
+/// The score of binary input, or of strings with `--bytes`, comparing
+/// bytes.
+fn compare_bytes(call: &EvaluatedCall, input: &Value) -> Result<Value, LabeledError> {
+    let Some(other) = call.opt::<Value>(0)? else {
+        return Err(LabeledError::new("found nothing")
+            .with_label("Expected a string or binary as a parameter", call.head)
+            .with_help("e.g. 0x[ff 00] | str similarity 0x[ff 01] -a hamming"));
+    };
+    let sim = match is_auto(call)? {
+        // how text looks says nothing about bytes
+        true => "levenshtein".to_string(),
+        false => algorithm(call)?,
+    };
+    bytes::check_algorithm(call, &sim)?;
+    validate::check_bytes(call)?;
+    let (Some(b1), Some(b2)) = (bytes::of(input), bytes::of(&other)) else {
+        return Err(LabeledError::new(format!(
+            "--bytes requires string or binary input, got {}",
+            input.get_type()
+        ))
+        .with_label("Expected a string or binary from pipeline", input.span())
+        .with_help("--coerce doesn't apply to bytes; convert the input with `into binary`"));
+    };
+    let params = AlgoParams::from_call(call)?;
+    let normalize = call.has_flag("normalize")?;
+    let orientation = Orientation::from_call(call)?;
+    Ok(Format::from_call(call)?.score(
+        compute_tokens(&sim, b1, b2, &params).oriented(&sim, normalize, orientation),
+        input.span(),
+    ))
+}
+
+/// Reads `--band`, the cells either side of the diagonal that levenshtein
+/// fills, or that tracebacks keep for strings too long for the full table.
+fn band(call: &EvaluatedCall) -> Result<Option<usize>, LabeledError> {
+    match call.get_flag::<Spanned<i64>>("band")? {
+        Some(b) if b.item < 1 => Err(LabeledError::new("invalid band width")
+            .with_label("must be at least 1", b.span)
+            .with_help("a band of k finds distances of up to k edits exactly")),
+        Some(b) => Ok(Some(b.item as usize)),
+        None => Ok(None),
+    }
+}
+
+/// The levenshtein score of `s1` and `s2` within a band of `k`, and whether
+/// it's exact. Past the band the distance is only known to be at least
+/// `k + 1`, which is what's scored.
+fn banded_score<E: Eq>(s1: &[E], s2: &[E], k: usize) -> (Score, bool) {
+    let (distance, exact) = match banded::levenshtein(s1, s2, k) {
+        Some(d) => (d, true),
+        None => (k + 1, false),
+    };
+    let max = s1.len().max(s2.len());
+    let score = Score {
+        raw: distance as f64,
+        normalized: match max {
+            0 => 0.0,
+            max => distance as f64 / max as f64,
+        },
+        max: max as f64,
+    };
+    (score, exact)
+}
+
+/// How `--detailed` and `--show-alignment` report a score.
+struct Details {
+    normalize: bool,
+    orientation: Option<Orientation>,
+    format: Format,
+    band: Option<usize>,
+}
+
+impl Details {
+    /// The score of `a` on `s1` and `s2` with `--show-alignment`'s lines:
+    /// the tokens of each as `a` aligns them and the markers between, and
+    /// whether the alignment is approximate.
+    fn aligned<E: Token>(
+        &self,
+        a: &str,
+        s1: &[E],
+        s2: &[E],
+        params: &AlgoParams,
+    ) -> (f64, [String; 3], bool) {
+        let score = compute_tokens(a, s1, s2, params).oriented(a, self.normalize, self.orientation);
+        let (edits, approximate) = match edit_ops::path(a, s1, s2, self.band) {
+            Some(path) => (path.edits, path.approximate),
+            None => (vec![], false),
+        };
+        let lines = edit_ops::render(&edits, s1, s2, E::text, E::SEPARATOR);
+        (score, lines, approximate)
+    }
+
+    /// The score of algorithm `a` with how it came about: the normalized
+    /// score, the largest raw value possible for these inputs, the edits
+    /// behind an edit distance, approximate for strings too long for the
+    /// full table, and the shared text behind an lcs, prefix or suffix
+    /// score.
+    fn of<E: Token>(&self, a: &str, s1: &[E], s2: &[E], params: &AlgoParams, span: Span) -> Value {
+        let score = compute_tokens(a, s1, s2, params);
+        let ops = edit_ops::of(a, s1, s2, self.band);
+        let shared = match a.to_lowercase().as_str() {
+            "lcsubseq" | "longest_common_subsequence" => {
+                let common: Vec<E> = hirschberg::lcs(s1, s2)
+                    .into_iter()
+                    .map(|(i, _)| s1[i])
+                    .collect();
+                Some(E::text(&common))
+            }
+            "lcsubstr" | "longest_common_substring" => Some(E::text(
+                &s2[suffix_automaton::longest_common_substring(s1, s2)],
+            )),
+            "pre" | "prefix" => {
+                let n = s1.iter().zip(s2).take_while(|(x, y)| x == y).count();
+                Some(E::text(&s1[..n]))
+            }
+            "suf" | "suffix" => {
+                let n = s1
+                    .iter()
+                    .rev()
+                    .zip(s2.iter().rev())
+                    .take_while(|(x, y)| x == y)
+                    .count();
+                Some(E::text(&s1[s1.len() - n..]))
+            }
+            _ => None,
+        };
+        Value::record(
+            record! {
+                "score" => self.format.score(score.oriented(a, self.normalize, self.orientation), span),
+                "normalized" => self.format.score(score.oriented(a, true, self.orientation), span),
+                "max_distance" => self.format.raw(score.max, span),
+                "edit_ops" => ops.as_ref().map_or(Value::nothing(span), |(ops, _)| ops.record(span)),
+                "approximate" => Value::bool(ops.is_some_and(|(_, approximate)| approximate), span),
+                "shared" => shared.map_or(Value::nothing(span), |s| Value::string(s, span)),
+            },
+            span,
+        )
+    }
+}
+
+/// The first capture group of `re` in `input`, or the whole match when the
+/// pattern has no groups.
+fn extract<'a>(re: &Regex, input: &'a str) -> Option<&'a str> {
+    let caps = re.captures(input)?;
+    caps.get(1).or_else(|| caps.get(0)).map(|m| m.as_str())
+}
+
+fn approximate_match(pattern: &str, input_val: &str, k: usize, span: Span) -> Value {
+    let rec = match agrep::find(pattern, input_val, k) {
+        Some(m) => record! {
+            "matched" => Value::bool(true, span),
+            "errors" => Value::int(m.errors as i64, span),
+            "start" => Value::int(m.start as i64, span),
+            "end" => Value::int(m.end as i64, span),
+            "text" => Value::string(
+                input_val.chars().skip(m.start).take(m.end - m.start).collect::<String>(),
+                span,
+            ),
+        },
+        None => record! {
+            "matched" => Value::bool(false, span),
+            "errors" => Value::nothing(span),
+            "start" => Value::nothing(span),
+            "end" => Value::nothing(span),
+            "text" => Value::nothing(span),
+        },
+    };
+    Value::record(rec, span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarity)
+    }
+}
@@ -0,0 +1,241 @@
+// Completion of a typed prefix against a set of candidates.
+//
+// The candidates go into a trie, which is walked depth first while keeping
+// the row of the Levenshtein table between the typed prefix and the path to
+// the current node. The last cell of the row is how many typos the prefix
+// is from that path, so every candidate below a node where it is small
+// enough completes the prefix, and a subtree is abandoned as soon as every
+// cell of the row is too large. Shared prefixes are only compared once.
+
+use std::sync::Arc;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value,
+};
+
+use crate::format::Format;
+use crate::session;
+use crate::topk::TopK;
+use crate::{algorithm, similarity, AlgoParams, StrSimilarityPlugin};
+
+/// Share of the score that comes from how well the prefix matches, the rest
+/// coming from the similarity of the prefix to the whole candidate.
+const PREFIX_WEIGHT: f64 = 0.8;
+
+#[derive(Default)]
+struct Node {
+    children: Vec<(char, usize)>,
+    /// Candidates ending here.
+    ends: Vec<usize>,
+}
+
+struct Trie(Vec<Node>);
+
+impl Trie {
+    fn new<'a>(words: impl Iterator<Item = &'a [char]>) -> Self {
+        let mut nodes = vec![Node::default()];
+        for (i, word) in words.enumerate() {
+            let mut at = 0;
+            for &c in word {
+                at = match nodes[at].children.iter().find(|(k, _)| *k == c) {
+                    Some(&(_, next)) => next,
+                    None => {
+                        nodes.push(Node::default());
+                        let next = nodes.len() - 1;
+                        nodes[at].children.push((c, next));
+                        next
+                    }
+                };
+            }
+            nodes[at].ends.push(i);
+        }
+        Trie(nodes)
+    }
+
+    /// For every candidate, the fewest edits turning `prefix` into one of
+    /// its prefixes, if that is at most `max`.
+    fn typos(&self, prefix: &[char], max: usize, count: usize) -> Vec<Option<usize>> {
+        let mut found = vec![None; count];
+        let row: Vec<usize> = (0..=prefix.len()).collect();
+        let best = row[prefix.len()];
+        self.walk(0, &row, best, prefix, max, &mut found);
+        found
+    }
+
+    fn walk(
+        &self,
+        node: usize,
+        row: &[usize],
+        best: usize,
+        prefix: &[char],
+        max: usize,
+        found: &mut [Option<usize>],
+    ) {
+        if best <= max {
+            for &i in &self.0[node].ends {
+                found[i] = Some(best);
+            }
+        }
+        for &(c, child) in &self.0[node].children {
+            let mut next = vec![row[0] + 1];
+            for (j, &p) in prefix.iter().enumerate() {
+                let substitute = row[j] + usize::from(p != c);
+                next.push(substitute.min(row[j + 1] + 1).min(next[j] + 1));
+            }
+            let best = best.min(next[prefix.len()]);
+            // once the prefix has matched, everything below completes it
+            if best <= max || next.iter().any(|&d| d <= max) {
+                self.walk(child, &next, best, prefix, max, found);
+            }
+        }
+    }
+}
+
+pub struct StrSimilarityComplete;
+
+impl SimplePluginCommand for StrSimilarityComplete {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity complete"
+    }
+
+    fn description(&self) -> &str {
+        "Rank the completions of a typed prefix among a list of candidates, tolerating typos in the prefix"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_types(vec![
+                (Type::List(Box::new(Type::String)), Type::table()),
+                (Type::Nothing, Type::table()),
+            ])
+            .required("prefix", SyntaxShape::String, "What has been typed so far")
+            .named(
+                "session",
+                SyntaxShape::String,
+                "Complete from the candidates held under this name with `str similarity session --hold` instead of the input",
+                None,
+            )
+            .named(
+                "max-typos",
+                SyntaxShape::Int,
+                "Largest number of edits between the prefix and the start of a completion (default: 1)",
+                None,
+            )
+            .named(
+                "top",
+                SyntaxShape::Int,
+                "Number of completions to list (default: 10)",
+                None,
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm comparing the prefix with whole candidates to break ties",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Complete a branch name with a typo",
+                example: "[main master maintenance feature/login] | str similarity complete 'maim'",
+                result: None,
+            },
+            Example {
+                description: "Back a custom completer with held candidates",
+                example: "str similarity complete 'str simi' --session cmds --top 5 | get value",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let format = Format::from_call(call)?;
+        let span = call.head;
+        let prefix: Spanned<String> = call.req(0)?;
+        let max = match call.get_flag::<Spanned<i64>>("max-typos")? {
+            Some(m) if m.item < 0 => {
+                return Err(LabeledError::new("invalid typo count")
+                    .with_label("must be zero or greater", m.span)
+                    .with_help("--max-typos 0 completes exact prefixes only"));
+            }
+            Some(m) => m.item as usize,
+            None => 1,
+        };
+        let top = match call.get_flag::<Spanned<i64>>("top")? {
+            Some(k) if k.item < 1 => {
+                return Err(LabeledError::new("invalid top count")
+                    .with_label("need at least one completion", k.span)
+                    .with_help("the default is 10 completions"));
+            }
+            Some(k) => k.item as usize,
+            None => 10,
+        };
+        let algo = algorithm(call)?;
+        let params = AlgoParams::from_call(call)?;
+        let candidates = match call.get_flag::<Spanned<String>>("session")? {
+            Some(name) => plugin.sessions.held(&name)?,
+            None => Arc::new(session::held_strings(input)?),
+        };
+
+        let prefix: Vec<char> = prefix.item.chars().collect();
+        let trie = Trie::new(candidates.iter().map(|(_, chars)| chars.as_slice()));
+        let typos = trie.typos(&prefix, max, candidates.len());
+        let mut best = TopK::new(top);
+        for (i, d) in typos.into_iter().enumerate() {
+            let Some(d) = d else { continue };
+            let (s, chars) = &candidates[i];
+            let matched = 1.0 - d as f64 / prefix.len().max(1) as f64;
+            let whole = similarity(&algo, &prefix, chars, &params);
+            let score = PREFIX_WEIGHT * matched + (1.0 - PREFIX_WEIGHT) * whole;
+            best.push(score, i, (s, d));
+        }
+        let rows = best
+            .into_sorted()
+            .into_iter()
+            .map(|((s, d), score)| {
+                Value::record(
+                    record! {
+                        "value" => Value::string(s.clone(), span),
+                        "score" => format.score(score, span),
+                        "typos" => Value::int(d as i64, span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
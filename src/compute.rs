@@ -0,0 +1,553 @@
+// The compute layer: every algorithm dispatched by name over tokens, the
+// policies applied on top of textdistance's results (empty strings, sets of
+// tokens, the prefix boost), and the scores they give in both orientations.
+// The commands read their flags and shape their output around it.
+
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::LabeledError;
+use textdistance::{
+    Algorithm, Bag, Cosine, DamerauLevenshtein, EntropyNCD, Hamming, Jaccard, Jaro, JaroWinkler,
+    LCSSeq, Length, Levenshtein, Overlap, Prefix, RatcliffObershelp, Roberts, Sift4Common,
+    Sift4Simple, SmithWaterman, SorensenDice, Suffix, Tversky, YujianBo, LIG3, MLIPNS,
+};
+
+use crate::registry::{lookup, Id};
+use crate::{custom, flag_span, hirschberg, jaro, phonetic, suffix_automaton, AlgoParams};
+
+pub fn compute(
+    a: &str,
+    s1: &str,
+    s2: &str,
+    norm: bool,
+    orientation: Option<Orientation>,
+    params: &AlgoParams,
+) -> f64 {
+    let c1: Vec<char> = s1.chars().collect();
+    let c2: Vec<char> = s2.chars().collect();
+    compute_tokens(a, &c1, &c2, params).oriented(a, norm, orientation)
+}
+
+/// [`compute`] on strings that were already split into tokens (chars, or
+/// lines or words in the token modes), so batch callers can split each input once and reuse
+/// it across every pair. The algorithm runs once and both the raw and the
+/// normalized value are kept.
+pub fn compute_tokens<E: Token>(a: &str, s1: &[E], s2: &[E], params: &AlgoParams) -> Score {
+    let custom = params.custom.as_deref();
+    if let Some(custom) = custom.filter(|c| c.weight().is_none()) {
+        let s = custom.score(&E::text(s1), &E::text(s2));
+        return Score {
+            raw: s,
+            normalized: s,
+            max: 1.0,
+        };
+    }
+    let score = if !params.multiset && counts_tokens(a) {
+        let (d1, d2) = (distinct_tokens(s1), distinct_tokens(s2));
+        empty_policy(a, &d1, &d2, run_algorithm(a, &d1, &d2, params))
+    } else {
+        empty_policy(a, s1, s2, run_algorithm(a, s1, s2, params))
+    };
+    let score = match params.prefix_boost {
+        Some(boost) => boost_prefix(a, s1, s2, score, boost),
+        None => score,
+    };
+    match custom {
+        // the closure alone is a weight of 1, returned above
+        Some(custom) => {
+            let s = custom.score(&E::text(s1), &E::text(s2));
+            blend(a, score, s, custom.weight().unwrap_or(1.0))
+        }
+        None => score,
+    }
+}
+
+/// `score` blended with the `--custom` closure's similarity `s`, which
+/// makes up `weight` of the result. Like [`boost_prefix`], it moves the raw
+/// value only when the algorithm has no separate raw scale.
+fn blend(a: &str, score: Score, s: f64, weight: f64) -> Score {
+    let distance = is_distance(a);
+    let sim = if distance {
+        1.0 - score.normalized
+    } else {
+        score.normalized
+    };
+    let blended = (1.0 - weight) * sim + weight * s;
+    let normalized = if distance { 1.0 - blended } else { blended };
+    Score {
+        raw: if score.raw == score.normalized {
+            normalized
+        } else {
+            score.raw
+        },
+        normalized,
+        ..score
+    }
+}
+
+/// `[weight length]` from `--prefix-boost`, unless it could push a score
+/// past 1.
+pub fn prefix_boost(b: &[f64]) -> Option<(f64, usize)> {
+    let (weight, len) = match *b {
+        [w] => (w, 4.0),
+        [w, l] => (w, l),
+        _ => return None,
+    };
+    let valid = weight >= 0.0 && len >= 1.0 && len.fract() == 0.0 && weight * len <= 1.0;
+    valid.then_some((weight, len as usize))
+}
+
+/// Winkler's shared-prefix bonus on any algorithm: each shared leading
+/// token, up to `len`, closes `weight` of the remaining gap to a perfect
+/// score. Like [`empty_policy`], it moves the raw value only when the
+/// algorithm has no separate raw scale.
+fn boost_prefix<E: Eq>(
+    a: &str,
+    s1: &[E],
+    s2: &[E],
+    score: Score,
+    (weight, len): (f64, usize),
+) -> Score {
+    let shared = s1
+        .iter()
+        .zip(s2)
+        .take(len)
+        .take_while(|(x, y)| x == y)
+        .count();
+    let distance = is_distance(a);
+    let sim = if distance {
+        1.0 - score.normalized
+    } else {
+        score.normalized
+    };
+    let boosted = sim + shared as f64 * weight * (1.0 - sim);
+    let normalized = if distance { 1.0 - boosted } else { boosted };
+    Score {
+        raw: if score.raw == score.normalized {
+            normalized
+        } else {
+            score.raw
+        },
+        normalized,
+        ..score
+    }
+}
+
+/// Algorithms that compare how often each token occurs rather than their
+/// order, so repeated tokens weigh in unless `--multiset false` is given.
+pub fn counts_tokens(a: &str) -> bool {
+    lookup(a).is_some_and(|a| a.params.contains(&"multiset"))
+}
+
+/// `s` with repeated tokens dropped, keeping the first of each.
+fn distinct_tokens<E: Eq + Hash + Copy>(s: &[E]) -> Vec<E> {
+    let mut seen = HashSet::new();
+    s.iter().copied().filter(|e| seen.insert(*e)).collect()
+}
+
+/// How comparisons with empty strings score, for every algorithm: two empty
+/// strings are identical, and an empty string has nothing in common with a
+/// non-empty one. textdistance already agrees for most algorithms, but
+/// entropy_ncd and mlipns don't, so the normalized value is pinned here.
+/// Raw values are kept (e.g. levenshtein's is the other string's length)
+/// unless the algorithm has no separate raw scale.
+fn empty_policy<E>(a: &str, s1: &[E], s2: &[E], score: Score) -> Score {
+    let identical = match (s1.is_empty(), s2.is_empty()) {
+        (true, true) => true,
+        (false, false) => return score,
+        _ => false,
+    };
+    let normalized = if identical == is_distance(a) {
+        0.0
+    } else {
+        1.0
+    };
+    if score.raw == score.normalized {
+        Score {
+            raw: normalized,
+            normalized,
+            ..score
+        }
+    } else {
+        Score {
+            normalized,
+            ..score
+        }
+    }
+}
+
+#[rustfmt::skip]
+fn run_algorithm<E: Token>(a: &str, s1: &[E], s2: &[E], params: &AlgoParams) -> Score {
+    let id = lookup(a).map_or(Id::Levenshtein, |a| a.id);
+    match id {
+        Id::Bag => run(Bag::default(), s1, s2),
+        Id::Cosine => run(Cosine::default(), s1, s2),
+        Id::DamerauLevenshtein => run(DamerauLevenshtein::default(), s1, s2),
+        Id::EntropyNcd => run(EntropyNCD::default(), s1, s2),
+        Id::Hamming => run(Hamming::default(), s1, s2),
+        Id::Jaccard => run(Jaccard::default(), s1, s2),
+        Id::Jaro => if let Some(w) = params.jaro_window { Score::flat(jaro::jaro(s1, s2, w)) } else { run(Jaro::default(), s1, s2) },
+        Id::JaroWinkler => if let Some(w) = params.jaro_window { Score::flat(jaro::jaro_winkler(s1, s2, w, params.winkler)) } else { run(JaroWinkler { prefix_weight: params.winkler.0, max_prefix: params.winkler.1, ..JaroWinkler::default() }, s1, s2) },
+        Id::Levenshtein => run(Levenshtein::default(), s1, s2),
+        Id::LcsSeq => lcsseq(s1, s2),
+        Id::LcsStr => lcsstr(s1, s2),
+        Id::Length => run(Length::default(), s1, s2),
+        Id::Lig3 => run(LIG3::default(), s1, s2),
+        Id::Mlipns => run(MLIPNS::default(), s1, s2),
+        Id::NameScore => Score::flat(name_score(&E::text(s1), &E::text(s2))),
+        Id::DoubleMetaphone | Id::Nysiis | Id::Soundex => Score::flat(sounds_alike(id, &E::text(s1), &E::text(s2))),
+        Id::Overlap => run(Overlap::default(), s1, s2),
+        Id::Prefix => params.affix_basis.rebase(run(Prefix::default(), s1, s2), s1.len(), s2.len()),
+        Id::RatcliffObershelp => run(RatcliffObershelp::default(), s1, s2),
+        Id::Roberts => run(Roberts::default(), s1, s2),
+        Id::Sift4Common => run(Sift4Common::default(), s1, s2),
+        Id::Sift4Simple => run(Sift4Simple::default(), s1, s2),
+        Id::SmithWaterman => run(SmithWaterman::default(), s1, s2),
+        Id::SorensenDice => run(SorensenDice::default(), s1, s2),
+        Id::Suffix => params.affix_basis.rebase(run(Suffix::default(), s1, s2), s1.len(), s2.len()),
+        Id::Tversky => run(Tversky { alpha: params.tversky.0, beta: params.tversky.1, ..Tversky::default() }, s1, s2),
+        Id::YujianBo => run(YujianBo::default(), s1, s2),
+    }
+}
+
+/// What the algorithms compare: chars, whole lines or words in the token
+/// modes, or bytes.
+pub trait Token: Eq + Hash + Copy + Sync {
+    /// What separates tokens shown side by side.
+    const SEPARATOR: &'static str;
+
+    /// The tokens as text again, for algorithms that read words and
+    /// `--custom` closures.
+    fn text(tokens: &[Self]) -> String;
+}
+
+impl Token for char {
+    const SEPARATOR: &'static str = "";
+
+    fn text(tokens: &[Self]) -> String {
+        tokens.iter().collect()
+    }
+}
+
+impl Token for &str {
+    const SEPARATOR: &'static str = " ";
+
+    fn text(tokens: &[Self]) -> String {
+        tokens.join(Self::SEPARATOR)
+    }
+}
+
+impl Token for u8 {
+    const SEPARATOR: &'static str = "";
+
+    fn text(tokens: &[Self]) -> String {
+        String::from_utf8_lossy(tokens).into_owned()
+    }
+}
+
+/// Share of [`name_score`] that comes from the spelling, the rest coming
+/// from how the names sound.
+const NAME_SPELLING_WEIGHT: f64 = 0.7;
+
+/// Person-name similarity, the usual blend for deduplicating people:
+/// 0.7 × jaro_winkler on the names as given plus 0.3 × the agreement of
+/// their Double Metaphone codes, which is 1 when the primary codes match,
+/// 0.5 when only an alternate code matches one of the other name's, and 0
+/// otherwise. Names without letters to encode score on spelling alone.
+fn name_score(a: &str, b: &str) -> f64 {
+    let (ca, cb): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let spelling = run(JaroWinkler::default(), &ca, &cb).normalized;
+    let Some(sound) = phonetic::double_metaphone_agreement(a, b) else {
+        return spelling;
+    };
+    NAME_SPELLING_WEIGHT * spelling + (1.0 - NAME_SPELLING_WEIGHT) * sound
+}
+
+/// Whether `a` and `b` sound alike to the phonetic algorithm `id`: 1 for
+/// the same code and 0 otherwise, with double_metaphone's alternate codes
+/// matching for 0.5. Strings without letters to encode only match
+/// themselves.
+fn sounds_alike(id: Id, a: &str, b: &str) -> f64 {
+    let same = |code: fn(&str) -> String| {
+        let (ca, cb) = (code(a), code(b));
+        match ca.is_empty() || cb.is_empty() {
+            true => None,
+            false => Some(f64::from(u8::from(ca == cb))),
+        }
+    };
+    let score = match id {
+        Id::Soundex => same(phonetic::soundex),
+        Id::Nysiis => same(phonetic::nysiis),
+        _ => phonetic::double_metaphone_agreement(a, b),
+    };
+    score.unwrap_or(f64::from(u8::from(a == b)))
+}
+
+/// Raw and normalized value of one algorithm run.
+#[derive(Clone, Copy)]
+pub struct Score {
+    pub raw: f64,
+    pub normalized: f64,
+    /// Largest raw value possible for the inputs, which turns a raw
+    /// distance into a raw similarity and back.
+    pub max: f64,
+}
+
+impl Score {
+    /// For metrics that have no separate raw value.
+    pub fn flat(val: f64) -> Self {
+        Score {
+            raw: val,
+            normalized: val,
+            max: 1.0,
+        }
+    }
+
+    pub fn get(self, norm: bool) -> f64 {
+        if norm {
+            self.normalized
+        } else {
+            self.raw
+        }
+    }
+
+    /// The value of algorithm `a` as `orientation` asks, converting between
+    /// distance and similarity the way textdistance's `dist` and `sim` do,
+    /// or as the algorithm reports it without one.
+    pub fn oriented(self, a: &str, norm: bool, orientation: Option<Orientation>) -> f64 {
+        let val = self.get(norm);
+        match orientation {
+            Some(o) if o != Orientation::of(a) => match norm {
+                true => 1.0 - val,
+                false => self.max - val,
+            },
+            _ => val,
+        }
+    }
+}
+
+/// Whether a score grows with how different the strings are, or with how
+/// alike they are.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Distance,
+    Similarity,
+}
+
+impl Orientation {
+    /// Reads `--similarity` and `--distance`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        match (call.has_flag("similarity")?, call.has_flag("distance")?) {
+            (true, true) => Err(LabeledError::new("conflicting orientations")
+                .with_label("can't also report a distance", flag_span(call, "distance"))
+                .with_help("pick one of --similarity and --distance")),
+            (true, false) => Ok(Some(Orientation::Similarity)),
+            (false, true) => Ok(Some(Orientation::Distance)),
+            (false, false) => Ok(None),
+        }
+    }
+
+    /// How algorithm `a` reports its scores.
+    pub fn of(a: &str) -> Self {
+        match is_distance(a) {
+            true => Orientation::Distance,
+            false => Orientation::Similarity,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Orientation::Distance => "distance",
+            Orientation::Similarity => "similarity",
+        }
+    }
+}
+
+/// Both values of a textdistance result. Float metrics report their
+/// normalized value as the raw one too, matching `textdistance::str`.
+trait Metric {
+    fn score(&self) -> Score;
+}
+
+impl Metric for textdistance::Result<usize> {
+    fn score(&self) -> Score {
+        Score {
+            raw: self.val() as f64,
+            normalized: self.nval(),
+            max: (self.dist() + self.sim()) as f64,
+        }
+    }
+}
+
+impl Metric for textdistance::Result<f64> {
+    fn score(&self) -> Score {
+        Score::flat(self.nval())
+    }
+}
+
+fn run<R, E: Eq + Hash>(alg: impl Algorithm<R>, s1: &[E], s2: &[E]) -> Score
+where
+    textdistance::Result<R>: Metric,
+{
+    alg.for_vec(s1, s2).score()
+}
+
+/// Algorithms whose raw output is a distance (0 = identical) rather than a
+/// similarity. Unknown names fall back to levenshtein, a distance.
+pub fn is_distance(a: &str) -> bool {
+    match lookup(a) {
+        Some(a) => a.orientation == Orientation::Distance,
+        None => !a.eq_ignore_ascii_case(custom::NAME),
+    }
+}
+
+/// The kind of algorithm `a` is: edit (counting or weighing edits), token
+/// (comparing bags or sets of tokens), sequence (common subsequences),
+/// compression, simple (lengths and affixes), phonetic (how the strings
+/// sound) or hybrid (a blend of several).
+pub fn family(a: &str) -> &'static str {
+    lookup(a).map_or("edit", |a| a.family)
+}
+
+/// Normalized similarity from 0 (nothing in common) to 1 (identical), whatever
+/// the orientation of the algorithm's raw output.
+pub fn similarity<E: Token>(a: &str, s1: &[E], s2: &[E], params: &AlgoParams) -> f64 {
+    let n = compute_tokens(a, s1, s2, params).normalized;
+    if is_distance(a) {
+        1.0 - n
+    } else {
+        n
+    }
+}
+
+/// Longest common subsequence, switching to Hirschberg's linear-space
+/// algorithm once textdistance's full DP table would get too large.
+fn lcsseq<E: Eq + Hash>(a: &[E], b: &[E]) -> Score {
+    if a.len().saturating_mul(b.len()) <= hirschberg::THRESHOLD_CELLS {
+        return run(LCSSeq::default(), a, b);
+    }
+
+    let len = hirschberg::lcs(a, b).len() as f64;
+    let max = a.len().max(b.len()) as f64;
+    Score {
+        raw: len,
+        normalized: len / max,
+        max,
+    }
+}
+
+/// Longest common substring in linear time using a suffix automaton.
+fn lcsstr<E: Eq + Hash + Copy>(a: &[E], b: &[E]) -> Score {
+    let len = suffix_automaton::longest_common_substring(a, b).len() as f64;
+    let max = a.len().max(b.len());
+    Score {
+        raw: len,
+        normalized: match max {
+            0 => 1.0,
+            max => len / max as f64,
+        },
+        max: max as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ALGORITHMS;
+
+    fn score(a: &str, s1: &str, s2: &str) -> Score {
+        let (c1, c2): (Vec<char>, Vec<char>) = (s1.chars().collect(), s2.chars().collect());
+        compute_tokens(a, &c1, &c2, &AlgoParams::default())
+    }
+
+    #[test]
+    fn words_read_as_the_text_they_came_from() {
+        let (s1, s2) = ("Smith John", "John Smith");
+        let w1: Vec<&str> = s1.split_whitespace().collect();
+        let w2: Vec<&str> = s2.split_whitespace().collect();
+        assert_eq!(<&str>::text(&w1), s1);
+        let words = compute_tokens("name_score", &w1, &w2, &AlgoParams::default());
+        assert_eq!(words.normalized, score("name_score", s1, s2).normalized);
+    }
+
+    #[test]
+    fn empty_strings_are_identical() {
+        for a in ALGORITHMS.map(|a| a.name) {
+            let n = score(a, "", "").normalized;
+            let expected = if is_distance(a) { 0.0 } else { 1.0 };
+            assert_eq!(n, expected, "{a}");
+        }
+    }
+
+    #[test]
+    fn empty_and_nonempty_have_nothing_in_common() {
+        for a in ALGORITHMS.map(|a| a.name) {
+            let expected = if is_distance(a) { 1.0 } else { 0.0 };
+            assert_eq!(score(a, "", "abc").normalized, expected, "{a}");
+            assert_eq!(score(a, "abc", "").normalized, expected, "{a}");
+        }
+    }
+
+    #[test]
+    fn empty_strings_keep_raw_edit_distance() {
+        assert_eq!(score("levenshtein", "", "abc").raw, 3.0);
+        assert_eq!(score("entropy_ncd", "", "abc").raw, 1.0);
+    }
+
+    #[test]
+    fn orientations_convert_both_values() {
+        let s = score("levenshtein", "kitten", "sitting");
+        assert_eq!((s.raw, s.max), (3.0, 7.0));
+        let similarity = Some(Orientation::Similarity);
+        assert_eq!(s.oriented("levenshtein", false, similarity), 4.0);
+        assert_eq!(s.oriented("levenshtein", true, similarity), 4.0 / 7.0);
+        assert_eq!(s.oriented("levenshtein", false, None), 3.0);
+    }
+
+    #[test]
+    fn blends_keep_the_algorithm_orientation() {
+        let jaro = Score {
+            raw: 0.5,
+            normalized: 0.5,
+            max: 1.0,
+        };
+        let blended = blend("jaro", jaro, 1.0, 0.5);
+        assert_eq!((blended.raw, blended.normalized), (0.75, 0.75));
+        // a distance blends as a similarity, its raw count left alone
+        let lev = Score {
+            raw: 2.0,
+            normalized: 0.5,
+            max: 4.0,
+        };
+        let blended = blend("levenshtein", lev, 0.0, 0.5);
+        assert_eq!((blended.raw, blended.normalized), (2.0, 0.75));
+    }
+
+    #[test]
+    fn sets_ignore_repeated_tokens() {
+        let (a, b): (Vec<char>, Vec<char>) = ("aab".chars().collect(), "ab".chars().collect());
+        let sets = AlgoParams {
+            multiset: false,
+            ..AlgoParams::default()
+        };
+        assert_eq!(compute_tokens("jaccard", &a, &b, &sets).normalized, 1.0);
+        assert!(score("jaccard", "aab", "ab").normalized < 1.0);
+    }
+
+    #[test]
+    fn prefix_boost_closes_the_gap() {
+        assert_eq!(prefix_boost(&[0.1]), Some((0.1, 4)));
+        assert_eq!(prefix_boost(&[0.3, 4.0]), None);
+        let boosted = boost_prefix(
+            "levenshtein",
+            &['a', 'b'],
+            &['a', 'c'],
+            score("levenshtein", "ab", "ac"),
+            (0.5, 4),
+        );
+        assert_eq!(boosted.normalized, 0.25);
+        assert_eq!(boosted.raw, 1.0);
+    }
+}
@@ -0,0 +1,161 @@
+// `--custom <closure>`: scores from a nushell closure instead of an
+// algorithm. The closure gets the two strings as preprocessing left them and
+// returns a normalized similarity, and everything else stays the plugin's:
+// streaming, threads, thresholds and the shape of the output. Scores are
+// needed deep in code that can't fail, so the first error the closure
+// raises is kept and reported once the scores that needed it are in. With
+// `--custom-weight`, the closure nudges an algorithm's score rather than
+// replacing it: the two similarities are blended, the closure's weighing
+// that much, so a domain rule can add a bonus without redoing the metric.
+
+use std::sync::{Arc, Mutex};
+
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{engine::Closure, Config, LabeledError, Spanned, Value};
+
+use crate::flag_span;
+use crate::validate::given;
+
+/// The name scores from the closure go by, where an algorithm's would.
+pub const NAME: &str = "custom";
+
+/// Flags picking or tuning an algorithm, which a closure scoring on its own
+/// replaces and one blended with an algorithm's scores goes with.
+const ALGORITHM: [&str; 6] = [
+    "algorithm",
+    "jaro-window",
+    "multiset",
+    "prefix-boost",
+    "affix-basis",
+    "params",
+];
+
+/// Flags comparing other than two whole strings with one score.
+const EXCLUSIVE: [&str; 14] = [
+    "all",
+    "agreement",
+    "errors",
+    "pattern",
+    "glob",
+    "mode",
+    "scheme",
+    "tokenize",
+    "bytes",
+    "chunked",
+    "token-weights",
+    "show-alignment",
+    "field-algorithms",
+    "cascade",
+];
+
+pub struct Custom {
+    engine: EngineInterface,
+    closure: Spanned<Closure>,
+    /// How much the closure's score weighs against the algorithm's, if
+    /// they're blended.
+    weight: Option<f64>,
+    error: Mutex<Option<LabeledError>>,
+}
+
+impl Custom {
+    /// Reads `--custom` and `--custom-weight`; `None` without a closure.
+    pub fn from_call(
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+    ) -> Result<Option<Arc<Self>>, LabeledError> {
+        let weight = match call.get_flag::<Spanned<f64>>("custom-weight")? {
+            Some(w) if !(0.0..=1.0).contains(&w.item) => {
+                return Err(LabeledError::new("invalid custom weight")
+                    .with_label("must be between 0 and 1", w.span)
+                    .with_help("the share of the blended score the closure's makes up"));
+            }
+            w => w,
+        };
+        let Some(closure) = call.get_flag::<Spanned<Closure>>("custom")? else {
+            return match weight {
+                Some(w) => Err(LabeledError::new("--custom-weight needs --custom")
+                    .with_label("no closure to weigh", w.span)
+                    .with_help("give the closure to blend with the algorithm's scores")),
+                None => Ok(None),
+            };
+        };
+        if let Some(flag) = exclusive(call).into_iter().find(|f| given(call, f)) {
+            return Err(
+                LabeledError::new(format!("--custom and --{flag} are exclusive"))
+                    .with_label("the closure does the scoring", flag_span(call, flag))
+                    .with_help(format!("drop --{flag}")),
+            );
+        }
+        Ok(Some(Arc::new(Custom {
+            engine: engine.clone(),
+            closure,
+            weight: weight.map(|w| w.item),
+            error: Mutex::new(None),
+        })))
+    }
+
+    /// The closure's score for `a` and `b`, or 0 once it has failed.
+    pub fn score(&self, a: &str, b: &str) -> f64 {
+        if self.failed() {
+            return 0.0;
+        }
+        let span = self.closure.span;
+        let args = vec![Value::string(a, span), Value::string(b, span)];
+        let score = self
+            .engine
+            .eval_closure(&self.closure, args, None)
+            .map_err(LabeledError::from)
+            .and_then(|v| match v.coerce_float() {
+                Ok(s) if (0.0..=1.0).contains(&s) => Ok(s),
+                _ => Err(LabeledError::new("invalid custom score")
+                    .with_label(
+                        format!(
+                            "returned {} for '{a}' and '{b}'",
+                            v.to_expanded_string(", ", &Config::default())
+                        ),
+                        span,
+                    )
+                    .with_help("the closure must return a similarity between 0 and 1")),
+            });
+        score.unwrap_or_else(|e| {
+            let mut error = self.error.lock().unwrap_or_else(|e| e.into_inner());
+            error.get_or_insert(e);
+            0.0
+        })
+    }
+
+    /// The closure's weight when blended with an algorithm.
+    pub fn weight(&self) -> Option<f64> {
+        self.weight
+    }
+
+    fn failed(&self) -> bool {
+        self.error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// The closure's first error, if it has raised one.
+    pub fn check(&self) -> Result<(), LabeledError> {
+        match self.error.lock().unwrap_or_else(|e| e.into_inner()).clone() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The flags `--custom` rejects: the algorithm's too unless
+/// `--custom-weight` blends the closure with it.
+pub fn exclusive(call: &EvaluatedCall) -> Vec<&'static str> {
+    let algorithm: &[&str] = match given(call, "custom-weight") {
+        true => &[],
+        false => &ALGORITHM,
+    };
+    algorithm.iter().chain(&EXCLUSIVE).copied().collect()
+}
+
+/// [`Custom::check`] when there's a closure.
+pub fn check(custom: Option<&Arc<Custom>>) -> Result<(), LabeledError> {
+    custom.map_or(Ok(()), |c| c.check())
+}
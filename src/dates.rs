@@ -0,0 +1,192 @@
+// Date mode: both strings are read as calendar dates in whatever common
+// format they're written, so "2024-03-05" and "March 5, 2024" are the same
+// day, and scored by how many days apart they are. Strings that aren't
+// dates are left to the algorithm.
+
+/// Days after which a date's score has halved.
+const HALF_LIFE_DAYS: f64 = 30.0;
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Closeness from 0 to 1 of two dates, halving every [`HALF_LIFE_DAYS`]
+/// apart, or `None` unless both strings are dates.
+pub fn similarity(a: &str, b: &str) -> Option<f64> {
+    let days = parse(a)?.abs_diff(parse(b)?);
+    Some(0.5f64.powf(days as f64 / HALF_LIFE_DAYS))
+}
+
+enum Part {
+    Number { value: u32, digits: usize },
+    Month(u32),
+}
+
+/// Days since 1970-01-01 of a date written as year, month and day in any
+/// order with separators (2024-03-05, 03/05/2024, 5.3.2024), as 20240305,
+/// or with the month's name (March 5, 2024; 5 Mar 2024), a weekday name,
+/// ordinal suffixes and a time of day being ignored. All-numeric dates
+/// with the year last are read month first unless the first number can't
+/// be a month or they're separated by dots, as is usual in Europe.
+pub fn parse(s: &str) -> Option<i64> {
+    use Part::{Month, Number};
+
+    let s = s.trim();
+    // an ISO 8601 time after the date
+    let time = s.char_indices().find(|&(i, c)| {
+        c == 'T'
+            && s[..i].ends_with(|c: char| c.is_ascii_digit())
+            && s[i + 1..].starts_with(|c: char| c.is_ascii_digit())
+    });
+    let s = match time {
+        Some((i, _)) => &s[..i],
+        None => s,
+    };
+    let dotted = s.contains('.') && !s.contains(['/', '-']);
+    let mut parts = vec![];
+    for word in s.split_whitespace() {
+        let lower = word.to_lowercase();
+        if lower.contains(':') || matches!(lower.as_str(), "am" | "pm") {
+            continue;
+        }
+        for token in lower.split(|c: char| !c.is_alphanumeric()) {
+            if token.is_empty() || weekday(token) {
+                continue;
+            }
+            parts.push(part(token)?);
+        }
+    }
+    let (year, month, day) = match parts.as_slice() {
+        [Number { value, digits: 8 }] => (value / 10000, value / 100 % 100, value % 100),
+        [Number {
+            value: y,
+            digits: 4,
+        }, Number { value: m, .. }, Number { value: d, .. }]
+        | [Number {
+            value: y,
+            digits: 4,
+        }, Month(m), Number { value: d, .. }] => (*y, *m, *d),
+        [Number { value: a, .. }, Number { value: b, .. }, Number { value: y, digits }] => {
+            let year = full_year(*y, *digits)?;
+            match *a > 12 || dotted {
+                true => (year, *b, *a),
+                false => (year, *a, *b),
+            }
+        }
+        [Month(m), Number { value: d, .. }, Number { value: y, digits }]
+        | [Number { value: d, .. }, Month(m), Number { value: y, digits }] => {
+            (full_year(*y, *digits)?, *m, *d)
+        }
+        _ => return None,
+    };
+    valid(year, month, day).then(|| days_from_civil(year as i64, month, day))
+}
+
+/// A number, possibly with an ordinal suffix, or a month name or its first
+/// three letters or more; `None` for other words.
+fn part(token: &str) -> Option<Part> {
+    let digits = token.trim_end_matches(|c: char| c.is_alphabetic());
+    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        let suffix = &token[digits.len()..];
+        if !matches!(suffix, "" | "st" | "nd" | "rd" | "th") {
+            return None;
+        }
+        return Some(Part::Number {
+            value: digits.parse().ok()?,
+            digits: digits.len(),
+        });
+    }
+    if token.len() < 3 {
+        return None;
+    }
+    let m = MONTHS.iter().position(|m| m.starts_with(token))?;
+    Some(Part::Month(m as u32 + 1))
+}
+
+/// A weekday name or its first three letters or more, which says nothing
+/// the rest of a date doesn't.
+fn weekday(token: &str) -> bool {
+    token.len() >= 3 && WEEKDAYS.iter().any(|d| d.starts_with(token))
+}
+
+/// A two-digit year in the century that puts it closest to 2000.
+fn full_year(year: u32, digits: usize) -> Option<u32> {
+    match digits {
+        4 => Some(year),
+        2 if year < 70 => Some(2000 + year),
+        2 => Some(1900 + year),
+        _ => None,
+    }
+}
+
+fn valid(year: u32, month: u32, day: u32) -> bool {
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+/// Howard Hinnant's days_from_civil: days since 1970-01-01 in the
+/// proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_agree() {
+        let day = parse("2024-03-05");
+        assert_eq!(day, Some(19787));
+        for s in [
+            "March 5, 2024",
+            "5 Mar 2024",
+            "Tuesday, March 5th, 2024",
+            "03/05/2024",
+            "5.3.2024",
+            "20240305",
+            "2024-03-05T10:30:00Z",
+            "2024 March 5",
+        ] {
+            assert_eq!(parse(s), day, "{s}");
+        }
+        assert_eq!(parse("2023-02-29"), None);
+        assert_eq!(parse("nushell"), None);
+        assert_eq!(similarity("2024-03-05", "April 4, 2024"), Some(0.5));
+    }
+}
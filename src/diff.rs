@@ -0,0 +1,344 @@
+use std::ops::Range;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{record, Category, Example, LabeledError, Signature, Span, SyntaxShape, Value};
+
+use crate::chunked::paragraphs;
+use crate::edits::{self, Op};
+use crate::format::Format;
+use crate::{algorithm, flag_span, parallel, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityDiff;
+
+impl SimplePluginCommand for StrSimilarityDiff {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity diff"
+    }
+
+    fn description(&self) -> &str {
+        "List the differences between two strings as a table of hunks"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .required(
+                "other",
+                SyntaxShape::String,
+                "String to diff the input against",
+            )
+            .switch(
+                "diff-tokens",
+                "Diff word by word instead of char by char",
+                Some('w'),
+            )
+            .switch(
+                "lines",
+                "Diff line by line; ranges count lines instead of chars",
+                Some('l'),
+            )
+            .switch(
+                "paragraphs",
+                "Map every paragraph of the input to its best match in the other text instead, to see what moved or changed",
+                Some('p'),
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "With --paragraphs, name of the algorithm to compare paragraphs with",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "With --paragraphs, minimum normalized similarity (0 to 1) for a paragraph to count as kept (default: 0.5)",
+                Some('t'),
+            )
+            .switch(
+                "patch",
+                "Emit a compact patch that `str similarity apply` can replay on the input",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "With --paragraphs, round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "With --paragraphs, report scores as percentages, 0 to 100",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "With --paragraphs, report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Diff two strings",
+                example: "'kitten' | str similarity diff 'sitting'",
+                result: None,
+            },
+            Example {
+                description: "Count the inserted characters",
+                example: "'kitten' | str similarity diff 'sitting' | where op == insert | get text | str length | math sum",
+                result: Some(Value::test_int(3)),
+            },
+            Example {
+                description: "Find the words that were added to a sentence",
+                example: "'the test passed' | str similarity diff 'the test has not passed' --diff-tokens | where op == insert",
+                result: None,
+            },
+            Example {
+                description: "Show the lines that changed between two versions of a file",
+                example: "open old.txt | str similarity diff (open new.txt) --lines | where op != equal",
+                result: None,
+            },
+            Example {
+                description: "See which paragraphs of a draft were moved or rewritten",
+                example: "open draft.md | str similarity diff (open final.md) --paragraphs -a ratcliff_obershelp | where status != unchanged",
+                result: None,
+            },
+            Example {
+                description: "Store a patch and rebuild the target from it",
+                example: "let p = ('kitten' | str similarity diff 'sitting' --patch); 'kitten' | str similarity apply $p",
+                result: Some(Value::test_string("sitting")),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let other: String = call.req(0)?;
+        let left = match input {
+            Value::String { val, .. } => val,
+            v => {
+                return Err(LabeledError::new(format!(
+                    "requires string input, got {}",
+                    v.get_type()
+                ))
+                .with_label("Expected a string from pipeline", v.span())
+                .with_help("diff two strings, e.g. 'kitten' | str similarity diff 'sitting'"))
+            }
+        };
+
+        let span = call.head;
+        let paragraph_mode = call.has_flag("paragraphs")?;
+        for flag in ["algorithm", "threshold"] {
+            if !paragraph_mode && call.has_flag(flag)? {
+                return Err(LabeledError::new(format!("--{flag} needs --paragraphs"))
+                    .with_label("only paragraphs are scored", flag_span(call, flag))
+                    .with_help("add --paragraphs, or drop the flag for a plain diff"));
+            }
+        }
+        if paragraph_mode {
+            let algo = algorithm(call)?;
+            let threshold: f64 = call.get_flag("threshold")?.unwrap_or(0.5);
+            let params = AlgoParams::from_call(call)?;
+            let format = Format::from_call(call)?;
+            return Ok(paragraph_map(
+                left, &other, &algo, threshold, &params, &format, span,
+            ));
+        }
+
+        let a: Vec<char> = left.chars().collect();
+        let b: Vec<char> = other.chars().collect();
+        if call.has_flag("lines")? {
+            return line_diff(left, &other, call.has_flag("patch")?, &b, span);
+        }
+
+        let hunks = if call.has_flag("diff-tokens")? {
+            let (wa, wb) = (edits::words(left), edits::words(&other));
+            edits::to_chars(edits::hunks(&wa, &wb), &wa, &wb)
+        } else {
+            edits::hunks(&a, &b)
+        };
+
+        if call.has_flag("patch")? {
+            let ops = hunks
+                .into_iter()
+                .map(|h| patch_op(h.op, &b, h.left, h.right, span))
+                .collect();
+            return Ok(Value::list(ops, span));
+        }
+
+        let rows = hunks
+            .into_iter()
+            .map(|h| {
+                let text: String = match h.op {
+                    Op::Insert => b[h.right.clone()].iter().collect(),
+                    _ => a[h.left.clone()].iter().collect(),
+                };
+                Value::record(
+                    record! {
+                        "op" => Value::string(h.op.name(), span),
+                        "left_range" => range(h.left, span),
+                        "right_range" => range(h.right, span),
+                        "text" => Value::string(text, span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// Hunks between the lines of `left` and `right`, with line ranges. Patches
+/// are still in chars so `str similarity apply` can replay them.
+fn line_diff(
+    left: &str,
+    right: &str,
+    patch: bool,
+    b: &[char],
+    span: Span,
+) -> Result<Value, LabeledError> {
+    let (la, lb) = (edits::lines(left), edits::lines(right));
+    if patch {
+        // line endings are part of the lines here, or a missing final
+        // newline would be lost on replay
+        let ops = edits::to_chars(edits::hunks(&la, &lb), &la, &lb)
+            .into_iter()
+            .map(|h| patch_op(h.op, b, h.left, h.right, span))
+            .collect();
+        return Ok(Value::list(ops, span));
+    }
+
+    let rows = edits::hunks(&edits::line_keys(left), &edits::line_keys(right))
+        .into_iter()
+        .map(|h| {
+            let text = match h.op {
+                Op::Insert => lb[h.right.clone()].concat(),
+                _ => la[h.left.clone()].concat(),
+            };
+            Value::record(
+                record! {
+                    "op" => Value::string(h.op.name(), span),
+                    "left_range" => range(h.left, span),
+                    "right_range" => range(h.right, span),
+                    "text" => Value::string(text, span),
+                },
+                span,
+            )
+        })
+        .collect();
+    Ok(Value::list(rows, span))
+}
+
+/// Every paragraph of `left` with its most similar paragraph of `right`:
+/// `unchanged` when identical and in the same place, `moved` when identical
+/// elsewhere, `changed` when similar enough, `removed` when nothing reaches
+/// `threshold`.
+fn paragraph_map(
+    left: &str,
+    right: &str,
+    algo: &str,
+    threshold: f64,
+    params: &AlgoParams,
+    format: &Format,
+    span: Span,
+) -> Value {
+    let chars = |text: &str| -> Vec<Vec<char>> {
+        paragraphs(text)
+            .iter()
+            .map(|p| p.chars().collect())
+            .collect()
+    };
+    let (pa, pb) = (chars(left), chars(right));
+    let indexed: Vec<(usize, &Vec<char>)> = pa.iter().enumerate().collect();
+    let rows = parallel::map_chunks(&indexed, 1, true, |chunk| {
+        let (i, p) = chunk[0];
+        // the first of equally good paragraphs wins, so unchanged text
+        // maps to its own place when it also appears elsewhere
+        let mut best: Option<(usize, f64)> = None;
+        for (j, q) in pb.iter().enumerate() {
+            let score = similarity(algo, p, q, params);
+            let closer = match best {
+                Some((_, b)) => score > b || (score == b && j == i),
+                None => true,
+            };
+            if score >= threshold && closer {
+                best = Some((j, score));
+            }
+        }
+        let text = |p: &[char]| Value::string(p.iter().collect::<String>(), span);
+        let status = match best {
+            None => "removed",
+            Some((_, s)) if s < 1.0 => "changed",
+            Some((j, _)) if j == i => "unchanged",
+            Some(_) => "moved",
+        };
+        Value::record(
+            record! {
+                "left_index" => Value::int(i as i64, span),
+                "left" => text(p),
+                "right_index" => best.map_or(Value::nothing(span), |(j, _)| Value::int(j as i64, span)),
+                "right" => best.map_or(Value::nothing(span), |(j, _)| text(&pb[j])),
+                "score" => best.map_or(Value::nothing(span), |(_, s)| format.score(s, span)),
+                "status" => Value::string(status, span),
+            },
+            span,
+        )
+    });
+    Value::list(rows, span)
+}
+
+/// One step of a patch: keep or drop `count` chars of the source, or insert
+/// `text`. Only insertions carry text, so the patch stays small when the
+/// strings are mostly equal.
+fn patch_op(op: Op, b: &[char], left: Range<usize>, right: Range<usize>, span: Span) -> Value {
+    let (count, text) = match op {
+        Op::Insert => (
+            right.len(),
+            Value::string(b[right].iter().collect::<String>(), span),
+        ),
+        _ => (left.len(), Value::nothing(span)),
+    };
+    Value::record(
+        record! {
+            "op" => Value::string(op.name(), span),
+            "count" => Value::int(count as i64, span),
+            "text" => text,
+        },
+        span,
+    )
+}
+
+/// Char offsets, `end` exclusive.
+fn range(r: Range<usize>, span: Span) -> Value {
+    Value::record(
+        record! {
+            "start" => Value::int(r.start as i64, span),
+            "end" => Value::int(r.end as i64, span),
+        },
+        span,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityDiff)
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Value,
+};
+
+use crate::format::Format;
+use crate::{algorithm, fingerprint, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityDirs;
+
+impl SimplePluginCommand for StrSimilarityDirs {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity dirs"
+    }
+
+    fn description(&self) -> &str {
+        "Match the files of two directories by name, reporting renamed and unmatched files"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .required("left", SyntaxShape::Directory, "First directory")
+            .required("right", SyntaxShape::Directory, "Second directory")
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compare file names with",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum normalized similarity (0 to 1) of two file names to call it a rename (default: 0.6)",
+                Some('t'),
+            )
+            .switch(
+                "by-content",
+                "Also compare file contents by SimHash, pairing renames on the mean of name and content similarity",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Reconcile a backup with the original folder",
+                example: "str similarity dirs ~/photos /mnt/backup/photos",
+                result: None,
+            },
+            Example {
+                description: "List only the files that look renamed",
+                example:
+                    "str similarity dirs old new -a jaro_winkler -t 0.8 | where status == renamed",
+                result: None,
+            },
+            Example {
+                description: "Catch renames whose new name looks nothing like the old one",
+                example: "str similarity dirs old new --by-content -t 0.7",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let algo = algorithm(call)?;
+        let threshold: f64 = call.get_flag("threshold")?.unwrap_or(0.6);
+        let params = AlgoParams::from_call(call)?;
+        let cwd = engine.get_current_dir()?;
+        let left_dir: Spanned<String> = call.req(0)?;
+        let right_dir: Spanned<String> = call.req(1)?;
+        let (left_root, left) = files(&left_dir, &cwd)?;
+        let (right_root, right) = files(&right_dir, &cwd)?;
+        let (left_hash, right_hash) = if call.has_flag("by-content")? {
+            (
+                Some(simhashes(&left_dir, &left_root, &left)?),
+                Some(simhashes(&right_dir, &right_root, &right)?),
+            )
+        } else {
+            (None, None)
+        };
+        let content = |i: usize, j: usize| match (&left_hash, &right_hash) {
+            (Some(l), Some(r)) => Some(1.0 - (l[i] ^ r[j]).count_ones() as f64 / 64.0),
+            _ => None,
+        };
+
+        // files at the same relative path are the same file; the rest are
+        // paired up by file name, best pairs first, each file used once
+        let mut right_of: Vec<Option<(usize, f64)>> = vec![None; left.len()];
+        let mut taken = vec![false; right.len()];
+        let index: HashMap<&str, usize> = right
+            .iter()
+            .enumerate()
+            .map(|(j, r)| (r.as_str(), j))
+            .collect();
+        for (i, l) in left.iter().enumerate() {
+            if let Some(&j) = index.get(l.as_str()) {
+                right_of[i] = Some((j, 1.0));
+                taken[j] = true;
+            }
+        }
+        let name = |p: &str| -> Vec<char> {
+            Path::new(p)
+                .file_name()
+                .map_or(p.into(), |n| n.to_string_lossy().into_owned())
+                .chars()
+                .collect()
+        };
+        let mut pairs = vec![];
+        for (i, l) in left.iter().enumerate() {
+            if right_of[i].is_some() {
+                continue;
+            }
+            let ln = name(l);
+            for (j, r) in right.iter().enumerate() {
+                if taken[j] {
+                    continue;
+                }
+                let score = similarity(&algo, &ln, &name(r), &params);
+                let confidence = content(i, j).map_or(score, |c| (score + c) / 2.0);
+                if confidence >= threshold {
+                    pairs.push((confidence, i, j));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+        for (_, i, j) in pairs {
+            if right_of[i].is_none() && !taken[j] {
+                right_of[i] = Some((
+                    j,
+                    similarity(&algo, &name(&left[i]), &name(&right[j]), &params),
+                ));
+                taken[j] = true;
+            }
+        }
+
+        let span = call.head;
+        let format = Format::from_call(call)?;
+        let by_content = left_hash.is_some();
+        let row = |status: &str, i: Option<usize>, j: Option<usize>, score: Option<f64>| {
+            let path =
+                |p: Option<&String>| p.map_or(Value::nothing(span), |p| Value::string(p, span));
+            let float = |v: Option<f64>| v.map_or(Value::nothing(span), |v| format.score(v, span));
+            let mut rec = record! {
+                "status" => Value::string(status, span),
+                "left" => path(i.map(|i| &left[i])),
+                "right" => path(j.map(|j| &right[j])),
+                "score" => float(score),
+            };
+            if by_content {
+                let c = i.zip(j).and_then(|(i, j)| content(i, j));
+                rec.push("content_score", float(c));
+                rec.push(
+                    "confidence",
+                    float(score.zip(c).map(|(s, c)| (s + c) / 2.0)),
+                );
+            }
+            Value::record(rec, span)
+        };
+        let mut rows: Vec<Value> = right_of
+            .iter()
+            .enumerate()
+            .map(|(i, m)| match *m {
+                Some((j, _)) if right[j] == left[i] => row("matched", Some(i), Some(j), Some(1.0)),
+                Some((j, score)) => row("renamed", Some(i), Some(j), Some(score)),
+                None => row("left_only", Some(i), None, None),
+            })
+            .collect();
+        rows.extend(
+            taken
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| !**t)
+                .map(|(j, _)| row("right_only", None, Some(j), None)),
+        );
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// SimHash of each file's contents, read lossily so binary files still get
+/// a (rough) fingerprint.
+fn simhashes(
+    dir: &Spanned<String>,
+    root: &Path,
+    files: &[String],
+) -> Result<Vec<u64>, LabeledError> {
+    files
+        .iter()
+        .map(|f| {
+            let path = root.join(f);
+            let bytes = fs::read(&path).map_err(|e| {
+                LabeledError::new(format!("cannot read {}", path.display()))
+                    .with_label(e.to_string(), dir.span)
+                    .with_help("drop --by-content to compare names only")
+            })?;
+            Ok(fingerprint::simhash(&String::from_utf8_lossy(&bytes)))
+        })
+        .collect()
+}
+
+/// Every file under `dir`, as sorted paths relative to it, with the expanded
+/// directory they are relative to.
+fn files(dir: &Spanned<String>, cwd: &str) -> Result<(PathBuf, Vec<String>), LabeledError> {
+    let root = nu_path::expand_path_with(&dir.item, cwd, true);
+    let mut found = vec![];
+    let mut pending: Vec<PathBuf> = vec![root.clone()];
+    while let Some(d) = pending.pop() {
+        let entries = fs::read_dir(&d).map_err(|e| {
+            LabeledError::new(format!("cannot read {}", d.display()))
+                .with_label(e.to_string(), dir.span)
+                .with_help("pass two readable directories")
+        })?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => pending.push(path),
+                Ok(_) => {
+                    let rel = path.strip_prefix(&root).unwrap_or(&path);
+                    found.push(rel.to_string_lossy().into_owned());
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    found.sort();
+    Ok((root, found))
+}
@@ -0,0 +1,225 @@
+// `str similarity diversify`: a few strings of a large, messy list that
+// are as unlike each other as possible, picked max-min: after the most
+// frequent string, each pick is the one whose closest earlier pick is the
+// least similar to it. Each row's score says how close that was, so the
+// point where picks start repeating each other shows.
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value,
+};
+use rayon::prelude::*;
+
+use crate::format::Format;
+use crate::group::distinct;
+use crate::{algorithm, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityDiversify;
+
+impl SimplePluginCommand for StrSimilarityDiversify {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity diversify"
+    }
+
+    fn description(&self) -> &str {
+        "Pick the strings of a list least similar to each other, as representative, non-redundant examples"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_type(Type::List(Box::new(Type::String)), Type::table())
+            .named(
+                "count",
+                SyntaxShape::Int,
+                "Number of strings to pick (default: 10)",
+                Some('n'),
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Pick five varied product names to review",
+                example: "open products.csv | get name | str similarity diversify --count 5",
+                result: None,
+            },
+            Example {
+                description: "Pick two strings unlike each other",
+                example: "[apple aple apples banana] | str similarity diversify -n 2 --digits 2",
+                result: Some(Value::test_list(vec![
+                    Value::test_record(record! {
+                        "value" => Value::test_string("apple"),
+                        "score" => Value::test_nothing(),
+                    }),
+                    Value::test_record(record! {
+                        "value" => Value::test_string("banana"),
+                        "score" => Value::test_float(0.17),
+                    }),
+                ])),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let format = Format::from_call(call)?;
+        let span = call.head;
+        let algo = algorithm(call)?;
+        let params = AlgoParams::from_call(call)?;
+        let count = match call.get_flag::<Spanned<i64>>("count")? {
+            Some(n) if n.item < 1 => {
+                return Err(LabeledError::new("invalid count")
+                    .with_label("need at least one string", n.span)
+                    .with_help("--count is how many strings to pick"));
+            }
+            Some(n) => n.item as usize,
+            None => 10,
+        };
+        let Value::List { vals, .. } = input else {
+            return Err(LabeledError::new(format!(
+                "requires list input, got {}",
+                input.get_type()
+            ))
+            .with_label("Expected a list of strings", input.span())
+            .with_help("e.g. [apple aple banana] | str similarity diversify -n 2"));
+        };
+        let strings = vals
+            .iter()
+            .map(|v| v.as_str())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // most frequent first, so the first pick is the usual string
+        let distinct = distinct(strings);
+        let chars: Vec<Vec<char>> = distinct.iter().map(|(s, _)| s.chars().collect()).collect();
+        let picks = max_min(chars.len(), count, |i, j| {
+            similarity(&algo, &chars[i], &chars[j], &params)
+        });
+        let rows = picks
+            .into_iter()
+            .map(|(i, score)| {
+                Value::record(
+                    record! {
+                        "value" => Value::string(distinct[i].0, span),
+                        "score" => score.map_or(Value::nothing(span), |s| format.score(s, span)),
+                    },
+                    span,
+                )
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// Up to `count` of `n` items, picked max-min by `score`: item 0, then
+/// each time the item least similar to its most similar earlier pick,
+/// earlier items winning ties. Each pick comes with that similarity, which
+/// the first has none of.
+fn max_min(
+    n: usize,
+    count: usize,
+    score: impl Fn(usize, usize) -> f64 + Sync,
+) -> Vec<(usize, Option<f64>)> {
+    if n == 0 {
+        return vec![];
+    }
+    let mut picks = vec![(0, None)];
+    // each item's similarity to its most similar pick so far
+    let mut nearest: Vec<f64> = (0..n).into_par_iter().map(|i| score(0, i)).collect();
+    let mut picked = vec![false; n];
+    picked[0] = true;
+    while picks.len() < count.min(n) {
+        let next = (0..n)
+            .filter(|&i| !picked[i])
+            .min_by(|&a, &b| nearest[a].total_cmp(&nearest[b]).then(a.cmp(&b)))
+            .expect("an item is left to pick");
+        picked[next] = true;
+        picks.push((next, Some(nearest[next])));
+        nearest
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, s)| *s = s.max(score(next, i)));
+    }
+    picks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityDiversify)
+    }
+
+    #[test]
+    fn picks_spread_out_before_repeating() {
+        // two tight groups: 0 and 1 close together, 2 and 3 close together
+        let groups = [0, 0, 1, 1];
+        let score = |i: usize, j: usize| match (i == j, groups[i] == groups[j]) {
+            (true, _) => 1.0,
+            (false, true) => 0.9,
+            (false, false) => 0.1,
+        };
+        let picks = max_min(4, 3, score);
+        assert_eq!(picks, [(0, None), (2, Some(0.1)), (1, Some(0.9))]);
+    }
+}
@@ -0,0 +1,528 @@
+// The edits behind an edit distance, recovered by tracing back through the
+// full DP table. textdistance only reports the distance, so the tables are
+// rebuilt here with the same costs and the path to the last cell is counted,
+// or laid out to show how the strings line up.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops::{Range, RangeInclusive};
+
+use nu_protocol::{record, Span, Value};
+
+use crate::registry::Id;
+use crate::{hirschberg, lookup};
+
+#[derive(Default, Debug, PartialEq)]
+pub struct Counts {
+    pub insertions: usize,
+    pub deletions: usize,
+    pub substitutions: usize,
+    pub transpositions: usize,
+}
+
+impl Counts {
+    pub fn record(&self, span: Span) -> Value {
+        Value::record(
+            record! {
+                "insertions" => Value::int(self.insertions as i64, span),
+                "deletions" => Value::int(self.deletions as i64, span),
+                "substitutions" => Value::int(self.substitutions as i64, span),
+                "transpositions" => Value::int(self.transpositions as i64, span),
+            },
+            span,
+        )
+    }
+}
+
+/// A step of an alignment, in order from the start of both strings.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    /// `a[i]` aligned with `b[j]`, kept or substituted.
+    Pair(usize, usize),
+    /// `a[i]` deleted.
+    Delete(usize),
+    /// `b[j]` inserted.
+    Insert(usize),
+    /// The first and last tokens of `a`'s range swapped into the last and
+    /// first of `b`'s, what lies between them deleted and inserted.
+    Transpose { a: Range<usize>, b: Range<usize> },
+}
+
+/// Cells of the full table past which a traceback keeps only a band around
+/// its diagonal, some 32 MB of them.
+const FULL_CELLS: usize = 1 << 22;
+
+/// Cells on either side of the diagonal a banded traceback keeps, unless
+/// `--band` says otherwise.
+const BAND: usize = 100;
+
+/// The edits of an alignment, and whether they were traced through a band
+/// of the table only, which misses a best path straying out of it.
+pub struct Alignment {
+    pub edits: Vec<Edit>,
+    pub approximate: bool,
+}
+
+/// The edits turning `a` into `b` for the edit-based algorithm `algo`, and
+/// whether they're approximate, or `None` when it isn't one.
+pub fn of<E: Eq + Hash>(
+    algo: &str,
+    a: &[E],
+    b: &[E],
+    band: Option<usize>,
+) -> Option<(Counts, bool)> {
+    match lookup(algo)?.id {
+        // its alignment is of the local match, not the whole strings
+        Id::SmithWaterman => None,
+        _ => path(algo, a, b, band).map(|p| (count(&p.edits, a, b), p.approximate)),
+    }
+}
+
+/// How `algo` aligns `a` and `b`, or `None` for algorithms without a
+/// traceback.
+/// smith_waterman aligns the local match it scores, not the whole strings.
+/// Strings too long for the full table are traced through the `band`
+/// cells either side of its diagonal, with damerau_levenshtein only
+/// transposing neighbours; levenshtein without a `band` is aligned exactly
+/// by Hirschberg's algorithm instead, in linear space but more time.
+pub fn path<E: Eq + Hash>(algo: &str, a: &[E], b: &[E], band: Option<usize>) -> Option<Alignment> {
+    let (n, m) = (a.len(), b.len());
+    let banded = n > 0 && m > 0 && (n + 1).saturating_mul(m + 1) > FULL_CELLS;
+    let id = lookup(algo)?.id;
+    if banded && band.is_none() && id == Id::Levenshtein {
+        return Some(Alignment {
+            edits: hirschberg::align(a, b),
+            approximate: false,
+        });
+    }
+    let width = if banded { band.unwrap_or(BAND) } else { m };
+    let edits = match id {
+        Id::Levenshtein => edits(a, b, width, false),
+        Id::DamerauLevenshtein if banded => edits(a, b, width, true),
+        Id::DamerauLevenshtein => damerau_levenshtein(a, b),
+        Id::Hamming => {
+            return Some(Alignment {
+                edits: hamming(a, b),
+                approximate: false,
+            })
+        }
+        Id::SmithWaterman => smith_waterman(a, b, width),
+        _ => return None,
+    };
+    Some(Alignment {
+        edits,
+        approximate: banded,
+    })
+}
+
+fn count<E: Eq>(path: &[Edit], a: &[E], b: &[E]) -> Counts {
+    let mut counts = Counts::default();
+    for edit in path {
+        match edit {
+            Edit::Pair(i, j) => counts.substitutions += usize::from(a[*i] != b[*j]),
+            Edit::Delete(_) => counts.deletions += 1,
+            Edit::Insert(_) => counts.insertions += 1,
+            Edit::Transpose { a, b } => {
+                counts.transpositions += 1;
+                counts.deletions += a.len() - 2;
+                counts.insertions += b.len() - 2;
+            }
+        }
+    }
+    counts
+}
+
+/// An alignment laid out as three lines: the tokens of `a`, a marker line
+/// and the tokens of `b`, each pair padded to the wider of the two and
+/// gaps drawn as `-`. The markers are `|` under kept tokens, `x` under
+/// substitutions, `~` under a transposition and a space under gaps.
+pub fn render<E: Eq>(
+    path: &[Edit],
+    a: &[E],
+    b: &[E],
+    text: impl Fn(&[E]) -> String,
+    separator: &str,
+) -> [String; 3] {
+    let mut lines = [String::new(), String::new(), String::new()];
+    for (n, edit) in path.iter().enumerate() {
+        let (top, mark, bottom) = match edit {
+            Edit::Pair(i, j) => {
+                let mark = if a[*i] == b[*j] { '|' } else { 'x' };
+                (Some(text(&a[*i..=*i])), mark, Some(text(&b[*j..=*j])))
+            }
+            Edit::Delete(i) => (Some(text(&a[*i..=*i])), ' ', None),
+            Edit::Insert(j) => (None, ' ', Some(text(&b[*j..=*j]))),
+            Edit::Transpose { a: ra, b: rb } => {
+                (Some(text(&a[ra.clone()])), '~', Some(text(&b[rb.clone()])))
+            }
+        };
+        let len = |s: &Option<String>| s.as_ref().map_or(0, |s| s.chars().count());
+        let width = len(&top).max(len(&bottom)).max(1);
+        if n > 0 {
+            for line in &mut lines {
+                line.push_str(separator);
+            }
+        }
+        for (line, s) in [(0, top), (2, bottom)] {
+            match s {
+                Some(s) => lines[line].push_str(&format!("{s:<width$}")),
+                None => lines[line].push_str(&"-".repeat(width)),
+            }
+        }
+        lines[1].extend(std::iter::repeat_n(mark, width));
+    }
+    lines
+}
+
+/// Mismatched positions are substitutions and the longer string's tail is
+/// inserted or deleted, as hamming counts it.
+fn hamming<E: Eq>(a: &[E], b: &[E]) -> Vec<Edit> {
+    let pairs = (0..a.len().min(b.len())).map(|i| Edit::Pair(i, i));
+    let tail = (b.len()..a.len())
+        .map(Edit::Delete)
+        .chain((a.len()..b.len()).map(Edit::Insert));
+    pairs.chain(tail).collect()
+}
+
+/// The cells of a DP table within `width` columns of its diagonal, which
+/// runs from the first cell to the last; every other cell reads `outside`.
+/// A width of the row length keeps the whole table.
+struct Band<T> {
+    rows: usize,
+    columns: usize,
+    width: usize,
+    cells: Vec<T>,
+    outside: T,
+}
+
+impl<T: Copy> Band<T> {
+    fn new(rows: usize, columns: usize, width: usize, outside: T) -> Self {
+        // neighbouring rows have to overlap for a path to cross the band
+        let width = match rows {
+            0 => columns,
+            _ => width.max(columns.div_ceil(rows)),
+        };
+        Band {
+            rows,
+            columns,
+            width,
+            cells: vec![outside; (rows + 1) * (2 * width + 1)],
+            outside,
+        }
+    }
+
+    fn center(&self, i: usize) -> usize {
+        match self.rows {
+            0 => 0,
+            rows => i * self.columns / rows,
+        }
+    }
+
+    /// The columns of row `i` inside the band.
+    fn span(&self, i: usize) -> RangeInclusive<usize> {
+        let center = self.center(i);
+        center.saturating_sub(self.width)..=(center + self.width).min(self.columns)
+    }
+
+    fn index(&self, i: usize, j: usize) -> Option<usize> {
+        let center = self.center(i);
+        let inside = j + self.width >= center && j <= center + self.width && j <= self.columns;
+        inside.then(|| i * (2 * self.width + 1) + j + self.width - center)
+    }
+
+    fn get(&self, i: usize, j: usize) -> T {
+        self.index(i, j).map_or(self.outside, |k| self.cells[k])
+    }
+
+    fn set(&mut self, i: usize, j: usize, value: T) {
+        if let Some(k) = self.index(i, j) {
+            self.cells[k] = value;
+        }
+    }
+}
+
+/// levenshtein, or with `transpose` optimal string alignment, which only
+/// transposes neighbours, traced through the cells within `width` of the
+/// table's diagonal.
+fn edits<E: Eq>(a: &[E], b: &[E], width: usize, transpose: bool) -> Vec<Edit> {
+    let (n, m) = (a.len(), b.len());
+    // out of reach, yet safe to add to
+    let mut d = Band::new(n, m, width, usize::MAX / 2);
+    let swapped = |i: usize, j: usize| {
+        transpose
+            && i > 1
+            && j > 1
+            && a[i - 1] != b[j - 1]
+            && a[i - 1] == b[j - 2]
+            && a[i - 2] == b[j - 1]
+    };
+    for i in 0..=n {
+        for j in d.span(i) {
+            let cell = if i == 0 || j == 0 {
+                i + j
+            } else {
+                let cost = usize::from(a[i - 1] != b[j - 1]);
+                let cell = (d.get(i - 1, j - 1) + cost)
+                    .min(d.get(i - 1, j) + 1)
+                    .min(d.get(i, j - 1) + 1);
+                match swapped(i, j) {
+                    true => cell.min(d.get(i - 2, j - 2) + 1),
+                    false => cell,
+                }
+            };
+            d.set(i, j, cell);
+        }
+    }
+
+    let mut path = vec![];
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let cell = d.get(i, j);
+        if swapped(i, j) && cell == d.get(i - 2, j - 2) + 1 {
+            path.push(Edit::Transpose {
+                a: i - 2..i,
+                b: j - 2..j,
+            });
+            (i, j) = (i - 2, j - 2);
+        } else if i > 0 && j > 0 && cell == d.get(i - 1, j - 1) + usize::from(a[i - 1] != b[j - 1])
+        {
+            path.push(Edit::Pair(i - 1, j - 1));
+            (i, j) = (i - 1, j - 1);
+        } else if i > 0 && cell == d.get(i - 1, j) + 1 {
+            path.push(Edit::Delete(i - 1));
+            i -= 1;
+        } else {
+            path.push(Edit::Insert(j - 1));
+            j -= 1;
+        }
+    }
+    path.reverse();
+    path
+}
+
+/// How a cell of the Damerau-Levenshtein table was reached.
+#[derive(Clone, Copy)]
+enum Step {
+    Start,
+    Diagonal,
+    Delete,
+    Insert,
+    /// From the cell before the transposed pair at rows and columns `k`
+    /// and `l`, deleting and inserting whatever lies between.
+    Transpose(usize, usize),
+}
+
+/// Unrestricted Damerau-Levenshtein (Lowrance-Wagner), like textdistance's
+/// default, so a transposition may have edits between its two chars.
+fn damerau_levenshtein<E: Eq + Hash>(a: &[E], b: &[E]) -> Vec<Edit> {
+    let (n, m) = (a.len(), b.len());
+    let mut d = vec![vec![0; m + 1]; n + 1];
+    let mut steps = vec![vec![Step::Start; m + 1]; n + 1];
+    for i in 1..=n {
+        d[i][0] = i;
+        steps[i][0] = Step::Delete;
+    }
+    for j in 1..=m {
+        d[0][j] = j;
+        steps[0][j] = Step::Insert;
+    }
+    // the last row each token of `a` was seen in
+    let mut seen: HashMap<&E, usize> = HashMap::new();
+    for i in 1..=n {
+        // the last column in this row where the tokens matched
+        let mut matched = 0;
+        for j in 1..=m {
+            let k = seen.get(&b[j - 1]).copied().unwrap_or(0);
+            let l = matched;
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            if cost == 0 {
+                matched = j;
+            }
+            let mut best = (d[i - 1][j - 1] + cost, Step::Diagonal);
+            if k > 0 && l > 0 {
+                let t = d[k - 1][l - 1] + (i - k - 1) + 1 + (j - l - 1);
+                if t < best.0 {
+                    best = (t, Step::Transpose(k, l));
+                }
+            }
+            if d[i - 1][j] + 1 < best.0 {
+                best = (d[i - 1][j] + 1, Step::Delete);
+            }
+            if d[i][j - 1] + 1 < best.0 {
+                best = (d[i][j - 1] + 1, Step::Insert);
+            }
+            (d[i][j], steps[i][j]) = best;
+        }
+        seen.insert(&a[i - 1], i);
+    }
+
+    let mut path = vec![];
+    let (mut i, mut j) = (n, m);
+    loop {
+        match steps[i][j] {
+            Step::Start => break,
+            Step::Diagonal => {
+                path.push(Edit::Pair(i - 1, j - 1));
+                (i, j) = (i - 1, j - 1);
+            }
+            Step::Delete => {
+                path.push(Edit::Delete(i - 1));
+                i -= 1;
+            }
+            Step::Insert => {
+                path.push(Edit::Insert(j - 1));
+                j -= 1;
+            }
+            Step::Transpose(k, l) => {
+                path.push(Edit::Transpose {
+                    a: k - 1..i,
+                    b: l - 1..j,
+                });
+                (i, j) = (k - 1, l - 1);
+            }
+        }
+    }
+    path.reverse();
+    path
+}
+
+/// Smith-Waterman with textdistance's costs, a kept token scoring 1, a
+/// substituted one 0 and a gap -1, traced back from the last cell, whose
+/// score textdistance reports, to where the local match starts. Only the
+/// cells within `width` of the diagonal are scored, the others starting
+/// afresh at 0.
+fn smith_waterman<E: Eq>(a: &[E], b: &[E], width: usize) -> Vec<Edit> {
+    let (n, m) = (a.len(), b.len());
+    let mut h = Band::new(n, m, width, 0isize);
+    for i in 1..=n {
+        for j in h.span(i).filter(|&j| j > 0) {
+            let diagonal = h.get(i - 1, j - 1) + isize::from(a[i - 1] == b[j - 1]);
+            let cell = diagonal
+                .max(h.get(i - 1, j) - 1)
+                .max(h.get(i, j - 1) - 1)
+                .max(0);
+            h.set(i, j, cell);
+        }
+    }
+
+    let mut path = vec![];
+    let (mut i, mut j) = (n, m);
+    while i > 0 && j > 0 && h.get(i, j) > 0 {
+        let cell = h.get(i, j);
+        if cell == h.get(i - 1, j - 1) + isize::from(a[i - 1] == b[j - 1]) {
+            path.push(Edit::Pair(i - 1, j - 1));
+            (i, j) = (i - 1, j - 1);
+        } else if cell == h.get(i - 1, j) - 1 {
+            path.push(Edit::Delete(i - 1));
+            i -= 1;
+        } else {
+            path.push(Edit::Insert(j - 1));
+            j -= 1;
+        }
+    }
+    path.reverse();
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use textdistance::{Algorithm, DamerauLevenshtein, Levenshtein, SmithWaterman};
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    fn total(c: &Counts) -> usize {
+        c.insertions + c.deletions + c.substitutions + c.transpositions
+    }
+
+    #[test]
+    fn long_levenshtein_tracebacks_are_exact_without_a_band() {
+        let a: Vec<u8> = (0..2100u32).map(|i| (i * 7 % 13) as u8).collect();
+        let b: Vec<u8> = (0..2100u32).map(|i| (i * 5 % 13) as u8).collect();
+        let lev = Levenshtein::default().for_vec(&a, &b).val();
+        let (counts, approximate) = of("levenshtein", &a, &b, None).unwrap();
+        assert_eq!((total(&counts), approximate), (lev, false));
+        let (_, approximate) = of("levenshtein", &a, &b, Some(10)).unwrap();
+        assert!(approximate);
+    }
+
+    #[test]
+    fn counts_add_up_to_the_distance() {
+        let pairs = [
+            ("kitten", "sitting"),
+            ("ca", "abc"),
+            ("", "abc"),
+            ("flaw", "lawn"),
+            ("abcdef", "badcfe"),
+            ("nushell", "nutshell"),
+        ];
+        for (a, b) in pairs {
+            let (a, b) = (chars(a), chars(b));
+            let lev = Levenshtein::default().for_vec(&a, &b).val();
+            assert_eq!(total(&count(&edits(&a, &b, b.len(), false), &a, &b)), lev);
+            let dlev = DamerauLevenshtein::default().for_vec(&a, &b).val();
+            assert_eq!(total(&count(&damerau_levenshtein(&a, &b), &a, &b)), dlev);
+        }
+    }
+
+    #[test]
+    fn transpositions_are_counted_as_such() {
+        let (counts, _) = of("damerau_levenshtein", &chars("ca"), &chars("abc"), None).unwrap();
+        assert_eq!(
+            counts,
+            Counts {
+                insertions: 1,
+                transpositions: 1,
+                ..Counts::default()
+            }
+        );
+        let (counts, _) = of("levenshtein", &chars("kitten"), &chars("sitting"), None).unwrap();
+        assert_eq!((counts.substitutions, counts.insertions), (2, 1));
+    }
+
+    #[test]
+    fn alignments_render_and_score() {
+        let text = |t: &[char]| t.iter().collect::<String>();
+        let (a, b) = (chars("nutshell"), chars("nushell"));
+        let lines = render(&edits(&a, &b, b.len(), false), &a, &b, text, "");
+        assert_eq!(
+            lines,
+            ["nutshell", "|| |||||", "nu-shell"].map(String::from)
+        );
+        let (a, b) = (chars("ca"), chars("abc"));
+        let lines = render(&damerau_levenshtein(&a, &b), &a, &b, text, "");
+        assert_eq!(lines, ["ca ", "~~~", "abc"].map(String::from));
+        // kept tokens less gaps is the local score
+        for (a, b) in [("check", "shrek"), ("AGACTAGTTAC", "CGAGACGT")] {
+            let (a, b) = (chars(a), chars(b));
+            let path = smith_waterman(&a, &b, b.len());
+            let kept = path
+                .iter()
+                .filter(|e| matches!(e, Edit::Pair(i, j) if a[*i] == b[*j]))
+                .count();
+            let gaps = path.iter().filter(|e| !matches!(e, Edit::Pair(..))).count();
+            let score = SmithWaterman::default().for_vec(&a, &b).val();
+            assert_eq!(kept - gaps, score);
+        }
+    }
+
+    #[test]
+    fn bands_keep_local_edits() {
+        let a = chars(&"abcdefghij".repeat(20));
+        let mut b = a.clone();
+        b.remove(50);
+        b.swap(120, 121);
+        b.insert(180, 'x');
+        let full = total(&count(&edits(&a, &b, b.len(), false), &a, &b));
+        let banded = total(&count(&edits(&a, &b, 2, false), &a, &b));
+        assert_eq!((full, banded), (4, 4));
+        let osa = edits(&a, &b, 2, true);
+        assert_eq!(total(&count(&osa, &a, &b)), 3);
+        // a band too narrow for the shift still reaches the last cell
+        let shifted = chars(&format!("{}{}", "z".repeat(30), "abcdefghij".repeat(20)));
+        let path = edits(&a, &shifted, 1, false);
+        assert!(total(&count(&path, &a, &shifted)) >= 30);
+    }
+}
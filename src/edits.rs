@@ -0,0 +1,113 @@
+// Edit scripts between two sequences, built from an LCS alignment.
+
+use std::ops::Range;
+
+use crate::hirschberg;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+impl Op {
+    pub fn name(self) -> &'static str {
+        match self {
+            Op::Equal => "equal",
+            Op::Delete => "delete",
+            Op::Insert => "insert",
+        }
+    }
+}
+
+/// A run of one operation. Deletions have an empty `right` range positioned
+/// where the text was removed, insertions an empty `left` range.
+pub struct Hunk {
+    pub op: Op,
+    pub left: Range<usize>,
+    pub right: Range<usize>,
+}
+
+/// Hunks turning `a` into `b`, in order. Deletions come before insertions at
+/// the same position and adjacent hunks never share an operation.
+pub fn hunks<E: Eq>(a: &[E], b: &[E]) -> Vec<Hunk> {
+    let mut out: Vec<Hunk> = vec![];
+    let mut push = |op, left: Range<usize>, right: Range<usize>| {
+        if left.is_empty() && right.is_empty() {
+            return;
+        }
+        match out.last_mut() {
+            Some(last) if last.op == op => {
+                last.left.end = left.end;
+                last.right.end = right.end;
+            }
+            _ => out.push(Hunk { op, left, right }),
+        }
+    };
+
+    let (mut i, mut j) = (0, 0);
+    let ends = std::iter::once((a.len(), b.len()));
+    for (x, y) in hirschberg::lcs(a, b).into_iter().chain(ends) {
+        push(Op::Delete, i..x, j..j);
+        push(Op::Insert, x..x, j..y);
+        if x < a.len() {
+            push(Op::Equal, x..x + 1, y..y + 1);
+        }
+        (i, j) = (x + 1, y + 1);
+    }
+    out
+}
+
+/// Splits `s` into alternating runs of whitespace and non-whitespace, so a
+/// diff over the pieces works word by word and still covers every char.
+pub fn words(s: &str) -> Vec<&str> {
+    let mut out = vec![];
+    let mut start = 0;
+    let mut last_space = None;
+    for (i, c) in s.char_indices() {
+        let space = c.is_whitespace();
+        if last_space.is_some_and(|l| l != space) {
+            out.push(&s[start..i]);
+            start = i;
+        }
+        last_space = Some(space);
+    }
+    if start < s.len() {
+        out.push(&s[start..]);
+    }
+    out
+}
+
+/// Rewrites hunks over `pieces` of two strings into char offsets of the
+/// strings themselves.
+pub fn to_chars(hunks: Vec<Hunk>, a: &[&str], b: &[&str]) -> Vec<Hunk> {
+    let offsets = |pieces: &[&str]| -> Vec<usize> {
+        std::iter::once(0)
+            .chain(pieces.iter().scan(0, |n, p| {
+                *n += p.chars().count();
+                Some(*n)
+            }))
+            .collect()
+    };
+    let (oa, ob) = (offsets(a), offsets(b));
+    hunks
+        .into_iter()
+        .map(|h| Hunk {
+            op: h.op,
+            left: oa[h.left.start]..oa[h.left.end],
+            right: ob[h.right.start]..ob[h.right.end],
+        })
+        .collect()
+}
+
+/// The lines of `s`, each with its line break, so the pieces add up to `s`.
+pub fn lines(s: &str) -> Vec<&str> {
+    s.split_inclusive('\n').collect()
+}
+
+/// The lines of `s` without their line breaks, for comparing lines whatever
+/// their ending.
+pub fn line_keys(s: &str) -> Vec<&str> {
+    s.lines().collect()
+}
@@ -0,0 +1,159 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Span, Spanned, SyntaxShape, Value,
+};
+
+use crate::{fingerprint, flag_span, phonetic, StrSimilarityPlugin};
+
+pub const METHODS: [&str; 7] = [
+    "simhash",
+    "minhash",
+    "soundex",
+    "metaphone",
+    "double_metaphone",
+    "nysiis",
+    "winnow",
+];
+
+pub struct StrSimilarityEncode;
+
+impl SimplePluginCommand for StrSimilarityEncode {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity encode"
+    }
+
+    fn description(&self) -> &str {
+        "Emit the fingerprint or phonetic encoding of strings so it can be stored and compared later"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .named(
+                "method",
+                SyntaxShape::String,
+                "Encoding to emit: simhash, minhash, soundex, metaphone, double_metaphone, nysiis or winnow",
+                Some('m'),
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "With --method minhash, pick another family of hash functions (default: 0); signatures only compare under the same seed",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Soundex code of a name",
+                example: "'Robert' | str similarity encode --method soundex",
+                result: Some(Value::test_string("R163")),
+            },
+            Example {
+                description: "MinHash signatures under a seed of one's own",
+                example: "open notes.txt | lines | str similarity encode -m minhash --seed 42",
+                result: None,
+            },
+            Example {
+                description: "SimHash every line of a file",
+                example: "open notes.txt | lines | str similarity encode -m simhash",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let method: Spanned<String> = match call.get_flag("method")? {
+            Some(m) => m,
+            None => {
+                return Err(LabeledError::new("missing encoding method")
+                    .with_label("--method is required", call.head)
+                    .with_help(format!("valid methods: {}", METHODS.join(", "))));
+            }
+        };
+        if !METHODS.contains(&method.item.as_str()) {
+            return Err(LabeledError::new("unknown encoding method")
+                .with_label(format!("'{}' is not an encoding", method.item), method.span)
+                .with_help(format!("valid methods: {}", METHODS.join(", "))));
+        }
+
+        let seed = match call.get_flag::<i64>("seed")? {
+            Some(_) if method.item != "minhash" => {
+                return Err(LabeledError::new("--seed needs --method minhash")
+                    .with_label("only minhash is randomized", flag_span(call, "seed"))
+                    .with_help("the other encodings are the same for everyone"));
+            }
+            s => s.unwrap_or(0) as u64,
+        };
+        let encode_one = |s: &str, span: Span| encode(&method.item, s, seed, span);
+
+        match input {
+            Value::String { val, .. } => Ok(encode_one(val, input.span())),
+            Value::List { vals, .. } => vals
+                .iter()
+                .map(|v| match v {
+                    Value::String { val, .. } => Ok(encode_one(val, v.span())),
+                    v => Err(
+                        LabeledError::new(format!("expected a string, got {}", v.get_type()))
+                            .with_label("not a string", v.span()),
+                    ),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(|vals| Value::list(vals, input.span())),
+            v => Err(
+                LabeledError::new(format!("requires string input, got {}", v.get_type()))
+                    .with_label(
+                        "Expected a string or list of strings from pipeline",
+                        v.span(),
+                    )
+                    .with_help("encode each string of a column with `get <column>` first"),
+            ),
+        }
+    }
+}
+
+fn encode(method: &str, s: &str, seed: u64, span: Span) -> Value {
+    let hashes = |hs: Vec<u64>| {
+        Value::list(
+            hs.into_iter().map(|h| Value::int(h as i64, span)).collect(),
+            span,
+        )
+    };
+    match method {
+        "simhash" => Value::string(format!("{:016x}", fingerprint::simhash(s)), span),
+        "minhash" => hashes(fingerprint::minhash(s, seed)),
+        "winnow" => hashes(fingerprint::winnow(s)),
+        "metaphone" => Value::string(phonetic::metaphone(s), span),
+        "nysiis" => Value::string(phonetic::nysiis(s), span),
+        "double_metaphone" => {
+            let (primary, alternate) = phonetic::double_metaphone(s);
+            Value::record(
+                record! {
+                    "primary" => Value::string(primary, span),
+                    "alternate" => Value::string(alternate, span),
+                },
+                span,
+            )
+        }
+        _ => Value::string(phonetic::soundex(s), span),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityEncode)
+    }
+}
@@ -0,0 +1,613 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Record, Signature, Span, Spanned, SyntaxShape, Value,
+};
+use rayon::prelude::*;
+
+use crate::histogram;
+use crate::report::{self, Report};
+use crate::sample::{self, Rng};
+use crate::{algorithm, flag_span, similarity, AlgoParams, StrSimilarityPlugin, ALGORITHMS};
+
+/// The metrics of a set of decisions, in the order [`Confusion::rates`]
+/// returns them.
+const METRICS: [&str; 4] = ["precision", "recall", "f1", "accuracy"];
+
+/// Flags that set or show a threshold, which `--calibrate` picks itself.
+const THRESHOLD_FLAGS: [&str; 6] = ["threshold", "sweep", "steps", "auc", "histogram", "report"];
+
+pub struct StrSimilarityEvaluate;
+
+impl SimplePluginCommand for StrSimilarityEvaluate {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity evaluate"
+    }
+
+    fn description(&self) -> &str {
+        "Measure how well an algorithm and threshold reproduce labeled match decisions"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Normalized similarity (0 to 1) at or above which a pair is predicted to match (default: 0.5)",
+                Some('t'),
+            )
+            .switch(
+                "sweep",
+                "Report the metrics at every threshold from 0 to 1 instead of at one",
+                None,
+            )
+            .named(
+                "steps",
+                SyntaxShape::Int,
+                "Number of equal steps between 0 and 1 for --sweep (default: 20); implies --sweep",
+                None,
+            )
+            .switch(
+                "auc",
+                "With --sweep, also report the area under the ROC curve",
+                None,
+            )
+            .switch(
+                "calibrate",
+                "Find the threshold with the best --metric for every algorithm (or the one given with -a), each with a profile record using it",
+                None,
+            )
+            .named(
+                "metric",
+                SyntaxShape::String,
+                "With --calibrate, the metric to maximize: f1 (the default), precision, recall or accuracy",
+                None,
+            )
+            .named(
+                "left",
+                SyntaxShape::String,
+                "Column holding the first string (default: left)",
+                None,
+            )
+            .named(
+                "right",
+                SyntaxShape::String,
+                "Column holding the second string (default: right)",
+                None,
+            )
+            .named(
+                "expected",
+                SyntaxShape::String,
+                "Column holding whether the pair should match (default: expected_match)",
+                None,
+            )
+            .named(
+                "histogram",
+                SyntaxShape::Int,
+                "Count expected matches and non-matches in this many score bins instead, to see how well they separate",
+                None,
+            )
+            .named(
+                "sample",
+                SyntaxShape::Int,
+                "Score only this many randomly chosen rows, for a quick estimate on a large set",
+                None,
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "Seed for --sample, so the same rows are drawn every run; the result holds the seed used",
+                None,
+            )
+            .named(
+                "report",
+                SyntaxShape::String,
+                "Render a markdown or html report with the metrics and the most suspicious pairs instead",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "See how jaro_winkler at 0.9 does on hand-labeled pairs",
+                example: "open labeled.csv | str similarity evaluate -a jaro_winkler -t 0.9",
+                result: None,
+            },
+            Example {
+                description: "Find the threshold with the best F1",
+                example: "open labeled.csv | str similarity evaluate -a jaro_winkler --sweep | sort-by f1 | last",
+                result: None,
+            },
+            Example {
+                description: "Pick the algorithm and threshold with the best F1 and save them as a profile",
+                example: "let best = (open labeled.csv | str similarity evaluate --calibrate | first); str similarity profile save names $best.profile",
+                result: None,
+            },
+            Example {
+                description: "Write a markdown report to attach to a pull request",
+                example: "open labeled.csv | str similarity evaluate -a jaro_winkler -t 0.9 --report markdown | save report.md",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let algo = algorithm(call)?;
+        let threshold: f64 = call.get_flag("threshold")?.unwrap_or(0.5);
+        let params = AlgoParams::from_call(call)?;
+        let left: String = call.get_flag("left")?.unwrap_or_else(|| "left".into());
+        let right: String = call.get_flag("right")?.unwrap_or_else(|| "right".into());
+        let steps = match call.get_flag::<Spanned<i64>>("steps")? {
+            Some(n) if n.item < 1 => {
+                return Err(LabeledError::new("invalid step count")
+                    .with_label("need at least one step", n.span)
+                    .with_help("the default is 20 steps of 0.05"));
+            }
+            Some(n) => Some(n.item as usize),
+            None if call.has_flag("sweep")? => Some(20),
+            None => None,
+        };
+        if steps.is_none() && call.has_flag("auc")? {
+            return Err(LabeledError::new("--auc needs --sweep")
+                .with_label("only reported alongside a sweep", flag_span(call, "auc"))
+                .with_help("add --sweep"));
+        }
+        let expected: String = call
+            .get_flag("expected")?
+            .unwrap_or_else(|| "expected_match".into());
+        let format = report::Format::from_call(call)?;
+        if format.is_some() && call.has_flag("histogram")? {
+            return Err(LabeledError::new("--report and --histogram are exclusive")
+                .with_label("a report has no histogram", flag_span(call, "report"))
+                .with_help("drop one of them"));
+        }
+
+        let rows = match input {
+            Value::List { vals, .. } => vals,
+            v => {
+                return Err(
+                    LabeledError::new(format!("requires a table, got {}", v.get_type()))
+                        .with_label("Expected labeled pairs from pipeline", v.span())
+                        .with_help(format!(
+                            "pipe in rows with {left}, {right} and {expected} columns"
+                        )),
+                )
+            }
+        };
+
+        let mut seed = None;
+        let rows: Vec<&Value> = match sample::size(call)? {
+            Some(n) => {
+                let mut rng = Rng::from_call(call)?;
+                seed = Some(rng.seed());
+                sample::indices(&mut rng, n, rows.len())
+                    .into_iter()
+                    .map(|i| &rows[i])
+                    .collect()
+            }
+            None => rows.iter().collect(),
+        };
+
+        let metric = match call.get_flag::<Spanned<String>>("metric")? {
+            Some(m) => match METRICS.iter().position(|n| *n == m.item) {
+                Some(i) => Some(i),
+                None => {
+                    return Err(LabeledError::new("unknown metric")
+                        .with_label(format!("'{}' is not a metric", m.item), m.span)
+                        .with_help(format!("valid metrics: {}", METRICS.join(", "))))
+                }
+            },
+            None => None,
+        };
+        let calibrate = call.has_flag("calibrate")?;
+        if metric.is_some() && !calibrate {
+            return Err(LabeledError::new("--metric needs --calibrate")
+                .with_label("only picks a threshold", flag_span(call, "metric"))
+                .with_help("add --calibrate"));
+        }
+        if let Some(flag) = THRESHOLD_FLAGS
+            .into_iter()
+            .find(|f| calibrate && call.named.iter().any(|(n, _)| n.item == *f))
+        {
+            return Err(
+                LabeledError::new(format!("--calibrate and --{flag} are exclusive"))
+                    .with_label("--calibrate picks the threshold", flag_span(call, flag))
+                    .with_help(format!("drop --{flag}")),
+            );
+        }
+
+        let mut scored = Vec::with_capacity(rows.len());
+        let mut pairs = Vec::with_capacity(rows.len());
+        let mut labels = Vec::with_capacity(rows.len());
+        for row in rows {
+            let field = |col: &str| {
+                row.as_record()
+                    .ok()
+                    .and_then(|r| r.get(col))
+                    .ok_or_else(|| {
+                        LabeledError::new(format!("missing column '{col}'"))
+                            .with_label("row has no such column", row.span())
+                            .with_help("pick the columns with --left, --right and --expected")
+                    })
+            };
+            let (a, b) = (field(&left)?.as_str()?, field(&right)?.as_str()?);
+            let want = field(&expected)?.as_bool()?;
+            pairs.push((a, b));
+            labels.push(want);
+        }
+        let chars: Vec<(Vec<char>, Vec<char>)> = pairs
+            .iter()
+            .map(|(a, b)| (a.chars().collect(), b.chars().collect()))
+            .collect();
+
+        let span = call.head;
+        if calibrate {
+            let algorithms: Vec<&str> = match call.get_flag::<String>("algorithm")? {
+                Some(_) => vec![algo.as_str()],
+                None => ALGORITHMS.iter().map(|a| a.name).collect(),
+            };
+            let metric = metric.unwrap_or(2);
+            let mut rows: Vec<(Option<f64>, Value)> = algorithms
+                .par_iter()
+                .map(|a| {
+                    let scored: Vec<(f64, bool)> = chars
+                        .iter()
+                        .zip(&labels)
+                        .map(|((x, y), want)| (similarity(a, x, y, &params), *want))
+                        .collect();
+                    let best = best_threshold(&scored, metric);
+                    let value = best.as_ref().and_then(|(_, c)| c.rates()[metric]);
+                    (value, calibrated(call, a, best, seed, span))
+                })
+                .collect();
+            // stable, so equally good algorithms stay in catalog order
+            rows.sort_by(|x, y| {
+                let v = |r: &Option<f64>| r.unwrap_or(f64::NEG_INFINITY);
+                v(&y.0).total_cmp(&v(&x.0))
+            });
+            return Ok(Value::list(
+                rows.into_iter().map(|(_, r)| r).collect(),
+                span,
+            ));
+        }
+        for ((a, b), want) in chars.iter().zip(&labels) {
+            scored.push((similarity(&algo, a, b, &params), *want));
+        }
+        if let Some(bins) = histogram::bins(call)? {
+            let (matches, others): (Vec<&(f64, bool)>, Vec<_>) =
+                scored.iter().partition(|(_, want)| *want);
+            let series = [
+                (
+                    "matches",
+                    histogram::count(matches.iter().map(|s| s.0), bins),
+                ),
+                (
+                    "non_matches",
+                    histogram::count(others.iter().map(|s| s.0), bins),
+                ),
+            ];
+            return Ok(Value::list(histogram::rows(&series, bins, span), span));
+        }
+        if let Some(format) = format {
+            let mut report = Report::new(format, &format!("Evaluation of {algo}"));
+            if let Some(seed) = seed {
+                report.summary(&record! {
+                    "sampled_rows" => Value::int(scored.len() as i64, span),
+                    "seed" => Value::int(seed, span),
+                });
+            }
+            let confusion = Confusion::at(&scored, threshold);
+            report.section(&format!("At threshold {threshold}"));
+            report.summary(&confusion.metrics(span));
+            report.summary(&confusion.counts(span));
+            if let Some(steps) = steps {
+                report.section("Sweep");
+                if call.has_flag("auc")? {
+                    report.summary(&record! {
+                        "auc" => auc(&scored).map_or(Value::nothing(span), |a| Value::float(a, span)),
+                    });
+                }
+                if let Value::List { vals, .. } = sweep(&scored, steps, span) {
+                    report.records(&vals);
+                }
+            }
+
+            // the wrong decisions furthest from the threshold are the ones
+            // the algorithm is most confidently wrong about
+            let mut wrong: Vec<usize> = (0..scored.len())
+                .filter(|&i| (scored[i].0 >= threshold) != scored[i].1)
+                .collect();
+            let margin = |i: usize| (scored[i].0 - threshold).abs();
+            wrong.sort_by(|&i, &j| margin(j).total_cmp(&margin(i)));
+            let rows: Vec<Vec<String>> = wrong
+                .into_iter()
+                .take(report::SUSPICIOUS)
+                .map(|i| {
+                    let (score, want) = scored[i];
+                    vec![
+                        pairs[i].0.to_string(),
+                        pairs[i].1.to_string(),
+                        format!("{score:.3}"),
+                        want.to_string(),
+                    ]
+                })
+                .collect();
+            report.section("Most suspicious pairs");
+            report.table(&[&left, &right, "score", &expected], &rows);
+            return Ok(report.into_value(span));
+        }
+        if let Some(steps) = steps {
+            let sweep = sweep(&scored, steps, span);
+            if call.has_flag("auc")? {
+                let mut rec = record! {
+                    "algorithm" => Value::string(algo, span),
+                    "auc" => auc(&scored).map_or(Value::nothing(span), |a| Value::float(a, span)),
+                    "sweep" => sweep,
+                };
+                if let Some(seed) = seed {
+                    rec.push("seed", Value::int(seed, span));
+                }
+                return Ok(Value::record(rec, span));
+            }
+            return Ok(sweep);
+        }
+        let mut rec = Confusion::at(&scored, threshold).report(&algo, threshold, span);
+        if let Some(seed) = seed {
+            rec.push("seed", Value::int(seed, span));
+        }
+        Ok(Value::record(rec, span))
+    }
+}
+
+/// One row per threshold from 0 to 1 in `steps` equal steps.
+fn sweep(scored: &[(f64, bool)], steps: usize, span: Span) -> Value {
+    let rows = (0..=steps)
+        .map(|i| {
+            let threshold = i as f64 / steps as f64;
+            let c = Confusion::at(scored, threshold);
+            let mut row = record! { "threshold" => Value::float(threshold, span) };
+            for (col, val) in c.metrics(span).into_iter().chain(c.counts(span)) {
+                row.push(col, val);
+            }
+            Value::record(row, span)
+        })
+        .collect();
+    Value::list(rows, span)
+}
+
+/// Area under the ROC curve: the chance that a random expected match scores
+/// above a random expected non-match, ties counting half. Empty unless both
+/// kinds of pairs are present.
+fn auc(scored: &[(f64, bool)]) -> Option<f64> {
+    let mut sorted = scored.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let positives = sorted.iter().filter(|(_, want)| *want).count();
+    let negatives = sorted.len() - positives;
+    if positives == 0 || negatives == 0 {
+        return None;
+    }
+
+    // for each group of tied scores, every positive beats the negatives
+    // below the group and ties with those inside it
+    let (mut below, mut wins, mut i) = (0usize, 0.0, 0);
+    while i < sorted.len() {
+        let j = i + sorted[i..]
+            .iter()
+            .take_while(|s| s.0 == sorted[i].0)
+            .count();
+        let pos = sorted[i..j].iter().filter(|(_, want)| *want).count();
+        let neg = (j - i) - pos;
+        wins += pos as f64 * (below as f64 + neg as f64 / 2.0);
+        below += neg;
+        i = j;
+    }
+    Some(wins / (positives * negatives) as f64)
+}
+
+/// The threshold at which metric `metric` of [`METRICS`] is best on
+/// `scored`, with the decisions there. The candidates are the scores
+/// themselves, where the decisions change; of equally good ones the highest
+/// wins, letting the fewest pairs match.
+fn best_threshold(scored: &[(f64, bool)], metric: usize) -> Option<(f64, Confusion)> {
+    let mut sorted = scored.to_vec();
+    sorted.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let positives = sorted.iter().filter(|(_, want)| *want).count();
+    // lowering the threshold past each score turns its pairs into matches
+    let mut c = Confusion {
+        true_negative: sorted.len() - positives,
+        false_negative: positives,
+        ..Confusion::default()
+    };
+    let mut best: Option<(f64, f64, Confusion)> = None;
+    let mut i = 0;
+    while i < sorted.len() {
+        let threshold = sorted[i].0;
+        while i < sorted.len() && sorted[i].0 == threshold {
+            if sorted[i].1 {
+                c.true_positive += 1;
+                c.false_negative -= 1;
+            } else {
+                c.false_positive += 1;
+                c.true_negative -= 1;
+            }
+            i += 1;
+        }
+        if let Some(v) = c.rates()[metric] {
+            if !best.as_ref().is_some_and(|b| b.1 >= v) {
+                best = Some((threshold, v, c.clone()));
+            }
+        }
+    }
+    best.map(|(threshold, _, c)| (threshold, c))
+}
+
+/// The `--calibrate` row of algorithm `algo`: its best threshold, the
+/// metrics there, and a profile of the algorithm, the threshold and the
+/// algorithm parameters of `call`. Empty without a threshold to pick, when
+/// the metric is never defined.
+fn calibrated(
+    call: &EvaluatedCall,
+    algo: &str,
+    best: Option<(f64, Confusion)>,
+    seed: Option<i64>,
+    span: Span,
+) -> Value {
+    let mut row = record! { "algorithm" => Value::string(algo, span) };
+    match best {
+        Some((threshold, c)) => {
+            row.push("threshold", Value::float(threshold, span));
+            for (col, val) in c.metrics(span) {
+                row.push(col, val);
+            }
+            let mut profile = record! {
+                "algorithm" => Value::string(algo, span),
+                "threshold" => Value::float(threshold, span),
+            };
+            for (flag, value) in &call.named {
+                if let ("params" | "jaro-window" | "multiset" | "prefix-boost", Some(v)) =
+                    (flag.item.as_str(), value)
+                {
+                    profile.push(flag.item.clone(), v.clone());
+                }
+            }
+            row.push("profile", Value::record(profile, span));
+        }
+        None => {
+            row.push("threshold", Value::nothing(span));
+            for metric in METRICS {
+                row.push(metric, Value::nothing(span));
+            }
+            row.push("profile", Value::nothing(span));
+        }
+    }
+    if let Some(seed) = seed {
+        row.push("seed", Value::int(seed, span));
+    }
+    Value::record(row, span)
+}
+
+/// Predicted against expected match decisions.
+#[derive(Clone, Default)]
+struct Confusion {
+    true_positive: usize,
+    false_positive: usize,
+    true_negative: usize,
+    false_negative: usize,
+}
+
+impl Confusion {
+    /// Decisions at `threshold` for pairs of (similarity, expected match).
+    fn at(scored: &[(f64, bool)], threshold: f64) -> Self {
+        let mut c = Confusion::default();
+        for &(score, want) in scored {
+            c.add(score >= threshold, want);
+        }
+        c
+    }
+
+    fn add(&mut self, predicted: bool, expected: bool) {
+        match (predicted, expected) {
+            (true, true) => self.true_positive += 1,
+            (true, false) => self.false_positive += 1,
+            (false, false) => self.true_negative += 1,
+            (false, true) => self.false_negative += 1,
+        }
+    }
+
+    /// Precision, recall, F1 and accuracy, which are empty when their
+    /// denominator is zero (e.g. precision when nothing was predicted to
+    /// match).
+    fn rates(&self) -> [Option<f64>; 4] {
+        let ratio = |num: usize, den: usize| match den {
+            0 => None,
+            den => Some(num as f64 / den as f64),
+        };
+        let tp = self.true_positive;
+        let precision = ratio(tp, tp + self.false_positive);
+        let recall = ratio(tp, tp + self.false_negative);
+        let f1 = match (precision, recall) {
+            (Some(p), Some(r)) if p + r > 0.0 => Some(2.0 * p * r / (p + r)),
+            (Some(_), Some(_)) => Some(0.0),
+            _ => None,
+        };
+        let total = tp + self.false_positive + self.true_negative + self.false_negative;
+        [precision, recall, f1, ratio(tp + self.true_negative, total)]
+    }
+
+    /// [`Confusion::rates`] as a record.
+    fn metrics(&self, span: Span) -> Record {
+        let float = |v: Option<f64>| v.map_or(Value::nothing(span), |v| Value::float(v, span));
+        METRICS
+            .into_iter()
+            .zip(self.rates())
+            .map(|(name, v)| (name.to_string(), float(v)))
+            .collect()
+    }
+
+    fn counts(&self, span: Span) -> Record {
+        let int = |n: usize| Value::int(n as i64, span);
+        record! {
+            "true_positive" => int(self.true_positive),
+            "false_positive" => int(self.false_positive),
+            "true_negative" => int(self.true_negative),
+            "false_negative" => int(self.false_negative),
+        }
+    }
+
+    /// The metrics at one threshold, with the counts they come from.
+    fn report(&self, algo: &str, threshold: f64, span: Span) -> Record {
+        let mut rec = record! {
+            "algorithm" => Value::string(algo, span),
+            "threshold" => Value::float(threshold, span),
+        };
+        for (col, val) in self.metrics(span) {
+            rec.push(col, val);
+        }
+        rec.push("confusion", Value::record(self.counts(span), span));
+        rec
+    }
+}
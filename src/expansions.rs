@@ -0,0 +1,134 @@
+// Abbreviation expansion before scoring, e.g. "St" → "Street", so that
+// "12 Main St." and "12 Main Street" compare as the same address.
+
+use std::collections::HashMap;
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Value};
+
+/// Abbreviations, lowercase and without a trailing period, mapped to every
+/// expansion given for them.
+pub struct Expansions(HashMap<String, Vec<String>>);
+
+impl Expansions {
+    /// Reads `--expansions`: a record of abbreviations to an expansion or a
+    /// list of alternatives, or a table with `abbreviation` and `expansion`
+    /// columns, where repeated abbreviations add alternatives.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        let Some(table) = call.get_flag::<Value>("expansions")? else {
+            return Ok(None);
+        };
+        let mut map: HashMap<String, Vec<String>> = HashMap::new();
+        let mut add = |abbr: &str, v: &Value| -> Result<(), LabeledError> {
+            let alternatives = match v {
+                Value::List { vals, .. } => vals.iter().map(expansion).collect::<Result<_, _>>()?,
+                v => vec![expansion(v)?],
+            };
+            map.entry(key(abbr)).or_default().extend(alternatives);
+            Ok(())
+        };
+        match &table {
+            Value::Record { val, .. } => {
+                for (abbr, v) in val.iter() {
+                    add(abbr, v)?;
+                }
+            }
+            Value::List { vals, .. } => {
+                for row in vals {
+                    let record = row.as_record()?;
+                    let column = |name: &str| {
+                        record.get(name).ok_or_else(|| {
+                            LabeledError::new(format!("missing column '{name}'"))
+                                .with_label("row has no such column", row.span())
+                                .with_help(
+                                    "expansion tables have abbreviation and expansion columns",
+                                )
+                        })
+                    };
+                    add(column("abbreviation")?.as_str()?, column("expansion")?)?;
+                }
+            }
+            v => {
+                return Err(LabeledError::new(format!(
+                    "expected a record or a table of expansions, got {}",
+                    v.get_type()
+                ))
+                .with_label("not an expansion table", v.span())
+                .with_help("e.g. --expansions {St: Street, Dr: [Doctor Drive]}"))
+            }
+        }
+        Ok(Some(Expansions(map)))
+    }
+
+    /// `a` and `b` with every abbreviation expanded. When an abbreviation
+    /// has several expansions, each occurrence gets the one `score` rates
+    /// best against the other string, trying one occurrence at a time.
+    /// Words end up separated by single spaces.
+    pub fn apply(&self, a: &str, b: &str, score: impl Fn(&str, &str) -> f64) -> (String, String) {
+        let mut sides = [self.words(a), self.words(b)];
+        for side in 0..2 {
+            for i in 0..sides[side].len() {
+                let alternatives = sides[side][i].1.clone();
+                if alternatives.len() < 2 {
+                    continue;
+                }
+                let best = alternatives
+                    .iter()
+                    .map(|alt| {
+                        let mut candidate = sides[side].clone();
+                        candidate[i].0 = alt.clone();
+                        let (x, y) = match side {
+                            0 => (join(&candidate), join(&sides[1])),
+                            _ => (join(&sides[0]), join(&candidate)),
+                        };
+                        (score(&x, &y), alt)
+                    })
+                    .fold(None, |best: Option<(f64, &String)>, (s, alt)| match best {
+                        Some((b, _)) if b >= s => best,
+                        _ => Some((s, alt)),
+                    })
+                    .map(|(_, alt)| alt.clone());
+                if let Some(best) = best {
+                    sides[side][i].0 = best;
+                }
+            }
+        }
+        let [a, b] = sides;
+        (join(&a), join(&b))
+    }
+
+    /// The words of `s`, each with its first expansion, or kept as is, and
+    /// every alternative.
+    fn words(&self, s: &str) -> Vec<(String, Vec<String>)> {
+        s.split_whitespace()
+            .map(|w| match self.0.get(&key(w)) {
+                Some(alts) => (alts[0].clone(), alts.clone()),
+                None => (w.to_string(), vec![]),
+            })
+            .collect()
+    }
+}
+
+fn key(word: &str) -> String {
+    word.trim_end_matches('.').to_lowercase()
+}
+
+fn expansion(v: &Value) -> Result<String, LabeledError> {
+    match v {
+        Value::String { val, .. } => Ok(val.clone()),
+        v => Err(LabeledError::new(format!(
+            "expected an expansion string, got {}",
+            v.get_type()
+        ))
+        .with_label("not a string", v.span())
+        .with_help("give an expansion, or a list of alternatives")),
+    }
+}
+
+fn join(words: &[(String, Vec<String>)]) -> String {
+    words
+        .iter()
+        .map(|(w, _)| w.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,205 @@
+// Record matching on several fields, each with its own algorithm.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{record, Config, LabeledError, Span, Spanned, Value};
+
+use crate::{aliases, check_algorithm, flag_span, number, similarity, text, AlgoParams};
+
+/// One field to compare and how.
+pub struct Field {
+    name: String,
+    algorithm: String,
+    /// Weight of the field in the mean when either side lacks it; it then
+    /// counts as a score of 0.
+    missing_weight: f64,
+}
+
+/// What to do when a record lacks a field, or has it empty.
+#[derive(Clone, Copy, PartialEq)]
+enum Missing {
+    /// Leave the field out of that pair's mean.
+    Ignore,
+    /// Count the field as a score of 0.
+    Penalize,
+    /// Stop with an error.
+    Error,
+}
+
+const POLICIES: [&str; 3] = ["ignore", "penalize", "error"];
+
+/// The fields of one record, `None` where missing.
+pub type Values = Vec<Option<Vec<char>>>;
+
+pub struct Fields {
+    fields: Vec<Field>,
+    missing: Missing,
+}
+
+impl Fields {
+    /// Reads `--field-algorithms`, `--missing` and `--missing-weight`,
+    /// checking every algorithm name. `None` without `--field-algorithms`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        let missing = match call.get_flag::<Spanned<String>>("missing")? {
+            Some(m) => match m.item.as_str() {
+                "ignore" => Missing::Ignore,
+                "penalize" => Missing::Penalize,
+                "error" => Missing::Error,
+                _ => {
+                    return Err(LabeledError::new("unknown missing-field policy")
+                        .with_label(format!("'{}' is not a policy", m.item), m.span)
+                        .with_help(format!("valid policies: {}", POLICIES.join(", "))))
+                }
+            },
+            None => Missing::Error,
+        };
+        let weights = match call.get_flag::<Value>("missing-weight")? {
+            Some(_) if missing == Missing::Error => {
+                return Err(LabeledError::new("--missing-weight needs a missing policy")
+                    .with_label(
+                        "missing fields are errors",
+                        flag_span(call, "missing-weight"),
+                    )
+                    .with_help("add --missing penalize or --missing ignore"));
+            }
+            Some(w) => Some(w.into_record()?),
+            None => None,
+        };
+
+        let Some(algorithms) = call.get_flag::<Value>("field-algorithms")? else {
+            for flag in ["missing", "missing-weight"] {
+                if call.has_flag(flag)? {
+                    return Err(
+                        LabeledError::new(format!("--{flag} needs --field-algorithms"))
+                            .with_label("only used for multi-field records", flag_span(call, flag))
+                            .with_help("list the fields to match in --field-algorithms"),
+                    );
+                }
+            }
+            return Ok(None);
+        };
+        let default_weight = match missing {
+            Missing::Ignore => 0.0,
+            _ => 1.0,
+        };
+        let fields = algorithms
+            .into_record()?
+            .into_iter()
+            .map(|(name, algo)| {
+                let spanned = Spanned {
+                    item: algo.coerce_str()?.to_string(),
+                    span: algo.span(),
+                };
+                let algorithm = aliases::resolve(call, spanned)?.item;
+                check_algorithm(&algorithm, algo.span())?;
+                let missing_weight = match weights.as_ref().and_then(|w| w.get(&name)) {
+                    Some(w) => w.coerce_float()?,
+                    None => default_weight,
+                };
+                Ok(Field {
+                    name,
+                    algorithm,
+                    missing_weight,
+                })
+            })
+            .collect::<Result<Vec<_>, LabeledError>>()?;
+        Ok(Some(Fields { fields, missing }))
+    }
+
+    /// The compared fields of record `v`. Absent and null fields are missing,
+    /// which is an error unless a `--missing` policy says otherwise.
+    pub fn values(&self, v: &Value, coerce: Option<&Config>) -> Result<Values, LabeledError> {
+        let record = v.as_record().map_err(|_| {
+            LabeledError::new(format!("expected a record, got {}", v.get_type()))
+                .with_label("not a record", v.span())
+                .with_help("--field-algorithms matches records field by field")
+        })?;
+        self.fields
+            .iter()
+            .map(|f| match record.get(&f.name) {
+                Some(Value::Nothing { .. }) | None if self.missing != Missing::Error => Ok(None),
+                Some(Value::Nothing { .. }) | None => {
+                    Err(LabeledError::new(format!("missing field '{}'", f.name))
+                        .with_label("row has no such field", v.span())
+                        .with_help(
+                            "pass --missing ignore or --missing penalize for sparse records",
+                        ))
+                }
+                Some(found) => match text(found, coerce) {
+                    Some(s) => Ok(Some(s.chars().collect())),
+                    None => Err(LabeledError::new(format!(
+                        "expected a string, got {}",
+                        found.get_type()
+                    ))
+                    .with_label("not a string", found.span())
+                    .with_help("only string fields can be matched, or scalars with --coerce")),
+                },
+            })
+            .collect()
+    }
+
+    /// Weighted mean of the fields' normalized similarities. Present fields
+    /// weigh 1; a field missing on either side scores 0 with its missing
+    /// weight. 0 when nothing could be compared.
+    pub fn similarity(&self, l: &Values, r: &Values, params: &AlgoParams) -> f64 {
+        let parts = self.parts(l, r, params);
+        let weight: f64 = parts.iter().map(|(_, w)| w).sum();
+        if weight == 0.0 {
+            return 0.0;
+        }
+        parts.iter().map(|(s, w)| s.unwrap_or(0.0) * w).sum::<f64>() / weight
+    }
+
+    /// Each field's score, `None` when missing, and weight in the mean.
+    fn parts(&self, l: &Values, r: &Values, params: &AlgoParams) -> Vec<(Option<f64>, f64)> {
+        self.fields
+            .iter()
+            .zip(l.iter().zip(r))
+            .map(|(f, (lf, rf))| match (lf, rf) {
+                (Some(a), Some(b)) => (Some(similarity(&f.algorithm, a, b, params)), 1.0),
+                _ => (None, f.missing_weight),
+            })
+            .collect()
+    }
+
+    /// Why a pair got its [`Fields::similarity`]: a row per field with its
+    /// algorithm, its score (empty when missing), its weight and its
+    /// contribution to the mean. The contributions add up to the score.
+    pub fn explain(&self, l: &Values, r: &Values, params: &AlgoParams, span: Span) -> Value {
+        let parts = self.parts(l, r, params);
+        let weight: f64 = parts.iter().map(|(_, w)| w).sum();
+        let rows = self
+            .fields
+            .iter()
+            .zip(parts)
+            .map(|(f, (score, w))| {
+                let contribution = match weight {
+                    0.0 => 0.0,
+                    total => score.unwrap_or(0.0) * w / total,
+                };
+                explanation(&f.name, &f.algorithm, score, w, contribution, span)
+            })
+            .collect();
+        Value::list(rows, span)
+    }
+}
+
+/// A row of `--explain-match` output.
+pub fn explanation(
+    field: &str,
+    algorithm: &str,
+    score: Option<f64>,
+    weight: f64,
+    contribution: f64,
+    span: Span,
+) -> Value {
+    Value::record(
+        record! {
+            "field" => Value::string(field, span),
+            "algorithm" => Value::string(algorithm, span),
+            "score" => score.map_or(Value::nothing(span), |s| number(s, span)),
+            "weight" => number(weight, span),
+            "contribution" => number(contribution, span),
+        },
+        span,
+    )
+}
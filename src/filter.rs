@@ -0,0 +1,261 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    record, Category, Example, IntoInterruptiblePipelineData, LabeledError, PipelineData,
+    Signature, Spanned, SyntaxShape, Type, Value,
+};
+
+use crate::format::Format;
+use crate::preprocess::Pipeline;
+use crate::profile;
+use crate::{algorithm, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityFilter;
+
+impl PluginCommand for StrSimilarityFilter {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity filter"
+    }
+
+    fn description(&self) -> &str {
+        "Keep the lines of a stream similar enough to a query, emitting each as soon as it passes"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_types(vec![
+                (
+                    Type::List(Box::new(Type::String)),
+                    Type::List(Box::new(Type::Any)),
+                ),
+                (Type::String, Type::List(Box::new(Type::Any))),
+            ])
+            .required("query", SyntaxShape::String, "String to compare every line to")
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum normalized similarity (0 to 1) for a line to pass (default: 0.8)",
+                Some('t'),
+            )
+            .switch(
+                "score",
+                "Emit {line, score} records instead of the bare lines",
+                Some('s'),
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "profile",
+                SyntaxShape::String,
+                "Take any flag not given here from the settings saved under this name with `str similarity profile save`",
+                None,
+            )
+            .named(
+                "config",
+                SyntaxShape::OneOf(vec![SyntaxShape::Record(vec![]), SyntaxShape::Filepath]),
+                "Take any flag not given here from this record of long flag names and values, or from the nuon or json file holding one",
+                None,
+            )
+            .named(
+                "preset",
+                SyntaxShape::String,
+                "Take any flag not given here or by --profile from a built-in preset: loose, standard or strict",
+                None,
+            )
+            .named(
+                "mask",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Replace volatile tokens in both strings with placeholders before anything else: uuids, timestamps, ips, hex, numbers",
+                None,
+            )
+            .named(
+                "mask-pattern",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Also replace matches of these regular expressions with a placeholder, like --mask",
+                None,
+            )
+            .named(
+                "preprocess",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Steps run in order on both strings before anything else: lowercase, strip-accents, strip-punctuation, collapse-spaces, trim, fold-compat, deleet",
+                None,
+            )
+            .switch(
+                "ignore-case",
+                "Lowercase both strings before scoring, like --preprocess [lowercase]",
+                None,
+            )
+            .switch(
+                "trim",
+                "Trim whitespace from both ends of both strings before scoring",
+                None,
+            )
+            .switch(
+                "collapse-whitespace",
+                "Squeeze runs of whitespace in both strings into one space before scoring",
+                None,
+            )
+            .switch(
+                "strip-accents",
+                "Drop diacritics from both strings before scoring, reading letters like ø and ł as o and l",
+                None,
+            )
+            .switch(
+                "strip-invisible",
+                "Drop soft hyphens, zero width spaces and joiners and byte order marks from both strings before scoring",
+                None,
+            )
+            .switch(
+                "fold-compat",
+                "Compare what a human reads: apply NFKC to both strings, turning ligatures, superscripts and roman numerals into plain letters and digits, and fold typographic quotes and dashes",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Grep a huge log for lines resembling an error message",
+                example: "open --raw huge.log | lines | str similarity filter 'connection reset by peer' -a jaro_winkler -t 0.85",
+                result: None,
+            },
+            Example {
+                description: "Keep the scores of the passing lines",
+                example: "open --raw huge.log | str similarity filter 'disk full' --score | first 10",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let call = &profile::apply(plugin, engine, call, &PluginCommand::signature(self))?;
+        let format = Format::from_call(call)?;
+        let query: Spanned<String> = call.req(0)?;
+        let algo = algorithm(call)?;
+        let threshold = match call.get_flag::<Spanned<f64>>("threshold")? {
+            Some(t) if !(0.0..=1.0).contains(&t.item) => {
+                return Err(LabeledError::new("invalid threshold")
+                    .with_label("must be between 0 and 1", t.span)
+                    .with_help("thresholds are normalized similarities, whatever the algorithm"));
+            }
+            Some(t) => t.item,
+            None => 0.8,
+        };
+        let with_score = call.has_flag("score")?;
+        let params = AlgoParams::from_call(call)?;
+        let pipeline = Pipeline::from_call(call)?;
+        let query: Vec<char> = pipeline.apply(&query.item).chars().collect();
+        let span = call.head;
+        let signals = engine.signals().clone();
+
+        // Nothing here collects: each line is scored when it is pulled
+        // downstream and dropped unless it passes, so memory stays flat and
+        // an interrupt ends the stream at the next line.
+        let keep = move |line: Value| -> Option<Value> {
+            let line_span = line.span();
+            let text = match line.as_str() {
+                Ok(text) => text,
+                Err(e) => return Some(Value::error(e, line_span)),
+            };
+            let chars: Vec<char> = pipeline.apply(text).chars().collect();
+            let score = similarity(&algo, &query, &chars, &params);
+            if score < threshold {
+                return None;
+            }
+            Some(match with_score {
+                true => Value::record(
+                    record! {
+                        "line" => line,
+                        "score" => format.score(score, line_span),
+                    },
+                    line_span,
+                ),
+                false => line,
+            })
+        };
+        match input {
+            // raw text is split into lines as it arrives
+            PipelineData::ByteStream(stream, _) => {
+                let Some(lines) = stream.lines() else {
+                    return Ok(PipelineData::Empty);
+                };
+                Ok(lines
+                    .filter_map(move |line| match line {
+                        Ok(line) => keep(Value::string(line, span)),
+                        Err(e) => Some(Value::error(e, span)),
+                    })
+                    .into_pipeline_data(span, signals))
+            }
+            PipelineData::Value(Value::String { .. }, _) => {
+                let text = input.into_value(span)?;
+                let lines: Vec<Value> = text
+                    .as_str()?
+                    .lines()
+                    .map(|l| Value::string(l, text.span()))
+                    .collect();
+                Ok(lines
+                    .into_iter()
+                    .filter_map(keep)
+                    .into_pipeline_data(span, signals))
+            }
+            input => Ok(input.flat_map(keep, &signals)?),
+        }
+    }
+}
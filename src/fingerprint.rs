@@ -0,0 +1,100 @@
+// Locality-sensitive fingerprints over character shingles.
+//
+// Hashing uses FNV-1a rather than std's hasher so fingerprints are stable
+// across Rust releases and can be stored and compared later.
+
+const SHINGLE: usize = 3;
+const MINHASH_SIZE: u64 = 32;
+const WINNOW_K: usize = 5;
+const WINNOW_WINDOW: usize = 4;
+
+pub fn fnv1a(chars: &[char]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for c in chars {
+        let mut buf = [0; 4];
+        for b in c.encode_utf8(&mut buf).bytes() {
+            hash ^= u64::from(b);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
+}
+
+/// splitmix64 finalizer, used to derive independent hash functions.
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Hashes of every `k`-char window, or of the whole string when it is shorter.
+fn gram_hashes(s: &str, k: usize) -> Vec<u64> {
+    let chars: Vec<char> = s.chars().collect();
+    if chars.len() <= k {
+        return if chars.is_empty() {
+            vec![]
+        } else {
+            vec![fnv1a(&chars)]
+        };
+    }
+    chars.windows(k).map(fnv1a).collect()
+}
+
+/// 64-bit SimHash: similar strings differ in few bits.
+pub fn simhash(s: &str) -> u64 {
+    let mut weights = [0i64; 64];
+    for h in gram_hashes(s, SHINGLE) {
+        for (bit, w) in weights.iter_mut().enumerate() {
+            *w += if h >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, w)| **w > 0)
+        .fold(0, |acc, (bit, _)| acc | 1 << bit)
+}
+
+/// MinHash signature: the fraction of equal slots estimates Jaccard
+/// similarity of the shingle sets. Each `seed` salts the slots with its own
+/// hash functions, so only signatures of the same seed compare.
+pub fn minhash(s: &str, seed: u64) -> Vec<u64> {
+    let hashes = gram_hashes(s, SHINGLE);
+    (0..MINHASH_SIZE)
+        .map(|slot| {
+            let salt = mix(seed.wrapping_mul(MINHASH_SIZE).wrapping_add(slot));
+            hashes
+                .iter()
+                .map(|h| mix(h ^ salt))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+/// Winnowing (Schleimer et al.): the rightmost minimal k-gram hash of every
+/// window, deduplicated, as used by document clone detectors.
+pub fn winnow(s: &str) -> Vec<u64> {
+    let hashes = gram_hashes(s, WINNOW_K);
+    if hashes.len() <= WINNOW_WINDOW {
+        return hashes.into_iter().min().into_iter().collect();
+    }
+
+    let mut picked = vec![];
+    let mut last = None;
+    for (start, window) in hashes.windows(WINNOW_WINDOW).enumerate() {
+        let (offset, &h) = window
+            .iter()
+            .enumerate()
+            .rev()
+            .min_by_key(|(_, h)| **h)
+            .unwrap_or((0, &window[0]));
+        let pos = start + offset;
+        if last != Some(pos) {
+            picked.push(h);
+            last = Some(pos);
+        }
+    }
+    picked
+}
@@ -0,0 +1,101 @@
+// Score formatting: every command's scores go through here, so the numeric
+// policies apply the same way to a single pair, `--all`, `matrix`, `join`
+// and the rest, and a profile setting them sets them for all of them.
+// Whole numbers are ints unless `--float-scores`; `--digits` rounds and
+// `--percent` scales normalized scores to 0–100.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Span, Spanned, Value};
+
+/// The most decimal places `--digits` keeps; an f64 has no more to give.
+const MAX_DIGITS: i64 = 15;
+
+#[derive(Clone, Copy, Default)]
+pub struct Format {
+    digits: Option<i32>,
+    percent: bool,
+    float: bool,
+}
+
+impl Format {
+    /// Reads `--digits`, `--percent` and `--float-scores`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let digits = match call.get_flag::<Spanned<i64>>("digits")? {
+            Some(d) if !(0..=MAX_DIGITS).contains(&d.item) => {
+                return Err(LabeledError::new("invalid digits")
+                    .with_label(format!("must be between 0 and {MAX_DIGITS}"), d.span)
+                    .with_help("--digits is how many decimal places scores keep"));
+            }
+            Some(d) => Some(d.item as i32),
+            None => None,
+        };
+        Ok(Format {
+            digits,
+            percent: call.has_flag("percent")?,
+            float: call.has_flag("float-scores")?,
+        })
+    }
+
+    /// `val` scaled and rounded as asked.
+    pub fn value(&self, val: f64) -> f64 {
+        let val = if self.percent { val * 100.0 } else { val };
+        self.round(val)
+    }
+
+    fn round(&self, val: f64) -> f64 {
+        match self.digits {
+            Some(d) => {
+                let scale = 10f64.powi(d);
+                (val * scale).round() / scale
+            }
+            None => val,
+        }
+    }
+
+    /// A score cell.
+    pub fn score(&self, val: f64, span: Span) -> Value {
+        self.cell(self.value(val), span)
+    }
+
+    /// A raw distance or bound, which `--percent` leaves alone as it isn't
+    /// a share of anything.
+    pub fn raw(&self, val: f64, span: Span) -> Value {
+        self.cell(self.round(val), span)
+    }
+
+    fn cell(&self, val: f64, span: Span) -> Value {
+        if val.fract() == 0.0 && !self.float {
+            Value::int(val as i64, span)
+        } else {
+            Value::float(val, span)
+        }
+    }
+}
+
+/// Whole-number scores are shown as ints, everything else as floats.
+pub fn number(val: f64, span: Span) -> Value {
+    Format::default().score(val, span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_round_scale_and_keep_floats() {
+        let span = Span::test_data();
+        let format = Format {
+            digits: Some(1),
+            percent: true,
+            float: false,
+        };
+        assert_eq!(format.score(0.8567, span), Value::test_float(85.7));
+        assert_eq!(format.score(1.0, span), Value::test_int(100));
+        assert_eq!(format.raw(3.0, span), Value::test_int(3));
+        let float = Format {
+            float: true,
+            ..Format::default()
+        };
+        assert_eq!(float.score(1.0, span), Value::test_float(1.0));
+    }
+}
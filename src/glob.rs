@@ -0,0 +1,72 @@
+// Fuzzy matching of paths against glob-like patterns.
+//
+// Both sides are split into segments on `/` or `\`. A `**` segment spans any
+// number of path segments for free; every other segment is compared with the
+// wildcard-aware edit distance from `agrep`, so a typo in a literal part only
+// costs a fraction of that one segment instead of failing the whole match.
+
+use crate::agrep;
+
+fn segments(path: &str) -> Vec<&str> {
+    path.split(['/', '\\'])
+        .filter(|s| !s.is_empty() && *s != ".")
+        .collect()
+}
+
+fn segment_cost(pattern: &str, segment: &str) -> f64 {
+    // Characters swallowed by `*` are free, so only charge against the
+    // pattern's literal length in that case.
+    let len = if pattern.contains('*') {
+        agrep::min_len(pattern)
+    } else {
+        agrep::min_len(pattern).max(segment.chars().count())
+    };
+    if len == 0 {
+        return 0.0;
+    }
+    (agrep::distance(pattern, segment) as f64 / len as f64).min(1.0)
+}
+
+/// Similarity of `path` to the glob `pattern`, from 0 (unrelated) to 1 (the
+/// path matches the glob exactly).
+pub fn score(pattern: &str, path: &str) -> f64 {
+    let pat = segments(pattern);
+    let segs = segments(path);
+    let (n, m) = (pat.len(), segs.len());
+
+    // dp[i][j]: cheapest alignment of pat[..i] with segs[..j]. Unaligned
+    // segments on either side cost a full point.
+    let mut dp = vec![vec![f64::INFINITY; m + 1]; n + 1];
+    dp[0][0] = 0.0;
+    for i in 0..=n {
+        for j in 0..=m {
+            let here = dp[i][j];
+            if here.is_infinite() {
+                continue;
+            }
+            if i < n && pat[i] == "**" {
+                dp[i + 1][j] = dp[i + 1][j].min(here);
+                if j < m {
+                    dp[i][j + 1] = dp[i][j + 1].min(here);
+                }
+                continue;
+            }
+            if i < n && j < m {
+                dp[i + 1][j + 1] = dp[i + 1][j + 1].min(here + segment_cost(pat[i], segs[j]));
+            }
+            if i < n {
+                dp[i + 1][j] = dp[i + 1][j].min(here + 1.0);
+            }
+            if j < m {
+                dp[i][j + 1] = dp[i][j + 1].min(here + 1.0);
+            }
+        }
+    }
+
+    let literal = pat.iter().filter(|s| **s != "**").count();
+    let len = literal.max(m);
+    if len == 0 {
+        return 1.0;
+    }
+    (1.0 - dp[n][m] / len as f64).max(0.0)
+}
@@ -0,0 +1,681 @@
+use std::collections::HashMap;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    ast::CellPath, record, Category, Example, LabeledError, Signals, Signature, Span, SyntaxShape,
+    Value,
+};
+
+use crate::budget::{self, Budget};
+use crate::casing;
+use crate::prefilter::Counts;
+use crate::rejects::{self, Rejects};
+use crate::report::{self, Report};
+use crate::{algorithm, flag_span, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityGroup;
+
+impl SimplePluginCommand for StrSimilarityGroup {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity group"
+    }
+
+    fn description(&self) -> &str {
+        "Bucket similar strings together under the most frequent spelling of each group"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .named(
+                "key",
+                SyntaxShape::CellPath,
+                "Group a table on the string at this cell path",
+                Some('k'),
+            )
+            .named(
+                "within",
+                SyntaxShape::CellPath,
+                "Only compare rows with equal values at this cell path, such as the same country",
+                Some('w'),
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum normalized similarity (0 to 1) to the canonical string to join its group (default: 0.8)",
+                Some('t'),
+            )
+            .switch(
+                "prefer-frequent",
+                "Weigh each row by its count column, as in the output of `uniq --count`, so the most frequent spelling becomes the canonical form",
+                None,
+            )
+            .named(
+                "count-column",
+                SyntaxShape::String,
+                "With --prefer-frequent, the column holding each row's count (default: count, when the rows have it)",
+                None,
+            )
+            .switch(
+                "canonicalize",
+                "Return the input with every value replaced by its canonical form instead of the groups",
+                None,
+            )
+            .named(
+                "reference",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "With --canonicalize, map values to their best match in this list instead of to group spellings",
+                None,
+            )
+            .switch(
+                "null-unmatched",
+                "With --canonicalize, empty values that match no reference instead of keeping them",
+                None,
+            )
+            .switch(
+                "preserve-case",
+                "With --canonicalize, write each replacement in the casing of the value it replaces: UPPER, lower or Title",
+                None,
+            )
+            .named(
+                "max-seconds",
+                SyntaxShape::Number,
+                "Stop comparing after this many seconds, trying the likeliest groups first, and return the grouping so far with the values left over ungrouped",
+                None,
+            )
+            .named(
+                "rejects",
+                SyntaxShape::Filepath,
+                "Also save the near misses, values that joined no group or matched no reference by a small margin, to this nuon file for review",
+                None,
+            )
+            .named(
+                "reject-margin",
+                SyntaxShape::Number,
+                "How far below the threshold a near miss may score (default: 0.1)",
+                None,
+            )
+            .named(
+                "report",
+                SyntaxShape::String,
+                "Render a markdown or html report of the groups and their most doubtful members instead",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Count messy category spellings",
+                example: "[Books books Bokos Music musik Books] | str similarity group -a jaro_winkler -t 0.8 | sort-by count --reverse",
+                result: None,
+            },
+            Example {
+                description: "Group a column of a table",
+                example: "open orders.csv | str similarity group --key city",
+                result: None,
+            },
+            Example {
+                description: "Group spellings already counted, under the most frequent of each",
+                example: "open orders.csv | get city | uniq --count | str similarity group --key value --prefer-frequent",
+                result: None,
+            },
+            Example {
+                description: "Find duplicate customers only among those of the same country",
+                example: "open customers.csv | str similarity group --key name --within country -a jaro_winkler",
+                result: None,
+            },
+            Example {
+                description: "Get a rough grouping of a huge column quickly",
+                example: "open huge.csv | str similarity group --key name --max-seconds 5",
+                result: None,
+            },
+            Example {
+                description: "See which values barely missed the official spellings",
+                example: "open orders.csv | str similarity group --key city --canonicalize --reference [Berlin Munich Hamburg] -t 0.7 --rejects cities-near.nuon",
+                result: None,
+            },
+            Example {
+                description: "Review the variant spellings of a column as html",
+                example: "open orders.csv | str similarity group --key city --report html | save cities.html",
+                result: None,
+            },
+            Example {
+                description: "Clean a column against the official spellings",
+                example: "open orders.csv | str similarity group --key city --canonicalize --reference [Berlin Munich Hamburg] -t 0.7",
+                result: None,
+            },
+            Example {
+                description: "Correct values without changing their casing",
+                example: "[berln 'new york'] | str similarity group --canonicalize --reference [Berlin 'New York'] -t 0.6 --preserve-case",
+                result: Some(Value::test_list(vec![
+                    Value::test_string("berlin"),
+                    Value::test_string("new york"),
+                ])),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let key: Option<CellPath> = call.get_flag("key")?;
+        let within: Option<CellPath> = call.get_flag("within")?;
+        let budget = Budget::from_seconds(call, "max-seconds")?;
+        let signals = engine.signals();
+        let mut stopped = None;
+        let algo = algorithm(call)?;
+        let threshold: f64 = call.get_flag("threshold")?.unwrap_or(0.8);
+        let rejects = Rejects::from_call(call, engine)?;
+        let limits = (
+            threshold,
+            rejects.as_ref().map_or(threshold, |r| r.floor(threshold)),
+        );
+        let mut near = vec![];
+        let params = AlgoParams::from_call(call)?;
+        let canonicalize = call.has_flag("canonicalize")?;
+        let null_unmatched = call.has_flag("null-unmatched")?;
+        let preserve_case = call.has_flag("preserve-case")?;
+        let reference: Option<Vec<String>> = call.get_flag("reference")?;
+        if !canonicalize && (reference.is_some() || null_unmatched || preserve_case) {
+            let flag = if null_unmatched {
+                "null-unmatched"
+            } else if preserve_case {
+                "preserve-case"
+            } else {
+                "reference"
+            };
+            return Err(LabeledError::new(format!("--{flag} needs --canonicalize"))
+                .with_label("only used when canonicalizing", flag_span(call, flag))
+                .with_help("add --canonicalize to map each value to its canonical form"));
+        }
+
+        let format = report::Format::from_call(call)?;
+        if format.is_some() && canonicalize {
+            return Err(
+                LabeledError::new("--report and --canonicalize are exclusive")
+                    .with_label("a report describes the groups", flag_span(call, "report"))
+                    .with_help("drop --canonicalize to report on the groups"),
+            );
+        }
+
+        let Value::List { vals, .. } = input else {
+            return Err(
+                LabeledError::new(format!("requires a list, got {}", input.get_type()))
+                    .with_label(
+                        "Expected a list of strings or a table from pipeline",
+                        input.span(),
+                    )
+                    .with_help("group a table's column with --key"),
+            );
+        };
+
+        let strings = vals
+            .iter()
+            .map(|v| string_at(v, key.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let weights = weights(call, vals)?;
+        let score = |a: &[char], b: &[char]| similarity(&algo, a, b, &params);
+        let (parts, part_of) = partitions(vals, within.as_ref())?;
+        // the distinct strings of one partition, compared only to each other
+        let members = |p: usize| {
+            strings
+                .iter()
+                .zip(&part_of)
+                .zip(&weights)
+                .filter(move |((_, q), _)| **q == p)
+                .map(|((s, _), w)| (s.as_str(), *w))
+        };
+
+        let span = call.head;
+        if canonicalize {
+            let refs: Option<Vec<(&str, Vec<char>)>> = reference.as_ref().map(|refs| {
+                refs.iter()
+                    .map(|r| (r.as_str(), r.chars().collect()))
+                    .collect()
+            });
+            let mut canonical: HashMap<(usize, &str), Option<String>> = HashMap::new();
+            for p in 0..parts.len() {
+                match &refs {
+                    Some(refs) => {
+                        for (s, _) in tally(members(p)) {
+                            let best = match best_reference(s, refs, score) {
+                                Some((r, sc)) if sc >= threshold => Some(r.to_string()),
+                                Some((r, sc)) if sc >= limits.1 => {
+                                    near.push((s, r, sc));
+                                    None
+                                }
+                                _ => None,
+                            };
+                            canonical.insert((p, s), best);
+                        }
+                    }
+                    None => {
+                        let distinct = tally(members(p));
+                        let found = groups(
+                            distinct,
+                            limits,
+                            score,
+                            &budget,
+                            signals,
+                            &mut stopped,
+                            &mut near,
+                        );
+                        for g in found {
+                            let c = g.members[0];
+                            canonical
+                                .extend(g.members.into_iter().map(|m| ((p, m), Some(c.into()))));
+                        }
+                    }
+                }
+            }
+
+            let rows = vals
+                .iter()
+                .zip(&strings)
+                .zip(&part_of)
+                .map(|((v, s), p)| {
+                    let cleaned = match &canonical[&(*p, s.as_str())] {
+                        Some(c) if preserve_case => Value::string(casing::restyle(s, c), v.span()),
+                        Some(c) => Value::string(c, v.span()),
+                        None if null_unmatched => Value::nothing(v.span()),
+                        None => Value::string(s, v.span()),
+                    };
+                    match &key {
+                        Some(path) => {
+                            let mut row = v.clone();
+                            row.update_cell_path(&path.members, Box::new(move |_| cleaned))?;
+                            Ok(row)
+                        }
+                        None => Ok(cleaned),
+                    }
+                })
+                .collect::<Result<Vec<_>, LabeledError>>()?;
+            save(rejects, near, threshold, span)?;
+            return Ok(finished(rows, stopped, span));
+        }
+
+        let groups: Vec<(usize, Group)> = (0..parts.len())
+            .flat_map(|p| {
+                groups(
+                    tally(members(p)),
+                    limits,
+                    score,
+                    &budget,
+                    signals,
+                    &mut stopped,
+                    &mut near,
+                )
+                .into_iter()
+                .map(move |g| (p, g))
+            })
+            .collect();
+        save(rejects, near, threshold, span)?;
+        if let Some(format) = format {
+            let groups: Vec<Group> = groups.into_iter().map(|(_, g)| g).collect();
+            return Ok(group_report(format, &algo, &strings, &groups, score).into_value(span));
+        }
+        let rows = groups
+            .into_iter()
+            .map(|(p, g)| {
+                let mut row = record! {
+                    "canonical" => Value::string(g.members[0], span),
+                    "members" => Value::list(
+                        g.members.into_iter().map(|m| Value::string(m, span)).collect(),
+                        span,
+                    ),
+                    "count" => Value::int(g.count as i64, span),
+                };
+                if let Some(path) = &within {
+                    row.push(path.to_string(), parts[p].clone());
+                }
+                Value::record(row, span)
+            })
+            .collect();
+        Ok(finished(rows, stopped, span))
+    }
+}
+
+/// Saves the near misses if `--rejects` asked for them.
+fn save(
+    rejects: Option<Rejects>,
+    near: Vec<(&str, &str, f64)>,
+    threshold: f64,
+    span: Span,
+) -> Result<(), LabeledError> {
+    let Some(rejects) = rejects else {
+        return Ok(());
+    };
+    let rows = near
+        .into_iter()
+        .map(|(s, to, sc)| {
+            let (s, to) = (Value::string(s, span), Value::string(to, span));
+            rejects::row(s, to, sc, threshold, span)
+        })
+        .collect();
+    rejects.save(rows, span)
+}
+
+/// The rows, or with the reason the run stopped early, the partial result.
+fn finished(rows: Vec<Value>, stopped: Option<&str>, span: Span) -> Value {
+    match stopped {
+        Some(reason) => budget::partial(rows, reason, span),
+        None => Value::list(rows, span),
+    }
+}
+
+/// Totals, the groups with more than one spelling, and the members least
+/// similar to their canonical form, which are the likeliest wrong merges.
+fn group_report(
+    format: report::Format,
+    algo: &str,
+    strings: &[String],
+    groups: &[Group],
+    score: impl Fn(&[char], &[char]) -> f64,
+) -> Report {
+    let span = Span::unknown();
+    let int = |n: usize| Value::int(n as i64, span);
+    let mut variants: Vec<&Group> = groups.iter().filter(|g| g.members.len() > 1).collect();
+    variants.sort_by_key(|g| std::cmp::Reverse(g.count));
+
+    let mut report = Report::new(format, &format!("Groups by {algo}"));
+    report.summary(&record! {
+        "values" => int(strings.len()),
+        "distinct" => int(groups.iter().map(|g| g.members.len()).sum()),
+        "groups" => int(groups.len()),
+        "groups with variants" => int(variants.len()),
+    });
+
+    report.section("Groups with variants");
+    let rows: Vec<Vec<String>> = variants
+        .iter()
+        .map(|g| {
+            vec![
+                g.members[0].to_string(),
+                g.count.to_string(),
+                g.members[1..].join(", "),
+            ]
+        })
+        .collect();
+    report.table(&["canonical", "count", "variants"], &rows);
+
+    let mut doubtful: Vec<(f64, &str, &str)> = variants
+        .iter()
+        .flat_map(|g| {
+            g.members[1..].iter().map(|m| {
+                let chars: Vec<char> = m.chars().collect();
+                (score(&g.chars, &chars), g.members[0], *m)
+            })
+        })
+        .collect();
+    doubtful.sort_by(|a, b| a.0.total_cmp(&b.0));
+    let rows: Vec<Vec<String>> = doubtful
+        .into_iter()
+        .take(report::SUSPICIOUS)
+        .map(|(s, c, m)| vec![c.to_string(), m.to_string(), format!("{s:.3}")])
+        .collect();
+    report.section("Most doubtful members");
+    report.table(&["canonical", "member", "score"], &rows);
+    report
+}
+
+/// Distinct spellings sharing one canonical form, the first member.
+struct Group<'a> {
+    chars: Vec<char>,
+    counts: Counts,
+    members: Vec<&'a str>,
+    count: usize,
+}
+
+/// Distinct values with how often they occur, most frequent first so the
+/// usual spelling of each group becomes its canonical form.
+pub fn distinct<'a>(strings: impl IntoIterator<Item = &'a str>) -> Vec<(&'a str, usize)> {
+    tally(strings.into_iter().map(|s| (s, 1)))
+}
+
+/// Like [`distinct`], each value counting as many times as it's paired with.
+fn tally<'a>(weighted: impl IntoIterator<Item = (&'a str, usize)>) -> Vec<(&'a str, usize)> {
+    let mut distinct: Vec<(&str, usize)> = vec![];
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    for (s, n) in weighted {
+        match seen.get(s) {
+            Some(&i) => distinct[i].1 += n,
+            None => {
+                seen.insert(s, distinct.len());
+                distinct.push((s, n));
+            }
+        }
+    }
+    distinct.sort_by_key(|d| std::cmp::Reverse(d.1));
+    distinct
+}
+
+/// How many times each row counts: once, or with `--prefer-frequent` the
+/// number in its count column. Rows without a `count` column count once,
+/// as they do without the flag; a `--count-column` named must be there.
+fn weights(call: &EvaluatedCall, vals: &[Value]) -> Result<Vec<usize>, LabeledError> {
+    let column: Option<String> = call.get_flag("count-column")?;
+    if !call.has_flag("prefer-frequent")? {
+        if column.is_some() {
+            return Err(LabeledError::new("--count-column needs --prefer-frequent")
+                .with_label(
+                    "counts only weigh the spellings",
+                    flag_span(call, "count-column"),
+                )
+                .with_help("add --prefer-frequent"));
+        }
+        return Ok(vec![1; vals.len()]);
+    }
+    let name = column.as_deref().unwrap_or("count");
+    vals.iter()
+        .map(|v| match v.as_record().ok().and_then(|r| r.get(name)) {
+            None if column.is_none() => Ok(1),
+            None => Err(LabeledError::new(format!("no column '{name}'"))
+                .with_label("row without a count", v.span())
+                .with_help("name the column holding the counts with --count-column")),
+            Some(n) => match n.as_int() {
+                Ok(n) if n >= 0 => Ok(n as usize),
+                _ => Err(LabeledError::new("invalid count")
+                    .with_label("not a count", n.span())
+                    .with_help(format!(
+                        "the '{name}' column needs whole numbers of at least 0"
+                    ))),
+            },
+        })
+        .collect()
+}
+
+/// Each value joins the first group whose canonical string is close enough,
+/// or starts a new one.
+///
+/// Under a time limit the groups are tried in order of their bag distance
+/// to the value instead, which is cheap and makes the first groups tried
+/// the likeliest to match. Once the budget runs out, `stopped` is set and
+/// the values left each start a group of their own.
+///
+/// A value that joins no group but scores at least `floor` against one is a
+/// near miss, recorded in `near` with the closest group's canonical form.
+fn groups<'a>(
+    distinct: Vec<(&'a str, usize)>,
+    (threshold, floor): (f64, f64),
+    score: impl Fn(&[char], &[char]) -> f64,
+    budget: &Budget,
+    signals: &Signals,
+    stopped: &mut Option<&'static str>,
+    near: &mut Vec<(&'a str, &'a str, f64)>,
+) -> Vec<Group<'a>> {
+    let mut groups: Vec<Group> = vec![];
+    for (s, count) in distinct {
+        let chars: Vec<char> = s.chars().collect();
+        let counts = Counts::new(&chars);
+        let mut order: Vec<usize> = (0..groups.len()).collect();
+        if budget.limited() && stopped.is_none() {
+            order.sort_by_key(|&i| groups[i].counts.bag_distance(&counts));
+        }
+        let mut found = None;
+        let mut closest: Option<(usize, f64)> = None;
+        for i in order {
+            if stopped.is_none() {
+                *stopped = budget.exhausted(signals);
+            }
+            if stopped.is_some() {
+                break;
+            }
+            let sc = score(&groups[i].chars, &chars);
+            if sc >= threshold {
+                found = Some(i);
+                break;
+            }
+            if sc >= floor && !closest.is_some_and(|c| c.1 >= sc) {
+                closest = Some((i, sc));
+            }
+        }
+        if let (None, Some((i, sc))) = (found, closest) {
+            near.push((s, groups[i].members[0], sc));
+        }
+        match found {
+            Some(i) => {
+                groups[i].members.push(s);
+                groups[i].count += count;
+            }
+            None => groups.push(Group {
+                chars,
+                counts,
+                members: vec![s],
+                count,
+            }),
+        }
+    }
+    groups
+}
+
+/// The reference `s` is most similar to and its score. A reference equal
+/// to `s` always wins.
+fn best_reference<'r>(
+    s: &str,
+    refs: &[(&'r str, Vec<char>)],
+    score: impl Fn(&[char], &[char]) -> f64,
+) -> Option<(&'r str, f64)> {
+    if let Some((r, _)) = refs.iter().find(|(r, _)| *r == s) {
+        return Some((r, f64::INFINITY));
+    }
+    let chars: Vec<char> = s.chars().collect();
+    let mut best: Option<(&str, f64)> = None;
+    for (r, rc) in refs {
+        let sc = score(&chars, rc);
+        // the first of equally good references wins
+        if !best.is_some_and(|b| b.1 >= sc) {
+            best = Some((r, sc));
+        }
+    }
+    best
+}
+
+/// The distinct values at `within`, in the order first seen, and for each
+/// row the index of its value. Without `within` all rows share one.
+fn partitions(
+    vals: &[Value],
+    within: Option<&CellPath>,
+) -> Result<(Vec<Value>, Vec<usize>), LabeledError> {
+    let Some(path) = within else {
+        return Ok((vec![Value::nothing(Span::unknown())], vec![0; vals.len()]));
+    };
+    let mut parts = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let part_of = vals
+        .iter()
+        .map(|v| {
+            let found = v
+                .clone()
+                .follow_cell_path(&path.members, false)
+                .map_err(|e| {
+                    LabeledError::new(format!("no value at '{path}'"))
+                        .with_label("missing partition", v.span())
+                        .with_help("every row needs the --within field")
+                        .with_inner(e)
+                })?;
+            let key = found.coerce_string().map_err(|_| {
+                LabeledError::new(format!("can't partition on {}", found.get_type()))
+                    .with_label("not a string or number", found.span())
+                    .with_help("partition on a column of strings or numbers")
+            })?;
+            Ok(*index.entry(key).or_insert_with(|| {
+                parts.push(found);
+                parts.len() - 1
+            }))
+        })
+        .collect::<Result<Vec<_>, LabeledError>>()?;
+    Ok((parts, part_of))
+}
+
+/// The string to group `v` by: itself, or the string at `key`.
+fn string_at(v: &Value, key: Option<&CellPath>) -> Result<String, LabeledError> {
+    let found = match key {
+        Some(path) => v
+            .clone()
+            .follow_cell_path(&path.members, false)
+            .map_err(|e| {
+                LabeledError::new(format!("no value at '{path}'"))
+                    .with_label("missing group key", v.span())
+                    .with_help("every row needs the --key field")
+                    .with_inner(e)
+            })?,
+        None => v.clone(),
+    };
+    found.as_str().map(str::to_string).map_err(|_| {
+        LabeledError::new(format!("expected a string, got {}", found.get_type()))
+            .with_label("not a string", found.span())
+            .with_help("only strings can be grouped; pick a string field with --key")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityGroup)
+    }
+}
@@ -0,0 +1,201 @@
+// Hirschberg's linear-space alignments: the LCS, and the global alignment
+// behind the levenshtein distance.
+//
+// textdistance's LCS keeps the whole (n + 1) x (m + 1) DP table, which is
+// hopeless for inputs in the hundreds of kilobytes, and so does a full
+// traceback. Hirschberg recovers the same alignment by divide and conquer
+// while only ever holding two DP rows sized by the shorter input, so memory
+// is O(min(n, m)).
+
+use crate::edit_ops::Edit;
+
+/// Above this many DP cells the full-table algorithms are swapped for this one.
+pub const THRESHOLD_CELLS: usize = 4_000_000;
+
+/// Index pairs `(i, j)`, increasing in both, where `a[i] == b[j]` and the pairs
+/// form a longest common subsequence of `a` and `b`.
+pub fn lcs<E: Eq>(a: &[E], b: &[E]) -> Vec<(usize, usize)> {
+    let mut pairs = Vec::new();
+    if a.len() >= b.len() {
+        split(a, b, 0, 0, &mut pairs);
+    } else {
+        split(b, a, 0, 0, &mut pairs);
+        for pair in pairs.iter_mut() {
+            *pair = (pair.1, pair.0);
+        }
+    }
+    pairs
+}
+
+fn split<E: Eq>(a: &[E], b: &[E], a_off: usize, b_off: usize, out: &mut Vec<(usize, usize)>) {
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+    if a.len() == 1 {
+        if let Some(j) = b.iter().position(|e| *e == a[0]) {
+            out.push((a_off, b_off + j));
+        }
+        return;
+    }
+
+    let mid = a.len() / 2;
+    let forward = last_row(a[..mid].iter(), b.iter(), b.len());
+    let backward = last_row(a[mid..].iter().rev(), b.iter().rev(), b.len());
+
+    let m = b.len();
+    let k = (0..=m)
+        .max_by_key(|&j| (forward[j] + backward[m - j], std::cmp::Reverse(j)))
+        .unwrap_or(0);
+
+    split(&a[..mid], &b[..k], a_off, b_off, out);
+    split(&a[mid..], &b[k..], a_off + mid, b_off + k, out);
+}
+
+/// A global alignment of `a` and `b` with as few edits as the levenshtein
+/// distance: pairs, deletions from `a` and insertions from `b`, in order.
+pub fn align<E: Eq>(a: &[E], b: &[E]) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    if a.len() >= b.len() {
+        align_split(a, b, 0, 0, &mut edits);
+    } else {
+        align_split(b, a, 0, 0, &mut edits);
+        for edit in edits.iter_mut() {
+            *edit = match *edit {
+                Edit::Pair(i, j) => Edit::Pair(j, i),
+                Edit::Delete(i) => Edit::Insert(i),
+                Edit::Insert(j) => Edit::Delete(j),
+                ref transpose => transpose.clone(),
+            };
+        }
+    }
+    edits
+}
+
+fn align_split<E: Eq>(a: &[E], b: &[E], a_off: usize, b_off: usize, out: &mut Vec<Edit>) {
+    if a.is_empty() || b.is_empty() {
+        out.extend((0..a.len()).map(|i| Edit::Delete(a_off + i)));
+        out.extend((0..b.len()).map(|j| Edit::Insert(b_off + j)));
+        return;
+    }
+    if a.len() == 1 {
+        // keep the element where `b` has it, or substitute the first
+        let j = b.iter().position(|e| *e == a[0]).unwrap_or(0);
+        out.extend((0..j).map(|j| Edit::Insert(b_off + j)));
+        out.push(Edit::Pair(a_off, b_off + j));
+        out.extend((j + 1..b.len()).map(|j| Edit::Insert(b_off + j)));
+        return;
+    }
+
+    let mid = a.len() / 2;
+    let forward = cost_row(a[..mid].iter(), b.iter(), b.len());
+    let backward = cost_row(a[mid..].iter().rev(), b.iter().rev(), b.len());
+
+    let m = b.len();
+    let k = (0..=m)
+        .min_by_key(|&j| forward[j] + backward[m - j])
+        .unwrap_or(0);
+
+    align_split(&a[..mid], &b[..k], a_off, b_off, out);
+    align_split(&a[mid..], &b[k..], a_off + mid, b_off + k, out);
+}
+
+/// Last row of the levenshtein distance table of `a` against `b`, using two
+/// rows of `m + 1` cells.
+fn cost_row<'a, E, A, B>(a: A, b: B, m: usize) -> Vec<usize>
+where
+    E: Eq + 'a,
+    A: Iterator<Item = &'a E>,
+    B: Iterator<Item = &'a E> + Clone,
+{
+    let mut prev: Vec<usize> = (0..=m).collect();
+    let mut curr = vec![0; m + 1];
+    for x in a {
+        curr[0] = prev[0] + 1;
+        for (j, y) in b.clone().enumerate() {
+            curr[j + 1] = (prev[j] + usize::from(x != y))
+                .min(prev[j + 1] + 1)
+                .min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev
+}
+
+/// Last row of the LCS length table of `a` against `b`, using two rows of
+/// `m + 1` cells.
+fn last_row<'a, E, A, B>(a: A, b: B, m: usize) -> Vec<usize>
+where
+    E: Eq + 'a,
+    A: Iterator<Item = &'a E>,
+    B: Iterator<Item = &'a E> + Clone,
+{
+    let mut prev = vec![0; m + 1];
+    let mut curr = vec![0; m + 1];
+    for x in a {
+        for (j, y) in b.clone().enumerate() {
+            curr[j + 1] = if x == y {
+                prev[j] + 1
+            } else {
+                curr[j].max(prev[j + 1])
+            };
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAIRS: [(&str, &str); 7] = [
+        ("kitten", "sitting"),
+        ("nushell", "nutshell"),
+        ("", "abc"),
+        ("abc", ""),
+        ("a", "bab"),
+        ("AGGTAB", "GXTXAYB"),
+        ("schön straße", "schon strasse"),
+    ];
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn lcs_length_matches_textdistance() {
+        for (a, b) in PAIRS {
+            let pairs = lcs(&chars(a), &chars(b));
+            assert_eq!(pairs.len(), textdistance::str::lcsseq(a, b), "{a} {b}");
+            let (a, b) = (chars(a), chars(b));
+            assert!(pairs.iter().all(|&(i, j)| a[i] == b[j]));
+            assert!(pairs.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+        }
+    }
+
+    #[test]
+    fn alignment_costs_the_levenshtein_distance() {
+        for (a, b) in PAIRS {
+            let (ca, cb) = (chars(a), chars(b));
+            let edits = align(&ca, &cb);
+            let cost = edits
+                .iter()
+                .filter(|e| !matches!(e, Edit::Pair(i, j) if ca[*i] == cb[*j]))
+                .count();
+            assert_eq!(cost, textdistance::str::levenshtein(a, b), "{a} {b}");
+            // every token of each side appears once, in order
+            let side = |left: bool| -> Vec<usize> {
+                edits
+                    .iter()
+                    .filter_map(|e| match (e, left) {
+                        (Edit::Pair(i, _) | Edit::Delete(i), true) => Some(*i),
+                        (Edit::Pair(_, j) | Edit::Insert(j), false) => Some(*j),
+                        _ => None,
+                    })
+                    .collect()
+            };
+            assert_eq!(side(true), (0..ca.len()).collect::<Vec<_>>());
+            assert_eq!(side(false), (0..cb.len()).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,42 @@
+// Binned score distributions, a quick view of how a batch's scores spread.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{record, LabeledError, Span, Spanned, Value};
+
+/// Reads `--histogram`, the number of equal-width bins between 0 and 1.
+pub fn bins(call: &EvaluatedCall) -> Result<Option<usize>, LabeledError> {
+    match call.get_flag::<Spanned<i64>>("histogram")? {
+        Some(n) if n.item < 1 => Err(LabeledError::new("invalid bin count")
+            .with_label("need at least one bin", n.span)
+            .with_help("e.g. --histogram 10 for bins 0.1 wide")),
+        Some(n) => Ok(Some(n.item as usize)),
+        None => Ok(None),
+    }
+}
+
+/// How many scores fall in each of `bins` equal bins over 0..=1. A score of
+/// exactly 1 goes in the last bin; scores outside the range are clamped.
+pub fn count(scores: impl IntoIterator<Item = f64>, bins: usize) -> Vec<usize> {
+    let mut counts = vec![0; bins];
+    for s in scores {
+        let bin = (s.clamp(0.0, 1.0) * bins as f64) as usize;
+        counts[bin.min(bins - 1)] += 1;
+    }
+    counts
+}
+
+/// One row per bin with its bounds and a count column for each series.
+pub fn rows(series: &[(&str, Vec<usize>)], bins: usize, span: Span) -> Vec<Value> {
+    (0..bins)
+        .map(|i| {
+            let mut row = record! {
+                "start" => Value::float(i as f64 / bins as f64, span),
+                "end" => Value::float((i + 1) as f64 / bins as f64, span),
+            };
+            for (name, counts) in series {
+                row.push(*name, Value::int(counts[i] as i64, span));
+            }
+            Value::record(row, span)
+        })
+        .collect()
+}
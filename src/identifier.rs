@@ -0,0 +1,259 @@
+// Identifier mode: fixed-length codes such as SKUs, ISBNs and container
+// numbers are compared position by position, like hamming, but a swap of
+// two adjacent characters, the most common keying error, costs half a
+// mismatch instead of two, and a check digit isn't counted against a code
+// whose body already differs, since it follows from the body.
+//
+// A scheme such as ISBN, EAN or IBAN adds what's known about its format:
+// ISBN-10s are rewritten as ISBN-13s and UPC-As as EAN-13s so either form
+// of a code matches the other, the check digits are wherever the scheme
+// puts them, and each side is validated against its checksum.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Spanned};
+
+/// What an adjacent transposition costs, in mismatches.
+const TRANSPOSITION_COST: f64 = 0.5;
+
+/// The letters and digits of `s`, uppercase, so "978-0 13" and "978013"
+/// are the same code.
+pub fn normalize(s: &str) -> Vec<char> {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// Similarity from 0 to 1 of two normalized codes: one minus the share of
+/// positions in error. With `check_digit`, the last char of each code is
+/// derived from the rest and only counts when the rest is identical.
+pub fn similarity(a: &[char], b: &[char], check_digit: bool) -> f64 {
+    compare(
+        a.len().max(b.len()),
+        split(a, check_digit),
+        split(b, check_digit),
+    )
+}
+
+/// `code` as its body and check digit, if it has one.
+fn split(code: &[char], check_digit: bool) -> (&[char], &[char]) {
+    match check_digit {
+        true => code.split_at(code.len().saturating_sub(1)),
+        false => (code, &[]),
+    }
+}
+
+/// Similarity of two codes `len` chars long at most, given as their bodies
+/// and check digits: the check digits only count when the bodies match.
+fn compare(len: usize, a: (&[char], &[char]), b: (&[char], &[char])) -> f64 {
+    if len == 0 {
+        return 1.0;
+    }
+    let mut errors = mismatches(a.0, b.0);
+    if errors == 0.0 {
+        errors = mismatches(a.1, b.1);
+    }
+    (1.0 - errors / len as f64).max(0.0)
+}
+
+/// Positional mismatches, counting an adjacent swap as
+/// [`TRANSPOSITION_COST`] and every char past the shorter code as one.
+fn mismatches(a: &[char], b: &[char]) -> f64 {
+    let n = a.len().min(b.len());
+    let mut errors = a.len().abs_diff(b.len()) as f64;
+    let mut i = 0;
+    while i < n {
+        if a[i] == b[i] {
+            i += 1;
+        } else if i + 1 < n && a[i] == b[i + 1] && a[i + 1] == b[i] {
+            errors += TRANSPOSITION_COST;
+            i += 2;
+        } else {
+            errors += 1.0;
+            i += 1;
+        }
+    }
+    errors
+}
+
+/// A code format with a known layout and checksum.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scheme {
+    /// ISBN-10 or ISBN-13, compared as ISBN-13.
+    Isbn,
+    /// EAN-8, EAN-13 or UPC-A, which is compared as EAN-13.
+    Ean,
+    /// International Bank Account Number, check digits in 3rd and 4th place.
+    Iban,
+}
+
+pub const SCHEMES: [&str; 3] = ["isbn", "ean", "iban"];
+
+/// Reads `--scheme`.
+pub fn scheme(call: &EvaluatedCall) -> Result<Option<Scheme>, LabeledError> {
+    let Some(name) = call.get_flag::<Spanned<String>>("scheme")? else {
+        return Ok(None);
+    };
+    match name.item.to_lowercase().as_str() {
+        "isbn" => Ok(Some(Scheme::Isbn)),
+        "ean" => Ok(Some(Scheme::Ean)),
+        "iban" => Ok(Some(Scheme::Iban)),
+        _ => Err(LabeledError::new("unknown scheme")
+            .with_label(format!("'{}' is not a scheme", name.item), name.span)
+            .with_help(format!("valid schemes: {}", SCHEMES.join(", ")))),
+    }
+}
+
+impl Scheme {
+    /// `s` as [`normalize`] leaves it, without a leading "ISBN" or "IBAN"
+    /// label, and in the scheme's canonical form when it's valid.
+    pub fn normalize(self, s: &str) -> Vec<char> {
+        let mut code = normalize(s);
+        let label: &[char] = match self {
+            Scheme::Isbn => &['I', 'S', 'B', 'N'],
+            Scheme::Iban => &['I', 'B', 'A', 'N'],
+            Scheme::Ean => &[],
+        };
+        if code.starts_with(label) && code.len() > label.len() {
+            code.drain(..label.len());
+        }
+        match self {
+            Scheme::Isbn if code.len() == 10 && isbn10_valid(&code) => {
+                let mut isbn13: Vec<char> =
+                    "978".chars().chain(code[..9].iter().copied()).collect();
+                isbn13.push(ean_check_digit(&isbn13));
+                isbn13
+            }
+            Scheme::Ean if code.len() == 12 && ean_valid(&code) => {
+                code.insert(0, '0');
+                code
+            }
+            _ => code,
+        }
+    }
+
+    /// Whether a normalized code is well formed and its checksum holds.
+    pub fn is_valid(self, code: &[char]) -> bool {
+        match self {
+            Scheme::Isbn if code.len() == 10 => isbn10_valid(code),
+            Scheme::Isbn => {
+                code.len() == 13
+                    && (code.starts_with(&['9', '7', '8']) || code.starts_with(&['9', '7', '9']))
+                    && ean_valid(code)
+            }
+            Scheme::Ean => matches!(code.len(), 8 | 12 | 13) && ean_valid(code),
+            Scheme::Iban => iban_valid(code),
+        }
+    }
+
+    /// [`similarity`] with the check digits where the scheme puts them.
+    pub fn similarity(self, a: &[char], b: &[char]) -> f64 {
+        match self {
+            Scheme::Isbn | Scheme::Ean => similarity(a, b, true),
+            Scheme::Iban => {
+                // country code and account, then the two check digits
+                let split = |code: &[char]| {
+                    let (head, account) = code.split_at(code.len().min(4));
+                    let (country, check) = head.split_at(head.len().min(2));
+                    let body: Vec<char> = country.iter().chain(account).copied().collect();
+                    (body, check.to_vec())
+                };
+                let ((a_body, a_check), (b_body, b_check)) = (split(a), split(b));
+                compare(
+                    a.len().max(b.len()),
+                    (&a_body, &a_check),
+                    (&b_body, &b_check),
+                )
+            }
+        }
+    }
+}
+
+/// The GS1 check digit of the digits in `body`: weights 3 and 1
+/// alternating from the right.
+fn ean_check_digit(body: &[char]) -> char {
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .zip([3, 1].into_iter().cycle())
+        .map(|(c, w)| c.to_digit(10).unwrap_or(0) * w)
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap_or('0')
+}
+
+fn ean_valid(code: &[char]) -> bool {
+    match code.split_last() {
+        Some((check, body)) => {
+            code.iter().all(char::is_ascii_digit) && *check == ean_check_digit(body)
+        }
+        None => false,
+    }
+}
+
+/// Digits weighted 10 down to 1 sum to a multiple of 11, the last of them
+/// written X when it's 10.
+fn isbn10_valid(code: &[char]) -> bool {
+    let digits: Option<Vec<u32>> = code
+        .iter()
+        .enumerate()
+        .map(|(i, c)| match c {
+            'X' if i == 9 => Some(10),
+            c => c.to_digit(10),
+        })
+        .collect();
+    match digits {
+        Some(d) if d.len() == 10 => {
+            d.iter()
+                .zip((1..=10).rev())
+                .map(|(d, w)| d * w)
+                .sum::<u32>()
+                % 11
+                == 0
+        }
+        _ => false,
+    }
+}
+
+/// ISO 13616: two letters, two digits and up to 30 more letters and digits,
+/// which read as a number with the first four moved to the end and letters
+/// as 10 to 35 leave 1 modulo 97.
+fn iban_valid(code: &[char]) -> bool {
+    if !(15..=34).contains(&code.len())
+        || !code[..2].iter().all(char::is_ascii_uppercase)
+        || !code[2..4].iter().all(char::is_ascii_digit)
+        || !code.iter().all(char::is_ascii_alphanumeric)
+    {
+        return false;
+    }
+    let remainder = code[4..].iter().chain(&code[..4]).fold(0u32, |r, c| {
+        let n = c.to_digit(36).unwrap_or(0);
+        match n < 10 {
+            true => (r * 10 + n) % 97,
+            false => (r * 100 + n) % 97,
+        }
+    });
+    remainder == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schemes_validate_and_canonicalize() {
+        let isbn10 = Scheme::Isbn.normalize("ISBN 0-306-40615-2");
+        assert_eq!(isbn10, normalize("978-0-306-40615-7"));
+        assert!(Scheme::Isbn.is_valid(&isbn10));
+        assert!(!Scheme::Isbn.is_valid(&Scheme::Isbn.normalize("0-306-40615-3")));
+        assert_eq!(
+            Scheme::Ean.normalize("036000291452"),
+            normalize("0036000291452")
+        );
+        assert!(Scheme::Ean.is_valid(&normalize("96385074")));
+        let iban = Scheme::Iban.normalize("GB82 WEST 1234 5698 7654 32");
+        assert!(Scheme::Iban.is_valid(&iban));
+        let typo = Scheme::Iban.normalize("GB82 WEST 1234 5698 7654 23");
+        assert!(!Scheme::Iban.is_valid(&typo));
+        assert_eq!(Scheme::Iban.similarity(&iban, &typo), 1.0 - 0.5 / 22.0);
+    }
+}
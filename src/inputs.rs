@@ -0,0 +1,170 @@
+// `--include-inputs`: the strings a row compared, written into the row, so
+// results saved to a file still say what was scored without the input
+// beside them. `--include-normalized` writes them as preprocessing left
+// them, which is what the algorithm actually saw, and `--preview` cuts long
+// ones short so wide tables stay readable.
+
+use std::sync::Arc;
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{Config, LabeledError, Record, Span, Spanned, Value};
+use unicode_normalization::char::is_combining_mark;
+
+use crate::preprocess::Pipeline;
+use crate::{flag_span, text};
+
+/// What ends a string `--preview` cut short.
+const ELLIPSIS: &str = "…";
+
+pub struct Inputs {
+    /// The preprocessing to show the strings after, with
+    /// `--include-normalized`.
+    pipeline: Option<Pipeline>,
+    /// The string argument, for rows that all compare with it.
+    right: Option<String>,
+    coerce: Option<Arc<Config>>,
+    /// With `--preview`, the most characters as displayed a string keeps,
+    /// ellipsis included.
+    preview: Option<usize>,
+}
+
+impl Inputs {
+    /// Reads `--include-inputs` and `--include-normalized`, which implies
+    /// it; `None` without either.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        let normalized = call.has_flag("include-normalized")?;
+        let preview = match call.get_flag::<Spanned<i64>>("preview")? {
+            Some(n) if n.item < 1 => {
+                return Err(LabeledError::new("invalid preview length")
+                    .with_label("must be at least 1", n.span)
+                    .with_help("--preview is how many characters of each input to keep"))
+            }
+            Some(n) => Some(n.item as usize),
+            None => None,
+        };
+        if !normalized && !call.has_flag("include-inputs")? {
+            if preview.is_some() {
+                return Err(LabeledError::new("--preview needs the inputs")
+                    .with_label("nothing to cut short", flag_span(call, "preview"))
+                    .with_help("add --include-inputs or --include-normalized"));
+            }
+            return Ok(None);
+        }
+        Ok(Some(Inputs {
+            pipeline: match normalized {
+                true => Some(Pipeline::from_call(call)?),
+                false => None,
+            },
+            right: None,
+            coerce: None,
+            preview,
+        }))
+    }
+
+    /// For rows that compare their value with the string argument of
+    /// `call`, displayed as `--coerce` would.
+    pub fn against(
+        self,
+        call: &EvaluatedCall,
+        coerce: Option<&Config>,
+    ) -> Result<Self, LabeledError> {
+        let right = call.opt::<Value>(0)?;
+        let coerce = coerce.map(|c| Arc::new(c.clone()));
+        Ok(Inputs {
+            right: right.and_then(|r| text(&r, coerce.as_deref()).map(|t| t.into_owned())),
+            coerce,
+            ..self
+        })
+    }
+
+    fn string(&self, s: Option<&str>, span: Span) -> Value {
+        let s = match (s, &self.pipeline) {
+            (Some(s), Some(pipeline)) => pipeline.apply(s),
+            (Some(s), None) => s.into(),
+            (None, _) => return Value::nothing(span),
+        };
+        match self.preview {
+            Some(n) => Value::string(preview(&s, n), span),
+            None => Value::string(s, span),
+        }
+    }
+
+    /// Adds `left_input` and `right_input` to `row`.
+    pub fn push(&self, row: &mut Record, left: Option<&str>, right: Option<&str>, span: Span) {
+        row.push("left_input", self.string(left, span));
+        row.push("right_input", self.string(right, span));
+    }
+
+    /// Adds only `left_input`, for rows whose other strings name their
+    /// columns.
+    pub fn push_left(&self, row: &mut Record, left: &str, span: Span) {
+        row.push("left_input", self.string(Some(left), span));
+    }
+
+    /// Adds the inputs of a row comparing `v` with the string argument.
+    pub fn push_value(&self, row: &mut Record, v: &Value) {
+        let left = text(v, self.coerce.as_deref());
+        self.push(row, left.as_deref(), self.right.as_deref(), v.span());
+    }
+}
+
+/// `s` cut to `n` characters as displayed, the last an ellipsis, if it's
+/// longer. Cuts fall between grapheme clusters, so an accent stays with its
+/// letter and an emoji sequence with its parts.
+fn preview(s: &str, n: usize) -> String {
+    let clusters = clusters(s);
+    if clusters.len() <= n {
+        return s.to_string();
+    }
+    let mut cut = clusters[..n - 1].concat();
+    cut.push_str(ELLIPSIS);
+    cut
+}
+
+/// `s` split into grapheme clusters, approximately: a character joins the
+/// one before it when it's a combining mark, a variation selector, a skin
+/// tone modifier or a zero width joiner or follows one, or the second of a
+/// pair of regional indicators making a flag.
+fn clusters(s: &str) -> Vec<&str> {
+    let regional = |c: char| ('\u{1F1E6}'..='\u{1F1FF}').contains(&c);
+    let extends = |c: char| {
+        is_combining_mark(c)
+            || matches!(c, '\u{200D}' | '\u{FE00}'..='\u{FE0F}' | '\u{1F3FB}'..='\u{1F3FF}' | '\u{E0020}'..='\u{E007F}' | '\u{E0100}'..='\u{E01EF}')
+    };
+    let mut clusters: Vec<&str> = vec![];
+    let mut start = 0;
+    let mut prev: Option<char> = None;
+    let mut flag = false;
+    for (i, c) in s.char_indices() {
+        let joins = match prev {
+            None => false,
+            Some(p) => extends(c) || p == '\u{200D}' || (regional(c) && regional(p) && !flag),
+        };
+        if !joins && i > 0 {
+            clusters.push(&s[start..i]);
+            start = i;
+        }
+        // a flag is two indicators; a third starts the next flag
+        flag = regional(c) && joins && prev.is_some_and(regional);
+        prev = Some(c);
+    }
+    if start < s.len() {
+        clusters.push(&s[start..]);
+    }
+    clusters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_cut_between_grapheme_clusters() {
+        // "e" with a combining acute accent, a family emoji and two flags
+        let s = "cafe\u{301} 👨\u{200D}👩\u{200D}👧 🇫🇷🇩🇪";
+        assert_eq!(preview(s, 5), "cafe\u{301}…");
+        assert_eq!(preview(s, 7), "cafe\u{301} 👨\u{200D}👩\u{200D}👧…");
+        assert_eq!(preview(s, 9), s);
+        assert_eq!(preview("🇫🇷🇩🇪🇮🇹", 2), "🇫🇷…");
+    }
+}
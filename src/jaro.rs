@@ -0,0 +1,112 @@
+// Jaro and Jaro-Winkler with a configurable match window.
+//
+// textdistance hard-codes the standard `max(len1, len2) / 2 - 1` window, so
+// this mirrors its implementation with the window exposed. With the standard
+// window the results are identical to `textdistance::str::jaro`.
+
+/// `prefix` is Winkler's weight per shared leading token and how many
+/// tokens count, (0.1, 4) in textdistance.
+pub fn jaro_winkler<E: Eq>(s1: &[E], s2: &[E], window: usize, prefix: (f64, usize)) -> f64 {
+    winklerize(jaro(s1, s2, window), s1, s2, prefix)
+}
+
+pub fn jaro<E: Eq>(s1: &[E], s2: &[E], window: usize) -> f64 {
+    let l1 = s1.len();
+    let l2 = s2.len();
+
+    if l1 == 0 || l2 == 0 {
+        return if l1 == 0 && l2 == 0 { 1.0 } else { 0.0 };
+    }
+    if l1 == 1 && l2 == 1 {
+        return if s1[0] == s2[0] { 1.0 } else { 0.0 };
+    }
+
+    let mut s2_consumed = vec![false; l2];
+    let mut matches: usize = 0;
+    let mut n_trans = 0.0;
+    let mut b_match_index = 0;
+
+    for (i, a_elem) in s1.iter().enumerate() {
+        let min_bound = i.saturating_sub(window);
+        let max_bound = usize::min(l2 - 1, i + window);
+        if min_bound > max_bound {
+            continue;
+        }
+
+        for j in min_bound..=max_bound {
+            if !s2_consumed[j] && *a_elem == s2[j] {
+                s2_consumed[j] = true;
+                matches += 1;
+                if j < b_match_index {
+                    n_trans += 1.0;
+                }
+                b_match_index = j;
+                break;
+            }
+        }
+    }
+
+    if matches == 0 {
+        return 0.0;
+    }
+    let ms = matches as f64;
+    ((ms / l1 as f64) + (ms / l2 as f64) + ((ms - n_trans) / ms)) / 3.0
+}
+
+/// Winkler's common-prefix bonus: `weight` per shared leading token, for
+/// at most `max` tokens.
+fn winklerize<E: Eq>(jaro: f64, s1: &[E], s2: &[E], (weight, max): (f64, usize)) -> f64 {
+    let prefix_len = s1
+        .iter()
+        .zip(s2)
+        .take(max)
+        .take_while(|(a, b)| a == b)
+        .count();
+    jaro + (weight * prefix_len as f64 * (1.0 - jaro))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAIRS: [(&str, &str); 8] = [
+        ("martha", "marhta"),
+        ("dixon", "dicksonx"),
+        ("nushell", "nutshell"),
+        ("", "abc"),
+        ("a", "a"),
+        ("ab", "ba"),
+        ("crate", "trace"),
+        ("größe", "grösse"),
+    ];
+
+    /// textdistance's window, `max(len1, len2) / 2 - 1`.
+    fn standard(a: &[char], b: &[char]) -> usize {
+        (a.len().max(b.len()) / 2).saturating_sub(1)
+    }
+
+    #[test]
+    fn standard_window_matches_textdistance() {
+        for (a, b) in PAIRS {
+            let (ca, cb): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+            let window = standard(&ca, &cb);
+            assert_eq!(
+                jaro(&ca, &cb, window),
+                textdistance::str::jaro(a, b),
+                "{a} {b}"
+            );
+            assert_eq!(
+                jaro_winkler(&ca, &cb, window, (0.1, 4)),
+                textdistance::str::jaro_winkler(a, b),
+                "{a} {b}"
+            );
+        }
+    }
+
+    #[test]
+    fn wider_windows_match_more() {
+        let (a, b): (Vec<char>, Vec<char>) =
+            ("abcdef".chars().collect(), "fabcde".chars().collect());
+        assert!(jaro(&a, &b, 0) < jaro(&a, &b, 5));
+    }
+}
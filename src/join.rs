@@ -0,0 +1,1182 @@
+use std::collections::{BTreeMap, HashMap};
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    ast::CellPath, record, Category, Config, Example, LabeledError, Record, ShellError, Signature,
+    Span, Spanned, SyntaxShape, Value,
+};
+use rayon::prelude::*;
+use rayon::ThreadPool;
+
+use crate::arrow;
+use crate::budget::{self, Budget};
+use crate::cascade::Cascade;
+use crate::checkpoint::Checkpoint;
+use crate::custom::{self, Custom};
+use crate::fields::{self, Fields};
+use crate::format::Format;
+use crate::histogram;
+use crate::inputs::Inputs;
+use crate::memo::PairCache;
+use crate::merge;
+use crate::parallel;
+use crate::plan;
+use crate::prefilter::{self, Counts};
+use crate::preprocess::Pipeline;
+use crate::profile;
+use crate::rejects::{self, Rejects};
+use crate::sample::{self, Rng};
+use crate::stats::{self, Stats};
+use crate::style::{self, Bands};
+use crate::topk::{Limit, Ties, TopK};
+use crate::why;
+use crate::{
+    algorithm, canonical, coerce, compute_tokens, counts_tokens, flag_span, is_distance,
+    similarity, text, AlgoParams, StrSimilarityPlugin,
+};
+
+pub struct StrSimilarityJoin;
+
+impl SimplePluginCommand for StrSimilarityJoin {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity join"
+    }
+
+    fn description(&self) -> &str {
+        "Pair each input string with its most similar string from another list"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .required(
+                "right",
+                SyntaxShape::List(Box::new(SyntaxShape::Any)),
+                "Strings (or records, with --key) to match the input against",
+            )
+            .named(
+                "key",
+                SyntaxShape::CellPath,
+                "Match lists of records on the string at this cell path",
+                Some('k'),
+            )
+            .named(
+                "field-algorithms",
+                SyntaxShape::Record(vec![]),
+                "Match records on several fields, each with its own algorithm, e.g. {name: jaro_winkler, zip: hamming}; the score is their mean",
+                None,
+            )
+            .named(
+                "missing",
+                SyntaxShape::String,
+                "With --field-algorithms, how to treat absent or null fields: ignore, penalize or error (default)",
+                None,
+            )
+            .named(
+                "missing-weight",
+                SyntaxShape::Record(vec![]),
+                "Weight a missing field carries as a score of 0, per field, e.g. {zip: 0.5} (default: 1 when penalizing, 0 when ignoring)",
+                None,
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .named(
+                "custom",
+                SyntaxShape::Closure(Some(vec![SyntaxShape::String, SyntaxShape::String])),
+                "Score with a closure instead of an algorithm: it gets the two preprocessed strings and returns a similarity from 0 to 1",
+                None,
+            )
+            .named(
+                "custom-weight",
+                SyntaxShape::Number,
+                "Blend the --custom closure's score with the algorithm's instead of replacing it, the closure's weighing this much (0 to 1)",
+                None,
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "cascade",
+                SyntaxShape::List(Box::new(SyntaxShape::Any)),
+                "Algorithms to try in turn, strictest first, each only for rows the ones before matched nothing above --threshold, e.g. [exact jaro_winkler levenshtein], or above their own, e.g. [{algo: jaro_winkler, min: 0.95}]; adds the stage that matched",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum normalized similarity (0 to 1) for a pair to be joined",
+                Some('t'),
+            )
+            .named(
+                "max",
+                SyntaxShape::Int,
+                "Largest raw edit distance for a pair to match (distance algorithms only)",
+                None,
+            )
+            .switch(
+                "no-prefilter",
+                "Run the algorithm on every pair, even when the length difference already exceeds --max",
+                None,
+            )
+            .switch(
+                "no-exact-first",
+                "Score every right row even for input rows whose key equals one of them exactly",
+                None,
+            )
+            .switch(
+                "charset-filter",
+                "With --max, also skip pairs whose char counts differ by more than the bound (edit distances only)",
+                None,
+            )
+            .named(
+                "top",
+                SyntaxShape::Int,
+                "Emit the best k matches of every input row instead of only the best one",
+                None,
+            )
+            .named(
+                "ties",
+                SyntaxShape::String,
+                "Which of equally scored candidates win: first (in input order, the default), shortest, alphabetical, or all of them even beyond --top",
+                None,
+            )
+            .named(
+                "rejects",
+                SyntaxShape::Filepath,
+                "Also save the near misses, pairs scoring just below the threshold, to this nuon file for review",
+                None,
+            )
+            .named(
+                "reject-margin",
+                SyntaxShape::Number,
+                "How far below the threshold a near miss may score (default: 0.1)",
+                None,
+            )
+            .named(
+                "timeout",
+                SyntaxShape::Duration,
+                "Stop after this long and return the rows matched so far",
+                None,
+            )
+            .named(
+                "chunk-rows",
+                SyntaxShape::Int,
+                "Input rows per parallel work unit (default: sized from the string lengths)",
+                None,
+            )
+            .named(
+                "checkpoint",
+                SyntaxShape::Filepath,
+                "Save the finished chunks of input rows to this nuon file every ten seconds, for --resume to continue from",
+                None,
+            )
+            .switch(
+                "resume",
+                "Continue the run --checkpoint saved, scoring only the chunks it hadn't finished",
+                None,
+            )
+            .named(
+                "style-threshold",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Color scores green at or above the first value, amber at or above the second and red below",
+                None,
+            )
+            .named(
+                "threads",
+                SyntaxShape::Int,
+                "Number of threads to match on (default: one per core)",
+                None,
+            )
+            .named(
+                "sort-by",
+                SyntaxShape::String,
+                "Order of the rows: input (the default) or score, best first",
+                None,
+            )
+            .named(
+                "limit",
+                SyntaxShape::Int,
+                "Keep only this many rows, the best ones with --sort-by score, without holding the rest",
+                None,
+            )
+            .switch(
+                "farthest",
+                "Sort by score with the least close rows first, and keep those with --limit, to find outliers",
+                None,
+            )
+            .switch(
+                "unordered",
+                "Emit rows as their chunk finishes instead of in input order",
+                None,
+            )
+            .switch(
+                "no-cache",
+                "Recompute repeated pairs instead of reusing their scores",
+                None,
+            )
+            .switch(
+                "stats",
+                "Return a record of the rows and counts of the work done: comparisons, cache hits, exact matches, prefilter rejections and the most candidates searched for one row",
+                None,
+            )
+            .switch(
+                "include-inputs",
+                "Add the strings each row compared as left_input and right_input, the keys of records with --key",
+                None,
+            )
+            .switch(
+                "include-normalized",
+                "Like --include-inputs, with the strings as preprocessing left them",
+                None,
+            )
+            .named(
+                "preview",
+                SyntaxShape::Int,
+                "With --include-inputs or --include-normalized, cut inputs longer than this many characters short with an ellipsis",
+                None,
+            )
+            .switch(
+                "arrow-friendly",
+                "Give every row the same columns, each of one type (numbers all floats where any is), for polars into-df",
+                None,
+            )
+            .switch(
+                "merge",
+                "Coalesce each matched pair of records into one row, preferring non-empty and longer values, with a provenance record; records that matched nothing are kept as they are",
+                None,
+            )
+            .switch(
+                "explain-match",
+                "Add an explanation column to matched rows: each field's algorithm, score, weight and contribution to the pair's score",
+                None,
+            )
+            .switch(
+                "why",
+                "Add a why column to matched rows: a line of text saying why they matched, e.g. the score, a shared prefix or equal phonetic codes",
+                None,
+            )
+            .switch(
+                "plan",
+                "Don't match; report how many comparisons the run would make and estimate its time and memory from a quick calibration",
+                None,
+            )
+            .switch(
+                "coerce",
+                "Match numbers, dates and other non-string values by how they are displayed; the output keeps the original values",
+                None,
+            )
+            .switch(
+                "plain",
+                "Never color the output, whatever the color config says",
+                None,
+            )
+            .named(
+                "histogram",
+                SyntaxShape::Int,
+                "Return how the scores spread over this many bins between 0 and 1 instead of the rows",
+                None,
+            )
+            .named(
+                "sample",
+                SyntaxShape::Int,
+                "Score this many random left/right pairs instead of joining, to preview the score distribution",
+                None,
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "Seed for --sample, so the same pairs are drawn every run; every sampled pair holds the seed used",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "profile",
+                SyntaxShape::String,
+                "Take any flag not given here from the settings saved under this name with `str similarity profile save`",
+                None,
+            )
+            .named(
+                "config",
+                SyntaxShape::OneOf(vec![SyntaxShape::Record(vec![]), SyntaxShape::Filepath]),
+                "Take any flag not given here from this record of long flag names and values, or from the nuon or json file holding one",
+                None,
+            )
+            .named(
+                "preset",
+                SyntaxShape::String,
+                "Take any flag not given here or by --profile from a built-in preset: loose, standard or strict",
+                None,
+            )
+            .named(
+                "mask",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Replace volatile tokens in both strings with placeholders before anything else: uuids, timestamps, ips, hex, numbers",
+                None,
+            )
+            .named(
+                "mask-pattern",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Also replace matches of these regular expressions with a placeholder, like --mask",
+                None,
+            )
+            .named(
+                "preprocess",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Steps run in order on both strings before anything else: lowercase, strip-accents, strip-punctuation, collapse-spaces, trim, fold-compat, deleet",
+                None,
+            )
+            .switch(
+                "ignore-case",
+                "Lowercase both strings before scoring, like --preprocess [lowercase]",
+                None,
+            )
+            .switch(
+                "trim",
+                "Trim whitespace from both ends of both strings before scoring",
+                None,
+            )
+            .switch(
+                "collapse-whitespace",
+                "Squeeze runs of whitespace in both strings into one space before scoring",
+                None,
+            )
+            .switch(
+                "strip-accents",
+                "Drop diacritics from both strings before scoring, reading letters like ø and ł as o and l",
+                None,
+            )
+            .switch(
+                "strip-invisible",
+                "Drop soft hyphens, zero width spaces and joiners and byte order marks from both strings before scoring",
+                None,
+            )
+            .switch(
+                "fold-compat",
+                "Compare what a human reads: apply NFKC to both strings, turning ligatures, superscripts and roman numerals into plain letters and digits, and fold typographic quotes and dashes",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Match misspelled names to a reference list",
+                example: "['jon smith' 'ana lee'] | str similarity join ['John Smith' 'Anna Lee'] -a jaro_winkler -t 0.8",
+                result: None,
+            },
+            Example {
+                description: "Estimate the cost of a large join before running it",
+                example: "$customers | str similarity join $accounts --key name -a levenshtein --plan",
+                result: None,
+            },
+            Example {
+                description: "Match customer records to accounts by name",
+                example: "$customers | str similarity join $accounts --key name -a jaro_winkler -t 0.9",
+                result: None,
+            },
+            Example {
+                description: "Save progress through a long join, and after a crash continue where it stopped",
+                example: "$customers | str similarity join $accounts --key name -t 0.9 --checkpoint join.nuon --resume",
+                result: None,
+            },
+            Example {
+                description: "Match with a closure of your own, keeping the join's threshold and threads",
+                example: "$customers | str similarity join $accounts --key name -t 0.9 --custom {|a, b| $a | str similarity $b -a jaro_winkler -n }",
+                result: None,
+            },
+            Example {
+                description: "Review the pairs that just missed the threshold",
+                example: "$customers | str similarity join $accounts --key name -t 0.9 --rejects near-misses.nuon; open near-misses.nuon",
+                result: None,
+            },
+            Example {
+                description: "See how much each field contributed to a match",
+                example: "$customers | str similarity join $accounts --field-algorithms {name: jaro_winkler, zip: hamming} --explain-match | select left.name right.name score explanation",
+                result: None,
+            },
+            Example {
+                description: "Match exactly where possible, falling back to looser algorithms, and see which stage matched",
+                example: "$crm | str similarity join $billing --key name -t 0.85 --cascade [exact jaro_winkler levenshtein] | select left.name right.name stage",
+                result: None,
+            },
+            Example {
+                description: "Merge two customer lists into one, filling each record's gaps from its match",
+                example: "$crm | str similarity join $billing --key name -a jaro_winkler -t 0.92 --merge",
+                result: None,
+            },
+            Example {
+                description: "Give up after a minute, keeping the rows matched so far under `rows`",
+                example: "open names.txt | lines | str similarity join (open ref.txt | lines) --timeout 1min",
+                result: None,
+            },
+            Example {
+                description: "Suggest dictionary words at most two edits away",
+                example: "open typos.txt | lines | str similarity join (open words.txt | lines) --max 2",
+                result: None,
+            },
+            Example {
+                description: "Match people on name and zip code, each with a suitable metric",
+                example: "$people | str similarity join $registry --field-algorithms {name: jaro_winkler, zip: hamming} -t 0.85",
+                result: None,
+            },
+            Example {
+                description: "Keep the hundred best matches of a large join",
+                example: "$customers | str similarity join $accounts --key name --sort-by score --limit 100",
+                result: None,
+            },
+            Example {
+                description: "Check how much work the prefilters save",
+                example: "$names | str similarity join $ref --max 2 --charset-filter --stats | get stats",
+                result: None,
+            },
+            Example {
+                description: "Score every pair, even for rows with an exact match",
+                example: "$names | str similarity join $ref --no-exact-first --rejects near.nuon",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let call = &profile::apply(plugin, engine, call, &SimplePluginCommand::signature(self))?;
+        let right_values: Vec<Value> = call.req(0)?;
+        let merge = merge::check(call)?;
+        let cascade = Cascade::from_call(call)?;
+        let key: Option<CellPath> = call.get_flag("key")?;
+        let algo = algorithm(call)?;
+        let threshold: f64 = call.get_flag("threshold")?.unwrap_or(0.0);
+        let custom = Custom::from_call(engine, call)?;
+        let params = AlgoParams {
+            custom: custom.clone(),
+            ..AlgoParams::from_call(call)?
+        };
+        let use_cache = !call.has_flag("no-cache")?;
+        let stats = Stats::from_call(call)?;
+        if stats.is_some() && call.has_flag("plan")? {
+            return Err(LabeledError::new("--stats and --plan are exclusive")
+                .with_label("a plan does no work to count", flag_span(call, "stats"))
+                .with_help("drop --plan to run the join and count its work"));
+        }
+        let budget = Budget::from_call(call)?;
+        let rejects = Rejects::from_call(call, engine)?;
+        let floor = rejects.as_ref().map_or(threshold, |r| r.floor(threshold));
+        let histogram = histogram::bins(call)?;
+        // a histogram counts the scores, so they have to stay numbers
+        let format = Format::from_call(call)?;
+        let bands = match histogram {
+            Some(_) => None,
+            None => Bands::from_call(call, engine)?,
+        };
+        let sample = sample::size(call)?;
+        let fields = Fields::from_call(call)?;
+        if fields.is_some() {
+            for flag in ["key", "max", "include-inputs", "include-normalized"] {
+                if call.has_flag(flag)? {
+                    return Err(LabeledError::new(format!(
+                        "--field-algorithms can't be combined with --{flag}"
+                    ))
+                    .with_label("matches on a single string", flag_span(call, flag))
+                    .with_help("list every field to match on in --field-algorithms"));
+                }
+            }
+        }
+        let max = match call.get_flag::<Spanned<i64>>("max")? {
+            Some(m) if m.item < 0 => {
+                return Err(LabeledError::new("invalid edit bound")
+                    .with_label("max must be zero or greater", m.span)
+                    .with_help("--max 0 keeps exact matches only"));
+            }
+            Some(m) if !is_distance(&algo) => {
+                return Err(LabeledError::new("--max needs a distance algorithm")
+                    .with_label(format!("{algo} is a similarity"), m.span)
+                    .with_help("use --threshold to bound similarities"));
+            }
+            Some(m) => Some(m.item as usize),
+            None => None,
+        };
+        // only sound when the distance can't be below the length difference,
+        // which dropping repeated tokens breaks
+        let prefilter = !call.has_flag("no-prefilter")?
+            && prefilter::length_bounded(&algo)
+            && (params.multiset || !counts_tokens(&algo));
+        let charset = call.has_flag("charset-filter")?;
+        if charset && max.is_none() {
+            return Err(LabeledError::new("--charset-filter needs --max")
+                .with_label(
+                    "no edit bound to check against",
+                    flag_span(call, "charset-filter"),
+                )
+                .with_help("give the largest edit distance to allow with --max"));
+        }
+        let charset = charset && prefilter::bag_bounded(&algo);
+        let top = match call.get_flag::<Spanned<i64>>("top")? {
+            Some(k) if k.item < 1 => {
+                return Err(LabeledError::new("invalid top count")
+                    .with_label("need at least one match per row", k.span)
+                    .with_help("leave out --top to keep only the best match"));
+            }
+            Some(k) => k.item as usize,
+            None => 1,
+        };
+        let ties = Ties::from_call(call)?;
+        let limit = Limit::from_call(call)?;
+        let pool = parallel::pool(call)?;
+        let chunk_rows = match call.get_flag::<Spanned<i64>>("chunk-rows")? {
+            Some(n) if n.item < 1 => {
+                return Err(LabeledError::new("invalid chunk size")
+                    .with_label("chunk size must be at least 1", n.span)
+                    .with_help("leave out --chunk-rows to size chunks automatically"));
+            }
+            Some(n) => Some(n.item as usize),
+            None => None,
+        };
+        let checkpoint =
+            Checkpoint::from_call(call, engine, std::iter::once(input).chain(&right_values))?;
+        // a resumed run's chunks have to line up with the saved ones
+        let chunk_rows = checkpoint
+            .as_ref()
+            .and_then(|c| c.chunk_rows())
+            .or(chunk_rows);
+
+        // Split every key into chars once up front; each left value is
+        // compared against every right value, so doing it per pair would
+        // repeat the work `left.len() * right.len()` times.
+        let coerce = coerce(call, engine)?;
+        let config = coerce.as_deref();
+        let pipeline = Pipeline::from_call(call)?;
+        let inputs = Inputs::from_call(call)?;
+        let left = items(
+            input_values(input)?,
+            key.as_ref(),
+            fields.as_ref(),
+            charset,
+            config,
+            &pipeline,
+            inputs.is_some(),
+        )?;
+        let right = items(
+            &right_values,
+            key.as_ref(),
+            fields.as_ref(),
+            charset,
+            config,
+            &pipeline,
+            inputs.is_some(),
+        )?;
+        // an exact match is the best one, but other rows have to be scored
+        // anyway to find the next best, everything tied with it, or the
+        // shortest or first alphabetically of the rows scoring 1
+        let exact_index;
+        let exact_first = match !call.has_flag("no-exact-first")? && top == 1 && ties == Ties::First
+        {
+            true => {
+                exact_index = ExactIndex::new(&right);
+                Some(&exact_index)
+            }
+            false => None,
+        };
+        let pair_similarity = |l: &Item, r: &Item| {
+            if let Some(stats) = &stats {
+                stats.compared();
+            }
+            match &fields {
+                Some(f) => f.similarity(&l.fields, &r.fields, &params),
+                None => similarity(&algo, &l.chars, &r.chars, &params),
+            }
+        };
+
+        let span = call.head;
+        let field = key.as_ref().map_or("value".into(), |k| k.to_string());
+        let explain_match = |l: &Item, r: &Item| match &fields {
+            Some(f) => f.explain(&l.fields, &r.fields, &params, span),
+            None => {
+                let score = similarity(&algo, &l.chars, &r.chars, &params);
+                let row = fields::explanation(&field, &algo, Some(score), 1.0, score, span);
+                Value::list(vec![row], span)
+            }
+        };
+        let why_match = |l: &Item, r: &Item| match &fields {
+            Some(f) => why::fields(&f.explain(&l.fields, &r.fields, &params, span)),
+            None => {
+                let score = similarity(&algo, &l.chars, &r.chars, &params);
+                why::justify(canonical(&algo), score, &l.chars, &r.chars)
+            }
+        };
+        let (explain, why) = (call.has_flag("explain-match")?, call.has_flag("why")?);
+        let annotate_match = |l: &Item, r: &Item, row: &mut Record| {
+            if explain {
+                row.push("explanation", explain_match(l, r));
+            }
+            if why {
+                row.push("why", Value::string(why_match(l, r), span));
+            }
+        };
+        let annotate = (explain || why).then_some(&annotate_match as &Annotate);
+        if let Some(cascade) = &cascade {
+            let candidates: Vec<&[char]> = right.iter().map(|r| r.chars.as_slice()).collect();
+            let chunk_rows =
+                chunk_rows.unwrap_or_else(|| default_chunk_rows(&left, &right, pool.as_ref()));
+            let signals = engine.signals();
+            let chunks =
+                parallel::install(pool.as_ref(), || {
+                    left.par_chunks(chunk_rows)
+                        .map(|chunk| {
+                            signals.check(span)?;
+                            Ok(chunk.iter().map(|l| {
+                        let found = cascade.best(&l.chars, &candidates, threshold, &params);
+                        let r = found.map(|(i, _, _)| &right[i]);
+                        let mut row = record! {
+                            "left" => l.value.clone(),
+                            "right" => r.map_or(Value::nothing(span), |r| r.value.clone()),
+                            "score" => found.map_or(Value::nothing(span), |(_, score, _)| {
+                                style::score(score, bands.as_ref(), &format, span)
+                            }),
+                            "stage" => found.map_or(Value::nothing(span), |(_, _, stage)| {
+                                Value::string(stage, span)
+                            }),
+                        };
+                        if let Some(inputs) = &inputs {
+                            let right = r.and_then(|r| r.input.as_deref());
+                            inputs.push(&mut row, l.input.as_deref(), right, span);
+                        }
+                        if let (true, Some((i, score, stage))) = (why, found) {
+                            let text = why::justify(stage, score, &l.chars, &right[i].chars);
+                            row.push("why", Value::string(text, span));
+                        }
+                        (found.map(|(_, score, _)| score), Value::record(row, span))
+                        }).collect::<Vec<_>>())
+                        })
+                        .collect::<Result<Vec<_>, ShellError>>()
+                })?;
+            let rows = limit.keep(chunks.into_iter().flatten());
+            let rows = match merge {
+                true => merge::rows(rows, &right_values, span)?,
+                false => rows,
+            };
+            let rows = histogram_rows(rows, histogram, span);
+            return Ok(Value::list(arrow::shape(call, rows)?, span));
+        }
+        if call.has_flag("plan")? {
+            let all = left.len().saturating_mul(right.len());
+            let pairs = sample.map_or(all, |n| n.min(all));
+            let nanos = plan::calibrate(all, |i| {
+                pair_similarity(&left[i / right.len()], &right[i % right.len()]);
+            });
+            let threads = parallel::threads(pool.as_ref());
+            let chunk_rows =
+                chunk_rows.unwrap_or_else(|| default_chunk_rows(&left, &right, pool.as_ref()));
+            let text: usize = left
+                .iter()
+                .chain(&right)
+                .map(|i| i.chars.len() * size_of::<char>() + i.key.len())
+                .sum();
+            // every thread holds the scores of the chunk it works on
+            let cache = match use_cache && sample.is_none() {
+                true => {
+                    threads * chunk_rows.min(left.len()) * right.len() * plan::CACHE_ENTRY_BYTES
+                }
+                false => 0,
+            };
+            let rows = match sample {
+                Some(_) => pairs,
+                None => left.len() * top,
+            };
+            let details = record! {
+                "left_rows" => Value::int(left.len() as i64, span),
+                "right_rows" => Value::int(right.len() as i64, span),
+                // the estimate times full comparisons, so it is an upper
+                // bound when the prefilter skips some
+                "prefilter" => Value::bool(prefilter && max.is_some(), span),
+                "exact_matches" => Value::int(exact_first.map_or(0, |e| e.matched(&left)) as i64, span),
+            };
+            let memory = text + cache + rows * plan::ROW_BYTES;
+            custom::check(custom.as_ref())?;
+            return Ok(plan::report(pairs, nanos, threads, memory, details, span));
+        }
+        if let Some(n) = sample {
+            let mut rng = Rng::from_call(call)?;
+            let seed = rng.seed();
+            let pairs = left.len().saturating_mul(right.len());
+            let rows = sample::indices(&mut rng, n, pairs).into_iter().map(|i| {
+                let (l, r) = (&left[i / right.len()], &right[i % right.len()]);
+                let score = pair_similarity(l, r);
+                let mut row = record! {
+                    "left" => l.value.clone(),
+                    "right" => r.value.clone(),
+                    "score" => style::score(score, bands.as_ref(), &format, span),
+                };
+                if let Some(inputs) = &inputs {
+                    inputs.push(&mut row, l.input.as_deref(), r.input.as_deref(), span);
+                }
+                if let Some(annotate) = annotate {
+                    annotate(l, r, &mut row);
+                }
+                row.push("seed", Value::int(seed, span));
+                (Some(score), Value::record(row, span))
+            });
+            let rows = limit.keep(rows);
+            custom::check(custom.as_ref())?;
+            return Ok(Value::list(histogram_rows(rows, histogram, span), span));
+        }
+
+        // in input order the rows past the limit come from the input rows
+        // past it, each giving at least one
+        let left = match limit.first() {
+            Some(n) => &left[..n.min(left.len())],
+            None => &left[..],
+        };
+        let chunk_rows =
+            chunk_rows.unwrap_or_else(|| default_chunk_rows(left, &right, pool.as_ref()));
+        let signals = engine.signals();
+        // whether the prefilters leave a pair to score against --max
+        let passes = |l: &Item, r: &Item| {
+            let Some(max) = max else {
+                return true;
+            };
+            if prefilter && l.chars.len().abs_diff(r.chars.len()) > max {
+                return false;
+            }
+            match (&l.counts, &r.counts) {
+                (Some(lb), Some(rb)) => lb.bag_distance(rb) <= max,
+                _ => true,
+            }
+        };
+        let score = |l: &Item, r: &Item| {
+            let Some(max) = max else {
+                return Some(pair_similarity(l, r));
+            };
+            if !passes(l, r) {
+                return None;
+            }
+            if let Some(stats) = &stats {
+                stats.compared();
+            }
+            let score = compute_tokens(&algo, &l.chars, &r.chars, &params);
+            (score.raw <= max as f64).then_some(1.0 - score.normalized)
+        };
+        let ordered = !call.has_flag("unordered")?;
+        // each chunk already drops what the limit would, keeping the scores
+        // for cutting down the rows of all chunks together
+        let cut =
+            |rows: Vec<(Option<f64>, Value)>| limit.keep(rows.into_iter().map(|row| (row.0, row)));
+        let done = match &checkpoint {
+            Some(c) => c.begin(chunk_rows, span)?,
+            None => BTreeMap::new(),
+        };
+        // chunks by their first row, leaving out those a resumed run finished
+        let starts: Vec<usize> = (0..left.len())
+            .step_by(chunk_rows)
+            .filter(|s| !done.contains_key(s))
+            .collect();
+        let chunks = parallel::install(pool.as_ref(), || {
+            parallel::map_chunks(&starts, 1, ordered, |start| {
+                let start = start[0];
+                let chunk = &left[start..(start + chunk_rows).min(left.len())];
+                let mut cache = PairCache::new(use_cache);
+                let mut rows = Vec::with_capacity(chunk.len());
+                let mut near = vec![];
+                let mut stopped = None;
+                for l in chunk {
+                    if let Some(reason) = budget.exhausted(signals) {
+                        stopped = Some(reason);
+                        break;
+                    }
+                    let mut missed = vec![];
+                    let exact = exact_first.and_then(|e| e.lookup(l, score));
+                    let best = match exact {
+                        Some(r) => {
+                            if let Some(stats) = &stats {
+                                stats.exact_match();
+                            }
+                            vec![(r, 1.0)]
+                        }
+                        None => {
+                            if let Some(stats) = &stats {
+                                let candidates = right.iter().filter(|r| passes(l, r)).count();
+                                stats.searched(candidates, right.len() - candidates);
+                            }
+                            let limits = (threshold, floor);
+                            top_matches(
+                                l,
+                                &right,
+                                &mut cache,
+                                top,
+                                limits,
+                                ties,
+                                &mut missed,
+                                score,
+                            )
+                        }
+                    };
+                    rows.extend(join_rows(
+                        l,
+                        best,
+                        bands.as_ref(),
+                        &format,
+                        annotate,
+                        inputs.as_ref(),
+                        span,
+                    ));
+                    near.extend(missed.into_iter().map(|(r, score)| {
+                        rejects::row(l.value.clone(), r.value.clone(), score, threshold, span)
+                    }));
+                }
+                if let Some(stats) = &stats {
+                    stats.cache_hits(cache.hits());
+                }
+                let rows = cut(rows);
+                // a chunk the budget stopped short is scored again on resuming
+                if let (Some(checkpoint), None) = (&checkpoint, stopped) {
+                    checkpoint.record(start, &rows, &near, span);
+                }
+                (start, rows, near, stopped)
+            })
+        });
+        let mut chunks: Vec<_> = done
+            .into_iter()
+            .map(|(start, c)| (start, c.rows, c.near, None))
+            .chain(chunks)
+            .collect();
+        if ordered {
+            chunks.sort_by_key(|c| c.0);
+        }
+
+        let mut rows = Vec::with_capacity(left.len());
+        let mut near = vec![];
+        let mut stopped = None;
+        for (_, chunk, missed, reason) in chunks {
+            rows.extend(chunk);
+            near.extend(missed);
+            stopped = stopped.or(reason);
+        }
+        custom::check(custom.as_ref())?;
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.finish(span)?;
+        }
+        if let Some(rejects) = rejects {
+            rejects.save(near, span)?;
+        }
+        let rows = limit.keep(rows);
+        let rows = match merge {
+            true => merge::rows(rows, &right_values, span)?,
+            false => rows,
+        };
+        let rows = histogram_rows(rows, histogram, span);
+        let rows = arrow::shape(call, rows)?;
+        let output = match stopped {
+            Some(reason) => budget::partial(rows, reason, span),
+            None => Value::list(rows, span),
+        };
+        Ok(match &stats {
+            Some(stats) => stats::attach(output, stats, span),
+            None => output,
+        })
+    }
+}
+
+/// The output rows, or with `--histogram` the distribution of their scores.
+/// Rows without a match have no score and aren't counted.
+fn histogram_rows(rows: Vec<Value>, bins: Option<usize>, span: Span) -> Vec<Value> {
+    let Some(bins) = bins else {
+        return rows;
+    };
+    let scores = rows
+        .iter()
+        .filter_map(|r| r.as_record().ok()?.get("score")?.as_float().ok());
+    histogram::rows(&[("count", histogram::count(scores, bins))], bins, span)
+}
+
+/// One value taking part in the join: the original value and the string it
+/// is matched on, already split into chars, and its char counts when
+/// `--charset-filter` is on. With `--field-algorithms`, `fields` holds the
+/// chars of each field and `key` all of them, for caching.
+struct Item<'a> {
+    value: &'a Value,
+    key: String,
+    chars: Vec<char>,
+    fields: fields::Values,
+    counts: Option<Counts>,
+    /// The string before preprocessing, kept for `--include-inputs`.
+    input: Option<String>,
+}
+
+/// Rows per parallel work unit when `--chunk-rows` isn't given. Aims for
+/// roughly `TARGET_CELLS` of work per chunk so a few huge documents still
+/// spread across threads, while many tiny strings aren't scheduled one by
+/// one, and keeps at least a few chunks per thread for load balancing.
+fn default_chunk_rows(left: &[Item], right: &[Item], pool: Option<&ThreadPool>) -> usize {
+    const TARGET_CELLS: usize = 1 << 20;
+    const CHUNKS_PER_THREAD: usize = 4;
+
+    if left.is_empty() {
+        return 1;
+    }
+    let left_chars: usize = left.iter().map(|i| i.chars.len().max(1)).sum();
+    let right_chars: usize = right.iter().map(|i| i.chars.len().max(1)).sum();
+    let row_cells = (left_chars / left.len()).saturating_mul(right_chars).max(1);
+
+    let by_work = (TARGET_CELLS / row_cells).max(1);
+    let by_threads = left
+        .len()
+        .div_ceil(parallel::threads(pool) * CHUNKS_PER_THREAD)
+        .max(1);
+    by_work.min(by_threads)
+}
+
+/// The `k` right items scoring highest against `l` and at least `threshold`,
+/// best first, with their scores. `score` returns `None` for pairs ruled out
+/// entirely. Items scoring from `floor` up to the threshold go to `missed`.
+#[allow(clippy::too_many_arguments)]
+fn top_matches<'a>(
+    l: &'a Item,
+    right: &'a [Item],
+    cache: &mut PairCache<'a, Option<f64>>,
+    k: usize,
+    (threshold, floor): (f64, f64),
+    ties: Ties,
+    missed: &mut Vec<(&'a Item<'a>, f64)>,
+    score: impl Fn(&Item, &Item) -> Option<f64>,
+) -> Vec<(&'a Item<'a>, f64)> {
+    let mut top = TopK::new(k);
+    for (seq, r) in right.iter().enumerate() {
+        match cache.score(&l.key, &r.key, || score(l, r)) {
+            Some(s) if s >= threshold => top.push(s, ties.rank(seq, &r.key), r),
+            Some(s) if s >= floor => missed.push((r, s)),
+            _ => {}
+        }
+    }
+    let mut best = top.into_sorted();
+    // --ties all doesn't let the limit split a group of equal scores
+    if ties == Ties::All && best.len() == k {
+        let last = best[k - 1].1;
+        for r in right {
+            let tied = cache.score(&l.key, &r.key, || score(l, r)) == Some(last);
+            if tied && !best[..k].iter().any(|(b, _)| std::ptr::eq(*b, r)) {
+                best.push((r, last));
+            }
+        }
+    }
+    best
+}
+
+/// The right items by key, for matching input rows that equal one of them
+/// exactly without scoring them against the rest.
+struct ExactIndex<'a>(HashMap<&'a str, &'a Item<'a>>);
+
+impl<'a> ExactIndex<'a> {
+    /// Keeps the first of the items sharing a key, as ties are broken in
+    /// input order among equal keys.
+    fn new(right: &'a [Item<'a>]) -> Self {
+        let mut index = HashMap::with_capacity(right.len());
+        for r in right {
+            index.entry(r.key.as_str()).or_insert(r);
+        }
+        ExactIndex(index)
+    }
+
+    /// The right item equal to `l`, if the pair gets a perfect score; it
+    /// doesn't when, say, both lack a field of `--field-algorithms`.
+    fn lookup(
+        &self,
+        l: &Item,
+        score: impl Fn(&Item, &Item) -> Option<f64>,
+    ) -> Option<&'a Item<'a>> {
+        let r = *self.0.get(l.key.as_str())?;
+        (score(l, r) == Some(1.0)).then_some(r)
+    }
+
+    /// How many of `left` have an equal key on the right.
+    fn matched(&self, left: &[Item]) -> usize {
+        left.iter()
+            .filter(|l| self.0.contains_key(l.key.as_str()))
+            .count()
+    }
+}
+
+/// Adds the columns `--explain-match` and `--why` describe a matched pair
+/// with.
+type Annotate<'f> = dyn Fn(&Item, &Item, &mut Record) + Sync + 'f;
+
+/// One row per match with its score, or a single row with empty `right` and
+/// `score` when nothing matched. With `annotate`, matched rows also get an
+/// explanation or justification, and with `inputs` every row the strings
+/// compared.
+fn join_rows(
+    l: &Item,
+    matches: Vec<(&Item, f64)>,
+    bands: Option<&Bands>,
+    format: &Format,
+    annotate: Option<&Annotate>,
+    inputs: Option<&Inputs>,
+    span: Span,
+) -> Vec<(Option<f64>, Value)> {
+    let row = |r: Option<&Item>, score| {
+        let mut row = record! {
+            "left" => l.value.clone(),
+            "right" => r.map_or(Value::nothing(span), |r| r.value.clone()),
+            "score" => score,
+        };
+        if let Some(inputs) = inputs {
+            let right = r.and_then(|r| r.input.as_deref());
+            inputs.push(&mut row, l.input.as_deref(), right, span);
+        }
+        row
+    };
+    if matches.is_empty() {
+        let row = row(None, Value::nothing(span));
+        return vec![(None, Value::record(row, span))];
+    }
+    matches
+        .into_iter()
+        .map(|(r, score)| {
+            let mut row = row(Some(r), style::score(score, bands, format, span));
+            if let Some(annotate) = annotate {
+                annotate(l, r, &mut row);
+            }
+            (Some(score), Value::record(row, span))
+        })
+        .collect()
+}
+
+/// The values of a single-value or list pipeline input.
+fn input_values(input: &Value) -> Result<&[Value], LabeledError> {
+    match input {
+        Value::List { vals, .. } => Ok(vals),
+        Value::String { .. } | Value::Record { .. } => Ok(std::slice::from_ref(input)),
+        v => Err(
+            LabeledError::new(format!("requires string input, got {}", v.get_type()))
+                .with_label(
+                    "Expected a list of strings or records from pipeline",
+                    v.span(),
+                )
+                .with_help("pipe in a list of strings, or of records to match with --key"),
+        ),
+    }
+}
+
+/// Pairs each value with the string it is matched on: the value itself, or
+/// the string at `key` when matching records.
+fn items<'a>(
+    vals: &'a [Value],
+    key: Option<&CellPath>,
+    fields: Option<&Fields>,
+    counts: bool,
+    coerce: Option<&Config>,
+    pipeline: &Pipeline,
+    keep_input: bool,
+) -> Result<Vec<Item<'a>>, LabeledError> {
+    vals.iter()
+        .map(|v| {
+            if let Some(fields) = fields {
+                let values = fields.values(v, coerce)?;
+                // separators no field is expected to contain, so missing and
+                // empty fields still cache apart
+                let key: String = values
+                    .iter()
+                    .map(|f| match f {
+                        Some(chars) => chars.iter().collect::<String>(),
+                        None => "\u{0}".into(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\u{1f}");
+                return Ok(Item {
+                    value: v,
+                    chars: key.chars().collect(),
+                    fields: values,
+                    counts: None,
+                    key,
+                    input: None,
+                });
+            }
+            let key = match key {
+                Some(path) => {
+                    let found = v
+                        .clone()
+                        .follow_cell_path(&path.members, false)
+                        .map_err(|e| {
+                            LabeledError::new(format!("no value at '{path}'"))
+                                .with_label("missing join key", v.span())
+                                .with_help("every row on both sides needs the --key field")
+                                .with_inner(e)
+                        })?;
+                    string_of(&found, coerce)?
+                }
+                None => string_of(v, coerce)?,
+            };
+            let input = keep_input.then(|| key.clone());
+            let key = pipeline.apply(&key).into_owned();
+            let chars: Vec<char> = key.chars().collect();
+            Ok(Item {
+                value: v,
+                counts: counts.then(|| Counts::new(&chars)),
+                chars,
+                fields: vec![],
+                key,
+                input,
+            })
+        })
+        .collect()
+}
+
+fn string_of(v: &Value, coerce: Option<&Config>) -> Result<String, LabeledError> {
+    text(v, coerce).map(|t| t.into_owned()).ok_or_else(|| {
+        LabeledError::new(format!("expected a string, got {}", v.get_type()))
+            .with_label("not a string", v.span())
+            .with_help(
+                "only strings can be matched; pick a string field with --key, or pass --coerce",
+            )
+    })
+}
@@ -0,0 +1,179 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{record, Category, Example, LabeledError, Signature, Type, Value};
+
+use crate::{algorithm_version, StrSimilarityPlugin, ALGORITHMS};
+
+pub struct StrSimilarityList;
+
+impl SimplePluginCommand for StrSimilarityList {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity list"
+    }
+
+    fn description(&self) -> &str {
+        "List the similarity algorithms with their families, versions and parameters"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_type(Type::Nothing, Type::table())
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "List all the included similarity algorithms",
+                example: "str similarity list",
+                result: None,
+            },
+            Example {
+                description: "Find the algorithm a short name stands for",
+                example: "str similarity list | where short == jarw | get 0.algorithm",
+                result: Some(Value::test_string("jaro_winkler")),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        Ok(list_algorithms().with_span(call.head))
+    }
+}
+
+#[rustfmt::skip]
+/// One row per algorithm in the registry, as `str similarity list` and
+/// `--list` show them.
+pub fn list_algorithms() -> Value {
+    let rows = ALGORITHMS
+        .iter()
+        .map(|a| {
+            Value::test_record(record! {
+                "algorithm" => Value::test_string(a.name),
+                "short" => Value::test_string(a.short),
+                "family" => Value::test_string(a.family),
+                "algorithm_version" => Value::test_string(algorithm_version(a.name)),
+                "parameters" => parameters(a.params),
+            })
+        })
+        .collect();
+
+    Value::test_list(rows)
+}
+
+/// The tunable parameters of an algorithm, from its `params` in the
+/// registry, each with the flag that sets it, its type, default and valid
+/// range, so scripts can check parameter values before a run. A default of
+/// nothing means it is derived from the input or the parameter is off
+/// unless given.
+fn parameters(names: &[&str]) -> Value {
+    let param =
+        |name: &str, flag: &str, ty: &str, default: Value, range: (Value, Value), note: &str| {
+            Value::test_record(record! {
+                "name" => Value::test_string(name),
+                "flag" => Value::test_string(flag),
+                "type" => Value::test_string(ty),
+                "default" => default,
+                "min" => range.0,
+                "max" => range.1,
+                "note" => Value::test_string(note),
+            })
+        };
+    let none = Value::test_nothing;
+    let mut params: Vec<Value> = names
+        .iter()
+        .map(|name| match *name {
+            "window" => param(
+                "window",
+                "--jaro-window",
+                "int",
+                none(),
+                (Value::test_int(0), none()),
+                "match window; half the longer length minus one when not given",
+            ),
+            "prefix_weight" => param(
+                "prefix_weight",
+                "--params",
+                "float",
+                Value::test_float(0.1),
+                (Value::test_float(0.0), Value::test_float(1.0)),
+                "bonus per shared leading token; times max_prefix must be at most 1",
+            ),
+            "max_prefix" => param(
+                "max_prefix",
+                "--params",
+                "int",
+                Value::test_int(4),
+                (Value::test_int(1), none()),
+                "most leading tokens the bonus counts",
+            ),
+            "alpha" => param(
+                "alpha",
+                "--params",
+                "float",
+                Value::test_float(1.0),
+                (Value::test_float(0.0), none()),
+                "weight of the tokens only the input has",
+            ),
+            "beta" => param(
+                "beta",
+                "--params",
+                "float",
+                Value::test_float(1.0),
+                (Value::test_float(0.0), none()),
+                "weight of the tokens only the argument has; alpha = beta = 0.5 is sorensen_dice",
+            ),
+            "affix_basis" => param(
+                "affix_basis",
+                "--affix-basis",
+                "string",
+                Value::test_string("longer"),
+                (none(), none()),
+                "length normalized by: shorter, longer or first",
+            ),
+            "multiset" => param(
+                "multiset",
+                "--multiset",
+                "bool",
+                Value::test_bool(true),
+                (none(), none()),
+                "false compares sets of distinct tokens",
+            ),
+            _ => unreachable!("parameter '{name}' has no description"),
+        })
+        .collect();
+    params.push(param(
+        "prefix_boost_weight",
+        "--prefix-boost",
+        "float",
+        none(),
+        (Value::test_float(0.0), Value::test_float(1.0)),
+        "first item; weight times length must be at most 1; off unless given",
+    ));
+    params.push(param(
+        "prefix_boost_length",
+        "--prefix-boost",
+        "int",
+        Value::test_int(4),
+        (Value::test_int(1), none()),
+        "second item, optional",
+    ));
+    Value::test_list(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityList)
+    }
+}
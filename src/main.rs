@@ -1,15 +1,146 @@
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
 use std::vec;
 
+use compute::{
+    compute, compute_tokens, counts_tokens, family, is_distance, prefix_boost, similarity,
+    Orientation, Score, Token,
+};
+use format::{number, Format};
+use inputs::Inputs;
+use memo::Lru;
 use nu_plugin::{
     serve_plugin, EngineInterface, EvaluatedCall, MsgPackSerializer, Plugin, PluginCommand,
-    SimplePluginCommand,
-};
-use nu_protocol::{
-    record, Category, Example, LabeledError, Signature, Span, Spanned, SyntaxShape, Value,
 };
-use textdistance::{nstr, str};
+use nu_protocol::{Config, LabeledError, Span, Spanned, Value};
+use registry::{lookup, Algorithm, ALGORITHMS};
+use topk::Limit;
+
+mod acronym;
+mod agreement;
+mod agrep;
+mod aliases;
+mod alignment;
+mod apply;
+mod arrow;
+mod auto;
+mod banded;
+mod bench;
+mod best;
+mod budget;
+mod bytes;
+mod capabilities;
+mod cascade;
+mod casing;
+mod check;
+mod checkpoint;
+mod chunked;
+mod classify;
+mod clone_detect;
+mod clones;
+mod cluster;
+mod code;
+mod compare;
+mod complete;
+mod compute;
+mod custom;
+mod dates;
+mod diff;
+mod dirs;
+mod diversify;
+mod edit_ops;
+mod edits;
+mod encode;
+mod evaluate;
+mod expansions;
+mod fields;
+mod filter;
+mod fingerprint;
+mod format;
+mod glob;
+mod group;
+mod hirschberg;
+mod histogram;
+mod identifier;
+mod inputs;
+mod jaro;
+mod join;
+mod list;
+mod map_columns;
+mod mask;
+mod matrix;
+mod memo;
+mod merge;
+mod ngrams;
+mod normalize;
+mod numbers;
+mod output;
+mod pairs;
+mod parallel;
+mod paths;
+mod phonetic;
+mod pick;
+mod plan;
+mod prefilter;
+mod preprocess;
+mod profile;
+mod registry;
+mod rejects;
+mod report;
+mod sample;
+mod self_test;
+mod session;
+mod skeleton;
+mod stats;
+mod style;
+mod suffix_array;
+mod suffix_automaton;
+mod synth;
+mod templates;
+mod topk;
+mod tree;
+mod tree_edit;
+mod uniq;
+mod validate;
+mod visual;
+mod weights;
+mod why;
+
+pub struct StrSimilarityPlugin {
+    sessions: session::Sessions,
+    preprocessed: CodeCache,
+}
+
+/// A string and the preprocessing options it went through.
+type Preprocess = (Option<code::Comments>, String);
+
+/// Preprocessed strings kept across calls.
+const PREPROCESS_CACHE: usize = 1024;
 
-struct StrSimilarityPlugin;
+impl Default for StrSimilarityPlugin {
+    fn default() -> Self {
+        StrSimilarityPlugin {
+            sessions: session::Sessions::default(),
+            preprocessed: CodeCache(Arc::new(Mutex::new(Lru::new(PREPROCESS_CACHE)))),
+        }
+    }
+}
+
+/// Strings normalized for code mode, kept across calls. Shared, so the
+/// streams a call returns can use it after the call is over.
+#[derive(Clone)]
+struct CodeCache(Arc<Mutex<Lru<Preprocess, Arc<str>>>>);
+
+impl CodeCache {
+    /// `code::normalize`, reusing the result when the same string was
+    /// normalized with the same options recently.
+    fn normalize(&self, s: &str, comments: Option<&code::Comments>) -> Arc<str> {
+        let mut cache = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        cache.get_or_insert_with((comments.cloned(), s.to_string()), || {
+            code::normalize(s, comments).into()
+        })
+    }
+}
 
 impl Plugin for StrSimilarityPlugin {
     fn version(&self) -> String {
@@ -17,258 +148,680 @@ impl Plugin for StrSimilarityPlugin {
     }
 
     fn commands(&self) -> Vec<Box<dyn PluginCommand<Plugin = Self>>> {
-        vec![Box::new(StrSimilarity)]
+        let commands: Vec<Box<dyn PluginCommand<Plugin = Self>>> = vec![
+            Box::new(compare::StrSimilarity),
+            Box::new(apply::StrSimilarityApply),
+            Box::new(bench::StrSimilarityBench),
+            Box::new(best::StrSimilarityBest),
+            Box::new(capabilities::StrSimilarityCapabilities),
+            Box::new(check::StrSimilarityCheck),
+            Box::new(classify::StrSimilarityClassify),
+            Box::new(clones::StrSimilarityClones),
+            Box::new(cluster::StrSimilarityCluster),
+            Box::new(complete::StrSimilarityComplete),
+            Box::new(diff::StrSimilarityDiff),
+            Box::new(dirs::StrSimilarityDirs),
+            Box::new(diversify::StrSimilarityDiversify),
+            Box::new(encode::StrSimilarityEncode),
+            Box::new(evaluate::StrSimilarityEvaluate),
+            Box::new(group::StrSimilarityGroup),
+            Box::new(join::StrSimilarityJoin),
+            Box::new(list::StrSimilarityList),
+            Box::new(map_columns::StrSimilarityMapColumns),
+            Box::new(matrix::StrSimilarityMatrix),
+            Box::new(pairs::StrSimilarityPairs),
+            Box::new(filter::StrSimilarityFilter),
+            Box::new(pick::StrSimilarityPick),
+            Box::new(profile::StrSimilarityProfileSave),
+            Box::new(profile::StrSimilarityProfileList),
+            Box::new(profile::StrSimilarityProfileDelete),
+            Box::new(self_test::StrSimilaritySelfTest),
+            Box::new(session::StrSimilaritySession),
+            Box::new(skeleton::StrSimilaritySkeleton),
+            Box::new(synth::StrSimilaritySynth),
+            Box::new(templates::StrSimilarityTemplates),
+            Box::new(tree::StrSimilarityTree),
+            Box::new(uniq::StrSimilarityUniq),
+        ];
+        // every command that resolves deprecated names warns of them
+        commands
+            .into_iter()
+            .map(
+                |c| match c.signature().named.iter().any(|f| f.long == "strict-names") {
+                    true => Box::new(aliases::Warned(c)) as Box<dyn PluginCommand<Plugin = Self>>,
+                    false => c,
+                },
+            )
+            .collect()
+    }
+}
+
+/// Where `--pad` fills up the shorter string, and with what.
+struct Pad {
+    left: bool,
+    fill: char,
+}
+
+impl Pad {
+    /// Pads the shorter of `a` and `b` to the other's length, returning how
+    /// many chars were added.
+    fn apply(&self, a: &mut Vec<char>, b: &mut Vec<char>) -> usize {
+        let padding = a.len().abs_diff(b.len());
+        let short = if a.len() < b.len() { a } else { b };
+        let fill = vec![self.fill; padding];
+        if self.left {
+            short.splice(0..0, fill);
+        } else {
+            short.extend(fill);
+        }
+        padding
     }
 }
 
-struct StrSimilarity;
+/// Reads `--pad` and `--pad-char`, which only make sense for hamming.
+fn pad(call: &EvaluatedCall) -> Result<Option<Pad>, LabeledError> {
+    let side: Option<Spanned<String>> = call.get_flag("pad")?;
+    let fill: Option<Spanned<String>> = call.get_flag("pad-char")?;
+    let Some(side) = side else {
+        return match fill {
+            Some(f) => Err(LabeledError::new("--pad-char needs --pad")
+                .with_label("nothing to pad", f.span)
+                .with_help("add --pad left or --pad right")),
+            None => Ok(None),
+        };
+    };
+    let left = match side.item.as_str() {
+        "left" => true,
+        "right" => false,
+        other => {
+            return Err(LabeledError::new("unknown pad side")
+                .with_label(format!("'{other}' is not a side"), side.span)
+                .with_help("pad on the left or the right"))
+        }
+    };
+    let fill = match fill {
+        Some(f) => {
+            let mut chars = f.item.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => {
+                    return Err(LabeledError::new("invalid pad char")
+                        .with_label("must be a single character", f.span)
+                        .with_help("e.g. --pad-char 0 for numbers"))
+                }
+            }
+        }
+        None => ' ',
+    };
+    Ok(Some(Pad { left, fill }))
+}
+
+/// How both strings are preprocessed before scoring.
+#[derive(Clone)]
+enum Mode {
+    /// Split identifiers and collapse whitespace, dropping comments too when
+    /// their syntax is known.
+    Code(Option<code::Comments>),
+    /// Compare line by line instead of char by char.
+    Lines,
+    /// Compare word by word.
+    Words,
+    /// Compare an acronym with the initials of what it may stand for.
+    Acronym,
+    /// Compare rendered characters, whatever their width form.
+    Visual,
+    /// Compare overlapping runs of this many chars.
+    Ngrams(usize),
+    /// Compare fixed-length codes position by position, tolerating swaps.
+    Identifier,
+    /// Compare dates by how many days apart they are.
+    Date,
+    /// Compare file paths by directory, stem and extension.
+    Path,
+}
 
-impl SimplePluginCommand for StrSimilarity {
-    type Plugin = StrSimilarityPlugin;
+const MODES: [&str; 8] = [
+    "code",
+    "lines",
+    "words",
+    "acronym",
+    "visual",
+    "identifier",
+    "date",
+    "path",
+];
 
-    fn name(&self) -> &str {
-        "str similarity"
+/// Reads `--mode` and `--language`.
+fn mode(call: &EvaluatedCall) -> Result<Option<Mode>, LabeledError> {
+    let mode: Option<Spanned<String>> = call.get_flag("mode")?;
+    let language: Option<Spanned<String>> = call.get_flag("language")?;
+    match (mode, language) {
+        (Some(m), _) if !MODES.contains(&m.item.as_str()) => Err(LabeledError::new("unknown mode")
+            .with_label(format!("'{}' is not a mode", m.item), m.span)
+            .with_help(format!("valid modes: {}", MODES.join(", ")))),
+        (Some(m), Some(lang)) if m.item == "code" => match code::comments(&lang.item) {
+            Some(c) => Ok(Some(Mode::Code(Some(c)))),
+            None => Err(LabeledError::new("unknown language")
+                .with_label(
+                    format!("no comment syntax known for '{}'", lang.item),
+                    lang.span,
+                )
+                .with_help(format!("known languages: {}", code::LANGUAGES.join(", ")))),
+        },
+        (Some(m), None) if m.item == "code" => Ok(Some(Mode::Code(None))),
+        (Some(m), None) if m.item == "acronym" => Ok(Some(Mode::Acronym)),
+        (Some(m), None) if m.item == "words" => Ok(Some(Mode::Words)),
+        (Some(m), None) if m.item == "visual" => Ok(Some(Mode::Visual)),
+        (Some(m), None) if m.item == "identifier" => Ok(Some(Mode::Identifier)),
+        (Some(m), None) if m.item == "date" => Ok(Some(Mode::Date)),
+        (Some(m), None) if m.item == "path" => Ok(Some(Mode::Path)),
+        (Some(_), None) => Ok(Some(Mode::Lines)),
+        (_, Some(lang)) => Err(LabeledError::new("--language needs --mode code")
+            .with_label("only used in code mode", lang.span)
+            .with_help("add --mode code")),
+        (None, None) => Ok(None),
     }
+}
 
-    fn description(&self) -> &str {
-        "Compare strings to find similarity by algorithm"
+/// `mode` as `--tokenize` and `--ngram-size` change it: `words` is
+/// `--mode words`, `ngram` compares n-grams and `chars` keeps the default.
+fn tokenize(call: &EvaluatedCall, mode: Option<Mode>) -> Result<Option<Mode>, LabeledError> {
+    let size = match call.get_flag::<Spanned<i64>>("ngram-size")? {
+        Some(n) if n.item < 1 => {
+            return Err(LabeledError::new("invalid n-gram size")
+                .with_label("must be at least 1", n.span)
+                .with_help("bigrams, --ngram-size 2, are the usual choice"));
+        }
+        n => n,
+    };
+    let Some(tokens) = call.get_flag::<Spanned<String>>("tokenize")? else {
+        if size.is_some() {
+            return Err(LabeledError::new("--ngram-size needs --tokenize ngram")
+                .with_label("only used for n-grams", flag_span(call, "ngram-size"))
+                .with_help("add --tokenize ngram"));
+        }
+        return Ok(mode);
+    };
+    if mode.is_some() && tokens.item != "chars" {
+        return Err(LabeledError::new("--tokenize and --mode conflict")
+            .with_label("the mode already decides the tokens", tokens.span)
+            .with_help("drop one of --tokenize and --mode"));
     }
-    fn signature(&self) -> Signature {
-        Signature::build(PluginCommand::name(self))
-            .required("string", SyntaxShape::String, "String to compare with")
-            .switch(
-                "normalize",
-                "Normalize the results between 0 and 1",
-                Some('n'),
-            )
-            .switch("list", "List all available algorithms", Some('l'))
-            .named(
-                "algorithm",
-                SyntaxShape::String,
-                "Name of the algorithm to compute",
-                Some('a'),
+    if size.is_some() && tokens.item != "ngram" {
+        return Err(LabeledError::new("--ngram-size needs --tokenize ngram")
+            .with_label("only used for n-grams", flag_span(call, "ngram-size"))
+            .with_help("use --tokenize ngram or drop --ngram-size"));
+    }
+    match tokens.item.as_str() {
+        "chars" => Ok(mode),
+        "words" => Ok(Some(Mode::Words)),
+        "ngram" => Ok(Some(Mode::Ngrams(size.map_or(2, |n| n.item as usize)))),
+        _ => Err(LabeledError::new("unknown tokens")
+            .with_label(
+                format!("can't tokenize into '{}'", tokens.item),
+                tokens.span,
             )
-            .switch("all", "Run all algorithms", Some('A'))
-            .category(Category::Experimental)
+            .with_help("valid tokens: chars, words, ngram")),
     }
+}
 
-    fn examples(&self) -> Vec<Example> {
-        vec![
-            Example {
-                description: "Compare two strings for similarity".into(),
-                example: "'nutshell' | str similarity 'nushell'".into(),
-                result: None,
-            },
-            Example {
-                description:
-                    "Compare two strings for similarity and normalize the output value".into(),
-                example: "'nutshell' | str similarity -n 'nushell'".into(),
-                result: None,
-            },
-            Example {
-                description: "Compare two strings for similarity using a specific algorithm"
-                    .into(),
-                example: "'nutshell' | str similarity 'nushell' -a levenshtein".into(),
-                result: None,
-            },
-            Example {
-                description: "List all the included similarity algorithms".into(),
-                example: "str similarity 'nu' --list".into(),
-                result: None,
-            },
-            Example {
-                description: "Compare two strings for similarity with all algorithms".into(),
-                example: "'nutshell' | str similarity 'nushell' -A".into(),
-                result: None,
-            },
-            Example {
-                description: "Compare two strings for similarity with all algorithms and normalize the output value".into(),
-                example: "'nutshell' | str similarity 'nushell' -A -n".into(),
-                result: None,
-            },
-        ]
+/// Tunables for algorithms that have them. `None` keeps textdistance's default.
+#[derive(Clone)]
+struct AlgoParams {
+    jaro_window: Option<usize>,
+    /// Count repeated tokens in the token-counting algorithms; off, they
+    /// compare sets.
+    multiset: bool,
+    /// Weight per shared leading token and how many tokens count, as in
+    /// jaro_winkler.
+    prefix_boost: Option<(f64, usize)>,
+    /// Length the prefix and suffix algorithms divide by when normalizing.
+    affix_basis: Basis,
+    /// Tversky's alpha and beta: how much the tokens only the first and
+    /// only the second string has weigh.
+    tversky: (f64, f64),
+    /// jaro_winkler's weight per shared leading token and how many count.
+    winkler: (f64, usize),
+    /// The `--custom` closure scoring instead of any algorithm.
+    custom: Option<Arc<custom::Custom>>,
+}
+
+impl Default for AlgoParams {
+    /// textdistance's own settings.
+    fn default() -> Self {
+        AlgoParams {
+            jaro_window: None,
+            multiset: true,
+            prefix_boost: None,
+            affix_basis: Basis::Longer,
+            tversky: (1.0, 1.0),
+            winkler: (0.1, 4),
+            custom: None,
+        }
     }
+}
 
-    fn run(
-        &self,
-        _config: &StrSimilarityPlugin,
-        _engine: &EngineInterface,
-        call: &EvaluatedCall,
-        input: &Value,
-    ) -> Result<Value, LabeledError> {
-        let compare_to_str_optn: Option<Spanned<String>> = call.opt(0)?;
-        let compare_to_str = match compare_to_str_optn {
-            Some(p) => p,
-            None => {
-                return Err(LabeledError::new("found nothing")
-                    .with_label("Expected a string as a parameter", call.head));
-            }
+/// Which string's length normalizes a shared prefix or suffix. textdistance
+/// uses the longer one, so a short string that fully starts a long one
+/// scores low; the shorter one scores it 1, and the first one makes the
+/// score asymmetric, as in "how much of the input is shared".
+#[derive(Clone, Copy, PartialEq)]
+enum Basis {
+    Shorter,
+    Longer,
+    First,
+}
+
+impl Basis {
+    const NAMES: [&'static str; 3] = ["shorter", "longer", "first"];
+
+    fn parse(name: &Spanned<String>) -> Result<Self, LabeledError> {
+        match name.item.as_str() {
+            "shorter" => Ok(Basis::Shorter),
+            "longer" => Ok(Basis::Longer),
+            "first" => Ok(Basis::First),
+            _ => Err(LabeledError::new("unknown affix basis")
+                .with_label(format!("'{}' is not a basis", name.item), name.span)
+                .with_help(format!("valid bases: {}", Basis::NAMES.join(", ")))),
+        }
+    }
+
+    /// `score`, a shared prefix or suffix length, normalized by this basis.
+    fn rebase(self, score: Score, len1: usize, len2: usize) -> Score {
+        let base = match self {
+            Basis::Shorter => len1.min(len2),
+            Basis::Longer => len1.max(len2),
+            Basis::First => len1,
         };
-        let normalize = call.has_flag("normalize")?;
-        let list = call.has_flag("list")?;
-        if list {
-            return Ok(list_algorithms());
+        // empty strings are scored by the empty policy
+        if self == Basis::Longer || base == 0 {
+            return score;
         }
-        let algo: Option<String> = call.get_flag("algorithm")?;
-        let sim = match algo {
-            Some(a) => a.to_string(),
-            None => "levenshtein".to_string(),
+        Score {
+            raw: score.raw,
+            normalized: (score.raw / base as f64).min(1.0),
+            max: base as f64,
+        }
+    }
+}
+
+impl AlgoParams {
+    fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let given = Given::from_call(call)?;
+        let window = match (call.get_flag::<Spanned<i64>>("jaro-window")?, given.window) {
+            (Some(_), Some(_)) => return Err(given_twice(call, "jaro-window", "window")),
+            (Some(w), None) => Some(w),
+            (None, w) => w,
+        };
+        let jaro_window = match window {
+            Some(w) if w.item < 0 => {
+                return Err(LabeledError::new("invalid jaro window")
+                    .with_label("window must be zero or greater", w.span)
+                    .with_help("leave out --jaro-window for the standard window"));
+            }
+            Some(w) => Some(w.item as usize),
+            None => None,
+        };
+        let multiset = match (call.get_flag::<bool>("multiset")?, given.multiset) {
+            (Some(_), Some(_)) => return Err(given_twice(call, "multiset", "multiset")),
+            (m, g) => m.or(g).unwrap_or(true),
+        };
+        let boost = match (
+            call.get_flag::<Spanned<Vec<f64>>>("prefix-boost")?,
+            given.boost,
+        ) {
+            (Some(_), Some(_)) => {
+                return Err(given_twice(call, "prefix-boost", "prefix_boost_weight"))
+            }
+            (b, g) => b.or(g),
+        };
+        let affix_basis = match (
+            call.get_flag::<Spanned<String>>("affix-basis")?,
+            given.basis,
+        ) {
+            (Some(_), Some(_)) => return Err(given_twice(call, "affix-basis", "affix_basis")),
+            (b, g) => match b.or(g) {
+                Some(b) => Basis::parse(&b)?,
+                None => Basis::Longer,
+            },
+        };
+        let prefix_boost = match boost {
+            Some(b) => Some(prefix_boost(&b.item).ok_or_else(|| {
+                LabeledError::new("invalid prefix boost")
+                    .with_label(
+                        "expected a weight and a length of at least 1, whose product is at most 1",
+                        b.span,
+                    )
+                    .with_help("jaro_winkler's own boost is [0.1 4]")
+            })?),
+            None => None,
         };
-        let all = call.has_flag("all")?;
-        let input_span = input.span();
-
-        let ret_val = match input {
-            Value::String { val: input_val, .. } => {
-                if all {
-                    compute_all(&compare_to_str.item, input_val, normalize)?
-                } else {
-                    compare_strings(&sim, compare_to_str, normalize, input_val, input_span)?
+        let invalid = |span, expected: &str, help: &str| {
+            LabeledError::new("invalid parameter")
+                .with_label(format!("must be {expected}"), span)
+                .with_help(help.to_string())
+        };
+        let defaults = AlgoParams::default();
+        let mut tversky = defaults.tversky;
+        for (weight, given) in [(&mut tversky.0, given.alpha), (&mut tversky.1, given.beta)] {
+            match given {
+                Some(w) if w.item < 0.0 => {
+                    return Err(invalid(
+                        w.span,
+                        "zero or greater",
+                        "tversky's default alpha and beta are 1",
+                    ))
                 }
+                Some(w) => *weight = w.item,
+                None => {}
             }
-            v => {
-                return Err(LabeledError::new(format!(
-                    "requires some input, got {}",
-                    v.get_type()
+        }
+        let max_prefix = match given.max_prefix {
+            Some(l) if l.item < 1 => {
+                return Err(invalid(
+                    l.span,
+                    "at least 1",
+                    "jaro_winkler counts at most 4 by default",
                 ))
-                .with_label("Expected something from pipeline", call.head));
             }
+            Some(l) => l.item as usize,
+            None => defaults.winkler.1,
         };
-
-        Ok(ret_val)
+        let prefix_weight = match given.prefix_weight {
+            Some(w) if w.item < 0.0 || w.item * max_prefix as f64 > 1.0 => {
+                return Err(invalid(
+                    w.span,
+                    "zero or greater, and at most 1 when multiplied by max_prefix",
+                    "jaro_winkler's default weight is 0.1 for 4 tokens",
+                ))
+            }
+            Some(w) => w.item,
+            None => defaults.winkler.0,
+        };
+        Ok(AlgoParams {
+            jaro_window,
+            multiset,
+            prefix_boost,
+            affix_basis,
+            tversky,
+            winkler: (prefix_weight, max_prefix),
+            custom: None,
+        })
     }
 }
 
-fn compute_all(s1: &str, s2: &str, norm: bool) -> Result<Value, LabeledError> {
-    let span = Span::unknown();
-    let algos = vec![
-        "bag",
-        "cosine",
-        "damerau_levenshtein",
-        "entropy_ncd",
-        "hamming",
-        "jaccard",
-        "jaro",
-        "jaro_winkler",
-        "levenshtein",
-        "longest_common_subsequence",
-        "longest_common_substring",
-        "length",
-        "lig3",
-        "mlipns",
-        "overlap",
-        "prefix",
-        "ratcliff_obershelp",
-        "roberts",
-        "sift4_common",
-        "sift4_simple",
-        "smith_waterman",
-        "sorensen_dice",
-        "suffix",
-        "tversky",
-        "yujian_bo",
-    ];
-    let mut rows = vec![];
-    for algo in algos {
-        let sim = Value::string(algo.to_string(), span);
-        let val_comp = compute(&algo, s1, s2, norm);
-        let val = if val_comp.fract() == 0.0 {
-            Value::int(val_comp as i64, span)
-        } else {
-            Value::float(val_comp, span)
+/// The parameters given in a `--params` record, shaped like their flags.
+/// Names are those `--list` shows under `parameters`.
+#[derive(Default)]
+struct Given {
+    window: Option<Spanned<i64>>,
+    multiset: Option<bool>,
+    boost: Option<Spanned<Vec<f64>>>,
+    basis: Option<Spanned<String>>,
+    alpha: Option<Spanned<f64>>,
+    beta: Option<Spanned<f64>>,
+    prefix_weight: Option<Spanned<f64>>,
+    max_prefix: Option<Spanned<i64>>,
+}
+
+impl Given {
+    fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let mut given = Given::default();
+        let Some(params) = call.get_flag::<Value>("params")? else {
+            return Ok(given);
         };
-        rows.push(Value::test_record(
-            record! { "algorithm" => sim, "distance" => val },
-        ));
+        let (mut weight, mut length) = (None, None);
+        for (name, v) in params.as_record()? {
+            let span = v.span();
+            let invalid = |expected: &str| {
+                LabeledError::new("invalid parameter")
+                    .with_label(format!("{name} must be {expected}"), span)
+                    .with_help("str similarity '' --list shows every parameter and its range")
+            };
+            match name.as_str() {
+                "window" => {
+                    let item = v.as_int().map_err(|_| invalid("an int"))?;
+                    given.window = Some(Spanned { item, span });
+                }
+                "multiset" => given.multiset = Some(v.as_bool().map_err(|_| invalid("a bool"))?),
+                "prefix_boost_weight" => {
+                    weight = Some((v.coerce_float().map_err(|_| invalid("a number"))?, span))
+                }
+                "prefix_boost_length" => {
+                    length = Some((v.as_int().map_err(|_| invalid("an int"))?, span))
+                }
+                "affix_basis" => {
+                    let item = v.as_str().map_err(|_| invalid("a string"))?.to_string();
+                    given.basis = Some(Spanned { item, span });
+                }
+                "alpha" | "beta" | "prefix_weight" => {
+                    let item = v.coerce_float().map_err(|_| invalid("a number"))?;
+                    let weight = Some(Spanned { item, span });
+                    match name.as_str() {
+                        "alpha" => given.alpha = weight,
+                        "beta" => given.beta = weight,
+                        _ => given.prefix_weight = weight,
+                    }
+                }
+                "max_prefix" => {
+                    let item = v.as_int().map_err(|_| invalid("an int"))?;
+                    given.max_prefix = Some(Spanned { item, span });
+                }
+                _ => {
+                    return Err(LabeledError::new("unknown parameter")
+                        .with_label(format!("no algorithm has a parameter '{name}'"), span)
+                        .with_help(format!("valid parameters: {}", PARAMETERS.join(", "))))
+                }
+            }
+        }
+        given.boost = match (weight, length) {
+            (Some((w, span)), l) => Some(Spanned {
+                item: vec![w, l.map_or(4.0, |(l, _)| l as f64)],
+                span,
+            }),
+            (None, Some((_, span))) => {
+                return Err(LabeledError::new("prefix_boost_length without a weight")
+                    .with_label("only used with prefix_boost_weight", span)
+                    .with_help("add prefix_boost_weight, e.g. 0.1"))
+            }
+            (None, None) => None,
+        };
+        Ok(given)
+    }
+}
+
+/// Every name `--params` accepts.
+const PARAMETERS: [&str; 9] = [
+    "window",
+    "multiset",
+    "prefix_boost_weight",
+    "prefix_boost_length",
+    "affix_basis",
+    "alpha",
+    "beta",
+    "prefix_weight",
+    "max_prefix",
+];
+
+fn given_twice(call: &EvaluatedCall, flag: &str, param: &str) -> LabeledError {
+    LabeledError::new(format!("{param} given twice"))
+        .with_label(format!("also set by --{flag}"), flag_span(call, "params"))
+        .with_help(format!("set it either in --params or with --{flag}"))
+}
+
+/// The textdistance release the algorithms come from; keep in step with
+/// Cargo.lock.
+const TEXTDISTANCE_VERSION: &str = "1.1.0";
+
+/// An algorithm's full name, whichever name it was given by.
+fn canonical(name: &str) -> &'static str {
+    if name.eq_ignore_ascii_case(custom::NAME) {
+        return custom::NAME;
     }
+    lookup(name).map_or("levenshtein", |a| a.name)
+}
 
-    Ok(Value::test_list(rows))
+/// Identifies the implementation behind an algorithm's scores, e.g.
+/// `textdistance-1.1.0+r1`.
+fn algorithm_version(name: &str) -> String {
+    format!(
+        "textdistance-{TEXTDISTANCE_VERSION}+r{}",
+        lookup(name).map_or(1, |a| a.revision)
+    )
 }
 
-#[rustfmt::skip]
-fn compute(a: &str, s1: &str, s2: &str, norm: bool) -> f64 {
-    let sim = a.to_lowercase();
-    match sim.as_str() {
-        "bag" => if norm { nstr::bag(s1, s2) } else {str::bag(s1, s2) as f64},
-        "cos" | "cosine" => if norm { nstr::cosine(s1, s2) } else {str::cosine(s1, s2) as f64},
-        "dlev" | "damerau_levenshtein" => if norm { nstr::damerau_levenshtein(s1, s2) } else {str::damerau_levenshtein(s1, s2) as f64},
-        "entncd" | "entropy_ncd" => if norm { nstr::entropy_ncd(s1, s2) } else {str::entropy_ncd(s1, s2) as f64},
-        "ham" | "hamming" => if norm { nstr::hamming(s1, s2) } else {str::hamming(s1, s2) as f64},
-        "jac" | "jaccard" => if norm { nstr::jaccard(s1, s2) } else {str::jaccard(s1, s2) as f64},
-        "jar" | "jaro" => if norm { nstr::jaro(s1, s2) } else {str::jaro(s1, s2) as f64},
-        "jarw" | "jaro_winkler" => if norm { nstr::jaro_winkler(s1, s2) } else {str::jaro_winkler(s1, s2) as f64},
-        "lev" | "levenshtein" => if norm { nstr::levenshtein(s1, s2) } else {str::levenshtein(s1, s2) as f64},
-        "lcsubseq" | "longest_common_subsequence" => if norm { nstr::lcsseq(s1, s2) } else {str::lcsseq(s1, s2) as f64},
-        "lcsubstr" | "longest_common_substring" => if norm { nstr::lcsstr(s1, s2) } else {str::lcsstr(s1, s2) as f64},
-        "len" | "length" => if norm { nstr::length(s1, s2) } else {str::length(s1, s2) as f64},
-        "lig" | "lig3" => if norm { nstr::lig3(s1, s2) } else {str::lig3(s1, s2) as f64},
-        "mli" | "mlipns" => if norm { nstr::mlipns(s1, s2) } else {str::mlipns(s1, s2) as f64},
-        "olap" | "overlap" => if norm { nstr::overlap(s1, s2) } else {str::overlap(s1, s2) as f64},
-        "pre" | "prefix" => if norm { nstr::prefix(s1, s2) } else {str::prefix(s1, s2) as f64},
-        "rat" | "ratcliff_obershelp" => if norm { nstr::ratcliff_obershelp(s1, s2) } else {str::ratcliff_obershelp(s1, s2) as f64},
-        "rob" | "roberts" => if norm { nstr::roberts(s1, s2) } else {str::roberts(s1, s2) as f64},
-        "scom" | "sift4_common" => if norm { nstr::sift4_common(s1, s2) } else {str::sift4_common(s1, s2) as f64},
-        "ssim" | "sift4_simple" => if norm { nstr::sift4_simple(s1, s2) } else {str::sift4_simple(s1, s2) as f64},
-        "smithw" | "smith_waterman" => if norm { nstr::smith_waterman(s1, s2) } else {str::smith_waterman(s1, s2) as f64},
-        "soredice" | "sorensen_dice" => if norm { nstr::sorensen_dice(s1, s2) } else {str::sorensen_dice(s1, s2) as f64},
-        "suf" | "suffix" => if norm { nstr::suffix(s1, s2) } else {str::suffix(s1, s2) as f64},
-        "tv" | "tversky" => if norm { nstr::tversky(s1, s2) } else {str::tversky(s1, s2) as f64},
-        "ybo" | "yujian_bo" => if norm { nstr::yujian_bo(s1, s2) } else {str::yujian_bo(s1, s2) as f64},
-        _ => if norm { nstr::levenshtein(s1, s2) } else {str::levenshtein(s1, s2) as f64},
+/// Reads `--algorithm`, defaulting to levenshtein. Unknown names are an
+/// error pointing at the flag rather than a silent fallback; deprecated
+/// ones resolve to the algorithm they stand for.
+fn algorithm(call: &EvaluatedCall) -> Result<String, LabeledError> {
+    // a closure blended with an algorithm leaves the scores that algorithm's
+    if call.get_flag::<Value>("custom")?.is_some()
+        && call.get_flag::<f64>("custom-weight")?.is_none()
+    {
+        return Ok(custom::NAME.into());
     }
+    let algo = match call.get_flag::<Spanned<String>>("algorithm")? {
+        Some(algo) => Some(aliases::resolve(call, algo)?),
+        None => None,
+    };
+    let algo = match algo {
+        Some(algo) if call.has_flag("lenient")? && closest_algorithm(&algo.item).is_err() => None,
+        algo => algo,
+    };
+    let Some(algo) = algo else {
+        check_params(call, "levenshtein")?;
+        return Ok("levenshtein".into());
+    };
+    check_algorithm(&algo.item, algo.span)?;
+    check_params(call, &algo.item)?;
+    Ok(algo.item)
 }
 
-#[rustfmt::skip]
-fn list_algorithms() -> Value {
-    let mut rows = vec![];
-
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("bag"), "short" => Value::test_string("bag")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("cosine"), "short" => Value::test_string("cos")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("damerau_levenshtein"), "short" => Value::test_string("dlev")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("entropy_ncd"), "short" => Value::test_string("entncd")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("hamming"), "short" => Value::test_string("ham")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("jaccard"), "short" => Value::test_string("jac")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("jaro"), "short" => Value::test_string("jar")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("jaro_winkler"), "short" => Value::test_string("jarw")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("levenshtein"), "short" => Value::test_string("lev")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("longest_common_subsequence"), "short" => Value::test_string("lcsubseq")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("longest_common_substring"), "short" => Value::test_string("lcsubstr")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("length"), "short" => Value::test_string("len")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("lig3"), "short" => Value::test_string("lig")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("mlipns"), "short" => Value::test_string("mli")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("overlap"), "short" => Value::test_string("olap")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("prefix"), "short" => Value::test_string("pre")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("ratcliff_obershelp"), "short" => Value::test_string("rat")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("roberts"), "short" => Value::test_string("rob")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("sift4_common"), "short" => Value::test_string("scom")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("sift4_simple"), "short" => Value::test_string("ssim")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("smith_waterman"), "short" => Value::test_string("smithw")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("sorensen_dice"), "short" => Value::test_string("soredice")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("suffix"), "short" => Value::test_string("suf")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("tversky"), "short" => Value::test_string("tv")}));
-    rows.push(Value::test_record(record! { "algorithm" => Value::test_string("yujian_bo"), "short" => Value::test_string("ybo")}));
-
-    Value::test_list(rows)
+/// Whether `--algorithm` is `auto`, which only the main command takes.
+fn is_auto(call: &EvaluatedCall) -> Result<bool, LabeledError> {
+    Ok(call
+        .get_flag::<String>("algorithm")?
+        .is_some_and(|a| a.eq_ignore_ascii_case("auto")))
 }
 
-fn compare_strings(
-    sim_algo: &str,
-    compare_to_str: Spanned<String>,
-    normalize: bool,
-    input_val: &str,
-    input_span: Span,
-) -> Result<Value, LabeledError> {
-    let compare_from = input_val;
-    let compare_to = compare_to_str.item;
+/// Errors on a `--params` entry `algo` has no use for.
+fn check_params(call: &EvaluatedCall, algo: &str) -> Result<(), LabeledError> {
+    let Some(params) = call.get_flag::<Value>("params")? else {
+        return Ok(());
+    };
+    let name = canonical(algo);
+    let own = lookup(name).map_or(&[][..], |a| a.params);
+    for (param, v) in params.as_record()? {
+        // the prefix boost and names --params rejects itself go with any
+        let anyone = !ALGORITHMS
+            .iter()
+            .any(|a| a.params.contains(&param.as_str()));
+        let applies = anyone || own.contains(&param.as_str());
+        if !applies {
+            return Err(LabeledError::new("parameter not used by the algorithm")
+                .with_label(format!("{name} has no parameter '{param}'"), v.span())
+                .with_help(format!(
+                    "str similarity '' --list | where algorithm == {name} shows its parameters"
+                )));
+        }
+    }
+    Ok(())
+}
 
-    let a_val = compute(sim_algo, compare_from, &compare_to, normalize);
+/// Errors unless `name` is an algorithm's name or short name, suggesting
+/// the closest one.
+fn check_algorithm(name: &str, span: Span) -> Result<(), LabeledError> {
+    let Err(closest) = closest_algorithm(name) else {
+        return Ok(());
+    };
+    Err(LabeledError::new("unknown algorithm")
+        .with_label(format!("'{name}' is not an algorithm"), span)
+        .with_help(format!(
+            "did you mean '{closest}'? valid algorithms: {} (short names are listed by `str similarity --list`)",
+            ALGORITHMS.map(|a| a.name).join(", ")
+        )))
+}
 
-    if a_val.fract() == 0.0 {
-        Ok(Value::int(a_val as i64, input_span))
+/// `Ok` if `name` is an algorithm's name or short name, else the name of the
+/// algorithm whose name or short name is closest by jaro_winkler.
+fn closest_algorithm(name: &str) -> Result<(), &'static str> {
+    if lookup(name).is_some() {
+        return Ok(());
+    }
+    let lower = name.to_lowercase();
+    let closeness = |a: &Algorithm| {
+        let score = |n| textdistance::str::jaro_winkler(&lower, n);
+        score(a.name).max(score(a.short))
+    };
+    let closest = ALGORITHMS
+        .iter()
+        .max_by(|a, b| closeness(a).total_cmp(&closeness(b)))
+        .map_or("levenshtein", |a| a.name);
+    Err(closest)
+}
+
+/// Reads `--coerce`: the config to display non-string input with, if given.
+fn coerce(
+    call: &EvaluatedCall,
+    engine: &EngineInterface,
+) -> Result<Option<Arc<Config>>, LabeledError> {
+    if call.has_flag("coerce")? {
+        Ok(Some(engine.get_config()?))
     } else {
-        Ok(Value::float(a_val, input_span))
+        Ok(None)
     }
 }
 
+/// `v` as text to compare: a string as it is and, given the config from
+/// `--coerce`, any other scalar the way nushell displays it.
+fn text<'a>(v: &'a Value, coerce: Option<&Config>) -> Option<Cow<'a, str>> {
+    match (v, coerce) {
+        (Value::String { val, .. }, _) => Some(Cow::Borrowed(val)),
+        (
+            Value::Int { .. }
+            | Value::Float { .. }
+            | Value::Bool { .. }
+            | Value::Date { .. }
+            | Value::Filesize { .. }
+            | Value::Duration { .. },
+            Some(config),
+        ) => Some(Cow::Owned(v.to_expanded_string(", ", config))),
+        _ => None,
+    }
+}
+
+/// Where `--name` was written in the call, or the command itself when it
+/// wasn't, for errors about a flag's presence rather than its value.
+fn flag_span(call: &EvaluatedCall, name: &str) -> Span {
+    call.named
+        .iter()
+        .find(|(flag, _)| flag.item == name)
+        .map_or(call.head, |(flag, _)| flag.span)
+}
+
 fn main() {
-    serve_plugin(&StrSimilarityPlugin, MsgPackSerializer);
+    serve_plugin(&StrSimilarityPlugin::default(), MsgPackSerializer);
+}
+
+/// Runs the examples of `command` with the plugin loaded, checking the
+/// results they show.
+#[cfg(test)]
+fn test_examples(
+    command: &impl PluginCommand<Plugin = StrSimilarityPlugin>,
+) -> Result<(), nu_protocol::ShellError> {
+    let plugin = StrSimilarityPlugin::default().into();
+    nu_plugin_test_support::PluginTest::new("str_similarity", plugin)?
+        .test_examples(&command.examples())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typos_suggest_the_closest_algorithm() {
+        assert_eq!(closest_algorithm("levenstein"), Err("levenshtein"));
+        assert_eq!(closest_algorithm("jarowinkler"), Err("jaro_winkler"));
+        assert_eq!(closest_algorithm("JARW"), Ok(()));
+    }
 }
@@ -0,0 +1,349 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{record, Category, Example, LabeledError, Signature, SyntaxShape, Value};
+
+use crate::format::Format;
+use crate::style::{self, Bands};
+use crate::{algorithm, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityMapColumns;
+
+impl SimplePluginCommand for StrSimilarityMapColumns {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity map-columns"
+    }
+
+    fn description(&self) -> &str {
+        "Propose a mapping between the column names of two tables"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .required(
+                "other",
+                SyntaxShape::Any,
+                "Table, record or list of column names to map the input's columns onto",
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum normalized similarity (0 to 1) for two columns to be mapped",
+                Some('t'),
+            )
+            .switch(
+                "units",
+                "Read units from headers, like 'Weight (kg)' or 'weight_kg', and compare names and units separately",
+                Some('u'),
+            )
+            .named(
+                "style-threshold",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Color scores green at or above the first value, amber at or above the second and red below",
+                None,
+            )
+            .switch(
+                "plain",
+                "Never color the output, whatever the color config says",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Map the columns of two CSV exports",
+                example: "open crm.csv | str similarity map-columns (open billing.csv) -t 0.6",
+                result: None,
+            },
+            Example {
+                description: "Map two lists of column names",
+                example:
+                    "['CustomerID' 'Email'] | str similarity map-columns ['customer_id' 'e_mail']",
+                result: None,
+            },
+            Example {
+                description: "Map headers that spell their units differently",
+                example: "['Weight (kg)' 'Height (cm)'] | str similarity map-columns ['height_centimeters' 'weight_kg'] --units",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let other: Value = call.req(0)?;
+        let algo = algorithm(call)?;
+        let threshold: f64 = call.get_flag("threshold")?.unwrap_or(0.0);
+        let params = AlgoParams::from_call(call)?;
+        let bands = Bands::from_call(call, engine)?;
+        let format = Format::from_call(call)?;
+
+        let left = column_names(input)?;
+        let right = column_names(&other)?;
+        let units = call.has_flag("units")?;
+        let key = |c: &String| if units { header(c) } else { (fold(c), None) };
+        let left_keys: Vec<(Vec<char>, Option<&str>)> = left.iter().map(key).collect();
+        let right_keys: Vec<(Vec<char>, Option<&str>)> = right.iter().map(key).collect();
+
+        // Score every pair, then hand out columns best-first so each column
+        // on either side is used at most once.
+        let mut pairs = vec![];
+        for (i, (l, lu)) in left_keys.iter().enumerate() {
+            for (j, (r, ru)) in right_keys.iter().enumerate() {
+                let mut score = similarity(&algo, l, r, &params);
+                // the same quantity in different units is likely a
+                // different column, or at least needs converting
+                if matches!((lu, ru), (Some(a), Some(b)) if a != b) {
+                    score /= 2.0;
+                }
+                if score >= threshold {
+                    pairs.push((score, i, j));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0).then((a.1, a.2).cmp(&(b.1, b.2))));
+
+        let mut mapped: Vec<Option<(usize, f64)>> = vec![None; left.len()];
+        let mut taken = vec![false; right.len()];
+        for (score, i, j) in pairs {
+            if mapped[i].is_none() && !taken[j] {
+                mapped[i] = Some((j, score));
+                taken[j] = true;
+            }
+        }
+
+        let span = call.head;
+        let rows = left
+            .iter()
+            .zip(mapped)
+            .map(|(l, m)| {
+                let (right, score) = match m {
+                    Some((j, score)) => (
+                        Value::string(&right[j], span),
+                        style::score(score, bands.as_ref(), &format, span),
+                    ),
+                    None => (Value::nothing(span), Value::nothing(span)),
+                };
+                Value::record(
+                    record! {
+                        "left" => Value::string(l, span),
+                        "right" => right,
+                        "score" => score,
+                    },
+                    span,
+                )
+            })
+            .collect();
+
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// Column names differ mostly in case and separators (`CustomerID` vs
+/// `customer_id`), so those are dropped before comparing.
+fn fold(name: &str) -> Vec<char> {
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' ' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Units by canonical name and the ways headers spell them.
+const UNITS: &[(&str, &[&str])] = &[
+    (
+        "kg",
+        &["kg", "kgs", "kilo", "kilos", "kilogram", "kilograms"],
+    ),
+    ("g", &["g", "gr", "gram", "grams"]),
+    ("lb", &["lb", "lbs", "pound", "pounds"]),
+    (
+        "km",
+        &["km", "kilometer", "kilometers", "kilometre", "kilometres"],
+    ),
+    ("m", &["m", "meter", "meters", "metre", "metres"]),
+    (
+        "cm",
+        &[
+            "cm",
+            "centimeter",
+            "centimeters",
+            "centimetre",
+            "centimetres",
+        ],
+    ),
+    (
+        "mm",
+        &[
+            "mm",
+            "millimeter",
+            "millimeters",
+            "millimetre",
+            "millimetres",
+        ],
+    ),
+    ("in", &["in", "inch", "inches"]),
+    ("ft", &["ft", "foot", "feet"]),
+    ("l", &["l", "liter", "liters", "litre", "litres"]),
+    (
+        "ml",
+        &[
+            "ml",
+            "milliliter",
+            "milliliters",
+            "millilitre",
+            "millilitres",
+        ],
+    ),
+    ("ms", &["ms", "millisecond", "milliseconds"]),
+    ("s", &["s", "sec", "secs", "second", "seconds"]),
+    ("min", &["min", "mins", "minute", "minutes"]),
+    ("h", &["h", "hr", "hrs", "hour", "hours"]),
+    ("pct", &["%", "pct", "percent", "percentage"]),
+    ("usd", &["usd", "$", "dollar", "dollars"]),
+    ("eur", &["eur", "€", "euro", "euros"]),
+    ("c", &["°c", "c", "degc", "celsius"]),
+    ("f", &["°f", "f", "degf", "fahrenheit"]),
+];
+
+/// Spellings too common as plain words to be read as a unit at the end of a
+/// name (`logged_in`, `price_min`); in parentheses they still count.
+const AMBIGUOUS: [&str; 9] = ["in", "h", "s", "m", "c", "f", "l", "g", "min"];
+
+fn unit(s: &str) -> Option<&'static str> {
+    UNITS
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&s))
+        .map(|(unit, _)| *unit)
+}
+
+/// A header as a folded name and its unit, taken from a bracketed qualifier
+/// (`Weight (kg)`) or the last word (`weight_kg`). Other qualifiers stay part
+/// of the name.
+fn header(name: &str) -> (Vec<char>, Option<&'static str>) {
+    let lower = name.to_lowercase();
+    let mut rest = String::new();
+    let mut found = None;
+    let mut chars = lower.chars();
+    while let Some(c) = chars.next() {
+        let close = match c {
+            '(' => ')',
+            '[' => ']',
+            _ => {
+                rest.push(c);
+                continue;
+            }
+        };
+        let qualifier: String = chars.by_ref().take_while(|&q| q != close).collect();
+        match unit(qualifier.trim()) {
+            Some(u) => found = found.or(Some(u)),
+            None => rest.push_str(&qualifier),
+        }
+    }
+    let mut words: Vec<&str> = rest
+        .split(['_', '-', ' ', '.', '/'])
+        .filter(|w| !w.is_empty())
+        .collect();
+    if found.is_none() && words.len() > 1 {
+        let last = words[words.len() - 1];
+        if let Some(u) = unit(last).filter(|_| !AMBIGUOUS.contains(&last)) {
+            found = Some(u);
+            words.pop();
+        }
+    }
+    (words.concat().chars().collect(), found)
+}
+
+/// Column names of a table (the union over its rows, in order of first
+/// appearance), of a record, or a list of names given directly.
+fn column_names(value: &Value) -> Result<Vec<String>, LabeledError> {
+    let mut names: Vec<String> = vec![];
+    let mut add = |name: &str| {
+        if !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    };
+    match value {
+        Value::Record { val, .. } => val.columns().for_each(|c| add(c)),
+        Value::List { vals, .. } => {
+            for v in vals {
+                match v {
+                    Value::Record { val, .. } => val.columns().for_each(|c| add(c)),
+                    Value::String { val, .. } => add(val),
+                    v => {
+                        return Err(LabeledError::new(format!(
+                            "expected a record or column name, got {}",
+                            v.get_type()
+                        ))
+                        .with_label("not a record or string", v.span())
+                        .with_help("list column names as strings, or pass the table itself"))
+                    }
+                }
+            }
+        }
+        v => {
+            return Err(
+                LabeledError::new(format!("expected a table, got {}", v.get_type()))
+                    .with_label("Expected a table, record or list of column names", v.span()),
+            )
+        }
+    }
+    Ok(names)
+}
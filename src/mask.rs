@@ -0,0 +1,136 @@
+// Masking of volatile tokens: ids, addresses, times and counters replaced
+// by a placeholder naming their kind, so operational strings that only
+// differ by them compare as identical. `--mask` picks the kinds and
+// `--mask-pattern` adds regexes of the user's own.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Spanned};
+use regex::{Captures, Regex};
+
+/// The kinds `--mask` takes, in the order they are masked: a timestamp's
+/// digits are masked whole before `numbers` would split it up.
+pub const KINDS: [&str; 5] = ["uuids", "timestamps", "ips", "hex", "numbers"];
+
+/// What a `--mask-pattern` match becomes.
+const PATTERN: &str = "<mask>";
+
+#[derive(Clone)]
+struct Mask {
+    regex: Regex,
+    placeholder: &'static str,
+    /// Only runs of hex digits mixing digits and letters are hex strings,
+    /// so words like "decade" and plain numbers are left.
+    hex: bool,
+}
+
+#[derive(Clone)]
+pub struct Masks(Vec<Mask>);
+
+impl Masks {
+    /// Reads `--mask` and `--mask-pattern`; `None` without either.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        let kinds = call.get_flag::<Spanned<Vec<String>>>("mask")?;
+        let patterns = call.get_flag::<Vec<Spanned<String>>>("mask-pattern")?;
+        if kinds.is_none() && patterns.is_none() {
+            return Ok(None);
+        }
+        // a user's patterns are the most specific, so they go first
+        let mut masks = vec![];
+        for p in patterns.into_iter().flatten() {
+            let regex = Regex::new(&p.item).map_err(|e| {
+                LabeledError::new("invalid mask pattern")
+                    .with_label(e.to_string(), p.span)
+                    .with_help("--mask-pattern takes regular expressions")
+            })?;
+            masks.push(Mask {
+                regex,
+                placeholder: PATTERN,
+                hex: false,
+            });
+        }
+        if let Some(kinds) = kinds {
+            if let Some(k) = kinds.item.iter().find(|k| !KINDS.contains(&k.as_str())) {
+                return Err(LabeledError::new("unknown mask")
+                    .with_label(format!("'{k}' is not a kind of token to mask"), kinds.span)
+                    .with_help(format!(
+                        "valid kinds: {}; mask anything else with --mask-pattern",
+                        KINDS.join(", ")
+                    )));
+            }
+            let kinds: Vec<&str> = KINDS
+                .into_iter()
+                .filter(|k| kinds.item.iter().any(|given| given == k))
+                .collect();
+            masks.extend(Masks::of(&kinds).0);
+        }
+        Ok(Some(Masks(masks)))
+    }
+
+    /// The masks of `kinds`, in the order of [`KINDS`].
+    pub fn of(kinds: &[&str]) -> Self {
+        let masks = KINDS
+            .into_iter()
+            .filter(|k| kinds.contains(k))
+            .map(|kind| {
+                let (pattern, placeholder) = match kind {
+                    "uuids" => (
+                        r"\b[[:xdigit:]]{8}-[[:xdigit:]]{4}-[[:xdigit:]]{4}-[[:xdigit:]]{4}-[[:xdigit:]]{12}\b",
+                        "<uuid>",
+                    ),
+                    "timestamps" => (
+                        r"\b\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}(?::\d{2}(?:[.,]\d+)?)?(?:Z|[+-]\d{2}:?\d{2})?)?\b|\b\d{1,2}:\d{2}:\d{2}(?:[.,]\d+)?\b",
+                        "<ts>",
+                    ),
+                    "ips" => (
+                        r"\b(?:\d{1,3}\.){3}\d{1,3}\b|\b(?:[[:xdigit:]]{1,4}:){2,7}[[:xdigit:]]{1,4}\b",
+                        "<ip>",
+                    ),
+                    "hex" => (r"\b(?:0[xX][[:xdigit:]]+|[[:xdigit:]]{6,})\b", "<hex>"),
+                    _ => (r"\d+(?:[.,]\d+)*", "<num>"),
+                };
+                Mask {
+                    regex: Regex::new(pattern).expect("mask patterns are valid"),
+                    placeholder,
+                    hex: kind == "hex",
+                }
+            })
+            .collect();
+        Masks(masks)
+    }
+
+    pub fn apply(&self, s: &str) -> String {
+        self.0.iter().fold(s.to_string(), |s, mask| {
+            mask.regex
+                .replace_all(&s, |c: &Captures| {
+                    let m = &c[0];
+                    let keep = mask.hex
+                        && !(m.starts_with("0x") || m.starts_with("0X"))
+                        && !(m.bytes().any(|b| b.is_ascii_digit())
+                            && m.bytes().any(|b| b.is_ascii_alphabetic()));
+                    match keep {
+                        true => m.to_string(),
+                        false => mask.placeholder.to_string(),
+                    }
+                })
+                .into_owned()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_each_kind_whole() {
+        let masks = Masks::of(&KINDS);
+        assert_eq!(
+            masks.apply("2024-05-01T10:32:01Z req 550e8400-e29b-41d4-a716-446655440000 from 10.0.0.12 at 0x7ffe took 12.5ms, decade"),
+            "<ts> req <uuid> from <ip> at <hex> took <num>ms, decade"
+        );
+        assert_eq!(
+            masks.apply("job 3fa9c2d1e retried 3 times"),
+            "job <hex> retried <num> times"
+        );
+    }
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value,
+};
+use rayon::prelude::*;
+
+use crate::arrow;
+use crate::checkpoint::{self, Checkpoint};
+use crate::custom::{self, Custom};
+use crate::format::Format;
+use crate::{
+    algorithm, compute_tokens, output::score_matrix, preprocess, profile, AlgoParams, Inputs,
+    Limit, Orientation, StrSimilarityPlugin,
+};
+
+pub struct StrSimilarityMatrix;
+
+impl SimplePluginCommand for StrSimilarityMatrix {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity matrix"
+    }
+
+    fn description(&self) -> &str {
+        "Score every string of the input list against every string of another list, one row per input string and one column per other string"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_types(vec![
+                (Type::List(Box::new(Type::String)), Type::table()),
+                (Type::table(), Type::table()),
+            ])
+            .required(
+                "other",
+                SyntaxShape::OneOf(vec![
+                    SyntaxShape::List(Box::new(SyntaxShape::String)),
+                    SyntaxShape::Table(vec![]),
+                ]),
+                "Strings to compare with, which name the score columns; with --columns, the table to compare with",
+            )
+            .named(
+                "columns",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Compare the distinct values of a column of the input table with those of a column of the other, one row per pair with how often each occurs: [input-column other-column]",
+                None,
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .named(
+                "custom",
+                SyntaxShape::Closure(Some(vec![SyntaxShape::String, SyntaxShape::String])),
+                "Score with a closure instead of an algorithm: it gets the two preprocessed strings and returns a similarity from 0 to 1",
+                None,
+            )
+            .named(
+                "custom-weight",
+                SyntaxShape::Number,
+                "Blend the --custom closure's score with the algorithm's instead of replacing it, the closure's weighing this much (0 to 1)",
+                None,
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .switch(
+                "similarity",
+                "Report the scores as similarities, higher meaning closer, converting distances",
+                None,
+            )
+            .switch(
+                "distance",
+                "Report the scores as distances, 0 meaning identical, converting similarities",
+                None,
+            )
+            .named(
+                "sort-by",
+                SyntaxShape::String,
+                "Order of the rows: input (the default) or score, the rows with the closest match first",
+                None,
+            )
+            .named(
+                "limit",
+                SyntaxShape::Int,
+                "Keep only this many rows, the closest ones with --sort-by score, without holding the rest",
+                None,
+            )
+            .switch(
+                "farthest",
+                "Sort by score with the least close rows first, and keep those with --limit, to find outliers",
+                None,
+            )
+            .switch(
+                "normalize",
+                "Normalize the scores between 0 and 1",
+                Some('n'),
+            )
+            .switch(
+                "include-inputs",
+                "Add each row's string as left_input; the other strings name the columns",
+                None,
+            )
+            .switch(
+                "include-normalized",
+                "Like --include-inputs, with the string as preprocessing left it",
+                None,
+            )
+            .named(
+                "preview",
+                SyntaxShape::Int,
+                "With --include-inputs or --include-normalized, cut inputs longer than this many characters short with an ellipsis",
+                None,
+            )
+            .switch(
+                "arrow-friendly",
+                "Give every row the same columns, each of one type (numbers all floats where any is), for polars into-df",
+                None,
+            )
+            .named(
+                "checkpoint",
+                SyntaxShape::Filepath,
+                "Save the finished chunks of input rows to this nuon file every ten seconds, for --resume to continue from",
+                None,
+            )
+            .switch(
+                "resume",
+                "Continue the run --checkpoint saved, scoring only the chunks it hadn't finished",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "profile",
+                SyntaxShape::String,
+                "Take any flag not given here from the settings saved under this name with `str similarity profile save`",
+                None,
+            )
+            .named(
+                "config",
+                SyntaxShape::OneOf(vec![SyntaxShape::Record(vec![]), SyntaxShape::Filepath]),
+                "Take any flag not given here from this record of long flag names and values, or from the nuon or json file holding one",
+                None,
+            )
+            .named(
+                "preset",
+                SyntaxShape::String,
+                "Take any flag not given here or by --profile from a built-in preset: loose, standard or strict",
+                None,
+            )
+            .named(
+                "mask",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Replace volatile tokens in both strings with placeholders before anything else: uuids, timestamps, ips, hex, numbers",
+                None,
+            )
+            .named(
+                "mask-pattern",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Also replace matches of these regular expressions with a placeholder, like --mask",
+                None,
+            )
+            .named(
+                "preprocess",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Steps run in order on both strings before anything else: lowercase, strip-accents, strip-punctuation, collapse-spaces, trim, fold-compat, deleet",
+                None,
+            )
+            .switch(
+                "ignore-case",
+                "Lowercase both strings before scoring, like --preprocess [lowercase]",
+                None,
+            )
+            .switch(
+                "trim",
+                "Trim whitespace from both ends of both strings before scoring",
+                None,
+            )
+            .switch(
+                "collapse-whitespace",
+                "Squeeze runs of whitespace in both strings into one space before scoring",
+                None,
+            )
+            .switch(
+                "strip-accents",
+                "Drop diacritics from both strings before scoring, reading letters like ø and ł as o and l",
+                None,
+            )
+            .switch(
+                "strip-invisible",
+                "Drop soft hyphens, zero width spaces and joiners and byte order marks from both strings before scoring",
+                None,
+            )
+            .switch(
+                "fold-compat",
+                "Compare what a human reads: apply NFKC to both strings, turning ligatures, superscripts and roman numerals into plain letters and digits, and fold typographic quotes and dashes",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Compare two lists pairwise",
+                example: "[kitten sitting] | str similarity matrix [mitten fitting] -a jaro_winkler",
+                result: None,
+            },
+            Example {
+                description: "Compare a list with itself to spot duplicates",
+                example: "let names = (open people.csv | get name); $names | str similarity matrix $names -n",
+                result: None,
+            },
+            Example {
+                description: "Keep the ten rows closest to any of the targets",
+                example: "open names.txt | lines | str similarity matrix [jon john] -a jaro_winkler --sort-by score --limit 10",
+                result: None,
+            },
+            Example {
+                description: "Explore how the names of two tables pair up before picking a join threshold",
+                example: "open crm.csv | str similarity matrix (open billing.csv) --columns [name customer] -a jaro_winkler --sort-by score",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let call = &profile::apply(plugin, engine, call, &SimplePluginCommand::signature(self))?;
+        let custom = Custom::from_call(engine, call)?;
+        let checkpoint = Checkpoint::from_call(call, engine, [input])?;
+        let output = match call.get_flag("columns")? {
+            Some(columns) => {
+                column_pairs(call, input, columns, custom.as_ref(), checkpoint.as_ref())?
+            }
+            None => score_matrix(
+                call,
+                input,
+                call.req(0)?,
+                custom.as_ref(),
+                checkpoint.as_ref(),
+            )?,
+        };
+        custom::check(custom.as_ref())?;
+        Ok(match output {
+            Value::List {
+                vals,
+                internal_span,
+            } => Value::list(arrow::shape(call, vals)?, internal_span),
+            output => output,
+        })
+    }
+}
+
+/// `--columns`: every distinct value of a column of `input` against every
+/// distinct value of a column of the other table, in the order they first
+/// appear, one row per pair with how many rows hold each value.
+fn column_pairs(
+    call: &EvaluatedCall,
+    input: &Value,
+    columns: Spanned<Vec<String>>,
+    custom: Option<&Arc<Custom>>,
+    checkpoint: Option<&Checkpoint>,
+) -> Result<Value, LabeledError> {
+    let [left, right] = columns.item.as_slice() else {
+        return Err(LabeledError::new("--columns takes two columns")
+            .with_label(
+                format!("{} columns given", columns.item.len()),
+                columns.span,
+            )
+            .with_help(
+                "name the input's column and the other table's, e.g. --columns [name customer]",
+            ));
+    };
+    let other: Value = call.req(0)?;
+    let lefts = distinct(input, left)?;
+    let rights = distinct(&other, right)?;
+    let sim = algorithm(call)?;
+    let normalize = call.has_flag("normalize")?;
+    let orientation = Orientation::from_call(call)?;
+    let params = AlgoParams {
+        custom: custom.cloned(),
+        ..AlgoParams::from_call(call)?
+    };
+    let pipeline = preprocess::Pipeline::from_call(call)?;
+    let limit = Limit::from_call(call)?;
+    let inputs = Inputs::from_call(call)?;
+    let format = Format::from_call(call)?;
+    let distance = orientation.unwrap_or(Orientation::of(&sim)) == Orientation::Distance;
+    let span = call.head;
+    let rights: Vec<(&str, usize, Vec<char>)> = rights
+        .iter()
+        .map(|(s, n)| (s.as_str(), *n, pipeline.apply(s).chars().collect()))
+        .collect();
+    let (sim, params, inputs, rights) = (&sim, &params, &inputs, &rights);
+    let rows = checkpoint::chunked(checkpoint, lefts.len(), span, |range| {
+        lefts[range]
+            .par_iter()
+            .flat_map_iter(|(l, l_count)| {
+                let chars: Vec<char> = pipeline.apply(l).chars().collect();
+                rights.iter().map(move |(r, r_count, target)| {
+                    let score = compute_tokens(sim, &chars, target, params).oriented(
+                        sim,
+                        normalize,
+                        orientation,
+                    );
+                    let mut row = record! {
+                        "left" => Value::string(l, span),
+                        "left_count" => Value::int(*l_count as i64, span),
+                        "right" => Value::string(*r, span),
+                        "right_count" => Value::int(*r_count as i64, span),
+                        "score" => format.score(score, span),
+                    };
+                    if let Some(inputs) = inputs {
+                        inputs.push(&mut row, Some(l), Some(r), span);
+                    }
+                    let closeness = if distance { -score } else { score };
+                    (Some(closeness), Value::record(row, span))
+                })
+            })
+            .collect()
+    })?;
+    Ok(Value::list(limit.keep(rows), span))
+}
+
+/// The distinct strings in `column` of the `table`, in the order they first
+/// appear, each with how many rows hold it. Empty cells are skipped.
+fn distinct(table: &Value, column: &str) -> Result<Vec<(String, usize)>, LabeledError> {
+    let Value::List { vals, .. } = table else {
+        return Err(
+            LabeledError::new(format!("--columns needs tables, got {}", table.get_type()))
+                .with_label("not a table", table.span())
+                .with_help("compare the column of the input table with one of the other table"),
+        );
+    };
+    let mut values: Vec<(String, usize)> = vec![];
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for row in vals {
+        let cell = row.as_record().ok().and_then(|r| r.get(column));
+        let value = match cell {
+            Some(Value::Nothing { .. }) => continue,
+            Some(v) => v.as_str()?,
+            None => {
+                return Err(LabeledError::new(format!("no column '{column}'"))
+                    .with_label("row without the column", row.span())
+                    .with_help("--columns names the input's column, then the other table's"));
+            }
+        };
+        match index.get(value) {
+            Some(&i) => values[i].1 += 1,
+            None => {
+                index.insert(value.to_string(), values.len());
+                values.push((value.to_string(), 1));
+            }
+        }
+    }
+    Ok(values)
+}
@@ -0,0 +1,79 @@
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Scores of already-seen `(left, right)` pairs within one unit of batch work,
+/// so repeated values in the input don't rerun expensive algorithms.
+pub struct PairCache<'a, V> {
+    enabled: bool,
+    scores: HashMap<(&'a str, &'a str), V>,
+    hits: usize,
+}
+
+impl<'a, V: Copy> PairCache<'a, V> {
+    pub fn new(enabled: bool) -> Self {
+        PairCache {
+            enabled,
+            scores: HashMap::new(),
+            hits: 0,
+        }
+    }
+
+    /// How many scores were reused.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn score(&mut self, left: &'a str, right: &'a str, f: impl FnOnce() -> V) -> V {
+        if !self.enabled {
+            return f();
+        }
+        match self.scores.entry((left, right)) {
+            Entry::Occupied(e) => {
+                self.hits += 1;
+                *e.get()
+            }
+            Entry::Vacant(e) => *e.insert(f()),
+        }
+    }
+}
+
+/// A map that keeps at most `capacity` entries, forgetting the least recently
+/// used one when full. Lives across calls in the plugin, so interactive use
+/// (completers, prompts) doesn't redo the same preprocessing every keystroke.
+pub struct Lru<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    recency: BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> Lru<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        Lru {
+            capacity: capacity.max(1),
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    pub fn get_or_insert_with(&mut self, key: K, f: impl FnOnce() -> V) -> V {
+        self.tick += 1;
+        if let Some((val, used)) = self.entries.get_mut(&key) {
+            self.recency.remove(used);
+            *used = self.tick;
+            self.recency.insert(self.tick, key);
+            return val.clone();
+        }
+        if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+        let val = f();
+        self.recency.insert(self.tick, key.clone());
+        self.entries.insert(key, (val.clone(), self.tick));
+        val
+    }
+}
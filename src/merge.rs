@@ -0,0 +1,155 @@
+// `join --merge`: matched records coalesced into one row each, and the
+// records neither side matched kept as they are, so two tables of the same
+// things become one deduplicated table rather than a list of pairs. Each
+// column of a merged row takes the value that says the most: the one that
+// isn't empty, or of two different strings the longer, or else the left
+// one. A `provenance` record says which side every column came from.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Record, Span, Value};
+
+use crate::flag_span;
+use crate::validate::given;
+
+/// Flags that change what a join row is, which merging needs as it is.
+pub const EXCLUSIVE: [&str; 6] = ["top", "ties", "histogram", "plan", "explain-match", "why"];
+
+/// Checks `--merge` goes with record rows and a threshold, and without
+/// flags giving rows of other shapes.
+pub fn check(call: &EvaluatedCall) -> Result<bool, LabeledError> {
+    if !call.has_flag("merge")? {
+        return Ok(false);
+    }
+    for flag in EXCLUSIVE {
+        if given(call, flag) {
+            return Err(
+                LabeledError::new(format!("--merge and --{flag} are exclusive"))
+                    .with_label("merges one match per row", flag_span(call, flag))
+                    .with_help(format!("drop --{flag}")),
+            );
+        }
+    }
+    if call.get_flag::<f64>("threshold")?.is_none() {
+        return Err(LabeledError::new("--merge needs --threshold")
+            .with_label(
+                "would merge every row with its best match",
+                flag_span(call, "merge"),
+            )
+            .with_help("give the score two rows need to be merged, e.g. --threshold 0.9"));
+    }
+    Ok(true)
+}
+
+/// The join `rows` merged, followed by the records of `right` that matched
+/// none of them.
+pub fn rows(rows: Vec<Value>, right: &[Value], span: Span) -> Result<Vec<Value>, LabeledError> {
+    let mut merged = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let row = row.as_record()?;
+        let side = |name: &str| row.get(name).cloned().unwrap_or(Value::nothing(span));
+        let (left, right, score) = (side("left"), side("right"), side("score"));
+        let left = record(&left)?;
+        let mut out = match &right {
+            Value::Nothing { .. } => alone(left, "left", span),
+            r => coalesce(left, record(r)?, span),
+        };
+        out.insert("score", score);
+        merged.push(Value::record(out, span));
+    }
+    let right_rows = rows
+        .iter()
+        .filter_map(|r| r.as_record().ok()?.get("right"))
+        .collect::<Vec<_>>();
+    for r in right {
+        if !right_rows.contains(&r) {
+            let mut out = alone(record(r)?, "right", span);
+            out.insert("score", Value::nothing(span));
+            merged.push(Value::record(out, span));
+        }
+    }
+    Ok(merged)
+}
+
+fn record(v: &Value) -> Result<&Record, LabeledError> {
+    v.as_record().map_err(|_| {
+        LabeledError::new(format!("--merge needs records, got {}", v.get_type()))
+            .with_label("not a record", v.span())
+            .with_help("join tables, matching on a column with --key or --field-algorithms")
+    })
+}
+
+/// A record only one side has, with that side as the provenance of all its
+/// columns.
+fn alone(r: &Record, side: &str, span: Span) -> Record {
+    let mut out = r.clone();
+    let provenance = r
+        .columns()
+        .map(|c| (c.clone(), Value::string(side, span)))
+        .collect();
+    out.insert("provenance", Value::record(provenance, span));
+    out
+}
+
+/// The columns of `left` then those only `right` has, each with the value
+/// that says the most.
+fn coalesce(left: &Record, right: &Record, span: Span) -> Record {
+    let mut out = Record::new();
+    let mut provenance = Record::new();
+    let names = left
+        .columns()
+        .chain(right.columns().filter(|c| !left.contains(c)));
+    for name in names {
+        let (value, side) = pick(left.get(name), right.get(name));
+        out.push(name.clone(), value.cloned().unwrap_or(Value::nothing(span)));
+        provenance.push(name.clone(), Value::string(side, span));
+    }
+    out.insert("provenance", Value::record(provenance, span));
+    out
+}
+
+/// The value of a column to keep from the left and right one, and which
+/// side it came from: `both` when they're the same.
+fn pick<'a>(l: Option<&'a Value>, r: Option<&'a Value>) -> (Option<&'a Value>, &'static str) {
+    let empty = |v: Option<&Value>| match v {
+        None | Some(Value::Nothing { .. }) => true,
+        Some(Value::String { val, .. }) => val.trim().is_empty(),
+        _ => false,
+    };
+    match (l, r) {
+        _ if empty(r) => (l, "left"),
+        _ if empty(l) => (r, "right"),
+        (Some(a), Some(b)) if a == b => (l, "both"),
+        (Some(Value::String { val: a, .. }), Some(Value::String { val: b, .. }))
+            if b.chars().count() > a.chars().count() =>
+        {
+            (r, "right")
+        }
+        _ => (l, "left"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    #[test]
+    fn merges_prefer_values_that_say_more() {
+        let left = record! {
+            "name" => Value::test_string("J. Smith"),
+            "email" => Value::test_nothing(),
+            "city" => Value::test_string("Paris"),
+        };
+        let right = record! {
+            "name" => Value::test_string("John Smith"),
+            "email" => Value::test_string("john@example.com"),
+            "city" => Value::test_string("Paris"),
+            "phone" => Value::test_string("555-0100"),
+        };
+        let merged = coalesce(&left, &right, Span::test_data());
+        let provenance = merged.get("provenance").unwrap().as_record().unwrap();
+        let sides: Vec<&str> = provenance.values().map(|v| v.as_str().unwrap()).collect();
+        assert_eq!(merged.get("name"), Some(&Value::test_string("John Smith")));
+        assert_eq!(sides, ["right", "right", "both", "right"]);
+    }
+}
@@ -0,0 +1,18 @@
+// Character n-grams, the tokens of `--tokenize ngram`, so the token-counting
+// algorithms compare overlapping runs of chars instead of single chars.
+
+/// Every run of `n` chars in `s`, in order, overlapping and repeats kept.
+/// A non-empty string shorter than `n` is a single n-gram of its own.
+pub fn ngrams(s: &str, n: usize) -> Vec<&str> {
+    let bounds: Vec<usize> = s.char_indices().map(|(i, _)| i).chain([s.len()]).collect();
+    let chars = bounds.len() - 1;
+    if chars == 0 {
+        return vec![];
+    }
+    if chars <= n {
+        return vec![s];
+    }
+    (0..=chars - n)
+        .map(|i| &s[bounds[i]..bounds[i + n]])
+        .collect()
+}
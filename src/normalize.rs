@@ -0,0 +1,32 @@
+// Normalization steps under which two strings count as "basically the same".
+
+use crate::preprocess;
+
+/// Step names, in the order they are applied.
+pub const STEPS: [&str; 4] = ["case", "accents", "punctuation", "whitespace"];
+
+fn apply(step: &str, s: &str) -> String {
+    match step {
+        "case" => preprocess::lowercase(s),
+        "accents" => preprocess::strip_accents(s),
+        "punctuation" => preprocess::strip_punctuation(s),
+        _ => preprocess::collapse_spaces(s),
+    }
+}
+
+/// The smallest set of steps that makes `a` and `b` identical: empty if they
+/// already are, `None` if not even all of them together do.
+pub fn trivially_equal(a: &str, b: &str) -> Option<Vec<&'static str>> {
+    let mut masks: Vec<u32> = (0..1 << STEPS.len()).collect();
+    masks.sort_by_key(|m| m.count_ones());
+    masks.into_iter().find_map(|mask| {
+        let steps: Vec<&str> = STEPS
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| mask >> i & 1 == 1)
+            .map(|(_, s)| *s)
+            .collect();
+        let run = |s: &str| steps.iter().fold(s.to_string(), |s, step| apply(step, &s));
+        (run(a) == run(b)).then_some(steps)
+    })
+}
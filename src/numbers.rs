@@ -0,0 +1,268 @@
+// Number normalization before scoring, so "2nd Ave" and "Second Ave" or
+// "21 Jump Street" and "Twenty-One Jump Street" compare as equal. Numbers
+// written either way are read, up to 999999, and written back the one way
+// asked for on both sides.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::LabeledError;
+
+use crate::flag_span;
+
+const UNITS: [&str; 20] = [
+    "zero",
+    "one",
+    "two",
+    "three",
+    "four",
+    "five",
+    "six",
+    "seven",
+    "eight",
+    "nine",
+    "ten",
+    "eleven",
+    "twelve",
+    "thirteen",
+    "fourteen",
+    "fifteen",
+    "sixteen",
+    "seventeen",
+    "eighteen",
+    "nineteen",
+];
+const TENS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety",
+];
+const LARGEST: u64 = 999_999;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Style {
+    Words,
+    Digits,
+}
+
+impl Style {
+    /// Reads `--numbers-as-words` and `--words-as-numbers`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        match (
+            call.has_flag("numbers-as-words")?,
+            call.has_flag("words-as-numbers")?,
+        ) {
+            (true, true) => Err(LabeledError::new("conflicting number styles")
+                .with_label(
+                    "can't also write numbers as words",
+                    flag_span(call, "words-as-numbers"),
+                )
+                .with_help("pick one of --numbers-as-words and --words-as-numbers")),
+            (true, false) => Ok(Some(Style::Words)),
+            (false, true) => Ok(Some(Style::Digits)),
+            (false, false) => Ok(None),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Zero,
+    /// 1 to 9
+    Unit,
+    /// 10 to 19
+    Teen,
+    Tens,
+    Hundred,
+    Thousand,
+}
+
+impl Kind {
+    /// Whether `next` may follow `self` within one number, as "hundred"
+    /// may follow "two" but "two" may not follow "three".
+    fn precedes(self, next: Kind) -> bool {
+        use Kind::*;
+        match self {
+            Zero => false,
+            Unit | Teen => matches!(next, Hundred | Thousand),
+            Tens => matches!(next, Unit | Thousand),
+            Hundred => matches!(next, Unit | Teen | Tens | Thousand),
+            Thousand => matches!(next, Unit | Teen | Tens),
+        }
+    }
+}
+
+/// A number word: its kind, value, and whether it is the ordinal form.
+fn word(w: &str) -> Option<(Kind, u64, bool)> {
+    let w = w.to_lowercase();
+    let cardinal = match w.as_str() {
+        "first" => "one".to_string(),
+        "second" => "two".to_string(),
+        "third" => "three".to_string(),
+        "fifth" => "five".to_string(),
+        "eighth" => "eight".to_string(),
+        "ninth" => "nine".to_string(),
+        "twelfth" => "twelve".to_string(),
+        _ => match w.strip_suffix("ieth") {
+            // "twentieth"
+            Some(stem) => format!("{stem}y"),
+            None => w.strip_suffix("th").unwrap_or(&w).to_string(),
+        },
+    };
+    let ordinal = cardinal != w;
+    let found = if let Some(v) = UNITS.iter().position(|u| *u == cardinal) {
+        let kind = match v {
+            0 => Kind::Zero,
+            1..=9 => Kind::Unit,
+            _ => Kind::Teen,
+        };
+        (kind, v as u64)
+    } else if let Some(v) = TENS.iter().position(|t| !t.is_empty() && *t == cardinal) {
+        (Kind::Tens, v as u64 * 10)
+    } else if cardinal == "hundred" {
+        (Kind::Hundred, 100)
+    } else if cardinal == "thousand" {
+        (Kind::Thousand, 1000)
+    } else {
+        return None;
+    };
+    Some((found.0, found.1, ordinal))
+}
+
+/// The longest number spelled out at the start of `words`: its value,
+/// whether it is an ordinal, and how many words it took. "and" is allowed
+/// after "hundred" and "thousand", as in "one hundred and five".
+fn spelled(words: &[&str]) -> Option<(u64, bool, usize)> {
+    let (mut total, mut current) = (0, 0);
+    let mut last: Option<Kind> = None;
+    let mut best = None;
+    let mut i = 0;
+    while i < words.len() {
+        let skip_and = matches!(last, Some(Kind::Hundred | Kind::Thousand))
+            && words[i].eq_ignore_ascii_case("and");
+        let at = i + usize::from(skip_and);
+        let Some((kind, value, ordinal)) = words.get(at).and_then(|w| word(w)) else {
+            break;
+        };
+        match last {
+            None if matches!(kind, Kind::Hundred | Kind::Thousand) => break,
+            Some(prev) if !prev.precedes(kind) => break,
+            _ => {}
+        }
+        match kind {
+            Kind::Hundred => current = current.max(1) * 100,
+            Kind::Thousand => {
+                total += current.max(1) * 1000;
+                current = 0;
+            }
+            _ => current += value,
+        }
+        i = at + 1;
+        last = Some(kind);
+        best = Some((total + current, ordinal, i));
+        if ordinal {
+            break;
+        }
+    }
+    best
+}
+
+/// A number in digits, with an ordinal suffix or not: "21", "2nd".
+fn digits(w: &str) -> Option<(u64, bool)> {
+    let end = w.find(|c: char| !c.is_ascii_digit()).unwrap_or(w.len());
+    let (number, suffix) = w.split_at(end);
+    let n: u64 = number.parse().ok().filter(|n| *n <= LARGEST)?;
+    match suffix.to_lowercase().as_str() {
+        "" => Some((n, false)),
+        "st" | "nd" | "rd" | "th" => Some((n, true)),
+        _ => None,
+    }
+}
+
+fn to_words(n: u64, ordinal: bool) -> String {
+    fn cardinal(n: u64) -> Vec<&'static str> {
+        let mut out = vec![];
+        if n >= 1000 {
+            out.extend(cardinal(n / 1000));
+            out.push("thousand");
+        }
+        let n = n % 1000;
+        if n >= 100 {
+            out.extend([UNITS[(n / 100) as usize], "hundred"]);
+        }
+        match n % 100 {
+            0 => {}
+            r @ 1..=19 => out.push(UNITS[r as usize]),
+            r => {
+                out.push(TENS[(r / 10) as usize]);
+                if r % 10 > 0 {
+                    out.push(UNITS[(r % 10) as usize]);
+                }
+            }
+        }
+        out
+    }
+    let mut words: Vec<String> = match n {
+        0 => vec!["zero".into()],
+        n => cardinal(n).into_iter().map(String::from).collect(),
+    };
+    if ordinal {
+        let last = words.pop().unwrap_or_default();
+        words.push(match last.as_str() {
+            "one" => "first".into(),
+            "two" => "second".into(),
+            "three" => "third".into(),
+            "five" => "fifth".into(),
+            "eight" => "eighth".into(),
+            "nine" => "ninth".into(),
+            "twelve" => "twelfth".into(),
+            w => match w.strip_suffix('y') {
+                Some(stem) => format!("{stem}ieth"),
+                None => format!("{w}th"),
+            },
+        });
+    }
+    words.join(" ")
+}
+
+fn to_digits(n: u64, ordinal: bool) -> String {
+    if !ordinal {
+        return n.to_string();
+    }
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+/// `s` with every number, in digits or spelled out, written in `style`.
+/// Spelled-out numbers come out lowercase; words end up separated by single
+/// spaces, and hyphenated numbers like "twenty-one" are split.
+pub fn normalize(s: &str, style: Style) -> String {
+    let words: Vec<&str> = s
+        .split_whitespace()
+        .flat_map(|w| match w.split('-').all(|p| word(p).is_some()) {
+            true => w.split('-').collect::<Vec<_>>(),
+            false => vec![w],
+        })
+        .collect();
+    let write = |n, ordinal| match style {
+        Style::Words => to_words(n, ordinal),
+        Style::Digits => to_digits(n, ordinal),
+    };
+    let mut out = vec![];
+    let mut i = 0;
+    while i < words.len() {
+        if let Some((n, ordinal)) = digits(words[i]) {
+            out.push(write(n, ordinal));
+            i += 1;
+        } else if let Some((n, ordinal, len)) = spelled(&words[i..]) {
+            out.push(write(n, ordinal));
+            i += len;
+        } else {
+            out.push(words[i].to_string());
+            i += 1;
+        }
+    }
+    out.join(" ")
+}
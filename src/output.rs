@@ -0,0 +1,420 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{ast::CellPath, record, Config, LabeledError, Span, Spanned, Value};
+use rayon::prelude::*;
+
+use crate::{
+    algorithm, algorithm_version, auto, canonical, checkpoint, compute_tokens, custom, dates,
+    family, flag_span, is_auto, mode, preprocess, text, tokenize, AlgoParams, Format, Inputs,
+    Limit, Mode, Orientation, Token, ALGORITHMS,
+};
+
+/// One row per algorithm with its score and the `kind` of score it is, so
+/// distances and similarities can be told apart.
+/// Every algorithm's raw `distance` and its `normalized` value, each as
+/// `orientation` asks. With `sort`, the closest normalized scores come first,
+/// whether the algorithm reports a distance or a similarity.
+pub fn compute_all<E: Token>(
+    s1: &[E],
+    s2: &[E],
+    orientation: Option<Orientation>,
+    versioned: bool,
+    sort: bool,
+    params: &AlgoParams,
+    format: &Format,
+) -> Result<Value, LabeledError> {
+    let span = Span::unknown();
+    let rows = ALGORITHMS.par_iter().map(|a| {
+        let algo = a.name;
+        let sim = Value::string(algo, span);
+        let score = compute_tokens(algo, s1, s2, params);
+        let normalized = score.oriented(algo, true, orientation);
+        let kind = orientation.unwrap_or(Orientation::of(algo));
+        let mut row = record! {
+            "algorithm" => sim,
+            "distance" => format.raw(score.oriented(algo, false, orientation), span),
+            "normalized" => format.score(normalized, span),
+            "kind" => Value::string(kind.name(), span),
+            "family" => Value::string(family(algo), span),
+        };
+        if versioned {
+            row.push(
+                "algorithm_version",
+                Value::string(algorithm_version(algo), span),
+            );
+        }
+        let closeness = match kind {
+            Orientation::Distance => 1.0 - normalized,
+            Orientation::Similarity => normalized,
+        };
+        (closeness, Value::test_record(row))
+    });
+    let mut rows: Vec<(f64, Value)> = rows.collect();
+    if sort {
+        rows.sort_by(|a, b| b.0.total_cmp(&a.0));
+    }
+
+    Ok(Value::test_list(
+        rows.into_iter().map(|(_, row)| row).collect(),
+    ))
+}
+
+/// `input`, a record or a table, with the values at `paths` replaced by
+/// their scores, or with `--as-column` the score added in a new column.
+pub fn in_columns(
+    call: &EvaluatedCall,
+    input: &Value,
+    paths: &[CellPath],
+    compare: impl Fn(&Value) -> Result<Value, LabeledError>,
+) -> Result<Value, LabeledError> {
+    let as_column: Option<Spanned<String>> = call.get_flag("as-column")?;
+    if let (Some(name), [_, _, ..]) = (&as_column, paths) {
+        return Err(LabeledError::new("--as-column takes one cell path")
+            .with_label(
+                format!("{} cell paths can't share '{}'", paths.len(), name.item),
+                name.span,
+            )
+            .with_help("score one column at a time, or drop --as-column to replace them all"));
+    }
+    let update = |row: &Value| -> Result<Value, LabeledError> {
+        let mut updated = row.clone();
+        for path in paths {
+            let found = row.clone().follow_cell_path(&path.members, false)?;
+            let score = compare(&found)?;
+            match &as_column {
+                Some(name) => {
+                    let mut record = updated.into_record()?;
+                    record.insert(&name.item, score);
+                    updated = Value::record(record, row.span());
+                }
+                None => updated.update_cell_path(&path.members, Box::new(move |_| score))?,
+            }
+        }
+        Ok(updated)
+    };
+    match input {
+        Value::List { vals, .. } => {
+            let rows = vals.iter().map(update).collect::<Result<_, _>>()?;
+            Ok(Value::list(rows, input.span()))
+        }
+        Value::Record { .. } => update(input),
+        v => Err(LabeledError::new(format!(
+            "cell paths need a record or table, got {}",
+            v.get_type()
+        ))
+        .with_label("not a data structure", v.span())
+        .with_help("pipe in a table, or leave out the cell paths to compare a string")),
+    }
+}
+
+/// One row per element of a list input: the element and its score, or with
+/// `--all` a column per algorithm holding the field of its result named by
+/// `all`, its raw `distance` or its `normalized` value.
+pub fn batch(
+    vals: &[Value],
+    all: Option<&str>,
+    verbose: Option<&Verbose>,
+    inputs: Option<&Inputs>,
+    span: Span,
+    compare: impl Fn(&Value) -> Result<Value, LabeledError> + Sync,
+) -> Result<Value, LabeledError> {
+    let rows = vals
+        .par_iter()
+        .map(|v| batch_row(v, all, verbose, inputs, &compare))
+        .collect::<Result<Vec<_>, LabeledError>>()?;
+    Ok(Value::list(rows, span))
+}
+
+/// The field of `--all` results that [`batch`] puts in each algorithm's
+/// column, as `--normalize` picks; `None` without `--all`.
+pub fn all_field(call: &EvaluatedCall) -> Result<Option<&'static str>, LabeledError> {
+    Ok(match (call.has_flag("all")?, call.has_flag("normalize")?) {
+        (false, _) => None,
+        (true, false) => Some("distance"),
+        (true, true) => Some("normalized"),
+    })
+}
+
+/// The [`batch`] row of one element.
+pub fn batch_row(
+    v: &Value,
+    all: Option<&str>,
+    verbose: Option<&Verbose>,
+    inputs: Option<&Inputs>,
+    compare: impl Fn(&Value) -> Result<Value, LabeledError>,
+) -> Result<Value, LabeledError> {
+    let mut row = record! { "value" => v.clone() };
+    if let Some(inputs) = inputs {
+        inputs.push_value(&mut row, v);
+    }
+    let start = std::time::Instant::now();
+    let scores = compare(v)?;
+    let elapsed = start.elapsed();
+    match scores {
+        Value::List { vals: scores, .. } if all.is_some() => {
+            for score in scores {
+                let mut score = score.into_record()?;
+                let (Some(algo), Some(distance)) = (
+                    score.remove("algorithm"),
+                    all.and_then(|field| score.remove(field)),
+                ) else {
+                    continue;
+                };
+                let cell = match score.remove("algorithm_version") {
+                    Some(version) => Value::record(
+                        record! {
+                            "score" => distance,
+                            "algorithm_version" => version,
+                        },
+                        v.span(),
+                    ),
+                    None => distance,
+                };
+                row.push(algo.as_str()?, cell);
+            }
+        }
+        result => row.push("score", result),
+    }
+    if let Some(verbose) = verbose {
+        let nanos = i64::try_from(elapsed.as_nanos()).unwrap_or(i64::MAX);
+        row.push("elapsed", Value::duration(nanos, v.span()));
+        if all.is_none() {
+            row.push("algorithm", Value::string(verbose.used(v), v.span()));
+        }
+        row.push(
+            "coerced",
+            Value::bool(
+                !matches!(v, Value::String { .. } | Value::Binary { .. }),
+                v.span(),
+            ),
+        );
+    }
+    Ok(Value::record(row, v.span()))
+}
+
+/// What `--verbose` needs to tell which algorithm scored a row.
+pub struct Verbose {
+    /// The algorithm's full name, after `--lenient`, or the mode or flag
+    /// that replaces it.
+    algorithm: &'static str,
+    /// The string compared with, when what scores a row depends on it.
+    other: Option<String>,
+    /// Whether `--mode date` scores the rows that are dates.
+    date: bool,
+    /// Whether `-a auto` picks the algorithm of each row.
+    auto: bool,
+    coerce: Option<Arc<Config>>,
+}
+
+impl Verbose {
+    /// Reads `--verbose`; `None` without it.
+    pub fn from_call(
+        call: &EvaluatedCall,
+        coerce: Option<&Config>,
+    ) -> Result<Option<Self>, LabeledError> {
+        if !call.has_flag("verbose")? {
+            return Ok(None);
+        }
+        let mode = tokenize(call, mode(call)?)?;
+        let auto = is_auto(call)?;
+        let algorithm = if call.has_flag("glob")? {
+            "glob"
+        } else if matches!(mode, Some(Mode::Identifier)) {
+            "identifier"
+        } else if auto {
+            "auto"
+        } else {
+            canonical(&algorithm(call)?)
+        };
+        let date = matches!(mode, Some(Mode::Date));
+        Ok(Some(Verbose {
+            algorithm,
+            other: match date || auto {
+                true => call.opt(0)?,
+                false => None,
+            },
+            date,
+            auto,
+            coerce: coerce.map(|c| Arc::new(c.clone())),
+        }))
+    }
+
+    /// The name of what scored `v`.
+    fn used(&self, v: &Value) -> &'static str {
+        let (Some(other), Some(t)) = (&self.other, text(v, self.coerce.as_deref())) else {
+            return self.algorithm;
+        };
+        if self.date && dates::similarity(&t, other).is_some() {
+            "date"
+        } else if self.auto && self.algorithm == "auto" {
+            auto::choose(&t, other).name()
+        } else {
+            self.algorithm
+        }
+    }
+}
+
+/// `--against-columns`: the [`score_matrix`] of the input and the targets.
+pub fn against_columns(
+    call: &EvaluatedCall,
+    input: &Value,
+    targets: Spanned<Vec<String>>,
+    custom: Option<&Arc<custom::Custom>>,
+) -> Result<Value, LabeledError> {
+    if !call.positional.is_empty() {
+        return Err(LabeledError::new("two things to compare with")
+            .with_label(
+                "--against-columns replaces the string argument",
+                flag_span(call, "against-columns"),
+            )
+            .with_help("add the string to the list of targets"));
+    }
+    score_matrix(call, input, targets, custom, None)
+}
+
+/// One row per input string with its score against each target in a column
+/// named after the target. Reads the algorithm, `--normalize` and the
+/// preprocessing and parameter flags from `call`.
+pub fn score_matrix(
+    call: &EvaluatedCall,
+    input: &Value,
+    targets: Spanned<Vec<String>>,
+    custom: Option<&Arc<custom::Custom>>,
+    checkpoint: Option<&checkpoint::Checkpoint>,
+) -> Result<Value, LabeledError> {
+    let mut seen = HashSet::from(["value"]);
+    if let Some(t) = targets.item.iter().find(|t| !seen.insert(t.as_str())) {
+        return Err(LabeledError::new("duplicate column")
+            .with_label(format!("'{t}' would name two columns"), targets.span)
+            .with_help("targets name the score columns, so each must be unique and not 'value'"));
+    }
+    let sim = algorithm(call)?;
+    let normalize = call.has_flag("normalize")?;
+    let orientation = Orientation::from_call(call)?;
+    let params = AlgoParams {
+        custom: custom.cloned(),
+        ..AlgoParams::from_call(call)?
+    };
+    let pipeline = preprocess::Pipeline::from_call(call)?;
+    let targets: Vec<(&str, Vec<char>)> = targets
+        .item
+        .iter()
+        .map(|t| (t.as_str(), pipeline.apply(t).chars().collect()))
+        .collect();
+    let limit = Limit::from_call(call)?;
+    let inputs = Inputs::from_call(call)?;
+    let format = Format::from_call(call)?;
+    let distance = orientation.unwrap_or(Orientation::of(&sim)) == Orientation::Distance;
+    let span = input.span();
+    let strings = match input {
+        Value::List { vals, .. } => vals.iter().collect(),
+        v => vec![v],
+    };
+    let strings = strings
+        .into_iter()
+        .map(|v| Ok((v, v.as_str()?)))
+        .collect::<Result<Vec<_>, LabeledError>>()?;
+    // a row is as close as its closest target
+    let score_row = |&(v, s): &(&Value, &str)| {
+        let chars: Vec<char> = pipeline.apply(s).chars().collect();
+        let mut row = record! { "value" => v.clone() };
+        if let Some(inputs) = &inputs {
+            inputs.push_left(&mut row, s, span);
+        }
+        let mut closest = None::<f64>;
+        for (name, target) in &targets {
+            let score = compute_tokens(&sim, &chars, target, &params).oriented(
+                &sim,
+                normalize,
+                orientation,
+            );
+            let closeness = if distance { -score } else { score };
+            closest = Some(closest.map_or(closeness, |c| c.max(closeness)));
+            row.push(*name, format.score(score, v.span()));
+        }
+        (closest, Value::record(row, v.span()))
+    };
+    // without a checkpoint to save them to, rows go straight to the limit
+    let rows = match checkpoint {
+        Some(_) => limit.keep(checkpoint::chunked(
+            checkpoint,
+            strings.len(),
+            span,
+            |range| strings[range].iter().map(score_row).collect(),
+        )?),
+        None => limit.keep(strings.iter().map(score_row)),
+    };
+    custom::check(custom)?;
+    Ok(Value::list(rows, span))
+}
+
+/// `--columns`: each row of `input`, a table or record, with the score of
+/// its first column against its second added as `score`, or under the
+/// `--as-column` name. `score` scores a row's first column, given a call
+/// with its second as the string argument.
+pub fn by_columns(
+    call: &EvaluatedCall,
+    input: &Value,
+    columns: Spanned<Vec<String>>,
+    score: impl Fn(&EvaluatedCall, &Value) -> Result<Value, LabeledError> + Sync,
+) -> Result<Value, LabeledError> {
+    let [left, right] = columns.item.as_slice() else {
+        return Err(LabeledError::new("--columns takes two columns")
+            .with_label(
+                format!("{} columns given", columns.item.len()),
+                columns.span,
+            )
+            .with_help("name the column to score and the one to compare it with, e.g. --columns [expected actual]"));
+    };
+    if !call.positional.is_empty() {
+        return Err(LabeledError::new("two things to compare with")
+            .with_label(
+                "--columns compares each row with itself",
+                flag_span(call, "columns"),
+            )
+            .with_help("drop the string argument and cell paths, or --columns"));
+    }
+    if call.has_flag("against-columns")? {
+        return Err(
+            LabeledError::new("--columns and --against-columns are exclusive")
+                .with_label("the targets come from the rows", flag_span(call, "columns"))
+                .with_help("drop one of --columns and --against-columns"),
+        );
+    }
+    let name = call
+        .get_flag::<String>("as-column")?
+        .unwrap_or_else(|| "score".into());
+    let score_row = |row: &Value| -> Result<Value, LabeledError> {
+        let mut record = row.as_record()?.clone();
+        let column = |c: &str| {
+            record.get(c).cloned().ok_or_else(|| {
+                LabeledError::new(format!("no column '{c}'"))
+                    .with_label("row without the column", row.span())
+                    .with_help(format!(
+                        "the rows have {}",
+                        record.columns().cloned().collect::<Vec<_>>().join(", ")
+                    ))
+            })
+        };
+        let (a, b) = (column(left)?, column(right)?);
+        let mut row_call = call.clone();
+        row_call.positional = vec![b];
+        record.insert(&name, score(&row_call, &a)?);
+        Ok(Value::record(record, row.span()))
+    };
+    match input {
+        Value::List { vals, .. } => {
+            let rows = vals.par_iter().map(score_row).collect::<Result<_, _>>()?;
+            Ok(Value::list(rows, input.span()))
+        }
+        Value::Record { .. } => score_row(input),
+        v => Err(LabeledError::new(format!(
+            "--columns needs a record or table, got {}",
+            v.get_type()
+        ))
+        .with_label("not a data structure", v.span())
+        .with_help("pipe in a table with the two columns")),
+    }
+}
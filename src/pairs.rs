@@ -0,0 +1,280 @@
+use std::time::Duration;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, PipelineData, ShellError, Signature, Span, SyntaxShape, Type,
+    Value,
+};
+
+use crate::budget;
+use crate::format::Format;
+use crate::preprocess::Pipeline;
+use crate::profile;
+use crate::{algorithm, compute_tokens, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityPairs;
+
+impl PluginCommand for StrSimilarityPairs {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity pairs"
+    }
+
+    fn description(&self) -> &str {
+        "Score a stream of rows holding two strings each, emitting every row as soon as it is scored"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_type(
+                Type::List(Box::new(Type::Any)),
+                Type::List(Box::new(Type::Any)),
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .switch(
+                "normalize",
+                "Normalize the results between 0 and 1",
+                Some('n'),
+            )
+            .named(
+                "left",
+                SyntaxShape::String,
+                "Column holding the first string (default: left)",
+                None,
+            )
+            .named(
+                "right",
+                SyntaxShape::String,
+                "Column holding the second string (default: right)",
+                None,
+            )
+            .named(
+                "timeout",
+                SyntaxShape::Duration,
+                "Give up on any single pair after this long, leaving its score empty and marking it timed_out",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "profile",
+                SyntaxShape::String,
+                "Take any flag not given here from the settings saved under this name with `str similarity profile save`",
+                None,
+            )
+            .named(
+                "config",
+                SyntaxShape::OneOf(vec![SyntaxShape::Record(vec![]), SyntaxShape::Filepath]),
+                "Take any flag not given here from this record of long flag names and values, or from the nuon or json file holding one",
+                None,
+            )
+            .named(
+                "preset",
+                SyntaxShape::String,
+                "Take any flag not given here or by --profile from a built-in preset: loose, standard or strict",
+                None,
+            )
+            .named(
+                "mask",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Replace volatile tokens in both strings with placeholders before anything else: uuids, timestamps, ips, hex, numbers",
+                None,
+            )
+            .named(
+                "mask-pattern",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Also replace matches of these regular expressions with a placeholder, like --mask",
+                None,
+            )
+            .named(
+                "preprocess",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Steps run in order on both strings before anything else: lowercase, strip-accents, strip-punctuation, collapse-spaces, trim, fold-compat, deleet",
+                None,
+            )
+            .switch(
+                "ignore-case",
+                "Lowercase both strings before scoring, like --preprocess [lowercase]",
+                None,
+            )
+            .switch(
+                "trim",
+                "Trim whitespace from both ends of both strings before scoring",
+                None,
+            )
+            .switch(
+                "collapse-whitespace",
+                "Squeeze runs of whitespace in both strings into one space before scoring",
+                None,
+            )
+            .switch(
+                "strip-accents",
+                "Drop diacritics from both strings before scoring, reading letters like ø and ł as o and l",
+                None,
+            )
+            .switch(
+                "strip-invisible",
+                "Drop soft hyphens, zero width spaces and joiners and byte order marks from both strings before scoring",
+                None,
+            )
+            .switch(
+                "fold-compat",
+                "Compare what a human reads: apply NFKC to both strings, turning ligatures, superscripts and roman numerals into plain letters and digits, and fold typographic quotes and dashes",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Score every pair of a large CSV without loading it all",
+                example: "open pairs.csv | str similarity pairs -a jaro_winkler | where score > 0.9 | save matches.csv",
+                result: None,
+            },
+            Example {
+                description: "Score pairs stored under other column names",
+                example: "[[a b]; [nutshell nushell]] | str similarity pairs --left a --right b",
+                result: None,
+            },
+            Example {
+                description: "Keep one huge pair from stalling the rest and list the pairs that gave up",
+                example: "open docs.csv | str similarity pairs --timeout 2sec | where timed_out",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let call = &profile::apply(plugin, engine, call, &PluginCommand::signature(self))?;
+        let algo = algorithm(call)?;
+        let normalize = call.has_flag("normalize")?;
+        let left: String = call.get_flag("left")?.unwrap_or_else(|| "left".into());
+        let right: String = call.get_flag("right")?.unwrap_or_else(|| "right".into());
+        let params = AlgoParams::from_call(call)?;
+        let limit = budget::duration(call, "timeout")?;
+        let pipeline = Pipeline::from_call(call)?;
+        let format = Format::from_call(call)?;
+
+        // `map` scores rows lazily as they are pulled downstream, so memory
+        // stays flat however many pairs flow through.
+        Ok(input.map(
+            move |row| {
+                let span = row.span();
+                match score_row(
+                    row, &algo, &left, &right, normalize, &params, &pipeline, &format, limit,
+                ) {
+                    Ok(v) => v,
+                    Err(e) => Value::error(e, span),
+                }
+            },
+            engine.signals(),
+        )?)
+    }
+}
+
+/// Pairs of at most this many characters multiplied finish in well under a
+/// millisecond, so `--timeout` scores them on the spot.
+const INLINE_CELLS: usize = 1 << 16;
+
+#[allow(clippy::too_many_arguments)]
+fn score_row(
+    row: Value,
+    algo: &str,
+    left: &str,
+    right: &str,
+    normalize: bool,
+    params: &AlgoParams,
+    pipeline: &Pipeline,
+    format: &Format,
+    limit: Option<Duration>,
+) -> Result<Value, ShellError> {
+    let span = row.span();
+    let mut record = row.into_record()?;
+    let text = |col: &str| -> Result<Vec<char>, ShellError> {
+        match record.get(col) {
+            Some(v) => Ok(pipeline.apply(v.as_str()?).chars().collect()),
+            None => Err(missing(col, span)),
+        }
+    };
+    let (a, b) = (text(left)?, text(right)?);
+    // too small to run over, so not worth a thread
+    let own = limit.filter(|_| a.len().saturating_mul(b.len()) > INLINE_CELLS);
+    let (algo, params) = (algo.to_string(), params.clone());
+    let score = budget::with_limit(own, move || {
+        compute_tokens(&algo, &a, &b, &params).get(normalize)
+    });
+    record.insert(
+        "score",
+        score.map_or(Value::nothing(span), |s| format.score(s, span)),
+    );
+    if limit.is_some() {
+        record.insert("timed_out", Value::bool(score.is_none(), span));
+    }
+    Ok(Value::record(record, span))
+}
+
+fn missing(col: &str, span: Span) -> ShellError {
+    ShellError::GenericError {
+        error: format!("missing column '{col}'"),
+        msg: "row has no such column".into(),
+        span: Some(span),
+        help: Some("pick the columns with --left and --right".into()),
+        inner: vec![],
+    }
+}
@@ -0,0 +1,104 @@
+// Chunked parallel map shared by the batch commands.
+//
+// Results come back in input order unless the caller opts out, so output is
+// the same from run to run however the threads get scheduled.
+
+use std::sync::mpsc;
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Spanned};
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+/// A pool of `--threads` threads; `None` without the flag, for the global
+/// pool with a thread per core.
+pub fn pool(call: &EvaluatedCall) -> Result<Option<ThreadPool>, LabeledError> {
+    let Some(n) = call.get_flag::<Spanned<i64>>("threads")? else {
+        return Ok(None);
+    };
+    if n.item < 1 {
+        return Err(LabeledError::new("invalid thread count")
+            .with_label("need at least one thread", n.span)
+            .with_help("leave out --threads to use every core"));
+    }
+    ThreadPoolBuilder::new()
+        .num_threads(n.item as usize)
+        .build()
+        .map(Some)
+        .map_err(|e| {
+            LabeledError::new("can't start the threads")
+                .with_label(e.to_string(), n.span)
+                .with_help("try fewer threads")
+        })
+}
+
+/// Runs `f` with its parallel work on `pool`, or on the global pool.
+pub fn install<R: Send>(pool: Option<&ThreadPool>, f: impl FnOnce() -> R + Send) -> R {
+    match pool {
+        Some(pool) => pool.install(f),
+        None => f(),
+    }
+}
+
+/// The number of threads `f` would run its parallel work on.
+pub fn threads(pool: Option<&ThreadPool>) -> usize {
+    pool.map_or_else(rayon::current_num_threads, ThreadPool::current_num_threads)
+}
+
+/// Applies `f` to consecutive `chunk_rows`-sized slices of `items` in
+/// parallel. With `ordered` the results are in chunk order; otherwise they are
+/// in completion order, which skips holding finished chunks back until the
+/// ones before them are done.
+pub fn map_chunks<T, R, F>(items: &[T], chunk_rows: usize, ordered: bool, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&[T]) -> R + Sync,
+{
+    if ordered {
+        return items.par_chunks(chunk_rows).map(&f).collect();
+    }
+
+    let (tx, rx) = mpsc::channel();
+    items.par_chunks(chunk_rows).for_each_with(tx, |tx, chunk| {
+        // the receiver outlives every sender, so this can't fail
+        let _ = tx.send(f(chunk));
+    });
+    rx.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    // Early chunks are made the slowest so they finish last.
+    fn slow_first(chunk: &[u64]) -> Vec<u64> {
+        thread::sleep(Duration::from_millis(20 - chunk[0]));
+        chunk.to_vec()
+    }
+
+    #[test]
+    fn ordered_results_follow_input_order() {
+        let items: Vec<u64> = (0..20).collect();
+        for chunk_rows in [1, 3, 20] {
+            let out: Vec<u64> = map_chunks(&items, chunk_rows, true, slow_first)
+                .into_iter()
+                .flatten()
+                .collect();
+            assert_eq!(out, items);
+        }
+    }
+
+    #[test]
+    fn unordered_results_keep_every_row() {
+        let items: Vec<u64> = (0..20).collect();
+        let mut out: Vec<u64> = map_chunks(&items, 2, false, slow_first)
+            .into_iter()
+            .flatten()
+            .collect();
+        out.sort_unstable();
+        assert_eq!(out, items);
+    }
+}
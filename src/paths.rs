@@ -0,0 +1,69 @@
+// Path mode: file paths compared by their parts, so a file that moved keeps
+// a high stem score and a renamed one a high directory score. `/` and `\`
+// both separate directories.
+
+use nu_protocol::{record, Span, Value};
+
+/// How much the directory, stem and extension scores weigh in the combined
+/// score, in that order.
+const WEIGHTS: [f64; 3] = [0.25, 0.6, 0.15];
+
+/// A path's directory, its file name without the extension, and the
+/// extension. A leading dot starts a name, not an extension.
+pub fn split(path: &str) -> (String, String, String) {
+    let path = path.replace('\\', "/");
+    let path = path.trim_end_matches('/');
+    let (dir, name) = match path.rsplit_once('/') {
+        Some((dir, name)) => (dir, name),
+        None => ("", path),
+    };
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, ext),
+        _ => (name, ""),
+    };
+    (dir.to_string(), stem.to_string(), ext.to_string())
+}
+
+/// The `dir_score`, `stem_score` and `ext_score` of two paths by `score`,
+/// and the weighted `score` of them all, each a cell by `orient`. Without
+/// the extensions, `ext_score` is empty and the others share its weight.
+pub fn compare(
+    a: &str,
+    b: &str,
+    ignore_extension: bool,
+    score: impl Fn(&str, &str) -> f64,
+    orient: impl Fn(f64) -> Value,
+    span: Span,
+) -> Value {
+    let (a, b) = (split(a), split(b));
+    let dir = score(&a.0, &b.0);
+    let stem = score(&a.1, &b.1);
+    let ext = (!ignore_extension).then(|| score(&a.2.to_lowercase(), &b.2.to_lowercase()));
+    let [w_dir, w_stem, w_ext] = WEIGHTS;
+    let combined = match ext {
+        Some(ext) => w_dir * dir + w_stem * stem + w_ext * ext,
+        None => (w_dir * dir + w_stem * stem) / (w_dir + w_stem),
+    };
+    Value::record(
+        record! {
+            "score" => orient(combined),
+            "dir_score" => orient(dir),
+            "stem_score" => orient(stem),
+            "ext_score" => ext.map_or(Value::nothing(span), orient),
+        },
+        span,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_either_separator() {
+        let parts = |d: &str, s: &str, e: &str| (d.to_string(), s.to_string(), e.to_string());
+        assert_eq!(split(r"src\bin\main.rs"), parts("src/bin", "main", "rs"));
+        assert_eq!(split("home/.bashrc"), parts("home", ".bashrc", ""));
+        assert_eq!(split("archive.tar.gz"), parts("", "archive.tar", "gz"));
+    }
+}
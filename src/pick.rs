@@ -0,0 +1,139 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Value};
+
+use crate::{algorithm, compute_tokens, flag_span, is_distance, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityPick;
+
+impl SimplePluginCommand for StrSimilarityPick {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity pick"
+    }
+
+    fn description(&self) -> &str {
+        "Resolve a possibly misspelled value to the one item of a list it is close to"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .required("value", SyntaxShape::String, "Value to resolve")
+            .named(
+                "from",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Canonical values to pick from",
+                None,
+            )
+            .named(
+                "max-distance",
+                SyntaxShape::Int,
+                "Largest raw edit distance for a candidate to count (default: 2)",
+                None,
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Distance algorithm to compute (default: levenshtein)",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Accept a typo in an option",
+                example: "str similarity pick 'relase' --from [debug release profile]",
+                result: Some(Value::test_string("release")),
+            },
+            Example {
+                description: "Validate a user-provided option in a script",
+                example: "let level = (str similarity pick $level --from [info warn error] --max-distance 1)",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let value: Spanned<String> = call.req(0)?;
+        let Some(from) = call.get_flag::<Vec<String>>("from")? else {
+            return Err(LabeledError::new("missing candidates")
+                .with_label("--from is required", call.head)
+                .with_help("list the values to pick from, e.g. --from [debug release]"));
+        };
+        let max = match call.get_flag::<Spanned<i64>>("max-distance")? {
+            Some(m) if m.item < 0 => {
+                return Err(LabeledError::new("invalid edit bound")
+                    .with_label("max distance must be zero or greater", m.span)
+                    .with_help("--max-distance 0 accepts exact values only"));
+            }
+            Some(m) => m.item as f64,
+            None => 2.0,
+        };
+        let algo = algorithm(call)?;
+        if !is_distance(&algo) {
+            return Err(LabeledError::new("pick needs a distance algorithm")
+                .with_label(
+                    format!("{algo} is a similarity"),
+                    flag_span(call, "algorithm"),
+                )
+                .with_help("e.g. levenshtein or damerau_levenshtein"));
+        }
+
+        // an exact value is never ambiguous, however close its neighbours are
+        if from.contains(&value.item) {
+            return Ok(Value::string(value.item, call.head));
+        }
+
+        let params = AlgoParams::default();
+        let v: Vec<char> = value.item.chars().collect();
+        let mut close: Vec<(&str, f64)> = from
+            .iter()
+            .map(|c| {
+                let chars: Vec<char> = c.chars().collect();
+                (c.as_str(), compute_tokens(&algo, &v, &chars, &params).raw)
+            })
+            .filter(|(_, d)| *d <= max)
+            .collect();
+
+        match close.len() {
+            1 => Ok(Value::string(close[0].0, call.head)),
+            0 => Err(LabeledError::new(format!("no match for '{}'", value.item))
+                .with_label(format!("nothing within {max} edits"), value.span)
+                .with_help(format!("expected one of: {}", from.join(", ")))),
+            _ => {
+                close.sort_by(|a, b| a.1.total_cmp(&b.1));
+                let options: Vec<String> =
+                    close.iter().map(|(c, d)| format!("{c} ({d})")).collect();
+                Err(LabeledError::new(format!("'{}' is ambiguous", value.item))
+                    .with_label(
+                        format!("{} values within {max} edits", close.len()),
+                        value.span,
+                    )
+                    .with_help(format!("did you mean one of: {}", options.join(", "))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityPick)
+    }
+}
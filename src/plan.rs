@@ -0,0 +1,55 @@
+// Dry runs of the batch commands: how much work a run would be, timed on a
+// small calibration sample instead of done, so blocking or thresholds can be
+// adjusted before starting an hour-long job.
+
+use std::time::Instant;
+
+use nu_protocol::{record, Record, Span, Value};
+
+use crate::sample::{self, Rng};
+
+/// Comparisons timed to estimate the cost of one.
+const CALIBRATION_PAIRS: usize = 200;
+
+/// Rough size of one output row, with its values.
+pub const ROW_BYTES: usize = 256;
+
+/// Rough size of one cached pair score, with the hash table's overhead.
+pub const CACHE_ENTRY_BYTES: usize = 64;
+
+/// Mean nanoseconds `score` takes on up to [`CALIBRATION_PAIRS`] of the
+/// `pairs` comparisons a run would make, picked at random but the same every
+/// time.
+pub fn calibrate(pairs: usize, mut score: impl FnMut(usize)) -> f64 {
+    let mut rng = Rng::new(0x9e37_79b9_7f4a_7c15);
+    let picks = sample::indices(&mut rng, CALIBRATION_PAIRS, pairs);
+    let start = Instant::now();
+    for &i in &picks {
+        score(i);
+    }
+    start.elapsed().as_nanos() as f64 / picks.len().max(1) as f64
+}
+
+/// The plan as a record: the work, the time it should take spread over the
+/// thread pool, and the memory it should need, followed by `details`.
+pub fn report(
+    comparisons: usize,
+    nanos_per_comparison: f64,
+    threads: usize,
+    memory: usize,
+    details: Record,
+    span: Span,
+) -> Value {
+    let total = nanos_per_comparison * comparisons as f64 / threads.max(1) as f64;
+    let mut plan = record! {
+        "comparisons" => Value::int(comparisons as i64, span),
+        "per_comparison" => Value::duration(nanos_per_comparison as i64, span),
+        "threads" => Value::int(threads as i64, span),
+        "estimated_time" => Value::duration(total as i64, span),
+        "estimated_memory" => Value::filesize(memory as i64, span),
+    };
+    for (col, val) in details {
+        plan.push(col, val);
+    }
+    Value::record(plan, span)
+}
@@ -0,0 +1,61 @@
+// Cheap bounds that rule candidates out before the full algorithm runs.
+
+use crate::lookup;
+use crate::registry::Property;
+
+/// Algorithms whose raw distance is at least the difference in length, so a
+/// pair whose lengths differ by more than the bound can't be within it.
+pub fn length_bounded(algo: &str) -> bool {
+    lookup(algo).is_some_and(|a| a.has(Property::LengthBounded))
+}
+
+/// Algorithms whose raw distance is at least the bag distance, since every
+/// edit changes the character counts of each side by at most one.
+pub fn bag_bounded(algo: &str) -> bool {
+    lookup(algo).is_some_and(|a| a.has(Property::BagBounded))
+}
+
+/// How often each char occurs in a string, sorted by char.
+pub struct Counts(Vec<(char, usize)>);
+
+impl Counts {
+    pub fn new(chars: &[char]) -> Self {
+        let mut sorted = chars.to_vec();
+        sorted.sort_unstable();
+        let mut counts: Vec<(char, usize)> = Vec::new();
+        for c in sorted {
+            match counts.last_mut() {
+                Some((last, n)) if *last == c => *n += 1,
+                _ => counts.push((c, 1)),
+            }
+        }
+        Counts(counts)
+    }
+
+    /// The bag distance: the larger of the number of chars only `self` has
+    /// and the number only `other` has, counting multiplicity. Linear in the
+    /// number of distinct chars, so far cheaper than the DP it rules out.
+    pub fn bag_distance(&self, other: &Counts) -> usize {
+        let (a, b) = (&self.0, &other.0);
+        let (mut i, mut j) = (0, 0);
+        let (mut only_a, mut only_b) = (0, 0);
+        while i < a.len() && j < b.len() {
+            let ((ca, na), (cb, nb)) = (a[i], b[j]);
+            if ca < cb {
+                only_a += na;
+                i += 1;
+            } else if cb < ca {
+                only_b += nb;
+                j += 1;
+            } else {
+                only_a += na.saturating_sub(nb);
+                only_b += nb.saturating_sub(na);
+                i += 1;
+                j += 1;
+            }
+        }
+        only_a += a[i..].iter().map(|(_, n)| n).sum::<usize>();
+        only_b += b[j..].iter().map(|(_, n)| n).sum::<usize>();
+        only_a.max(only_b)
+    }
+}
@@ -0,0 +1,238 @@
+// Preprocessing pipeline: steps run in the order given on every string
+// before it is scored, whatever the mode or algorithm. The common steps
+// also have switches of their own, which run before the listed ones, and
+// `--mask` runs before them all, while ids and times are still whole.
+
+use std::borrow::Cow;
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Spanned};
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::mask::Masks;
+
+pub const STEPS: [&str; 8] = [
+    "strip-invisible",
+    "lowercase",
+    "strip-accents",
+    "strip-punctuation",
+    "collapse-spaces",
+    "trim",
+    "fold-compat",
+    "deleet",
+];
+
+/// Switches that add a step, in the order their steps run.
+const SWITCHES: [(&str, &str); 6] = [
+    ("strip-invisible", "strip-invisible"),
+    ("fold-compat", "fold-compat"),
+    ("trim", "trim"),
+    ("strip-accents", "strip-accents"),
+    ("ignore-case", "lowercase"),
+    ("collapse-whitespace", "collapse-spaces"),
+];
+
+#[derive(Clone, Default)]
+pub struct Pipeline {
+    masks: Option<Masks>,
+    steps: Vec<&'static str>,
+}
+
+impl Pipeline {
+    /// Reads `--mask`, the step switches and `--preprocess`, a list of step
+    /// names.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let mut pipeline = Pipeline {
+            masks: Masks::from_call(call)?,
+            steps: vec![],
+        };
+        for (switch, step) in SWITCHES {
+            if call.has_flag(switch)? {
+                pipeline.steps.push(step);
+            }
+        }
+        let Some(steps) = call.get_flag::<Spanned<Vec<String>>>("preprocess")? else {
+            return Ok(pipeline);
+        };
+        for s in &steps.item {
+            let step = STEPS.iter().find(|step| **step == s).ok_or_else(|| {
+                LabeledError::new("unknown preprocessing step")
+                    .with_label(format!("'{s}' is not a step"), steps.span)
+                    .with_help(format!("valid steps: {}", STEPS.join(", ")))
+            })?;
+            pipeline.steps.push(step);
+        }
+        Ok(pipeline)
+    }
+
+    pub fn apply<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        let s = match &self.masks {
+            Some(masks) => Cow::Owned(masks.apply(s)),
+            None => Cow::Borrowed(s),
+        };
+        self.steps
+            .iter()
+            .fold(s, |s, step| Cow::Owned(run(step, &s)))
+    }
+}
+
+fn run(step: &str, s: &str) -> String {
+    match step {
+        "strip-invisible" => strip_invisible(s),
+        "lowercase" => lowercase(s),
+        "strip-accents" => strip_accents(s),
+        "strip-punctuation" => strip_punctuation(s),
+        "collapse-spaces" => collapse_spaces(s),
+        "trim" => s.trim().to_string(),
+        "fold-compat" => fold_compat(s),
+        _ => deleet(s),
+    }
+}
+
+pub fn lowercase(s: &str) -> String {
+    s.chars().flat_map(char::to_lowercase).collect()
+}
+
+/// Drops diacritics: "é" becomes "e". Letters whose stroke or ligature
+/// Unicode doesn't decompose are spelled out in ASCII, so "Bjørn" becomes
+/// "Bjorn" and "straße" "strasse".
+pub fn strip_accents(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.nfd().filter(|c| !is_combining_mark(*c)) {
+        match c {
+            'ø' => out.push('o'),
+            'Ø' => out.push('O'),
+            'ł' => out.push('l'),
+            'Ł' => out.push('L'),
+            'đ' | 'ð' => out.push('d'),
+            'Đ' | 'Ð' => out.push('D'),
+            'ħ' => out.push('h'),
+            'Ħ' => out.push('H'),
+            'ı' => out.push('i'),
+            'ß' => out.push_str("ss"),
+            'æ' => out.push_str("ae"),
+            'Æ' => out.push_str("AE"),
+            'œ' => out.push_str("oe"),
+            'Œ' => out.push_str("OE"),
+            'þ' => out.push_str("th"),
+            'Þ' => out.push_str("TH"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Keeps letters, digits, whitespace and combining marks.
+pub fn strip_punctuation(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || is_combining_mark(*c))
+        .collect()
+}
+
+/// Squeezes runs of whitespace into one space and trims the ends.
+pub fn collapse_spaces(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Drops characters that take no room: soft hyphens, zero width spaces,
+/// joiners and non-joiners, word joiners, invisible operators and byte
+/// order marks. Copied text picks them up unseen, and they keep strings
+/// that read the same from comparing equal.
+pub fn strip_invisible(s: &str) -> String {
+    s.chars()
+        .filter(|c| {
+            !matches!(
+                c,
+                '\u{AD}' | '\u{180E}' | '\u{200B}'..='\u{200D}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}'
+            )
+        })
+        .collect()
+}
+
+/// NFKC, which spells out compatibility characters the way they read
+/// ("ﬁ" as "fi", "²" as "2", "Ⅻ" as "XII", fullwidth "Ａ" as "A"), then
+/// typographic quotes, dashes and the minus sign as their ASCII forms,
+/// which NFKC keeps.
+pub fn fold_compat(s: &str) -> String {
+    s.nfkc()
+        .map(|c| match c {
+            '\u{2018}' | '\u{2019}' | '\u{201a}' | '\u{201b}' | '\u{2032}' => '\'',
+            '\u{201c}' | '\u{201d}' | '\u{201e}' | '\u{201f}' | '\u{2033}' => '"',
+            '\u{2010}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2015}' | '\u{2212}' => '-',
+            c => c,
+        })
+        .collect()
+}
+
+/// Reads leetspeak back as letters: "h4x0r" becomes "haxor". Digits are
+/// replaced wherever they are, so real numbers change too.
+pub fn deleet(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            '8' => 'b',
+            '9' => 'g',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pipeline(steps: &[&'static str]) -> Pipeline {
+        Pipeline {
+            masks: None,
+            steps: steps.to_vec(),
+        }
+    }
+
+    #[test]
+    fn strips_accents_that_do_not_decompose() {
+        assert_eq!(strip_accents("Bjørn Łódź"), "Bjorn Lodz");
+        assert_eq!(strip_accents("Straße Æther œuvre"), "Strasse AEther oeuvre");
+        assert_eq!(strip_accents("Þórður"), "THordur");
+    }
+
+    #[test]
+    fn strips_precomposed_and_combining_accents_alike() {
+        // "é" precomposed, then "e" followed by a combining acute accent
+        assert_eq!(strip_accents("caf\u{e9}"), strip_accents("cafe\u{301}"));
+        assert_eq!(strip_accents("Ångström"), "Angstrom");
+    }
+
+    #[test]
+    fn strips_invisible_characters() {
+        // a byte order mark, a soft hyphen and a zero width space
+        assert_eq!(
+            strip_invisible("\u{FEFF}co\u{AD}op\u{200B}erate"),
+            "cooperate"
+        );
+    }
+
+    #[test]
+    fn lowercases_beyond_ascii() {
+        assert_eq!(lowercase("ÀÉÎ ΣΑΣ İ"), "àéî σασ i\u{307}");
+    }
+
+    #[test]
+    fn collapses_and_trims_unicode_whitespace() {
+        // no-break, ideographic and em spaces
+        let s = "\u{a0} Bjørn\u{3000}\u{2003} Olsen\t\n";
+        assert_eq!(collapse_spaces(s), "Bjørn Olsen");
+        assert_eq!(run("trim", s), "Bjørn\u{3000}\u{2003} Olsen");
+    }
+
+    #[test]
+    fn normalizes_both_spellings_to_one() {
+        let p = pipeline(&["trim", "strip-accents", "lowercase", "collapse-spaces"]);
+        assert_eq!(p.apply("Bjørn  Olsen "), p.apply("bjorn olsen"));
+    }
+}
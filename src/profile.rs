@@ -0,0 +1,585 @@
+// Named profiles: flag settings stored in a nuon file and applied with
+// `--profile`, so one configuration can be shared across scripts and people.
+//
+// The file is `str_similarity_profiles.nuon` in the nushell config dir, or
+// wherever `$env.config.plugins.str_similarity.profiles` points. It holds a
+// record from profile name to a record of long flag names and their values,
+// e.g. {names: {algorithm: jaro_winkler, preprocess: [lowercase], threshold: 0.85}}.
+//
+// Every other key of `$env.config.plugins.str_similarity` is a default for
+// the flag of that name, dashes or underscores alike, which the command line
+// and the profile override.
+//
+// `--preset` picks one of a few built-in profiles, curated answers to "are
+// these the same thing?" from loose to strict. A saved profile overrides it
+// and may pick it, and the plugin config's defaults fill in after it.
+//
+// `--config` is a whole invocation written down: a record of the same shape
+// as a profile, given inline or kept in a nuon or json file beside the job
+// it describes. It overrides profiles and presets, and may pick them.
+
+use std::path::{Path, PathBuf};
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, Flag, LabeledError, Record, Signature, Span, Spanned, SyntaxShape,
+    Type, Value,
+};
+
+use crate::validate::given;
+use crate::{canonical, counts_tokens, StrSimilarityPlugin};
+use crate::{cascade, checkpoint, custom, merge};
+
+const FILE: &str = "str_similarity_profiles.nuon";
+
+pub const PRESETS: [&str; 3] = ["loose", "standard", "strict"];
+
+/// The settings of a preset, as a profile would hold them.
+fn preset(name: &str, span: Span) -> Option<Record> {
+    let s = |s: &str| Value::string(s, span);
+    let steps = |steps: &[&str]| Value::list(steps.iter().map(|t| s(t)).collect(), span);
+    let mut settings = match name {
+        // word order, spelling and punctuation may differ
+        "loose" => record! {
+            "algorithm" => s("sorensen_dice"),
+            "tokenize" => s("ngram"),
+            "ngram-size" => Value::int(2, span),
+            "preprocess" => steps(&["lowercase", "strip-accents", "strip-punctuation", "collapse-spaces"]),
+            "threshold" => Value::float(0.6, span),
+        },
+        // a typo or two, in any case and accents
+        "standard" => record! {
+            "algorithm" => s("jaro_winkler"),
+            "preprocess" => steps(&["lowercase", "strip-accents", "collapse-spaces"]),
+            "threshold" => Value::float(0.85, span),
+        },
+        // the same but for a slip of the keyboard
+        "strict" => record! {
+            "algorithm" => s("damerau_levenshtein"),
+            "preprocess" => steps(&["trim"]),
+            "threshold" => Value::float(0.95, span),
+        },
+        _ => return None,
+    };
+    // the main command reports the same scale the thresholds are on
+    settings.push("normalize", Value::bool(true, span));
+    settings.push("similarity", Value::bool(true, span));
+    Some(settings)
+}
+
+fn path(engine: &EngineInterface, span: Span) -> Result<PathBuf, LabeledError> {
+    let configured = engine
+        .get_plugin_config()?
+        .and_then(|c| c.get_data_by_key("profiles"));
+    if let Some(p) = configured {
+        let cwd = engine.get_current_dir()?;
+        return Ok(nu_path::expand_path_with(
+            p.coerce_str()?.as_ref(),
+            cwd,
+            true,
+        ));
+    }
+    nu_path::config_dir()
+        .map(|dir| dir.join("nushell").join(FILE).into_std_path_buf())
+        .ok_or_else(|| {
+            LabeledError::new("no place to keep profiles")
+                .with_label("couldn't find the config directory", span)
+                .with_help("set $env.config.plugins.str_similarity.profiles to a file path")
+        })
+}
+
+/// Every stored profile; none if the file doesn't exist yet.
+fn load(path: &Path, span: Span) -> Result<Record, LabeledError> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Record::new()),
+        Err(e) => {
+            return Err(LabeledError::new("can't read profiles")
+                .with_label(format!("{}: {e}", path.display()), span)
+                .with_help("check the file's permissions"))
+        }
+    };
+    nuon::from_nuon(&text, Some(span))
+        .and_then(Value::into_record)
+        .map_err(|e| {
+            LabeledError::new("invalid profiles file")
+                .with_label(format!("{} is not a nuon record", path.display()), span)
+                .with_help("each profile is a record of flag names and values under its name")
+                .with_inner(e)
+        })
+}
+
+fn store(path: &Path, profiles: Record, span: Span) -> Result<(), LabeledError> {
+    let text = nuon::to_nuon(
+        &Value::record(profiles, span),
+        nuon::ToStyle::Spaces(2),
+        Some(span),
+    )?;
+    let written = match path.parent() {
+        Some(dir) => std::fs::create_dir_all(dir).and_then(|_| std::fs::write(path, text)),
+        None => std::fs::write(path, text),
+    };
+    written.map_err(|e| {
+        LabeledError::new("can't save profiles")
+            .with_label(format!("{}: {e}", path.display()), span)
+            .with_help("check the file's permissions")
+    })
+}
+
+/// `call` with the settings of `--config`, of the profile named by
+/// `--profile` and of `--preset`, then the defaults of the plugin config,
+/// added for every flag of `signature` it doesn't set itself, and
+/// `--config` and `--profile` themselves removed. Settings for flags
+/// the command doesn't take are left out, so one profile serves all
+/// commands.
+pub fn apply(
+    plugin: &StrSimilarityPlugin,
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    signature: &Signature,
+) -> Result<EvaluatedCall, LabeledError> {
+    let mut call = call.clone();
+    if let Some(config) = call.get_flag::<Value>("config")? {
+        call.named.retain(|(f, _)| f.item != "config");
+        add_config(engine, &mut call, signature, config)?;
+    }
+    if let Some(name) = call.get_flag::<Spanned<String>>("profile")? {
+        call.named.retain(|(f, _)| f.item != "profile");
+        add_profile(engine, &mut call, signature, name)?;
+    }
+    if let Some(name) = call.get_flag::<Spanned<String>>("preset")? {
+        let Some(mut settings) = preset(&name.item, name.span) else {
+            return Err(LabeledError::new("unknown preset")
+                .with_label(format!("no preset named '{}'", name.item), name.span)
+                .with_help(format!("presets: {}", PRESETS.join(", "))));
+        };
+        // flags the preset's would conflict with win, as given
+        if call.has_flag("distance")? {
+            settings.remove("similarity");
+        }
+        let algorithm = call.get_flag::<String>("algorithm")?;
+        if call.get_flag::<Value>("mode")?.is_some()
+            || algorithm.is_some_and(|a| !counts_tokens(canonical(&a)))
+        {
+            settings.remove("tokenize");
+            settings.remove("ngram-size");
+        }
+        add(&mut call, signature, settings, name.span);
+    }
+    add_defaults(plugin, engine, &mut call, signature)?;
+    Ok(call)
+}
+
+/// Adds `settings` for the flags of `signature` that `call` doesn't set,
+/// labeled with `span`.
+fn add(call: &mut EvaluatedCall, signature: &Signature, settings: Record, span: Span) {
+    for (flag, value) in settings {
+        let taken = signature.named.iter().any(|f| f.long == flag);
+        let given = call.named.iter().any(|(f, _)| f.item == flag);
+        if taken && !given && !clashes(call, &flag) {
+            let value = value.with_span(span);
+            call.named.push((Spanned { item: flag, span }, Some(value)));
+        }
+    }
+}
+
+/// The flags `flag` rejects when `call` has it.
+fn exclusive(call: &EvaluatedCall, flag: &str) -> Vec<&'static str> {
+    match flag {
+        "custom" => custom::exclusive(call),
+        "cascade" => cascade::EXCLUSIVE.to_vec(),
+        "checkpoint" => checkpoint::EXCLUSIVE.to_vec(),
+        "merge" => merge::EXCLUSIVE.to_vec(),
+        _ => vec![],
+    }
+}
+
+/// Whether setting `flag` would clash with a flag `call` has, either one
+/// rejecting the other, so a profile, preset or default leaves it out
+/// rather than fail a call that didn't ask for it.
+fn clashes(call: &EvaluatedCall, flag: &str) -> bool {
+    call.named
+        .iter()
+        .filter(|(f, _)| given(call, &f.item))
+        .any(|(f, _)| {
+            exclusive(call, &f.item).contains(&flag)
+                || exclusive(call, flag).contains(&f.item.as_str())
+        })
+}
+
+fn add_profile(
+    engine: &EngineInterface,
+    call: &mut EvaluatedCall,
+    signature: &Signature,
+    name: Spanned<String>,
+) -> Result<(), LabeledError> {
+    let mut profiles = load(&path(engine, name.span)?, name.span)?;
+    let Some(settings) = profiles.remove(&name.item) else {
+        let known: Vec<&String> = profiles.columns().collect();
+        return Err(LabeledError::new("unknown profile")
+            .with_label(format!("no profile named '{}'", name.item), name.span)
+            .with_help(match known.is_empty() {
+                true => "save one with `str similarity profile save`".to_string(),
+                false => format!(
+                    "saved profiles: {}",
+                    known
+                        .iter()
+                        .map(|s| s.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }));
+    };
+    add(call, signature, settings.into_record()?, name.span);
+    Ok(())
+}
+
+/// Adds the settings of `--config`, a record or the path of a nuon or json
+/// file holding one. Unlike a profile's, every setting has to be a flag of
+/// `signature`, so a job's file can't silently ask for what the command
+/// doesn't do.
+fn add_config(
+    engine: &EngineInterface,
+    call: &mut EvaluatedCall,
+    signature: &Signature,
+    config: Value,
+) -> Result<(), LabeledError> {
+    let span = config.span();
+    let settings = match config {
+        Value::Record { val, .. } => val.into_owned(),
+        path => {
+            let path = nu_path::expand_path_with(
+                path.coerce_str()?.as_ref(),
+                engine.get_current_dir()?,
+                true,
+            );
+            let text = std::fs::read_to_string(&path).map_err(|e| {
+                LabeledError::new("can't read config")
+                    .with_label(format!("{}: {e}", path.display()), span)
+                    .with_help("give the path of a nuon or json file, or the record itself")
+            })?;
+            // json is nuon too
+            nuon::from_nuon(&text, Some(span))
+                .and_then(Value::into_record)
+                .map_err(|e| {
+                    LabeledError::new("invalid config")
+                        .with_label(format!("{} is not a record", path.display()), span)
+                        .with_help("a config is a record of long flag names and their values")
+                        .with_inner(e)
+                })?
+        }
+    };
+    for (flag, value) in settings.iter() {
+        let taken = signature.named.iter().find(|f| f.long == *flag);
+        let Some(taken) = taken.filter(|f| f.long != "config") else {
+            return Err(LabeledError::new("invalid config")
+                .with_label(format!("{} takes no --{flag}", signature.name), span)
+                .with_help("settings are long flag names of the command, without the dashes"));
+        };
+        check_type(
+            taken,
+            value,
+            "invalid config",
+            "fix the setting in the config",
+        )?;
+    }
+    add(call, signature, settings, span);
+    Ok(())
+}
+
+/// Adds the defaults of `$env.config.plugins.str_similarity` for the flags
+/// of `signature` that `call` doesn't set. Keys no command takes and values
+/// of the wrong type are errors, labeled in the config.
+fn add_defaults(
+    plugin: &StrSimilarityPlugin,
+    engine: &EngineInterface,
+    call: &mut EvaluatedCall,
+    signature: &Signature,
+) -> Result<(), LabeledError> {
+    let Some(config) = engine.get_plugin_config()? else {
+        return Ok(());
+    };
+    let Value::Record { val, .. } = config else {
+        return Err(LabeledError::new("invalid plugin config")
+            .with_label("expected a record", config.span())
+            .with_help("$env.config.plugins.str_similarity maps flag names to their defaults"));
+    };
+    let known = flags(plugin);
+    for (key, value) in val.iter().filter(|(k, _)| *k != "profiles") {
+        let flag = key.replace('_', "-");
+        if !known.contains(&flag) {
+            return Err(LabeledError::new("invalid plugin config")
+                .with_label(format!("no command takes --{flag}"), value.span())
+                .with_help("keys are long flag names, with dashes or underscores"));
+        }
+        let Some(taken) = signature.named.iter().find(|f| f.long == flag) else {
+            continue;
+        };
+        check_type(
+            taken,
+            value,
+            "invalid plugin config",
+            "fix the default in $env.config.plugins.str_similarity",
+        )?;
+        if !call.named.iter().any(|(f, _)| f.item == flag) && !clashes(call, &flag) {
+            call.named.push((
+                Spanned {
+                    item: flag,
+                    span: value.span(),
+                },
+                Some(value.clone()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Errors unless `value` fits the argument of `flag`, a bool for a switch,
+/// with `message` and `help`.
+fn check_type(flag: &Flag, value: &Value, message: &str, help: &str) -> Result<(), LabeledError> {
+    let fits = match (&flag.arg, value) {
+        (None | Some(SyntaxShape::Boolean), Value::Bool { .. }) => true,
+        (None | Some(SyntaxShape::Boolean), _) => false,
+        (Some(SyntaxShape::Int), v) => matches!(v, Value::Int { .. }),
+        (Some(SyntaxShape::Number), v) => matches!(v, Value::Int { .. } | Value::Float { .. }),
+        (Some(SyntaxShape::String | SyntaxShape::Filepath), v) => {
+            matches!(v, Value::String { .. })
+        }
+        (Some(SyntaxShape::List(_)), v) => matches!(v, Value::List { .. }),
+        (Some(SyntaxShape::Record(_)), v) => matches!(v, Value::Record { .. }),
+        _ => true,
+    };
+    if fits {
+        return Ok(());
+    }
+    let expected = match &flag.arg {
+        None => "a bool, as it's a switch".to_string(),
+        Some(shape) => format!("a {shape}"),
+    };
+    Err(LabeledError::new(message)
+        .with_label(
+            format!("--{} takes {expected}, not {}", flag.long, value.get_type()),
+            value.span(),
+        )
+        .with_help(help))
+}
+
+/// Long flag names any command of the plugin takes, `--profile` and
+/// `--config` aside.
+fn flags(plugin: &StrSimilarityPlugin) -> Vec<String> {
+    use nu_plugin::Plugin;
+    plugin
+        .commands()
+        .iter()
+        .flat_map(|c| c.signature().named)
+        .map(|f| f.long)
+        .filter(|f| !["profile", "config", "help"].contains(&f.as_str()))
+        .collect()
+}
+
+pub struct StrSimilarityProfileSave;
+
+impl SimplePluginCommand for StrSimilarityProfileSave {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity profile save"
+    }
+
+    fn description(&self) -> &str {
+        "Store flag settings under a name for use with --profile, replacing any profile of that name"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_type(Type::Nothing, Type::Nothing)
+            .required("name", SyntaxShape::String, "Name of the profile")
+            .required(
+                "settings",
+                SyntaxShape::Record(vec![]),
+                "Long flag names and their values, e.g. {algorithm: jaro_winkler, threshold: 0.85}; true turns a switch on",
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![Example {
+            description: "Save the settings used to match people's names",
+            example: "str similarity profile save names {algorithm: jaro_winkler, preprocess: [lowercase strip-accents collapse-spaces], threshold: 0.85}",
+            result: None,
+        }]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let name: Spanned<String> = call.req(0)?;
+        let settings: Value = call.req(1)?;
+        let known = flags(plugin);
+        for (flag, value) in settings.as_record()?.iter() {
+            if !known.contains(flag) {
+                return Err(LabeledError::new("unknown setting")
+                    .with_label(format!("no command takes --{flag}"), value.span())
+                    .with_help("settings are long flag names without the dashes"));
+            }
+        }
+        let path = path(engine, name.span)?;
+        let mut profiles = load(&path, name.span)?;
+        profiles.insert(name.item, settings);
+        store(&path, profiles, call.head)?;
+        Ok(Value::nothing(call.head))
+    }
+}
+
+pub struct StrSimilarityProfileList;
+
+impl SimplePluginCommand for StrSimilarityProfileList {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity profile list"
+    }
+
+    fn description(&self) -> &str {
+        "List the saved profiles and their settings"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_type(Type::Nothing, Type::table())
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![Example {
+            description: "Show the saved profiles",
+            example: "str similarity profile list",
+            result: None,
+        }]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let span = call.head;
+        let profiles = load(&path(engine, span)?, span)?;
+        let rows = profiles
+            .into_iter()
+            .map(|(name, settings)| {
+                Value::record(
+                    record! {
+                        "name" => Value::string(name, span),
+                        "settings" => settings,
+                    },
+                    span,
+                )
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
+
+pub struct StrSimilarityProfileDelete;
+
+impl SimplePluginCommand for StrSimilarityProfileDelete {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity profile delete"
+    }
+
+    fn description(&self) -> &str {
+        "Delete a saved profile"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_type(Type::Nothing, Type::Nothing)
+            .required("name", SyntaxShape::String, "Name of the profile")
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![Example {
+            description: "Delete the profile named names",
+            example: "str similarity profile delete names",
+            result: None,
+        }]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let name: Spanned<String> = call.req(0)?;
+        let path = path(engine, name.span)?;
+        let mut profiles = load(&path, name.span)?;
+        if profiles.remove(&name.item).is_none() {
+            return Err(LabeledError::new("unknown profile")
+                .with_label(format!("no profile named '{}'", name.item), name.span)
+                .with_help("`str similarity profile list` shows the saved ones"));
+        }
+        store(&path, profiles, call.head)?;
+        Ok(Value::nothing(call.head))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_leave_out_flags_the_call_rejects() {
+        let span = Span::test_data();
+        let signature = Signature::build("str similarity join")
+            .named("algorithm", SyntaxShape::String, "", Some('a'))
+            .named("custom", SyntaxShape::Any, "", None)
+            .named("threshold", SyntaxShape::Number, "", Some('t'))
+            .switch("similarity", "", None);
+        let custom = Spanned {
+            item: "custom".to_string(),
+            span,
+        };
+        let mut call = EvaluatedCall {
+            head: span,
+            positional: vec![],
+            named: vec![(custom, Some(Value::test_string("{|a b| 1}")))],
+        };
+        add(
+            &mut call,
+            &signature,
+            preset("standard", span).unwrap(),
+            span,
+        );
+        let flags: Vec<&str> = call.named.iter().map(|(f, _)| f.item.as_str()).collect();
+        assert_eq!(flags, ["custom", "threshold", "similarity"]);
+    }
+
+    #[test]
+    fn profiles_naming_deprecated_algorithms_are_warned() {
+        let span = Span::test_data();
+        let signature = Signature::build("str similarity")
+            .named("algorithm", SyntaxShape::String, "", Some('a'))
+            .switch("strict-names", "", None);
+        let mut call = EvaluatedCall {
+            head: span,
+            positional: vec![],
+            named: vec![],
+        };
+        let settings = record! { "algorithm" => Value::test_string("lcsseq") };
+        add(&mut call, &signature, settings, span);
+        let warnings = crate::aliases::warnings(&call, span).unwrap();
+        assert!(warnings.is_some_and(|w| w.into_list().is_ok_and(|w| w.len() == 1)));
+    }
+}
@@ -0,0 +1,129 @@
+// The algorithm registry: one row per algorithm declaring its names, family,
+// orientation, parameters, properties and revision. Name resolution, `--list`, the
+// unknown-name suggestions and parameter checks all read the table, and the
+// compute layer dispatches on the `Id` it declares, so adding an algorithm
+// is a row here and an arm in `run_algorithm` the compiler asks for.
+
+use crate::Orientation::{self, Distance, Similarity};
+use Property::{BagBounded, Bytes, LengthBounded, Traceback};
+
+/// What the plugin knows about an algorithm besides how it runs.
+pub struct Algorithm {
+    pub id: Id,
+    pub name: &'static str,
+    pub short: &'static str,
+    /// edit, token, sequence, compression, simple, phonetic or hybrid.
+    pub family: &'static str,
+    /// How its raw output runs: a distance is 0 for identical strings.
+    pub orientation: Orientation,
+    /// The `--params` names it reads, beyond the prefix boost every
+    /// algorithm takes, in the order `--list` shows them.
+    pub params: &'static [&'static str],
+    /// What else the plugin can rely on it for.
+    pub properties: &'static [Property],
+    /// The plugin's own revision on top of textdistance's; bump it whenever
+    /// the plugin changes what the algorithm returns, so stored scores of
+    /// the old version can be told apart.
+    pub revision: u32,
+}
+
+/// A guarantee an algorithm gives, for the code that shortcuts or explains
+/// its scores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Property {
+    /// Its raw distance is at least the difference in length.
+    LengthBounded,
+    /// Its raw distance is at least the bag distance, as every edit changes
+    /// the char counts of each side by at most one.
+    BagBounded,
+    /// Its edits can be traced back, for `--detailed` counts and
+    /// `--show-alignment`.
+    Traceback,
+    /// It means something on raw bytes.
+    Bytes,
+}
+
+impl Algorithm {
+    pub fn has(&self, property: Property) -> bool {
+        self.properties.contains(&property)
+    }
+}
+
+macro_rules! registry {
+    ($($id:ident { $($field:ident: $value:expr),* $(,)? }),* $(,)?) => {
+        /// Which algorithm a registry row is.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum Id {
+            $($id),*
+        }
+
+        /// Every algorithm, by name.
+        pub const ALGORITHMS: [Algorithm; [$(Id::$id),*].len()] = [
+            $(Algorithm { id: Id::$id, $($field: $value),* }),*
+        ];
+    };
+}
+
+registry! {
+    Bag { name: "bag", short: "bag", family: "token", orientation: Distance, params: &["multiset"], properties: &[LengthBounded], revision: 1 },
+    Cosine { name: "cosine", short: "cos", family: "token", orientation: Similarity, params: &["multiset"], properties: &[], revision: 1 },
+    DamerauLevenshtein { name: "damerau_levenshtein", short: "dlev", family: "edit", orientation: Distance, params: &[], properties: &[LengthBounded, BagBounded, Traceback], revision: 1 },
+    DoubleMetaphone { name: "double_metaphone", short: "dmph", family: "phonetic", orientation: Similarity, params: &[], properties: &[], revision: 1 },
+    // scores against empty strings were pinned by the empty-string policy
+    EntropyNcd { name: "entropy_ncd", short: "entncd", family: "compression", orientation: Distance, params: &[], properties: &[], revision: 2 },
+    Hamming { name: "hamming", short: "ham", family: "edit", orientation: Distance, params: &[], properties: &[LengthBounded, BagBounded, Traceback, Bytes], revision: 1 },
+    Jaccard { name: "jaccard", short: "jac", family: "token", orientation: Similarity, params: &["multiset"], properties: &[Bytes], revision: 1 },
+    Jaro { name: "jaro", short: "jar", family: "edit", orientation: Similarity, params: &["window"], properties: &[], revision: 1 },
+    JaroWinkler { name: "jaro_winkler", short: "jarw", family: "edit", orientation: Similarity, params: &["window", "prefix_weight", "max_prefix"], properties: &[], revision: 1 },
+    Levenshtein { name: "levenshtein", short: "lev", family: "edit", orientation: Distance, params: &[], properties: &[LengthBounded, BagBounded, Traceback, Bytes], revision: 1 },
+    LcsSeq { name: "longest_common_subsequence", short: "lcsubseq", family: "sequence", orientation: Similarity, params: &[], properties: &[], revision: 1 },
+    LcsStr { name: "longest_common_substring", short: "lcsubstr", family: "sequence", orientation: Similarity, params: &[], properties: &[], revision: 1 },
+    Length { name: "length", short: "len", family: "simple", orientation: Distance, params: &[], properties: &[LengthBounded], revision: 1 },
+    Lig3 { name: "lig3", short: "lig", family: "edit", orientation: Similarity, params: &[], properties: &[], revision: 1 },
+    Mlipns { name: "mlipns", short: "mli", family: "edit", orientation: Similarity, params: &[], properties: &[], revision: 2 },
+    NameScore { name: "name_score", short: "name", family: "hybrid", orientation: Similarity, params: &[], properties: &[], revision: 1 },
+    Nysiis { name: "nysiis", short: "nys", family: "phonetic", orientation: Similarity, params: &[], properties: &[], revision: 1 },
+    Overlap { name: "overlap", short: "olap", family: "token", orientation: Similarity, params: &["multiset"], properties: &[], revision: 1 },
+    Prefix { name: "prefix", short: "pre", family: "simple", orientation: Similarity, params: &["affix_basis"], properties: &[], revision: 1 },
+    RatcliffObershelp { name: "ratcliff_obershelp", short: "rat", family: "sequence", orientation: Similarity, params: &[], properties: &[], revision: 1 },
+    Roberts { name: "roberts", short: "rob", family: "token", orientation: Similarity, params: &["multiset"], properties: &[], revision: 1 },
+    Sift4Common { name: "sift4_common", short: "scom", family: "edit", orientation: Distance, params: &[], properties: &[], revision: 1 },
+    Sift4Simple { name: "sift4_simple", short: "ssim", family: "edit", orientation: Distance, params: &[], properties: &[], revision: 1 },
+    SmithWaterman { name: "smith_waterman", short: "smithw", family: "edit", orientation: Similarity, params: &[], properties: &[Traceback], revision: 1 },
+    SorensenDice { name: "sorensen_dice", short: "soredice", family: "token", orientation: Similarity, params: &["multiset"], properties: &[], revision: 1 },
+    Soundex { name: "soundex", short: "sdx", family: "phonetic", orientation: Similarity, params: &[], properties: &[], revision: 1 },
+    Suffix { name: "suffix", short: "suf", family: "simple", orientation: Similarity, params: &["affix_basis"], properties: &[], revision: 1 },
+    Tversky { name: "tversky", short: "tv", family: "token", orientation: Similarity, params: &["alpha", "beta", "multiset"], properties: &[], revision: 1 },
+    YujianBo { name: "yujian_bo", short: "ybo", family: "edit", orientation: Distance, params: &[], properties: &[], revision: 1 },
+}
+
+/// The algorithm going by `name` or short name, in any case.
+pub fn lookup(name: &str) -> Option<&'static Algorithm> {
+    ALGORITHMS
+        .iter()
+        .find(|a| a.name.eq_ignore_ascii_case(name) || a.short.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_unique() {
+        let mut names: Vec<&str> = ALGORITHMS.iter().flat_map(|a| [a.name, a.short]).collect();
+        names.sort();
+        names.dedup();
+        // bag is its own short name
+        assert_eq!(names.len(), 2 * ALGORITHMS.len() - 1);
+    }
+
+    #[test]
+    fn tracebacks_are_the_aligning_algorithms() {
+        let (a, b): (Vec<char>, Vec<char>) =
+            ("kitten".chars().collect(), "sitten".chars().collect());
+        for algo in &ALGORITHMS {
+            let aligns = crate::edit_ops::path(algo.name, &a, &b, None).is_some();
+            assert_eq!(aligns, algo.has(Traceback), "{}", algo.name);
+        }
+    }
+}
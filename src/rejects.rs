@@ -0,0 +1,86 @@
+// Near misses: pairs scoring just below the threshold, saved to a file so
+// thresholds can be tuned and false negatives caught without rerunning.
+
+use std::path::PathBuf;
+
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{record, LabeledError, Span, Spanned, Value};
+
+use crate::{flag_span, number};
+
+/// How far below the threshold a pair may score to count as a near miss
+/// when `--reject-margin` isn't given.
+const DEFAULT_MARGIN: f64 = 0.1;
+
+pub struct Rejects {
+    path: PathBuf,
+    margin: f64,
+}
+
+impl Rejects {
+    /// Reads `--rejects` and `--reject-margin`; `None` without `--rejects`.
+    pub fn from_call(
+        call: &EvaluatedCall,
+        engine: &EngineInterface,
+    ) -> Result<Option<Self>, LabeledError> {
+        let margin = match call.get_flag::<Spanned<f64>>("reject-margin")? {
+            Some(m) if !(0.0..=1.0).contains(&m.item) => {
+                return Err(LabeledError::new("invalid margin")
+                    .with_label("must be between 0 and 1", m.span)
+                    .with_help("the margin is a difference of normalized similarities"));
+            }
+            Some(m) => m.item,
+            None => DEFAULT_MARGIN,
+        };
+        let Some(path) = call.get_flag::<String>("rejects")? else {
+            if call.has_flag("reject-margin")? {
+                return Err(LabeledError::new("--reject-margin needs --rejects")
+                    .with_label(
+                        "only used for near misses",
+                        flag_span(call, "reject-margin"),
+                    )
+                    .with_help("add --rejects <path> to save the near misses"));
+            }
+            return Ok(None);
+        };
+        let cwd = engine.get_current_dir()?;
+        Ok(Some(Rejects {
+            path: nu_path::expand_path_with(path, cwd, true),
+            margin,
+        }))
+    }
+
+    /// The lowest score of a near miss below `threshold`.
+    pub fn floor(&self, threshold: f64) -> f64 {
+        threshold - self.margin
+    }
+
+    /// Writes the near misses as a nuon table, closest to the threshold first.
+    pub fn save(&self, mut rows: Vec<Value>, span: Span) -> Result<(), LabeledError> {
+        let score = |v: &Value| {
+            v.get_data_by_key("score")
+                .and_then(|s| s.coerce_float().ok())
+                .unwrap_or(0.0)
+        };
+        rows.sort_by(|a, b| score(b).total_cmp(&score(a)));
+        let text = nuon::to_nuon(&Value::list(rows, span), nuon::ToStyle::Raw, Some(span))?;
+        std::fs::write(&self.path, text).map_err(|e| {
+            LabeledError::new("can't save the near misses")
+                .with_label(format!("{}: {e}", self.path.display()), span)
+                .with_help("pick a writable path for --rejects")
+        })
+    }
+}
+
+/// A near miss: the two values, the pair's score and how far it fell short.
+pub fn row(left: Value, right: Value, score: f64, threshold: f64, span: Span) -> Value {
+    Value::record(
+        record! {
+            "left" => left,
+            "right" => right,
+            "score" => number(score, span),
+            "shortfall" => number(threshold - score, span),
+        },
+        span,
+    )
+}
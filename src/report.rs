@@ -0,0 +1,145 @@
+// Markdown and HTML renderings of batch results, to drop into a pull request
+// or a dashboard straight from a pipeline.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Record, Span, Spanned, Value};
+
+const FORMATS: [&str; 2] = ["markdown", "html"];
+
+/// How many of the most questionable results a report lists.
+pub const SUSPICIOUS: usize = 10;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    /// Reads `--report`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        match call.get_flag::<Spanned<String>>("report")? {
+            Some(f) if f.item == "markdown" => Ok(Some(Format::Markdown)),
+            Some(f) if f.item == "html" => Ok(Some(Format::Html)),
+            Some(f) => Err(LabeledError::new("unknown report format")
+                .with_label(format!("'{}' is not a report format", f.item), f.span)
+                .with_help(format!("valid formats: {}", FORMATS.join(", ")))),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A document of headings and tables, built up section by section.
+pub struct Report {
+    format: Format,
+    out: String,
+}
+
+impl Report {
+    pub fn new(format: Format, title: &str) -> Self {
+        let mut report = Report {
+            format,
+            out: String::new(),
+        };
+        report.heading(1, title);
+        report
+    }
+
+    pub fn section(&mut self, title: &str) {
+        self.heading(2, title);
+    }
+
+    fn heading(&mut self, level: usize, title: &str) {
+        let title = self.escape(title);
+        match self.format {
+            Format::Markdown => self.out += &format!("{} {title}\n\n", "#".repeat(level)),
+            Format::Html => self.out += &format!("<h{level}>{title}</h{level}>\n"),
+        }
+    }
+
+    /// Two columns of names and values, e.g. the metrics of a run.
+    pub fn summary(&mut self, record: &Record) {
+        let rows: Vec<Vec<String>> = record
+            .iter()
+            .map(|(name, v)| vec![name.clone(), cell(v)])
+            .collect();
+        self.table(&["", "value"], &rows);
+    }
+
+    /// A table of records, with the columns of the first.
+    pub fn records(&mut self, records: &[Value]) {
+        let Some(Value::Record { val: first, .. }) = records.first() else {
+            return;
+        };
+        let columns: Vec<&str> = first.columns().map(String::as_str).collect();
+        let rows: Vec<Vec<String>> = records
+            .iter()
+            .filter_map(|r| r.as_record().ok())
+            .map(|r| {
+                columns
+                    .iter()
+                    .map(|c| r.get(c).map(cell).unwrap_or_default())
+                    .collect()
+            })
+            .collect();
+        self.table(&columns, &rows);
+    }
+
+    pub fn table(&mut self, columns: &[&str], rows: &[Vec<String>]) {
+        let line = |cells: Vec<String>| match self.format {
+            Format::Markdown => format!("| {} |\n", cells.join(" | ")),
+            Format::Html => format!("<tr>{}</tr>\n", cells.concat()),
+        };
+        let header = columns
+            .iter()
+            .map(|c| match self.format {
+                Format::Markdown => self.escape(c),
+                Format::Html => format!("<th>{}</th>", self.escape(c)),
+            })
+            .collect();
+        let mut table = line(header);
+        if self.format == Format::Markdown {
+            table += &line(vec!["---".into(); columns.len()]);
+        }
+        for row in rows {
+            let cells = row
+                .iter()
+                .map(|c| match self.format {
+                    Format::Markdown => self.escape(c),
+                    Format::Html => format!("<td>{}</td>", self.escape(c)),
+                })
+                .collect();
+            table += &line(cells);
+        }
+        match self.format {
+            Format::Markdown => self.out += &format!("{table}\n"),
+            Format::Html => self.out += &format!("<table>\n{table}</table>\n"),
+        }
+    }
+
+    fn escape(&self, text: &str) -> String {
+        match self.format {
+            Format::Markdown => text.replace('|', "\\|").replace('\n', " "),
+            Format::Html => text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;"),
+        }
+    }
+
+    pub fn into_value(self, span: Span) -> Value {
+        Value::string(self.out, span)
+    }
+}
+
+/// A value as a table cell: scores to three decimals, nothing as blank.
+pub fn cell(v: &Value) -> String {
+    match v {
+        Value::Float { val, .. } => format!("{val:.3}"),
+        Value::Int { val, .. } => val.to_string(),
+        Value::Bool { val, .. } => val.to_string(),
+        Value::String { val, .. } => val.clone(),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,94 @@
+// Seedable random sampling, for estimates that don't need every pair.
+
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Spanned};
+
+/// xorshift64: tiny and fast, and plenty for picking samples.
+pub struct Rng {
+    state: u64,
+    seed: i64,
+}
+
+impl Rng {
+    /// Starts from `state` as is, which must not be zero.
+    pub fn new(state: u64) -> Self {
+        Rng {
+            state,
+            seed: state as i64,
+        }
+    }
+
+    /// Starts from `seed`, as `--seed` gives it.
+    pub fn seeded(seed: i64) -> Self {
+        // splitmix64 spreads small seeds like 1 or 42 over all the bits,
+        // and never leaves the state at zero
+        let mut z = (seed as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng {
+            state: (z ^ (z >> 31)).max(1),
+            seed,
+        }
+    }
+
+    /// Reads `--seed`, so a sample can be repeated; without it every run
+    /// draws a different one, whose seed [`Rng::seed`] tells.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let seed = match call.get_flag::<i64>("seed")? {
+            Some(s) => s,
+            None => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| (d.as_nanos() as u64 & i64::MAX as u64) as i64),
+        };
+        Ok(Rng::seeded(seed))
+    }
+
+    /// The seed to pass to `--seed` to draw the same numbers again.
+    pub fn seed(&self) -> i64 {
+        self.seed
+    }
+
+    pub fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number in `0..n`; `n` must not be zero.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// `n` distinct indices in `0..len` (all of them when `n >= len`), in
+/// increasing order. Floyd's algorithm: `n` draws however large `len` is.
+pub fn indices(rng: &mut Rng, n: usize, len: usize) -> Vec<usize> {
+    if n >= len {
+        return (0..len).collect();
+    }
+    let mut picked = HashSet::with_capacity(n);
+    for j in len - n..len {
+        let t = rng.below(j + 1);
+        if !picked.insert(t) {
+            picked.insert(j);
+        }
+    }
+    let mut picked: Vec<usize> = picked.into_iter().collect();
+    picked.sort_unstable();
+    picked
+}
+
+/// Reads `--sample`, which must be at least one.
+pub fn size(call: &EvaluatedCall) -> Result<Option<usize>, LabeledError> {
+    match call.get_flag::<Spanned<i64>>("sample")? {
+        Some(n) if n.item < 1 => Err(LabeledError::new("invalid sample size")
+            .with_label("need at least one sample", n.span)
+            .with_help("leave out --sample to score everything")),
+        Some(n) => Ok(Some(n.item as usize)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,244 @@
+// `str similarity self-test`: every algorithm run on a built-in table of
+// pairs with the scores the docs promise, so a packager or user can check
+// an installed binary computes them. The scores are the raw ones a plain
+// `str similarity` reports, each with the slack rounding may take.
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{record, Category, Example, LabeledError, Signature, SyntaxShape, Type, Value};
+
+use crate::{algorithm, canonical, compute_tokens, AlgoParams, StrSimilarityPlugin, ALGORITHMS};
+
+/// Golden vectors: algorithm, left, right, expected raw score and the most
+/// the score may be off by. Counts are exact; ratios are given to six
+/// places.
+#[rustfmt::skip]
+const VECTORS: [(&str, &str, &str, f64, f64); 87] = [
+    ("bag", "kitten", "sitting", 3.0, 0.0),
+    ("bag", "nushell", "nutshell", 1.0, 0.0),
+    ("bag", "Robert", "Rupert", 2.0, 0.0),
+    ("cosine", "kitten", "sitting", 0.617213, 1e-6),
+    ("cosine", "nushell", "nutshell", 0.935414, 1e-6),
+    ("cosine", "Robert", "Rupert", 0.666667, 1e-6),
+    ("damerau_levenshtein", "kitten", "sitting", 3.0, 0.0),
+    ("damerau_levenshtein", "nushell", "nutshell", 1.0, 0.0),
+    ("damerau_levenshtein", "Robert", "Rupert", 2.0, 0.0),
+    ("double_metaphone", "kitten", "sitting", 0.0, 0.0),
+    ("double_metaphone", "nushell", "nutshell", 0.0, 0.0),
+    ("double_metaphone", "Robert", "Rupert", 1.0, 0.0),
+    ("entropy_ncd", "kitten", "sitting", 0.101341, 1e-6),
+    ("entropy_ncd", "nushell", "nutshell", 0.0494, 1e-6),
+    ("entropy_ncd", "Robert", "Rupert", 0.092981, 1e-6),
+    ("hamming", "kitten", "sitting", 3.0, 0.0),
+    ("hamming", "nushell", "nutshell", 5.0, 0.0),
+    ("hamming", "Robert", "Rupert", 2.0, 0.0),
+    ("jaccard", "kitten", "sitting", 0.444444, 1e-6),
+    ("jaccard", "nushell", "nutshell", 0.875, 1e-6),
+    ("jaccard", "Robert", "Rupert", 0.5, 1e-6),
+    ("jaro", "kitten", "sitting", 0.746032, 1e-6),
+    ("jaro", "nushell", "nutshell", 0.958333, 1e-6),
+    ("jaro", "Robert", "Rupert", 0.777778, 1e-6),
+    ("jaro_winkler", "kitten", "sitting", 0.746032, 1e-6),
+    ("jaro_winkler", "nushell", "nutshell", 0.966667, 1e-6),
+    ("jaro_winkler", "Robert", "Rupert", 0.8, 1e-6),
+    ("levenshtein", "kitten", "sitting", 3.0, 0.0),
+    ("levenshtein", "nushell", "nutshell", 1.0, 0.0),
+    ("levenshtein", "Robert", "Rupert", 2.0, 0.0),
+    ("longest_common_subsequence", "kitten", "sitting", 4.0, 0.0),
+    ("longest_common_subsequence", "nushell", "nutshell", 7.0, 0.0),
+    ("longest_common_subsequence", "Robert", "Rupert", 4.0, 0.0),
+    ("longest_common_substring", "kitten", "sitting", 3.0, 0.0),
+    ("longest_common_substring", "nushell", "nutshell", 5.0, 0.0),
+    ("longest_common_substring", "Robert", "Rupert", 3.0, 0.0),
+    ("length", "kitten", "sitting", 1.0, 0.0),
+    ("length", "nushell", "nutshell", 1.0, 0.0),
+    ("length", "Robert", "Rupert", 0.0, 0.0),
+    ("lig3", "kitten", "sitting", 0.727273, 1e-6),
+    ("lig3", "nushell", "nutshell", 0.857143, 1e-6),
+    ("lig3", "Robert", "Rupert", 0.8, 1e-6),
+    ("mlipns", "kitten", "sitting", 1.0, 0.0),
+    ("mlipns", "nushell", "nutshell", 0.0, 0.0),
+    ("mlipns", "Robert", "Rupert", 1.0, 0.0),
+    ("name_score", "kitten", "sitting", 0.522222, 1e-6),
+    ("name_score", "nushell", "nutshell", 0.676667, 1e-6),
+    ("name_score", "Robert", "Rupert", 0.86, 1e-6),
+    ("nysiis", "kitten", "sitting", 0.0, 0.0),
+    ("nysiis", "nushell", "nutshell", 0.0, 0.0),
+    ("nysiis", "Robert", "Rupert", 0.0, 0.0),
+    ("overlap", "kitten", "sitting", 0.666667, 1e-6),
+    ("overlap", "nushell", "nutshell", 1.0, 0.0),
+    ("overlap", "Robert", "Rupert", 0.666667, 1e-6),
+    ("prefix", "kitten", "sitting", 0.0, 0.0),
+    ("prefix", "nushell", "nutshell", 2.0, 0.0),
+    ("prefix", "Robert", "Rupert", 1.0, 0.0),
+    ("ratcliff_obershelp", "kitten", "sitting", 8.0, 0.0),
+    ("ratcliff_obershelp", "nushell", "nutshell", 14.0, 0.0),
+    ("ratcliff_obershelp", "Robert", "Rupert", 8.0, 0.0),
+    ("roberts", "kitten", "sitting", 0.576923, 1e-6),
+    ("roberts", "nushell", "nutshell", 0.933333, 1e-6),
+    ("roberts", "Robert", "Rupert", 0.666667, 1e-6),
+    ("sift4_common", "kitten", "sitting", 3.0, 0.0),
+    ("sift4_common", "nushell", "nutshell", 1.0, 0.0),
+    ("sift4_common", "Robert", "Rupert", 2.0, 0.0),
+    ("sift4_simple", "kitten", "sitting", 3.0, 0.0),
+    ("sift4_simple", "nushell", "nutshell", 1.0, 0.0),
+    ("sift4_simple", "Robert", "Rupert", 2.0, 0.0),
+    ("smith_waterman", "kitten", "sitting", 3.0, 0.0),
+    ("smith_waterman", "nushell", "nutshell", 6.0, 0.0),
+    ("smith_waterman", "Robert", "Rupert", 4.0, 0.0),
+    ("sorensen_dice", "kitten", "sitting", 0.615385, 1e-6),
+    ("sorensen_dice", "nushell", "nutshell", 0.933333, 1e-6),
+    ("sorensen_dice", "Robert", "Rupert", 0.666667, 1e-6),
+    ("soundex", "kitten", "sitting", 0.0, 0.0),
+    ("soundex", "nushell", "nutshell", 0.0, 0.0),
+    ("soundex", "Robert", "Rupert", 1.0, 0.0),
+    ("suffix", "kitten", "sitting", 0.0, 0.0),
+    ("suffix", "nushell", "nutshell", 5.0, 0.0),
+    ("suffix", "Robert", "Rupert", 3.0, 0.0),
+    ("tversky", "kitten", "sitting", 0.444444, 1e-6),
+    ("tversky", "nushell", "nutshell", 0.875, 1e-6),
+    ("tversky", "Robert", "Rupert", 0.5, 1e-6),
+    ("yujian_bo", "kitten", "sitting", 0.375, 1e-6),
+    ("yujian_bo", "nushell", "nutshell", 0.125, 1e-6),
+    ("yujian_bo", "Robert", "Rupert", 0.285714, 1e-6),
+];
+
+pub struct StrSimilaritySelfTest;
+
+impl SimplePluginCommand for StrSimilaritySelfTest {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity self-test"
+    }
+
+    fn description(&self) -> &str {
+        "Run every algorithm on built-in pairs with known scores and report which pass"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Test only this algorithm",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .switch(
+                "assert",
+                "Error when any algorithm fails, for scripts and packaging checks",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Check an installed plugin computes the documented scores",
+                example: "str similarity self-test | where status != pass",
+                result: None,
+            },
+            Example {
+                description: "Fail a packaging script if any algorithm is off",
+                example: "str similarity self-test --assert",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let span = call.head;
+        let only = match call.get_flag::<String>("algorithm")? {
+            Some(_) => Some(canonical(&algorithm(call)?)),
+            None => None,
+        };
+        let params = AlgoParams::default();
+        let mut failing = vec![];
+        let rows = ALGORITHMS
+            .iter()
+            .map(|a| a.name)
+            .filter(|a| only.is_none() || only == Some(*a))
+            .map(|a| {
+                let mut passed = 0;
+                let mut failures = vec![];
+                for (_, left, right, expected, tolerance) in VECTORS.iter().filter(|v| v.0 == a) {
+                    let (l, r): (Vec<char>, Vec<char>) =
+                        (left.chars().collect(), right.chars().collect());
+                    let actual = compute_tokens(a, &l, &r, &params).get(false);
+                    if (actual - expected).abs() <= *tolerance {
+                        passed += 1;
+                    } else {
+                        failures.push(Value::record(
+                            record! {
+                                "left" => Value::string(*left, span),
+                                "right" => Value::string(*right, span),
+                                "expected" => Value::float(*expected, span),
+                                "actual" => Value::float(actual, span),
+                            },
+                            span,
+                        ));
+                    }
+                }
+                if !failures.is_empty() {
+                    failing.push(a);
+                }
+                let status = match failures.is_empty() {
+                    true => "pass",
+                    false => "fail",
+                };
+                Value::record(
+                    record! {
+                        "algorithm" => Value::string(a, span),
+                        "status" => Value::string(status, span),
+                        "passed" => Value::int(passed, span),
+                        "failed" => Value::int(failures.len() as i64, span),
+                        "failures" => Value::list(failures, span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+        if call.has_flag("assert")? && !failing.is_empty() {
+            return Err(LabeledError::new("self-test failed")
+                .with_label(
+                    format!("{} computed unexpected scores", failing.join(", ")),
+                    span,
+                )
+                .with_help("run without --assert to see each failing pair"));
+        }
+        Ok(Value::list(rows, span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_algorithm_has_passing_vectors() {
+        let params = AlgoParams::default();
+        for a in ALGORITHMS.map(|a| a.name) {
+            assert!(VECTORS.iter().any(|v| v.0 == a), "no vectors for {a}");
+        }
+        for (a, left, right, expected, tolerance) in VECTORS {
+            let (l, r): (Vec<char>, Vec<char>) = (left.chars().collect(), right.chars().collect());
+            let actual = compute_tokens(a, &l, &r, &params).get(false);
+            assert!(
+                (actual - expected).abs() <= tolerance,
+                "{a} {left} {right}: {actual}"
+            );
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Value,
+};
+
+use crate::format::Format;
+use crate::topk::{Ties, TopK};
+use crate::{algorithm, similarity, AlgoParams, StrSimilarityPlugin};
+
+/// A held candidate: the string and its chars, split once when it is held.
+type Candidate = (String, Vec<char>);
+
+/// Candidate sets kept in plugin memory between calls, by session name.
+#[derive(Default)]
+pub struct Sessions(Mutex<HashMap<String, Arc<Vec<Candidate>>>>);
+
+impl Sessions {
+    fn hold(&self, name: String, candidates: Vec<Candidate>) {
+        let mut sessions = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.insert(name, Arc::new(candidates));
+    }
+
+    /// The candidates held under `name`. Shared, so a query doesn't keep
+    /// the lock while it scans.
+    fn get(&self, name: &str) -> Option<Arc<Vec<Candidate>>> {
+        let sessions = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.get(name).cloned()
+    }
+
+    /// The candidates held under `name`, for other commands to query.
+    pub fn held(&self, name: &Spanned<String>) -> Result<Arc<Vec<Candidate>>, LabeledError> {
+        self.get(&name.item).ok_or_else(|| unknown_session(name))
+    }
+
+    /// Drops the set held under `name`, returning how many sets are left.
+    fn release(&self, name: &str) -> Option<usize> {
+        let mut sessions = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        sessions.remove(name).map(|_| sessions.len())
+    }
+}
+
+pub struct StrSimilaritySession;
+
+impl SimplePluginCommand for StrSimilaritySession {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity session"
+    }
+
+    fn description(&self) -> &str {
+        "Hold a list of candidates in plugin memory and query it by name on later calls"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .named(
+                "hold",
+                SyntaxShape::String,
+                "Keep the input list of strings under this name",
+                None,
+            )
+            .named(
+                "session",
+                SyntaxShape::String,
+                "Rank the candidates held under this name against the input string",
+                None,
+            )
+            .named(
+                "release",
+                SyntaxShape::String,
+                "Forget the candidates held under this name",
+                None,
+            )
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Minimum normalized similarity (0 to 1) for a candidate to be listed",
+                Some('t'),
+            )
+            .named(
+                "top",
+                SyntaxShape::Int,
+                "Number of candidates to list (default: 10)",
+                None,
+            )
+            .named(
+                "ties",
+                SyntaxShape::String,
+                "Which of equally scored candidates come first: first (in the order held, the default), shortest, alphabetical, or all of them even beyond --top",
+                None,
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Hold the names of the commands once",
+                example: "help commands | get name | str similarity session --hold cmds",
+                result: None,
+            },
+            Example {
+                description: "Look up the closest held names",
+                example: "'str simlarity' | str similarity session --session cmds -a jaro_winkler --top 5",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let span = call.head;
+        let format = Format::from_call(call)?;
+        let hold: Option<String> = call.get_flag("hold")?;
+        let session: Option<Spanned<String>> = call.get_flag("session")?;
+        let release: Option<Spanned<String>> = call.get_flag("release")?;
+
+        match (hold, session, release) {
+            (Some(name), None, None) => {
+                let candidates = held_strings(input)?;
+                let count = candidates.len();
+                plugin.sessions.hold(name, candidates);
+                // the held sets live in this process, so it must not be
+                // stopped while it is idle between queries
+                engine.set_gc_disabled(true)?;
+                Ok(Value::int(count as i64, span))
+            }
+            (None, Some(name), None) => {
+                let Some(candidates) = plugin.sessions.get(&name.item) else {
+                    return Err(unknown_session(&name));
+                };
+                let query: Vec<char> = match input {
+                    Value::String { val, .. } => val.chars().collect(),
+                    v => {
+                        return Err(LabeledError::new(format!(
+                            "requires string input, got {}",
+                            v.get_type()
+                        ))
+                        .with_label("Expected a string to look up", span))
+                    }
+                };
+                let algo = algorithm(call)?;
+                let threshold: f64 = call.get_flag("threshold")?.unwrap_or(0.0);
+                let params = AlgoParams::from_call(call)?;
+                let top = match call.get_flag::<Spanned<i64>>("top")? {
+                    Some(k) if k.item < 1 => {
+                        return Err(LabeledError::new("invalid top count")
+                            .with_label("need at least one candidate", k.span)
+                            .with_help("the default is 10 candidates"));
+                    }
+                    Some(k) => k.item as usize,
+                    None => 10,
+                };
+
+                let ties = Ties::from_call(call)?;
+
+                let mut best = TopK::new(top);
+                for (seq, (s, chars)) in candidates.iter().enumerate() {
+                    let score = similarity(&algo, &query, chars, &params);
+                    if score >= threshold {
+                        best.push(score, ties.rank(seq, s), s);
+                    }
+                }
+                let mut best = best.into_sorted();
+                if ties == Ties::All && best.len() == top {
+                    let last = best[top - 1].1;
+                    for (s, chars) in candidates.iter() {
+                        let tied = similarity(&algo, &query, chars, &params) == last;
+                        if tied && !best[..top].iter().any(|(b, _)| std::ptr::eq(*b, s)) {
+                            best.push((s, last));
+                        }
+                    }
+                }
+                let rows = best
+                    .into_iter()
+                    .map(|(s, score)| {
+                        Value::record(
+                            record! {
+                                "value" => Value::string(s.clone(), span),
+                                "score" => format.score(score, span),
+                            },
+                            span,
+                        )
+                    })
+                    .collect();
+                Ok(Value::list(rows, span))
+            }
+            (None, None, Some(name)) => {
+                let Some(left) = plugin.sessions.release(&name.item) else {
+                    return Err(unknown_session(&name));
+                };
+                if left == 0 {
+                    engine.set_gc_disabled(false)?;
+                }
+                Ok(Value::nothing(span))
+            }
+            _ => Err(LabeledError::new("expected exactly one session action")
+                .with_label("pass one of --hold, --session or --release", span)),
+        }
+    }
+}
+
+pub fn held_strings(input: &Value) -> Result<Vec<Candidate>, LabeledError> {
+    let Value::List { vals, .. } = input else {
+        return Err(
+            LabeledError::new(format!("requires list input, got {}", input.get_type()))
+                .with_label("Expected a list of strings to hold", input.span())
+                .with_help("e.g. [apple apricot] | str similarity session --hold fruit"),
+        );
+    };
+    vals.iter()
+        .map(|v| match v {
+            Value::String { val, .. } => Ok((val.clone(), val.chars().collect())),
+            v => Err(
+                LabeledError::new(format!("expected a string, got {}", v.get_type()))
+                    .with_label("not a string", v.span()),
+            ),
+        })
+        .collect()
+}
+
+fn unknown_session(name: &Spanned<String>) -> LabeledError {
+    LabeledError::new("no such session")
+        .with_label(format!("nothing is held under '{}'", name.item), name.span)
+        .with_help("hold candidates first with --hold")
+}
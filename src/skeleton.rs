@@ -0,0 +1,103 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{Category, Example, LabeledError, Signature, Span, SyntaxShape, Value};
+use unicode_security::confusable_detection;
+
+use crate::StrSimilarityPlugin;
+
+pub struct StrSimilaritySkeleton;
+
+impl SimplePluginCommand for StrSimilaritySkeleton {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity skeleton"
+    }
+
+    fn description(&self) -> &str {
+        "Emit the Unicode (UTS #39) confusable skeleton of strings, or check whether two look the same"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .named(
+                "same-skeleton",
+                SyntaxShape::String,
+                "Return whether the input has the same skeleton as this string instead",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Skeleton of an identifier",
+                example: "'paypal' | str similarity skeleton",
+                result: None,
+            },
+            Example {
+                description: "Spot a Cyrillic 'а' posing as a Latin 'a'",
+                example: "'pаypal' | str similarity skeleton --same-skeleton 'paypal'",
+                result: Some(Value::test_bool(true)),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        // skeletons are only meant to be compared with each other, never
+        // shown as text, but emitting them lets them be stored and indexed
+        let other = call
+            .get_flag::<String>("same-skeleton")?
+            .map(|o| skeleton(&o));
+        let transform = |s: &str, span: Span| match &other {
+            Some(o) => Value::bool(skeleton(s) == *o, span),
+            None => Value::string(skeleton(s), span),
+        };
+
+        match input {
+            Value::String { val, .. } => Ok(transform(val, input.span())),
+            Value::List { vals, .. } => vals
+                .iter()
+                .map(|v| match v {
+                    Value::String { val, .. } => Ok(transform(val, v.span())),
+                    v => Err(
+                        LabeledError::new(format!("expected a string, got {}", v.get_type()))
+                            .with_label("not a string", v.span())
+                            .with_help("pass a list of strings"),
+                    ),
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(|vals| Value::list(vals, input.span())),
+            v => Err(
+                LabeledError::new(format!("requires string input, got {}", v.get_type()))
+                    .with_label(
+                        "Expected a string or list of strings from pipeline",
+                        v.span(),
+                    )
+                    .with_help("select a column with `get <column>` first"),
+            ),
+        }
+    }
+}
+
+/// The UTS #39 skeleton: NFD, every confusable mapped to its prototype, NFD
+/// again. Two strings are confusable exactly when their skeletons are equal.
+fn skeleton(s: &str) -> String {
+    confusable_detection::skeleton(s).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilaritySkeleton)
+    }
+}
@@ -0,0 +1,73 @@
+// `--stats`: counts of the work a join did for its rows, to check that the
+// exact-match index, the prefilters and the cache take work off the
+// algorithm on real data. Nushell's pipeline metadata has no room for
+// custom fields, so the rows come back in a record beside the counts.
+
+use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{record, LabeledError, Span, Value};
+
+/// Shared by the threads of a run, which add to it as they go.
+#[derive(Default)]
+pub struct Stats {
+    comparisons: AtomicUsize,
+    cache_hits: AtomicUsize,
+    exact_matches: AtomicUsize,
+    prefilter_rejections: AtomicUsize,
+    peak_candidates: AtomicUsize,
+}
+
+impl Stats {
+    /// `None` without `--stats`.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        Ok(call.has_flag("stats")?.then(Stats::default))
+    }
+
+    /// A pair scored by the algorithm.
+    pub fn compared(&self) {
+        self.comparisons.fetch_add(1, Relaxed);
+    }
+
+    /// Pairs whose scores came from the cache.
+    pub fn cache_hits(&self, hits: usize) {
+        self.cache_hits.fetch_add(hits, Relaxed);
+    }
+
+    /// A row matched through the exact-match index, without a search.
+    pub fn exact_match(&self) {
+        self.exact_matches.fetch_add(1, Relaxed);
+    }
+
+    /// A row searched among `candidates` rows, the prefilters having ruled
+    /// out `rejected` more.
+    pub fn searched(&self, candidates: usize, rejected: usize) {
+        self.peak_candidates.fetch_max(candidates, Relaxed);
+        self.prefilter_rejections.fetch_add(rejected, Relaxed);
+    }
+
+    fn record(&self, span: Span) -> Value {
+        let int = |n: &AtomicUsize| Value::int(n.load(Relaxed) as i64, span);
+        Value::record(
+            record! {
+                "comparisons" => int(&self.comparisons),
+                "cache_hits" => int(&self.cache_hits),
+                "exact_matches" => int(&self.exact_matches),
+                "prefilter_rejections" => int(&self.prefilter_rejections),
+                "peak_candidates" => int(&self.peak_candidates),
+            },
+            span,
+        )
+    }
+}
+
+/// `output` with the `stats` beside it: a record of the `rows` and the
+/// `stats`, or for a run cut short its record with the `stats` added.
+pub fn attach(output: Value, stats: &Stats, span: Span) -> Value {
+    let mut record = match output {
+        Value::Record { .. } => output.into_record().unwrap_or_default(),
+        rows => record! { "rows" => rows },
+    };
+    record.push("stats", stats.record(span));
+    Value::record(record, span)
+}
@@ -0,0 +1,79 @@
+// ANSI coloring of score cells in batch output.
+
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{LabeledError, Span, Spanned, Value};
+
+use crate::format::Format;
+
+const GREEN: &str = "\x1b[32m";
+const AMBER: &str = "\x1b[33m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+/// Score bands from `--style-threshold [good fair]`.
+pub struct Bands {
+    good: f64,
+    fair: f64,
+}
+
+impl Bands {
+    /// `None` unless `--style-threshold` was given and colors are enabled.
+    pub fn from_call(
+        call: &EvaluatedCall,
+        engine: &EngineInterface,
+    ) -> Result<Option<Self>, LabeledError> {
+        let Some(bands) = call.get_flag::<Spanned<Vec<f64>>>("style-threshold")? else {
+            return Ok(None);
+        };
+        let (good, fair) = match bands.item[..] {
+            [good, fair] if good >= fair => (good, fair),
+            [good] => (good, good),
+            _ => {
+                return Err(LabeledError::new("invalid style thresholds")
+                    .with_label("expected [good fair] with good >= fair", bands.span)
+                    .with_help("e.g. --style-threshold [0.9 0.7]"));
+            }
+        };
+        if !color_enabled(call, engine)? {
+            return Ok(None);
+        }
+        Ok(Some(Bands { good, fair }))
+    }
+
+    /// The score, shown as `shown`, as a string colored by its band.
+    pub fn paint(&self, score: f64, shown: f64, span: Span) -> Value {
+        let color = if score >= self.good {
+            GREEN
+        } else if score >= self.fair {
+            AMBER
+        } else {
+            RED
+        };
+        Value::string(format!("{color}{shown}{RESET}"), span)
+    }
+}
+
+/// Whether styled output may use ANSI codes: not with `--plain`, not when
+/// `NO_COLOR` is set to anything non-empty (see no-color.org), and only when
+/// Nushell's `use_ansi_coloring` is on. Every command that colors its output
+/// goes through here.
+pub fn color_enabled(call: &EvaluatedCall, engine: &EngineInterface) -> Result<bool, LabeledError> {
+    if call.has_flag("plain")? {
+        return Ok(false);
+    }
+    let no_color = engine
+        .get_env_var("NO_COLOR")?
+        .is_some_and(|v| v.coerce_string().is_ok_and(|s| !s.is_empty()));
+    if no_color {
+        return Ok(false);
+    }
+    Ok(engine.get_config()?.use_ansi_coloring)
+}
+
+/// A score cell, colored when `bands` is set.
+pub fn score(val: f64, bands: Option<&Bands>, format: &Format, span: Span) -> Value {
+    match bands {
+        Some(b) => b.paint(val, format.value(val), span),
+        None => format.score(val, span),
+    }
+}
@@ -0,0 +1,173 @@
+// Catalog of the maximal common substrings of two inputs.
+//
+// Both inputs are concatenated around a separator and the suffix array of
+// the result is built together with its LCP array. Every run of suffixes
+// sharing a prefix of some length (an LCP interval) is a repeated substring;
+// it is common when the run holds suffixes of both inputs, and maximal when
+// it can't be extended to the left either, i.e. not every occurrence in both
+// inputs is preceded by the same char. Walking the intervals bottom up finds
+// them all in linear time after the sort.
+
+/// A substring found in both inputs.
+pub struct Common {
+    /// Length in chars.
+    pub len: usize,
+    /// Char offset of its first occurrence in the first input.
+    pub left_start: usize,
+    /// Char offset of its first occurrence in the second input.
+    pub right_start: usize,
+    pub left_count: usize,
+    pub right_count: usize,
+}
+
+/// The chars preceding the occurrences of a substring in one input.
+#[derive(Clone, Copy, PartialEq)]
+enum Prev {
+    None,
+    One(char),
+    /// Different chars, or the start of the input.
+    Many,
+}
+
+#[derive(Clone, Copy)]
+struct Side {
+    count: usize,
+    first: usize,
+    prev: Prev,
+}
+
+impl Side {
+    const EMPTY: Side = Side {
+        count: 0,
+        first: usize::MAX,
+        prev: Prev::None,
+    };
+
+    fn merge(&mut self, other: &Side) {
+        self.count += other.count;
+        self.first = self.first.min(other.first);
+        self.prev = match (self.prev, other.prev) {
+            (Prev::None, p) | (p, Prev::None) => p,
+            (Prev::One(x), Prev::One(y)) if x == y => Prev::One(x),
+            _ => Prev::Many,
+        };
+    }
+}
+
+/// Every maximal substring of at least `min` chars occurring in both `a`
+/// and `b`, longest first.
+pub fn common_substrings(a: &[char], b: &[char], min: usize) -> Vec<Common> {
+    // chars shifted up by one so that 0 can separate the inputs
+    let text: Vec<u32> = a
+        .iter()
+        .map(|&c| c as u32 + 1)
+        .chain([0])
+        .chain(b.iter().map(|&c| c as u32 + 1))
+        .collect();
+    let sa = suffix_array(&text);
+    let lcp = lcp_array(&text, &sa);
+
+    let leaf = |p: usize| {
+        let mut sides = [Side::EMPTY; 2];
+        let (side, pos, input) = match p {
+            p if p < a.len() => (0, p, a),
+            p if p > a.len() => (1, p - a.len() - 1, b),
+            _ => return sides,
+        };
+        sides[side] = Side {
+            count: 1,
+            first: pos,
+            prev: match pos {
+                0 => Prev::Many,
+                _ => Prev::One(input[pos - 1]),
+            },
+        };
+        sides
+    };
+    let merge = |into: &mut [Side; 2], from: &[Side; 2]| {
+        into[0].merge(&from[0]);
+        into[1].merge(&from[1]);
+    };
+
+    let mut found = vec![];
+    let mut stack: Vec<(usize, [Side; 2])> = vec![(0, [Side::EMPTY; 2])];
+    for (i, &p) in sa.iter().enumerate() {
+        let mut carry = leaf(p);
+        let next = lcp.get(i + 1).copied().unwrap_or(0);
+        while let Some(&(len, _)) = stack.last() {
+            if len <= next {
+                break;
+            }
+            let (len, mut sides) = stack.pop().expect("stack is not empty");
+            merge(&mut sides, &carry);
+            let [left, right] = sides;
+            let extensible =
+                matches!((left.prev, right.prev), (Prev::One(x), Prev::One(y)) if x == y);
+            if len >= min && left.count > 0 && right.count > 0 && !extensible {
+                found.push(Common {
+                    len,
+                    left_start: left.first,
+                    right_start: right.first,
+                    left_count: left.count,
+                    right_count: right.count,
+                });
+            }
+            carry = sides;
+        }
+        match stack.last_mut() {
+            Some((len, sides)) if *len == next => merge(sides, &carry),
+            _ => stack.push((next, carry)),
+        }
+    }
+    found.sort_by(|x, y| y.len.cmp(&x.len).then(x.left_start.cmp(&y.left_start)));
+    found
+}
+
+/// Suffix array by prefix doubling: suffixes are sorted by their first 2^k
+/// elements, ranked, and the ranks of pairs give the order on 2^(k+1).
+fn suffix_array(text: &[u32]) -> Vec<usize> {
+    let n = text.len();
+    let mut sa: Vec<usize> = (0..n).collect();
+    let mut rank: Vec<usize> = text.iter().map(|&c| c as usize).collect();
+    let mut next = vec![0; n];
+    let mut k = 1;
+    while k < n {
+        let key = |i: usize| (rank[i], rank.get(i + k).map_or(0, |r| r + 1));
+        sa.sort_unstable_by_key(|&i| key(i));
+        next[sa[0]] = 0;
+        for w in 1..n {
+            next[sa[w]] = next[sa[w - 1]] + usize::from(key(sa[w]) != key(sa[w - 1]));
+        }
+        std::mem::swap(&mut rank, &mut next);
+        if rank[sa[n - 1]] == n - 1 {
+            break;
+        }
+        k *= 2;
+    }
+    sa
+}
+
+/// `lcp[i]` is the length of the common prefix of the suffixes at `sa[i - 1]`
+/// and `sa[i]` (Kasai et al.); `lcp[0]` is 0.
+fn lcp_array(text: &[u32], sa: &[usize]) -> Vec<usize> {
+    let n = text.len();
+    let mut rank = vec![0; n];
+    for (i, &p) in sa.iter().enumerate() {
+        rank[p] = i;
+    }
+    let mut lcp = vec![0; n];
+    let mut h = 0;
+    for p in 0..n {
+        if rank[p] == 0 {
+            h = 0;
+            continue;
+        }
+        let q = sa[rank[p] - 1];
+        while p + h < n && q + h < n && text[p + h] == text[q + h] {
+            h += 1;
+        }
+        lcp[rank[p]] = h;
+        h = h.saturating_sub(1);
+    }
+    lcp
+}
@@ -0,0 +1,139 @@
+// Longest common substring via a suffix automaton.
+//
+// The automaton of the first input is built in linear time, then the second
+// input is streamed through it while tracking the longest run that is still a
+// substring of the first. Unlike the textdistance DP this needs neither
+// O(n * m) time nor memory, so two large log files can be compared.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+struct State<E> {
+    len: usize,
+    link: Option<usize>,
+    next: HashMap<E, usize>,
+}
+
+struct SuffixAutomaton<E> {
+    states: Vec<State<E>>,
+    last: usize,
+}
+
+impl<E: Eq + std::hash::Hash + Copy> SuffixAutomaton<E> {
+    fn new(s: &[E]) -> Self {
+        let mut sam = SuffixAutomaton {
+            states: Vec::with_capacity(2 * s.len() + 1),
+            last: 0,
+        };
+        sam.states.push(State {
+            len: 0,
+            link: None,
+            next: HashMap::new(),
+        });
+        for &e in s {
+            sam.extend(e);
+        }
+        sam
+    }
+
+    fn extend(&mut self, e: E) {
+        let cur = self.states.len();
+        self.states.push(State {
+            len: self.states[self.last].len + 1,
+            link: None,
+            next: HashMap::new(),
+        });
+
+        let mut p = Some(self.last);
+        while let Some(pi) = p {
+            if self.states[pi].next.contains_key(&e) {
+                break;
+            }
+            self.states[pi].next.insert(e, cur);
+            p = self.states[pi].link;
+        }
+
+        match p {
+            None => self.states[cur].link = Some(0),
+            Some(pi) => {
+                let q = self.states[pi].next[&e];
+                if self.states[pi].len + 1 == self.states[q].len {
+                    self.states[cur].link = Some(q);
+                } else {
+                    let clone = self.states.len();
+                    self.states.push(State {
+                        len: self.states[pi].len + 1,
+                        link: self.states[q].link,
+                        next: self.states[q].next.clone(),
+                    });
+                    let mut p = Some(pi);
+                    while let Some(pi) = p {
+                        if self.states[pi].next.get(&e) != Some(&q) {
+                            break;
+                        }
+                        self.states[pi].next.insert(e, clone);
+                        p = self.states[pi].link;
+                    }
+                    self.states[q].link = Some(clone);
+                    self.states[cur].link = Some(clone);
+                }
+            }
+        }
+        self.last = cur;
+    }
+}
+
+/// The range of `b` holding the first longest substring that also occurs in `a`.
+pub fn longest_common_substring<E: Eq + std::hash::Hash + Copy>(a: &[E], b: &[E]) -> Range<usize> {
+    let sam = SuffixAutomaton::new(a);
+    let mut state = 0;
+    let mut len = 0;
+    let mut best = 0..0;
+
+    for (i, e) in b.iter().enumerate() {
+        while state != 0 && !sam.states[state].next.contains_key(e) {
+            state = sam.states[state].link.unwrap_or(0);
+            len = sam.states[state].len;
+        }
+        match sam.states[state].next.get(e) {
+            Some(&next) => {
+                state = next;
+                len += 1;
+            }
+            None => {
+                state = 0;
+                len = 0;
+            }
+        }
+        if len > best.len() {
+            best = (i + 1 - len)..(i + 1);
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_textdistance() {
+        let pairs = [
+            ("kitten", "sitting"),
+            ("nushell", "nutshell"),
+            ("", "abc"),
+            ("abc", ""),
+            ("xabcdy", "zzabcd"),
+            ("abab", "baba"),
+            ("grüße aus köln", "viele grüße"),
+        ];
+        for (a, b) in pairs {
+            let (ca, cb): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+            let range = longest_common_substring(&ca, &cb);
+            assert_eq!(range.len(), textdistance::str::lcsstr(a, b), "{a} {b}");
+            // the range is of `b`, and what it holds occurs in `a`
+            let common: String = cb[range].iter().collect();
+            assert!(a.contains(&common), "{a} {b}");
+        }
+    }
+}
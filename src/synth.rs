@@ -0,0 +1,195 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Value,
+};
+
+use crate::sample::Rng;
+use crate::StrSimilarityPlugin;
+
+const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
+
+pub struct StrSimilaritySynth;
+
+impl SimplePluginCommand for StrSimilaritySynth {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity synth"
+    }
+
+    fn description(&self) -> &str {
+        "Generate pairs of strings with a controlled number of random edits, for benchmarking and calibrating"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .named(
+                "count",
+                SyntaxShape::Int,
+                "Number of pairs to generate (default: 100)",
+                Some('c'),
+            )
+            .named(
+                "noise",
+                SyntaxShape::Int,
+                "Random edits (substitution, insertion, deletion or transposition) applied to each string to get its pair (default: 1)",
+                Some('k'),
+            )
+            .named(
+                "length",
+                SyntaxShape::Int,
+                "Length of the generated strings when none are piped in (default: 8)",
+                None,
+            )
+            .named(
+                "unrelated",
+                SyntaxShape::Number,
+                "Share of pairs (0 to 1) whose second string is unrelated instead, marked as not expected to match (default: 0)",
+                None,
+            )
+            .named(
+                "seed",
+                SyntaxShape::Int,
+                "Seed for the generator, so the same pairs come out every run; every pair holds the seed used",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Generate pairs two edits apart",
+                example: "str similarity synth --count 5 --noise 2 --seed 1",
+                result: None,
+            },
+            Example {
+                description: "Calibrate a threshold on synthetic typos of real names",
+                example: "open names.txt | lines | str similarity synth --noise 1 --unrelated 0.5 --seed 7 | str similarity evaluate -a jaro_winkler --sweep",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let count = at_least(call, "count", 100, 1)?;
+        let noise = at_least(call, "noise", 1, 0)?;
+        let length = at_least(call, "length", 8, 1)?;
+        let unrelated = match call.get_flag::<Spanned<f64>>("unrelated")? {
+            Some(u) if !(0.0..=1.0).contains(&u.item) => {
+                return Err(LabeledError::new("invalid share")
+                    .with_label("must be between 0 and 1", u.span)
+                    .with_help("e.g. --unrelated 0.5 for half non-matching pairs"));
+            }
+            Some(u) => u.item,
+            None => 0.0,
+        };
+        let mut rng = Rng::from_call(call)?;
+
+        let bases: Option<Vec<Vec<char>>> = match input {
+            Value::Nothing { .. } => None,
+            Value::List { vals, .. } if !vals.is_empty() => Some(
+                vals.iter()
+                    .map(|v| v.as_str().map(|s| s.chars().collect()))
+                    .collect::<Result<_, _>>()?,
+            ),
+            v => {
+                return Err(LabeledError::new(format!(
+                    "requires a list of strings, got {}",
+                    v.get_type()
+                ))
+                .with_label("Expected base strings from pipeline", v.span())
+                .with_help(
+                    "pipe in a non-empty list of strings, or nothing to generate random ones",
+                ))
+            }
+        };
+        let base = |rng: &mut Rng| match &bases {
+            Some(b) => b[rng.below(b.len())].clone(),
+            None => (0..length).map(|_| letter(rng)).collect(),
+        };
+
+        let span = call.head;
+        let seed = rng.seed();
+        let rows = (0..count)
+            .map(|_| {
+                let left = base(&mut rng);
+                let is_unrelated = (rng.next() as f64 / u64::MAX as f64) < unrelated;
+                let right = if is_unrelated {
+                    base(&mut rng)
+                } else {
+                    let mut right = left.clone();
+                    for _ in 0..noise {
+                        edit(&mut right, &mut rng);
+                    }
+                    right
+                };
+                Value::record(
+                    record! {
+                        "left" => Value::string(left.iter().collect::<String>(), span),
+                        "right" => Value::string(right.iter().collect::<String>(), span),
+                        "edits" => if is_unrelated {
+                            Value::nothing(span)
+                        } else {
+                            Value::int(noise as i64, span)
+                        },
+                        "expected_match" => Value::bool(!is_unrelated, span),
+                        "seed" => Value::int(seed, span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// An integer flag of at least `min`.
+fn at_least(
+    call: &EvaluatedCall,
+    name: &str,
+    default: usize,
+    min: i64,
+) -> Result<usize, LabeledError> {
+    match call.get_flag::<Spanned<i64>>(name)? {
+        Some(n) if n.item < min => Err(LabeledError::new(format!("invalid --{name}"))
+            .with_label(format!("must be at least {min}"), n.span)
+            .with_help(format!("the default is {default}"))),
+        Some(n) => Ok(n.item as usize),
+        None => Ok(default),
+    }
+}
+
+fn letter(rng: &mut Rng) -> char {
+    ALPHABET[rng.below(ALPHABET.len())] as char
+}
+
+/// One random substitution, insertion, deletion or transposition. Edits a
+/// string is too short for become insertions. The distance can still come
+/// out lower than the number of edits, e.g. when a letter is replaced by
+/// itself or a later edit undoes an earlier one.
+fn edit(s: &mut Vec<char>, rng: &mut Rng) {
+    match (rng.below(4), s.len()) {
+        (0, n) if n > 0 => {
+            let i = rng.below(n);
+            s[i] = letter(rng);
+        }
+        (1, n) if n > 0 => {
+            s.remove(rng.below(n));
+        }
+        (2, n) if n > 1 => {
+            let i = rng.below(n - 1);
+            s.swap(i, i + 1);
+        }
+        (_, n) => {
+            let i = rng.below(n + 1);
+            s.insert(i, letter(rng));
+        }
+    }
+}
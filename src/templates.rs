@@ -0,0 +1,182 @@
+// `str similarity templates`: a Drain-style log pattern miner. Every kind
+// of volatile token `--mask` knows is masked first, so lines that only
+// differ by ids or times are the same line; what's left is clustered by
+// how many word positions lines share, and each cluster becomes a template
+// with `<*>` where its lines differ.
+
+use std::collections::HashMap;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{
+    record, Category, Example, LabeledError, Signature, Spanned, SyntaxShape, Type, Value,
+};
+
+use crate::cluster::greedy;
+use crate::group::distinct;
+use crate::mask::{self, Masks};
+use crate::StrSimilarityPlugin;
+
+/// What a word differing between a template's lines becomes.
+const WILDCARD: &str = "<*>";
+
+pub struct StrSimilarityTemplates;
+
+impl SimplePluginCommand for StrSimilarityTemplates {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity templates"
+    }
+
+    fn description(&self) -> &str {
+        "Infer the templates of log lines, masking numbers, ids, addresses and times, with how many lines each covers"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_types(vec![
+                (Type::List(Box::new(Type::String)), Type::table()),
+                (Type::String, Type::table()),
+            ])
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Share of word positions (0 to 1) a line needs in common with a template's first line to join it (default: 0.5)",
+                Some('t'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Find the kinds of lines in a log, most frequent first",
+                example: "open --raw app.log | lines | str similarity templates",
+                result: None,
+            },
+            Example {
+                description: "Mine templates from a few lines",
+                example: "['user 12 logged in' 'user 7 logged in' 'user 7 logged out'] | str similarity templates -t 0.7",
+                result: Some(Value::test_list(vec![Value::test_record(record! {
+                    "template" => Value::test_string("user <num> logged <*>"),
+                    "count" => Value::test_int(3),
+                    "example" => Value::test_string("user 12 logged in"),
+                })])),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let span = call.head;
+        let threshold = match call.get_flag::<Spanned<f64>>("threshold")? {
+            Some(t) if !(0.0..=1.0).contains(&t.item) => {
+                return Err(LabeledError::new("invalid threshold")
+                    .with_label("must be between 0 and 1", t.span)
+                    .with_help("the threshold is the share of word positions in common"));
+            }
+            Some(t) => t.item,
+            None => 0.5,
+        };
+        let lines: Vec<&str> = match input {
+            Value::List { vals, .. } => {
+                vals.iter().map(|v| v.as_str()).collect::<Result<_, _>>()?
+            }
+            Value::String { val, .. } => val.lines().collect(),
+            v => {
+                return Err(
+                    LabeledError::new(format!("requires strings, got {}", v.get_type()))
+                        .with_label("Expected log lines from pipeline", v.span())
+                        .with_help("e.g. open --raw app.log | lines | str similarity templates"),
+                )
+            }
+        };
+
+        let masks = Masks::of(&mask::KINDS);
+        let masked: Vec<String> = lines.iter().map(|l| masks.apply(l)).collect();
+        let mut example: HashMap<&str, &str> = HashMap::new();
+        for (m, l) in masked.iter().zip(&lines) {
+            example.entry(m).or_insert(l);
+        }
+        // most frequent first, so a template's first line is its usual one
+        let distinct = distinct(masked.iter().map(String::as_str));
+        let words: Vec<Vec<&str>> = distinct
+            .iter()
+            .map(|(s, _)| s.split_whitespace().collect())
+            .collect();
+
+        // lines of different lengths never share a template
+        let mut by_length: Vec<(usize, Vec<usize>)> = vec![];
+        for (i, w) in words.iter().enumerate() {
+            match by_length.iter_mut().find(|(n, _)| *n == w.len()) {
+                Some((_, members)) => members.push(i),
+                None => by_length.push((w.len(), vec![i])),
+            }
+        }
+        let mut templates: Vec<(String, usize, &str)> = vec![];
+        for (_, members) in &by_length {
+            let score = |i: usize, j: usize| shared(&words[members[i]], &words[members[j]]);
+            for cluster in greedy(members.len(), threshold, score) {
+                let lines: Vec<usize> = cluster.iter().map(|&c| members[c]).collect();
+                let count = lines.iter().map(|&l| distinct[l].1).sum();
+                let first = distinct[lines[0]].0;
+                templates.push((template(&words, &lines), count, example[first]));
+            }
+        }
+        // stable, so equally frequent templates keep the order first seen
+        templates.sort_by_key(|t| std::cmp::Reverse(t.1));
+
+        let rows = templates
+            .into_iter()
+            .map(|(template, count, example)| {
+                Value::record(
+                    record! {
+                        "template" => Value::string(template, span),
+                        "count" => Value::int(count as i64, span),
+                        "example" => Value::string(example, span),
+                    },
+                    span,
+                )
+            })
+            .collect();
+        Ok(Value::list(rows, span))
+    }
+}
+
+/// The share of positions where `a` and `b`, of equal length, have the same
+/// word. Two empty lines are the same.
+fn shared(a: &[&str], b: &[&str]) -> f64 {
+    if a.is_empty() {
+        return 1.0;
+    }
+    let same = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    same as f64 / a.len() as f64
+}
+
+/// The words all `lines` share at each position, with a wildcard where
+/// they differ.
+fn template(words: &[Vec<&str>], lines: &[usize]) -> String {
+    let first = &words[lines[0]];
+    (0..first.len())
+        .map(|i| match lines.iter().all(|&l| words[l][i] == first[i]) {
+            true => first[i],
+            false => WILDCARD,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityTemplates)
+    }
+}
@@ -0,0 +1,198 @@
+// Bounded selection of the best-scoring items of a scan.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Spanned};
+
+struct Entry<T, R> {
+    score: f64,
+    rank: R,
+    item: T,
+}
+
+// Ordered so the heap's top is the worst entry kept: the lowest score, and
+// among equal scores the one ranked last.
+impl<T, R: Ord> Ord for Entry<T, R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .score
+            .total_cmp(&self.score)
+            .then(self.rank.cmp(&other.rank))
+    }
+}
+
+impl<T, R: Ord> PartialOrd for Entry<T, R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, R: Ord> PartialEq for Entry<T, R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, R: Ord> Eq for Entry<T, R> {}
+
+/// Keeps the `k` highest-scoring items pushed, in O(k) memory and
+/// O(log k) per push, instead of collecting and sorting everything. Equal
+/// scores are ordered by a rank pushed along with each item.
+pub struct TopK<T, R> {
+    k: usize,
+    heap: BinaryHeap<Entry<T, R>>,
+}
+
+impl<T, R: Ord> TopK<T, R> {
+    pub fn new(k: usize) -> Self {
+        TopK {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    /// Pushes an item that ranks among equal scores by `rank`, lowest first.
+    pub fn push(&mut self, score: f64, rank: R, item: T) {
+        let entry = Entry { score, rank, item };
+        if self.heap.len() < self.k {
+            self.heap.push(entry);
+        } else if matches!(self.heap.peek(), Some(worst) if entry < *worst) {
+            self.heap.pop();
+            self.heap.push(entry);
+        }
+    }
+
+    /// Best first; equal scores by rank.
+    pub fn into_sorted(self) -> Vec<(T, f64)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|e| (e.item, e.score))
+            .collect()
+    }
+}
+
+const TIES: [&str; 4] = ["all", "first", "shortest", "alphabetical"];
+
+/// Which of several equally good candidates come first, and so which one
+/// is the best match.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ties {
+    /// Keep every candidate tied with the last one kept, even past the limit.
+    All,
+    First,
+    Shortest,
+    Alphabetical,
+}
+
+impl Ties {
+    /// Reads `--ties`, which defaults to input order.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let Some(ties) = call.get_flag::<Spanned<String>>("ties")? else {
+            return Ok(Ties::First);
+        };
+        match ties.item.as_str() {
+            "all" => Ok(Ties::All),
+            "first" => Ok(Ties::First),
+            "shortest" => Ok(Ties::Shortest),
+            "alphabetical" => Ok(Ties::Alphabetical),
+            other => Err(LabeledError::new("unknown tie policy")
+                .with_label(format!("'{other}' is not a tie policy"), ties.span)
+                .with_help(format!("valid policies: {}", TIES.join(", ")))),
+        }
+    }
+
+    /// Rank of the `seq`-th candidate `s` among equal scores; input order
+    /// settles whatever the policy leaves tied.
+    pub fn rank(self, seq: usize, s: &str) -> (usize, &str, usize) {
+        match self {
+            Ties::Shortest => (s.chars().count(), "", seq),
+            Ties::Alphabetical => (0, s, seq),
+            Ties::All | Ties::First => (0, "", seq),
+        }
+    }
+}
+
+const SORT_KEYS: [&str; 2] = ["input", "score"];
+
+/// `--sort-by` and `--limit`: which rows of a command's output to keep, so
+/// a huge result is cut down as it is produced rather than afterwards.
+/// `--farthest` keeps the least close instead, for outliers.
+#[derive(Clone, Copy)]
+pub struct Limit {
+    by_score: bool,
+    farthest: bool,
+    rows: Option<usize>,
+}
+
+impl Limit {
+    /// Reads `--sort-by`, which defaults to input order, `--limit` and
+    /// `--farthest`, which sorts by score.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Self, LabeledError> {
+        let farthest = call.has_flag("farthest")?;
+        let by_score = match call.get_flag::<Spanned<String>>("sort-by")? {
+            None => farthest,
+            Some(s) if farthest && s.item == "input" => {
+                return Err(LabeledError::new("--farthest sorts by score")
+                    .with_label("rows would stay in input order", s.span)
+                    .with_help("drop --sort-by input, or --farthest"))
+            }
+            Some(s) => match s.item.as_str() {
+                "input" => false,
+                "score" => true,
+                other => {
+                    return Err(LabeledError::new("unknown sort key")
+                        .with_label(format!("can't sort by '{other}'"), s.span)
+                        .with_help(format!("valid keys: {}", SORT_KEYS.join(", "))))
+                }
+            },
+        };
+        let rows = match call.get_flag::<Spanned<i64>>("limit")? {
+            Some(n) if n.item < 1 => {
+                return Err(LabeledError::new("invalid limit")
+                    .with_label("need at least one row", n.span)
+                    .with_help("leave out --limit to keep every row"));
+            }
+            Some(n) => Some(n.item as usize),
+            None => None,
+        };
+        Ok(Limit {
+            by_score,
+            farthest,
+            rows,
+        })
+    }
+
+    /// The most rows kept when they stay in input order, so the caller can
+    /// stop producing them there.
+    pub fn first(&self) -> Option<usize> {
+        self.rows.filter(|_| !self.by_score)
+    }
+
+    /// The rows kept of `rows`, each with how close its match is, higher
+    /// being closer. Rows without a match sort last, even with
+    /// `--farthest`, and equal scores stay in input order. Only the rows
+    /// kept are held at any time.
+    pub fn keep<T>(&self, rows: impl IntoIterator<Item = (Option<f64>, T)>) -> Vec<T> {
+        let rows = rows.into_iter();
+        let sign = if self.farthest { -1.0 } else { 1.0 };
+        let closeness = |s: Option<f64>| s.map_or(f64::NEG_INFINITY, |s| sign * s);
+        match (self.by_score, self.rows) {
+            (false, n) => rows.take(n.unwrap_or(usize::MAX)).map(|(_, r)| r).collect(),
+            (true, Some(k)) => {
+                let mut top = TopK::new(k);
+                for (seq, (score, row)) in rows.enumerate() {
+                    top.push(closeness(score), seq, row);
+                }
+                top.into_sorted().into_iter().map(|(r, _)| r).collect()
+            }
+            (true, None) => {
+                let mut rows: Vec<_> = rows.collect();
+                rows.sort_by(|(a, _), (b, _)| closeness(*b).total_cmp(&closeness(*a)));
+                rows.into_iter().map(|(_, r)| r).collect()
+            }
+        }
+    }
+}
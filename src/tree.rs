@@ -0,0 +1,118 @@
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand, SimplePluginCommand};
+use nu_protocol::{Category, Example, LabeledError, Signature, SyntaxShape, Value};
+
+use crate::format::Format;
+use crate::tree_edit::{self, Node};
+use crate::StrSimilarityPlugin;
+
+pub struct StrSimilarityTree;
+
+impl SimplePluginCommand for StrSimilarityTree {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity tree"
+    }
+
+    fn description(&self) -> &str {
+        "Tree edit distance between two nested records or lists"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .required("other", SyntaxShape::Any, "Value to compare the input to")
+            .switch(
+                "normalize",
+                "Normalize by the size of the larger tree",
+                Some('n'),
+            )
+            .named(
+                "digits",
+                SyntaxShape::Int,
+                "Round scores to this many decimal places",
+                None,
+            )
+            .switch(
+                "percent",
+                "Report scores as percentages, 0 to 100; for normalized scores",
+                None,
+            )
+            .switch(
+                "float-scores",
+                "Report every score as a float, whole ones included, rather than whole ones as ints",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description: "Structural distance between two JSON documents",
+                example: "open a.json | str similarity tree (open b.json)",
+                result: None,
+            },
+            Example {
+                description: "One renamed field",
+                example:
+                    "{name: 'nu', tags: [a b]} | str similarity tree {title: 'nu', tags: [a b]}",
+                result: Some(Value::test_int(1)),
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _config: &StrSimilarityPlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: &Value,
+    ) -> Result<Value, LabeledError> {
+        let format = Format::from_call(call)?;
+        let other: Value = call.req(0)?;
+        let a = tree(input);
+        let b = tree(&other);
+        let dist = tree_edit::distance(&a, &b) as f64;
+
+        let val = if call.has_flag("normalize")? {
+            dist / a.size().max(b.size()) as f64
+        } else {
+            dist
+        };
+        Ok(format.score(val, input.span()))
+    }
+}
+
+/// Records become a `{}` node with one child per field, labelled with the
+/// field name and holding the field's value; lists become a `[]` node with
+/// one child per item, and everything else a leaf labelled with its text.
+fn tree(value: &Value) -> Node<String> {
+    let (label, children) = match value {
+        Value::Record { val, .. } => (
+            "{}".to_string(),
+            val.iter()
+                .map(|(k, v)| Node {
+                    label: k.clone(),
+                    children: vec![tree(v)],
+                })
+                .collect(),
+        ),
+        Value::List { vals, .. } => ("[]".to_string(), vals.iter().map(tree).collect()),
+        v => (
+            v.coerce_string()
+                .unwrap_or_else(|_| v.get_type().to_string()),
+            vec![],
+        ),
+    };
+    Node { label, children }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityTree)
+    }
+}
@@ -0,0 +1,99 @@
+// Zhang-Shasha ordered tree edit distance with unit costs.
+//
+// Nodes are numbered in post-order. For every pair of "keyroots" (nodes
+// that are the highest node with their leftmost leaf) the forest distance
+// between their subtrees is filled in, reusing the tree distances of smaller
+// subtrees computed before. Runs in O(n1 * n2 * min(depth, leaves)^2).
+
+use std::collections::HashSet;
+
+pub struct Node<L> {
+    pub label: L,
+    pub children: Vec<Node<L>>,
+}
+
+impl<L> Node<L> {
+    pub fn size(&self) -> usize {
+        1 + self.children.iter().map(Node::size).sum::<usize>()
+    }
+}
+
+/// A tree in post-order, with the leftmost leaf descendant of every node.
+struct Flat<'a, L> {
+    labels: Vec<&'a L>,
+    lmd: Vec<usize>,
+}
+
+impl<'a, L> Flat<'a, L> {
+    fn new(root: &'a Node<L>) -> Self {
+        let mut flat = Flat {
+            labels: vec![],
+            lmd: vec![],
+        };
+        flat.push(root);
+        flat
+    }
+
+    fn push(&mut self, node: &'a Node<L>) -> usize {
+        let mut leftmost = None;
+        for child in &node.children {
+            let c = self.push(child);
+            leftmost.get_or_insert(self.lmd[c]);
+        }
+        let idx = self.labels.len();
+        self.labels.push(&node.label);
+        self.lmd.push(leftmost.unwrap_or(idx));
+        idx
+    }
+
+    /// Nodes that have no ancestor sharing their leftmost leaf, ascending.
+    fn keyroots(&self) -> Vec<usize> {
+        let mut seen = HashSet::new();
+        let mut roots: Vec<usize> = (0..self.lmd.len())
+            .rev()
+            .filter(|&i| seen.insert(self.lmd[i]))
+            .collect();
+        roots.reverse();
+        roots
+    }
+}
+
+/// Minimum number of node insertions, deletions and relabelings turning
+/// `a` into `b`.
+pub fn distance<L: Eq>(a: &Node<L>, b: &Node<L>) -> usize {
+    let t1 = Flat::new(a);
+    let t2 = Flat::new(b);
+    let (n1, n2) = (t1.labels.len(), t2.labels.len());
+    let mut td = vec![vec![0; n2]; n1];
+
+    for &i in &t1.keyroots() {
+        for &j in &t2.keyroots() {
+            let (li, lj) = (t1.lmd[i], t2.lmd[j]);
+            let mut fd = vec![vec![0; j - lj + 2]; i - li + 2];
+            for x in 1..fd.len() {
+                fd[x][0] = fd[x - 1][0] + 1;
+            }
+            for y in 1..fd[0].len() {
+                fd[0][y] = fd[0][y - 1] + 1;
+            }
+            for x in li..=i {
+                for y in lj..=j {
+                    let (xi, yj) = (x - li + 1, y - lj + 1);
+                    let delete = fd[xi - 1][yj] + 1;
+                    let insert = fd[xi][yj - 1] + 1;
+                    if t1.lmd[x] == li && t2.lmd[y] == lj {
+                        // both prefixes are whole subtrees
+                        let relabel =
+                            fd[xi - 1][yj - 1] + usize::from(t1.labels[x] != t2.labels[y]);
+                        fd[xi][yj] = delete.min(insert).min(relabel);
+                        td[x][y] = fd[xi][yj];
+                    } else {
+                        let subtree = fd[t1.lmd[x] - li][t2.lmd[y] - lj] + td[x][y];
+                        fd[xi][yj] = delete.min(insert).min(subtree);
+                    }
+                }
+            }
+        }
+    }
+    td[n1 - 1][n2 - 1]
+}
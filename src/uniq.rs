@@ -0,0 +1,273 @@
+// `str similarity uniq`: a fuzzy `uniq` keeping the first of each run of
+// near-duplicates, for log-style data where lines differ only by a
+// timestamp or a counter. Every element is compared with those kept so
+// far, so the output streams in input order while memory grows only with
+// what's kept; `--window` bounds both to the last few kept, for endless
+// streams.
+
+use std::collections::VecDeque;
+
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, IntoInterruptiblePipelineData, LabeledError, PipelineData, Signature,
+    Spanned, SyntaxShape, Type, Value,
+};
+
+use crate::preprocess::Pipeline;
+use crate::profile;
+use crate::{algorithm, similarity, AlgoParams, StrSimilarityPlugin};
+
+pub struct StrSimilarityUniq;
+
+impl PluginCommand for StrSimilarityUniq {
+    type Plugin = StrSimilarityPlugin;
+
+    fn name(&self) -> &str {
+        "str similarity uniq"
+    }
+
+    fn description(&self) -> &str {
+        "Drop the elements of a list similar enough to one kept before them, keeping first occurrences in order"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(PluginCommand::name(self))
+            .input_output_types(vec![
+                (
+                    Type::List(Box::new(Type::String)),
+                    Type::List(Box::new(Type::Any)),
+                ),
+                (Type::String, Type::List(Box::new(Type::Any))),
+            ])
+            .named(
+                "algorithm",
+                SyntaxShape::String,
+                "Name of the algorithm to compute",
+                Some('a'),
+            )
+            .switch(
+                "strict-names",
+                "Error on a deprecated algorithm name instead of resolving it to the algorithm it stands for",
+                None,
+            )
+            .named(
+                "threshold",
+                SyntaxShape::Number,
+                "Normalized similarity (0 to 1) from which an element duplicates one kept before it (default: 0.8)",
+                Some('t'),
+            )
+            .named(
+                "window",
+                SyntaxShape::Int,
+                "Only compare each element with the last this many kept, for runs of near-identical consecutive lines (default: all of them)",
+                Some('w'),
+            )
+            .named(
+                "jaro-window",
+                SyntaxShape::Int,
+                "Match window for jaro and jaro_winkler (default: half the longer length minus one)",
+                None,
+            )
+            .named(
+                "multiset",
+                SyntaxShape::Boolean,
+                "Whether bag, cosine, jaccard, overlap, roberts, sorensen_dice and tversky count repeated tokens (default: true); false compares sets of distinct tokens",
+                None,
+            )
+            .named(
+                "prefix-boost",
+                SyntaxShape::List(Box::new(SyntaxShape::Number)),
+                "Winkler-style bonus for a shared prefix on any algorithm's normalized score: [weight max-length], the length defaulting to 4",
+                None,
+            )
+            .named(
+                "params",
+                SyntaxShape::Record(vec![]),
+                "Algorithm parameters as a record, e.g. {window: 3 multiset: false}; --list shows each algorithm's",
+                None,
+            )
+            .named(
+                "profile",
+                SyntaxShape::String,
+                "Take any flag not given here from the settings saved under this name with `str similarity profile save`",
+                None,
+            )
+            .named(
+                "config",
+                SyntaxShape::OneOf(vec![SyntaxShape::Record(vec![]), SyntaxShape::Filepath]),
+                "Take any flag not given here from this record of long flag names and values, or from the nuon or json file holding one",
+                None,
+            )
+            .named(
+                "preset",
+                SyntaxShape::String,
+                "Take any flag not given here or by --profile from a built-in preset: loose, standard or strict",
+                None,
+            )
+            .named(
+                "mask",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Replace volatile tokens in both strings with placeholders before anything else: uuids, timestamps, ips, hex, numbers",
+                None,
+            )
+            .named(
+                "mask-pattern",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Also replace matches of these regular expressions with a placeholder, like --mask",
+                None,
+            )
+            .named(
+                "preprocess",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "Steps run in order on both strings before anything else: lowercase, strip-accents, strip-punctuation, collapse-spaces, trim, fold-compat, deleet",
+                None,
+            )
+            .switch(
+                "ignore-case",
+                "Lowercase both strings before scoring, like --preprocess [lowercase]",
+                None,
+            )
+            .switch(
+                "trim",
+                "Trim whitespace from both ends of both strings before scoring",
+                None,
+            )
+            .switch(
+                "collapse-whitespace",
+                "Squeeze runs of whitespace in both strings into one space before scoring",
+                None,
+            )
+            .switch(
+                "strip-accents",
+                "Drop diacritics from both strings before scoring, reading letters like ø and ł as o and l",
+                None,
+            )
+            .switch(
+                "strip-invisible",
+                "Drop soft hyphens, zero width spaces and joiners and byte order marks from both strings before scoring",
+                None,
+            )
+            .switch(
+                "fold-compat",
+                "Compare what a human reads: apply NFKC to both strings, turning ligatures, superscripts and roman numerals into plain letters and digits, and fold typographic quotes and dashes",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![
+            Example {
+                description:
+                    "Drop log lines that only differ from an earlier one by a number or two",
+                example: "open --raw app.log | lines | str similarity uniq -a jaro_winkler -t 0.9",
+                result: None,
+            },
+            Example {
+                description: "Keep the first spelling of each name",
+                example:
+                    "[nushell Nushell nutshell bash] | str similarity uniq --ignore-case -t 0.85",
+                result: Some(Value::test_list(vec![
+                    Value::test_string("nushell"),
+                    Value::test_string("bash"),
+                ])),
+            },
+            Example {
+                description: "Collapse runs of near-identical lines of a live log",
+                example: "tail -f app.log | lines | str similarity uniq --window 5 -t 0.9",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &StrSimilarityPlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let call = &profile::apply(plugin, engine, call, &PluginCommand::signature(self))?;
+        let algo = algorithm(call)?;
+        let threshold = match call.get_flag::<Spanned<f64>>("threshold")? {
+            Some(t) if !(0.0..=1.0).contains(&t.item) => {
+                return Err(LabeledError::new("invalid threshold")
+                    .with_label("must be between 0 and 1", t.span)
+                    .with_help("thresholds are normalized similarities, whatever the algorithm"));
+            }
+            Some(t) => t.item,
+            None => 0.8,
+        };
+        let window = match call.get_flag::<Spanned<i64>>("window")? {
+            Some(w) if w.item < 1 => {
+                return Err(LabeledError::new("invalid window")
+                    .with_label("need at least one kept element", w.span)
+                    .with_help("leave out --window to compare with every element kept"));
+            }
+            Some(w) => w.item as usize,
+            None => usize::MAX,
+        };
+        let params = AlgoParams::from_call(call)?;
+        let pipeline = Pipeline::from_call(call)?;
+        let span = call.head;
+        let signals = engine.signals().clone();
+
+        // the elements kept so far, or the last `window` of them, as compared
+        let mut kept: VecDeque<Vec<char>> = VecDeque::new();
+        let mut keep = move |element: Value| -> Option<Value> {
+            let text = match element.as_str() {
+                Ok(text) => text,
+                Err(e) => return Some(Value::error(e, element.span())),
+            };
+            let chars: Vec<char> = pipeline.apply(text).chars().collect();
+            if kept
+                .iter()
+                .any(|k| similarity(&algo, k, &chars, &params) >= threshold)
+            {
+                return None;
+            }
+            if kept.len() == window {
+                kept.pop_front();
+            }
+            kept.push_back(chars);
+            Some(element)
+        };
+        match input {
+            // raw text is split into lines as it arrives
+            PipelineData::ByteStream(stream, _) => {
+                let Some(lines) = stream.lines() else {
+                    return Ok(PipelineData::Empty);
+                };
+                Ok(lines
+                    .filter_map(move |line| match line {
+                        Ok(line) => keep(Value::string(line, span)),
+                        Err(e) => Some(Value::error(e, span)),
+                    })
+                    .into_pipeline_data(span, signals))
+            }
+            PipelineData::Value(Value::String { .. }, _) => {
+                let text = input.into_value(span)?;
+                let lines: Vec<Value> = text
+                    .as_str()?
+                    .lines()
+                    .map(|l| Value::string(l, text.span()))
+                    .collect();
+                Ok(lines
+                    .into_iter()
+                    .filter_map(keep)
+                    .into_pipeline_data(span, signals))
+            }
+            input => Ok(input.flat_map(keep, &signals)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() -> Result<(), nu_protocol::ShellError> {
+        crate::test_examples(&StrSimilarityUniq)
+    }
+}
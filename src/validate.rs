@@ -0,0 +1,305 @@
+// Flags that only mean something alongside others: `str similarity` errors
+// on a combination where one would be silently ignored, so a typo'd or
+// misremembered option doesn't pass for a score it had no part in. Flags
+// whose values need parsing check their own companions where they're read.
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Value};
+
+use crate::registry::{Id, Property};
+use crate::{
+    algorithm, counts_tokens, flag_span, is_auto, lookup, mode, tokenize, Algorithm, Mode,
+};
+
+/// What the rules look at, read from the call once.
+struct Context {
+    /// The algorithm; `None` with `-a auto`, which picks one per pair, and
+    /// a custom one.
+    algorithm: Option<&'static Algorithm>,
+    all: bool,
+    detailed: bool,
+    show_alignment: bool,
+    mode: Option<Mode>,
+}
+
+impl Context {
+    /// Whether the one algorithm is among `ids`.
+    fn uses(&self, ids: &[Id]) -> bool {
+        !self.all && self.algorithm.is_some_and(|a| ids.contains(&a.id))
+    }
+
+    /// Whether one of `ids` scores, alone or among all algorithms.
+    fn scores_with(&self, ids: &[Id]) -> bool {
+        self.all || self.uses(ids)
+    }
+
+    /// Whether the one algorithm has `property`.
+    fn has(&self, property: Property) -> bool {
+        !self.all && self.algorithm.is_some_and(|a| a.has(property))
+    }
+
+    /// Whether a token-counting algorithm scores, alone or among all.
+    fn counts_tokens(&self) -> bool {
+        self.all || self.algorithm.is_some_and(|a| counts_tokens(a.name))
+    }
+}
+
+/// A flag and when it has a use.
+struct Rule {
+    flag: &'static str,
+    applies: fn(&Context) -> bool,
+    message: &'static str,
+    label: &'static str,
+    help: &'static str,
+}
+
+const TOKEN_ALGORITHMS: &str =
+    "use bag, cosine, jaccard, overlap, roberts, sorensen_dice or tversky";
+
+const RULES: [Rule; 19] = [
+    Rule {
+        flag: "jaro-window",
+        applies: |c| c.scores_with(&[Id::Jaro, Id::JaroWinkler]),
+        message: "--jaro-window needs jaro or jaro_winkler",
+        label: "only jaro and jaro_winkler have a match window",
+        help: "add -a jaro or -a jaro_winkler",
+    },
+    Rule {
+        flag: "multiset",
+        applies: Context::counts_tokens,
+        message: "--multiset needs a token-counting algorithm",
+        label: "only token-counting algorithms count repeats",
+        help: TOKEN_ALGORITHMS,
+    },
+    Rule {
+        flag: "affix-basis",
+        applies: |c| c.scores_with(&[Id::Prefix, Id::Suffix]),
+        message: "--affix-basis needs prefix or suffix",
+        label: "only prefix and suffix are normalized by an affix length",
+        help: "add -a prefix or -a suffix",
+    },
+    Rule {
+        flag: "pad",
+        applies: |c| c.uses(&[Id::Hamming]),
+        message: "--pad needs -a hamming",
+        label: "only hamming compares position by position",
+        help: "add -a hamming",
+    },
+    Rule {
+        flag: "show",
+        applies: |c| c.uses(&[Id::Prefix, Id::Suffix]),
+        message: "--show needs a prefix or suffix algorithm",
+        label: "only prefix and suffix have a shared string to show",
+        help: "add -a prefix or -a suffix",
+    },
+    Rule {
+        flag: "show-alignment",
+        applies: |c| c.has(Property::Traceback),
+        message: "--show-alignment needs an aligning algorithm",
+        label: "only levenshtein, damerau_levenshtein, hamming and smith_waterman align the strings",
+        help: "pick one of them with -a",
+    },
+    Rule {
+        flag: "show-alignment",
+        applies: |c| !c.detailed,
+        message: "--show-alignment and --detailed are exclusive",
+        label: "can't show the alignment in the details",
+        help: "drop one of --show-alignment and --detailed",
+    },
+    Rule {
+        flag: "band",
+        applies: |c| c.detailed || c.show_alignment || c.uses(&[Id::Levenshtein]),
+        message: "--band needs levenshtein, --detailed or --show-alignment",
+        label: "only levenshtein and tracebacks are banded",
+        help: "add -a levenshtein, --detailed or --show-alignment",
+    },
+    Rule {
+        flag: "tokenize",
+        applies: |c| !matches!(c.mode, Some(Mode::Ngrams(_))) || c.counts_tokens(),
+        message: "--tokenize ngram needs a token-counting algorithm",
+        label: "n-grams overlap, so only their counts compare",
+        help: TOKEN_ALGORITHMS,
+    },
+    Rule {
+        flag: "ignore-extension",
+        applies: |c| matches!(c.mode, Some(Mode::Path)),
+        message: "--ignore-extension needs --mode path",
+        label: "only paths have extensions",
+        help: "add --mode path",
+    },
+    Rule {
+        flag: "check-digit",
+        applies: |c| matches!(c.mode, Some(Mode::Identifier)),
+        message: "--check-digit needs --mode identifier",
+        label: "only identifiers have check digits",
+        help: "add --mode identifier",
+    },
+    Rule {
+        flag: "scheme",
+        applies: |c| matches!(c.mode, Some(Mode::Identifier)),
+        message: "--scheme needs --mode identifier",
+        label: "only identifiers have schemes",
+        help: "add --mode identifier",
+    },
+    Rule {
+        flag: "token-alignment",
+        applies: |c| matches!(c.mode, Some(Mode::Words)),
+        message: "--token-alignment needs --mode words",
+        label: "only word tokens are aligned",
+        help: "add --mode words",
+    },
+    Rule {
+        flag: "token-weights",
+        applies: |c| matches!(c.mode, Some(Mode::Words)) && c.counts_tokens(),
+        message: "--token-weights needs word tokens",
+        label: "only token-counting algorithms in words mode weigh tokens",
+        help: "add --mode words and use bag, cosine, jaccard, overlap, roberts, sorensen_dice or tversky",
+    },
+    Rule {
+        flag: "token-weights",
+        applies: |c| !c.all,
+        message: "--token-weights works on one algorithm",
+        label: "can't weigh tokens for every algorithm",
+        help: "pick a token-counting algorithm with -a instead of --all",
+    },
+    Rule {
+        flag: "sort",
+        applies: |c| c.all,
+        message: "--sort needs --all",
+        label: "only orders the algorithms",
+        help: "add --all to compare every algorithm",
+    },
+    Rule {
+        flag: "agreement",
+        applies: |c| c.all,
+        message: "--agreement needs --all",
+        label: "compares the verdicts of every algorithm",
+        help: "add --all; a record of thresholds picks the algorithms to compare",
+    },
+    Rule {
+        flag: "detailed",
+        applies: |c| !c.all,
+        message: "--detailed works on one algorithm",
+        label: "can't detail every algorithm",
+        help: "pick the algorithm with -a instead of --all",
+    },
+    Rule {
+        flag: "empty-score",
+        applies: |c| !c.all,
+        message: "--empty-score works on one algorithm",
+        label: "every algorithm scores empty strings its own way",
+        help: "pick the algorithm with -a instead of --all",
+    },
+];
+
+/// Errors on the first flag `user` gave that the rest of `call`, the
+/// same call with its profile, preset and defaults applied, gives no use.
+/// Flags those added are dropped from `call` instead: a profile's
+/// `jaro-window` shouldn't fail `-a levenshtein`.
+pub fn check(call: &mut EvaluatedCall, user: &EvaluatedCall) -> Result<(), LabeledError> {
+    loop {
+        let context = Context {
+            algorithm: match is_auto(call)? {
+                true => None,
+                false => lookup(&algorithm(call)?),
+            },
+            all: call.has_flag("all")?,
+            detailed: call.has_flag("detailed")?,
+            show_alignment: call.has_flag("show-alignment")?,
+            mode: tokenize(call, mode(call)?)?,
+        };
+        match RULES
+            .iter()
+            .find(|r| given(call, r.flag) && !(r.applies)(&context))
+        {
+            Some(rule) if given(user, rule.flag) => {
+                return Err(LabeledError::new(rule.message)
+                    .with_label(rule.label, flag_span(call, rule.flag))
+                    .with_help(rule.help))
+            }
+            Some(rule) => call.named.retain(|(f, _)| f.item != rule.flag),
+            None => return Ok(()),
+        }
+    }
+}
+
+/// The flags that only apply to text, which bytes have none of.
+const TEXT_ONLY: [&str; 21] = [
+    "all",
+    "sort",
+    "agreement",
+    "detailed",
+    "mode",
+    "tokenize",
+    "pattern",
+    "glob",
+    "errors",
+    "chunked",
+    "pad",
+    "show",
+    "show-alignment",
+    "band",
+    "common-substrings",
+    "trivially-equal",
+    "empty-score",
+    "expansions",
+    "token-weights",
+    "numbers-as-words",
+    "words-as-numbers",
+];
+
+/// Errors on the first flag of `call` that only applies to text, for
+/// comparing bytes.
+pub fn check_bytes(call: &EvaluatedCall) -> Result<(), LabeledError> {
+    match TEXT_ONLY.into_iter().find(|flag| given(call, flag)) {
+        Some(flag) => Err(
+            LabeledError::new(format!("--{flag} doesn't apply to bytes"))
+                .with_label("only used on text", flag_span(call, flag))
+                .with_help("drop it, or compare strings without --bytes"),
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Whether `flag` is given, and isn't a switch turned off. `--multiset`
+/// takes a bool, so `--multiset false` is a setting rather than a switch.
+pub fn given(call: &EvaluatedCall, flag: &str) -> bool {
+    call.named.iter().any(|(f, v)| {
+        f.item == flag && (flag == "multiset" || !matches!(v, Some(Value::Bool { val: false, .. })))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use nu_protocol::{Span, Spanned};
+
+    use super::*;
+
+    fn flag(name: &str, value: Value) -> (Spanned<String>, Option<Value>) {
+        let span = Span::test_data();
+        (
+            Spanned {
+                item: name.to_string(),
+                span,
+            },
+            Some(value),
+        )
+    }
+
+    #[test]
+    fn only_the_users_flags_are_rejected() {
+        let user = EvaluatedCall {
+            head: Span::test_data(),
+            positional: vec![],
+            named: vec![flag("algorithm", Value::test_string("levenshtein"))],
+        };
+        let mut applied = user.clone();
+        applied.named.push(flag("jaro-window", Value::test_int(3)));
+        assert!(check(&mut applied, &user).is_ok());
+        assert!(!given(&applied, "jaro-window"));
+
+        let mut user = user;
+        user.named.push(flag("jaro-window", Value::test_int(3)));
+        assert!(check(&mut user.clone(), &user).is_err());
+    }
+}
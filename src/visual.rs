@@ -0,0 +1,28 @@
+// Visual mode: strings are compared as they render rather than by code
+// point, so fullwidth and halfwidth forms of the same character, and the
+// ideographic space and a plain one, count as the same character.
+
+use unicode_normalization::UnicodeNormalization;
+
+/// Whether `c` is a width variant of another character: the ideographic
+/// space, or one of the Halfwidth and Fullwidth Forms.
+fn width_variant(c: char) -> bool {
+    c == '\u{3000}' || ('\u{ff00}'..='\u{ffef}').contains(&c)
+}
+
+/// `s` with every width variant replaced by its usual form ("ＡＢＣ" by
+/// "ABC", halfwidth "ｶﾞ" by "ガ") and composed, so each rendered character
+/// is one char whichever way it was encoded. Other compatibility forms, like
+/// ligatures and circled digits, look different and are kept.
+pub fn fold(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match width_variant(c) {
+            true => out.extend(c.to_string().nfkc()),
+            false => out.push(c),
+        }
+    }
+    // a halfwidth sound mark becomes a combining one, which composes with
+    // the kana before it
+    out.nfc().collect()
+}
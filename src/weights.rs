@@ -0,0 +1,81 @@
+// Token-counting metrics with some tokens counting for less, e.g. "inc" or
+// "the" in company names, without dropping them altogether.
+
+use std::collections::HashMap;
+
+use nu_plugin::EvaluatedCall;
+use nu_protocol::{LabeledError, Value};
+
+/// Weights by lowercase token; tokens not listed weigh 1.
+pub struct TokenWeights(HashMap<String, f64>);
+
+impl TokenWeights {
+    /// Reads `--token-weights`, a record of tokens to non-negative weights.
+    pub fn from_call(call: &EvaluatedCall) -> Result<Option<Self>, LabeledError> {
+        let Some(weights) = call.get_flag::<Value>("token-weights")? else {
+            return Ok(None);
+        };
+        let mut map = HashMap::new();
+        for (token, v) in weights.as_record()? {
+            let weight = match v.coerce_float() {
+                Ok(w) if w >= 0.0 => w,
+                _ => {
+                    return Err(LabeledError::new("invalid token weight")
+                        .with_label(format!("{token} needs a weight of 0 or more"), v.span())
+                        .with_help("e.g. --token-weights {inc: 0.2, the: 0.1}"))
+                }
+            };
+            map.insert(token.to_lowercase(), weight);
+        }
+        Ok(Some(TokenWeights(map)))
+    }
+
+    /// Each distinct token's total weight in `tokens`: its weight times how
+    /// often it occurs, or just its weight when not counting repeats.
+    fn bag(&self, tokens: &[&str], multiset: bool) -> HashMap<String, f64> {
+        let mut bag: HashMap<String, f64> = HashMap::new();
+        for t in tokens {
+            let key = t.to_lowercase();
+            let w = self.0.get(&key).copied().unwrap_or(1.0);
+            let total = bag.entry(key).or_default();
+            *total = if multiset { *total + w } else { w };
+        }
+        bag
+    }
+
+    /// The normalized score of token-counting algorithm `algo` on the
+    /// weighted tokens: a similarity, or for bag a distance. With every
+    /// weight 1 it is the algorithm's usual score on lowercase tokens.
+    pub fn score(&self, algo: &str, a: &[&str], b: &[&str], multiset: bool) -> f64 {
+        let (x, y) = (self.bag(a, multiset), self.bag(b, multiset));
+        let (sx, sy): (f64, f64) = (x.values().sum(), y.values().sum());
+        if sx + sy == 0.0 {
+            // nothing of any weight on either side
+            return if algo.eq_ignore_ascii_case("bag") {
+                0.0
+            } else {
+                1.0
+            };
+        }
+        let shared = |f: fn(f64, f64) -> f64| -> f64 {
+            x.iter()
+                .filter_map(|(t, w)| y.get(t).map(|v| f(*w, *v)))
+                .sum()
+        };
+        let min = shared(f64::min);
+        let union = sx + sy - min;
+        let ratio = |n: f64, d: f64| if d == 0.0 { 0.0 } else { n / d };
+        match algo.to_lowercase().as_str() {
+            "bag" => ratio(sx.max(sy) - min, sx.max(sy)),
+            "cos" | "cosine" => ratio(min, (sx * sy).sqrt()),
+            "olap" | "overlap" => ratio(min, sx.min(sy)),
+            "rob" | "roberts" => {
+                let num = shared(|w, v| (w + v) * w.min(v) / w.max(v));
+                ratio(num, sx + sy)
+            }
+            "soredice" | "sorensen_dice" => ratio(2.0 * min, sx + sy),
+            // jaccard, and tversky with its default equal weights
+            _ => ratio(min, union),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// `--why`: a line of plain text per accepted match saying why it was
+// accepted, for reviewers signing off on automated merges without reading
+// `--explain-match` records. It starts with the algorithm and its score and
+// adds what a reader can check at a glance: the strings being equal, a
+// shared prefix or suffix, the same words in another order, equal phonetic
+// codes or a few edits apart.
+
+use nu_protocol::Value;
+
+use crate::phonetic::double_metaphone;
+use crate::{compute_tokens, AlgoParams};
+
+/// The fewest characters a shared prefix or suffix needs to be worth saying.
+const MIN_AFFIX: usize = 3;
+
+/// The most edits worth counting; past it, the score says more.
+const MAX_EDITS: f64 = 3.0;
+
+/// Why `a` and `b` matched with `score` by `algo`, e.g. `jaro_winkler 0.97;
+/// shared prefix 'anders'; phonetic codes equal`.
+pub fn justify(algo: &str, score: f64, a: &[char], b: &[char]) -> String {
+    let mut reasons = vec![format!("{algo} {}", rounded(score))];
+    if a == b {
+        reasons.push("equal after preprocessing".into());
+        return reasons.join("; ");
+    }
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a
+        .iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    if prefix >= MIN_AFFIX && prefix >= suffix {
+        let shared: String = a[..prefix].iter().collect();
+        reasons.push(format!("shared prefix '{}'", shared.trim_end()));
+    } else if suffix >= MIN_AFFIX {
+        let shared: String = a[a.len() - suffix..].iter().collect();
+        reasons.push(format!("shared suffix '{}'", shared.trim_start()));
+    }
+    let (a, b): (String, String) = (a.iter().collect(), b.iter().collect());
+    let words = |s: &str| {
+        let mut w: Vec<String> = s.split_whitespace().map(str::to_lowercase).collect();
+        w.sort();
+        w
+    };
+    let (wa, wb) = (words(&a), words(&b));
+    if wa.len() > 1 && wa == wb {
+        reasons.push("same words in another order".into());
+    }
+    let (pa, pb) = (double_metaphone(&a).0, double_metaphone(&b).0);
+    if !pa.is_empty() && pa == pb {
+        reasons.push("phonetic codes equal".into());
+    }
+    let (ca, cb): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let edits = compute_tokens("levenshtein", &ca, &cb, &AlgoParams::default()).raw;
+    if edits <= MAX_EDITS {
+        let s = if edits == 1.0 { "" } else { "s" };
+        reasons.push(format!("{edits} edit{s} apart"));
+    }
+    reasons.join("; ")
+}
+
+/// Why records matched on several fields, from their `--explain-match`
+/// rows: each field's algorithm and score, e.g. `name jaro_winkler 0.97,
+/// zip hamming 1`.
+pub fn fields(explanation: &Value) -> String {
+    let Value::List { vals, .. } = explanation else {
+        return String::new();
+    };
+    vals.iter()
+        .filter_map(|row| {
+            let row = row.as_record().ok()?;
+            let text = |c: &str| row.get(c).and_then(|v| v.coerce_string().ok());
+            let score = match row.get("score")?.coerce_float() {
+                Ok(s) => rounded(s),
+                Err(_) => "no score".into(),
+            };
+            Some(format!("{} {} {score}", text("field")?, text("algorithm")?))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `score` to two decimal places, without trailing zeros.
+fn rounded(score: f64) -> String {
+    let s = format!("{score:.2}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn justifications_say_what_matches() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(
+            justify(
+                "jaro_winkler",
+                0.9667,
+                &chars("andersen"),
+                &chars("anderson")
+            ),
+            "jaro_winkler 0.97; shared prefix 'anders'; phonetic codes equal; 1 edit apart"
+        );
+        assert_eq!(
+            justify("levenshtein", 1.0, &chars("nu"), &chars("nu")),
+            "levenshtein 1; equal after preprocessing"
+        );
+    }
+}